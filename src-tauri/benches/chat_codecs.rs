@@ -0,0 +1,97 @@
+//! Per-chunk cost of turning an OpenAI Chat stream into Anthropic events,
+//! with the built-in decoder and with an equivalent JSONata rule, and of
+//! evaluating that rule from a runtime worker the way the proxy does.
+//!
+//! Run with `cargo bench --bench chat_codecs`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use omnikit_lib::bench::{
+    engine, get_decoder, get_encoder, ChatFormat, ConversionRule, Decoder, JsonataDecoder,
+};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Instant;
+
+const CHUNK: &str = r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1700000000,"model":"gpt-4o","choices":[{"index":0,"delta":{"content":"Hello, world"},"finish_reason":null}]}"#;
+
+const DECODE_STREAM_CHUNK: &str = r#"{
+  "id": id,
+  "model": model,
+  "delta_content": choices[0].delta.content,
+  "finish_reason": choices[0].finish_reason
+}"#;
+
+fn rule() -> ConversionRule {
+    ConversionRule {
+        id: "bench".into(),
+        slug: "bench".into(),
+        name: "Bench".into(),
+        description: None,
+        author: None,
+        version: "1.0.0".into(),
+        tags: None,
+        rule_type: "user".into(),
+        modality: "chat".into(),
+        decode_request: "$".into(),
+        encode_request: "$".into(),
+        decode_response: "$".into(),
+        encode_response: "$".into(),
+        decode_stream_chunk: Some(DECODE_STREAM_CHUNK.into()),
+        encode_stream_chunk: None,
+        http_config: None,
+        test_fixtures: None,
+        enabled: true,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
+
+fn stream_chunk(c: &mut Criterion) {
+    let builtin = get_decoder(ChatFormat::OpenaiChat);
+    let jsonata = JsonataDecoder { rule: Arc::new(rule()) };
+    let encoder = get_encoder(ChatFormat::Anthropic);
+
+    let mut group = c.benchmark_group("openai_chunk_to_anthropic");
+    group.bench_function("builtin", |b| {
+        b.iter(|| {
+            let chunk = builtin.decode_stream_chunk("message", black_box(CHUNK)).unwrap().unwrap();
+            encoder.encode_stream_chunk(&chunk).unwrap()
+        })
+    });
+    group.bench_function("jsonata", |b| {
+        b.iter(|| {
+            let chunk = jsonata.decode_stream_chunk("message", black_box(CHUNK)).unwrap().unwrap();
+            encoder.encode_stream_chunk(&chunk).unwrap()
+        })
+    });
+    // What every chunk used to pay on top of evaluation before programs were cached
+    group.bench_function("jsonata_parse", |b| {
+        b.iter(|| engine::validate(black_box(DECODE_STREAM_CHUNK)).unwrap())
+    });
+    group.finish();
+}
+
+/// A rule evaluated from a task on the multi-threaded runtime, which hands
+/// the job to an evaluator thread and waits for its reply.
+fn evaluate_rule(c: &mut Criterion) {
+    let rule = rule();
+    let input: serde_json::Value = serde_json::from_str(CHUNK).unwrap();
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+
+    c.bench_function("evaluate_rule_from_worker", |b| {
+        b.iter_custom(|iters| {
+            let (rule, input) = (rule.clone(), input.clone());
+            let task = runtime.spawn(async move {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    black_box(engine::evaluate_rule(&rule, DECODE_STREAM_CHUNK, black_box(&input)).unwrap());
+                }
+                start.elapsed()
+            });
+            runtime.block_on(task).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, stream_chunk, evaluate_rule);
+criterion_main!(benches);
@@ -0,0 +1,5 @@
+-- Failover attempts: each failed upstream attempt is logged as a child row of the
+-- original request. parent_id is NULL for top-level requests.
+ALTER TABLE request_logs ADD COLUMN parent_id TEXT;
+ALTER TABLE request_logs ADD COLUMN attempt INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX IF NOT EXISTS idx_request_logs_parent_id ON request_logs(parent_id);
@@ -0,0 +1,3 @@
+-- Key scheduling strategy used when key_rotation is enabled:
+-- 'round_robin', 'least_recently_used' or 'random'.
+ALTER TABLE channels ADD COLUMN key_strategy TEXT NOT NULL DEFAULT 'round_robin';
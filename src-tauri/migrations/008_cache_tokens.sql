@@ -0,0 +1,4 @@
+-- Prompt tokens read from / written to the provider's prompt cache. Both are
+-- included in prompt_tokens and NULL when the upstream reports none.
+ALTER TABLE request_logs ADD COLUMN cache_read_tokens INTEGER;
+ALTER TABLE request_logs ADD COLUMN cache_write_tokens INTEGER;
@@ -0,0 +1,3 @@
+-- Why a request failed, including streams that broke off after the status
+-- line was sent. NULL for successful requests.
+ALTER TABLE request_logs ADD COLUMN error_message TEXT;
@@ -0,0 +1,3 @@
+-- Golden fixtures a rule must pass before it is enabled: a JSON array of
+-- {name, step, input, expected}. NULL when the rule has none.
+ALTER TABLE conversion_rules ADD COLUMN test_fixtures TEXT;
@@ -0,0 +1,3 @@
+-- Whether the client asked for a stream. Gemini carries this in the path
+-- rather than the body, so a replay needs it to rebuild the route.
+ALTER TABLE request_logs ADD COLUMN stream BOOLEAN NOT NULL DEFAULT 0;
@@ -1,5 +1,6 @@
 use crate::db::models::{Channel, ChannelApiKey};
 use crate::error::IpcError;
+use crate::routing::limiter::RateLimitConfig;
 use crate::AppState;
 use tauri::State;
 
@@ -10,7 +11,18 @@ pub async fn list_channels(state: State<'_, AppState>) -> Result<Vec<Channel>, I
         .await?)
 }
 
+/// Check a `rate_limit` argument. An empty value stands for no limit.
+fn validate_rate_limit(rate_limit: Option<&str>) -> Result<(), IpcError> {
+    match rate_limit.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(raw) => serde_json::from_str::<RateLimitConfig>(raw)
+            .map(|_| ())
+            .map_err(|e| IpcError::validation(format!("Invalid rate limit: {}", e))),
+        None => Ok(()),
+    }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_channel(
     state: State<'_, AppState>,
     name: String,
@@ -18,14 +30,18 @@ pub async fn create_channel(
     base_url: String,
     priority: i32,
     weight: i32,
+    key_strategy: Option<String>,
+    rate_limit: Option<String>,
 ) -> Result<Channel, IpcError> {
+    validate_rate_limit(rate_limit.as_deref())?;
     let id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
     sqlx::query(
-        "INSERT INTO channels (id, name, provider, base_url, priority, weight, enabled, key_rotation, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, 1, 0, ?, ?)"
+        "INSERT INTO channels (id, name, provider, base_url, priority, weight, enabled, key_rotation, key_strategy, rate_limit, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, 1, 0, COALESCE(?, 'round_robin'), NULLIF(TRIM(?), ''), ?, ?)"
     )
     .bind(&id).bind(&name).bind(&provider).bind(&base_url)
-    .bind(priority).bind(weight).bind(&now).bind(&now)
+    .bind(priority).bind(weight).bind(&key_strategy).bind(&rate_limit)
+    .bind(&now).bind(&now)
     .execute(&state.db)
     .await?;
 
@@ -35,7 +51,10 @@ pub async fn create_channel(
         .await?)
 }
 
+/// `key_strategy` and `rate_limit` keep their stored values when absent; an
+/// empty `rate_limit` removes the limit.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_channel(
     state: State<'_, AppState>,
     id: String,
@@ -46,14 +65,18 @@ pub async fn update_channel(
     weight: i32,
     enabled: bool,
     key_rotation: bool,
+    key_strategy: Option<String>,
+    rate_limit: Option<String>,
 ) -> Result<(), IpcError> {
+    validate_rate_limit(rate_limit.as_deref())?;
+
     let now = chrono::Utc::now().to_rfc3339();
     sqlx::query(
-        "UPDATE channels SET name = ?, provider = ?, base_url = ?, priority = ?, weight = ?, enabled = ?, key_rotation = ?, updated_at = ? WHERE id = ?"
+        "UPDATE channels SET name = ?, provider = ?, base_url = ?, priority = ?, weight = ?, enabled = ?, key_rotation = ?, key_strategy = COALESCE(?, key_strategy), rate_limit = CASE WHEN ? IS NULL THEN rate_limit ELSE NULLIF(TRIM(?), '') END, updated_at = ? WHERE id = ?"
     )
     .bind(&name).bind(&provider).bind(&base_url)
     .bind(priority).bind(weight).bind(enabled).bind(key_rotation)
-    .bind(&now).bind(&id)
+    .bind(&key_strategy).bind(&rate_limit).bind(&rate_limit).bind(&now).bind(&id)
     .execute(&state.db)
     .await?;
     Ok(())
@@ -204,7 +227,7 @@ async fn send_test_request(
             }
             let body = resp.text().await.unwrap_or_default();
             serde_json::json!({
-                "success": status >= 200 && status < 300,
+                "success": (200..300).contains(&status),
                 "request": request_info,
                 "response": {
                     "status": status,
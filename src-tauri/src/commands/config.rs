@@ -1,6 +1,9 @@
 use crate::error::IpcError;
 use crate::AppState;
 use crate::config::AppConfig;
+use crate::routing::retry::RetryPolicy;
+use crate::rules::engine::{self, Limits};
+use crate::server::access::CachePricing;
 use tauri::State;
 
 #[tauri::command]
@@ -26,10 +29,19 @@ pub async fn get_server_status(state: State<'_, AppState>) -> Result<serde_json:
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_config(
     state: State<'_, AppState>,
     server_port: u16,
     log_retention_days: u32,
+    retry_max_attempts: Option<u32>,
+    retry_status_codes: Option<Vec<u16>>,
+    retry_backoff_ms: Option<u64>,
+    cache_read_price_ratio: Option<f64>,
+    cache_write_price_ratio: Option<f64>,
+    rule_timeout_ms: Option<u64>,
+    rule_max_depth: Option<usize>,
+    rule_max_output_bytes: Option<usize>,
 ) -> Result<AppConfig, IpcError> {
     // UPSERT into app_config table
     sqlx::query(
@@ -46,10 +58,62 @@ pub async fn update_config(
     .execute(&state.db)
     .await?;
 
+    let retry_status_codes = retry_status_codes.map(|codes| {
+        codes.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")
+    });
+    let optional_values = [
+        ("retry_max_attempts", retry_max_attempts.map(|v| v.to_string())),
+        ("retry_status_codes", retry_status_codes.clone()),
+        ("retry_backoff_ms", retry_backoff_ms.map(|v| v.to_string())),
+        ("cache_read_price_ratio", cache_read_price_ratio.map(|v| v.to_string())),
+        ("cache_write_price_ratio", cache_write_price_ratio.map(|v| v.to_string())),
+        ("rule_timeout_ms", rule_timeout_ms.map(|v| v.to_string())),
+        ("rule_max_depth", rule_max_depth.map(|v| v.to_string())),
+        ("rule_max_output_bytes", rule_max_output_bytes.map(|v| v.to_string())),
+    ];
+    for (key, value) in optional_values {
+        if let Some(value) = value {
+            sqlx::query(
+                "INSERT INTO app_config (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = ?2",
+            )
+            .bind(key)
+            .bind(value)
+            .execute(&state.db)
+            .await?;
+        }
+    }
+
     // Update in-memory config
     let mut config = state.config.write().await;
     config.server_port = server_port;
     config.log_retention_days = log_retention_days;
+    if let Some(v) = retry_max_attempts {
+        config.retry_max_attempts = v;
+    }
+    if let Some(v) = retry_status_codes {
+        config.retry_status_codes = crate::config::parse_status_codes(&v);
+    }
+    if let Some(v) = retry_backoff_ms {
+        config.retry_backoff_ms = v;
+    }
+    if let Some(v) = cache_read_price_ratio {
+        config.cache_read_price_ratio = v;
+    }
+    if let Some(v) = cache_write_price_ratio {
+        config.cache_write_price_ratio = v;
+    }
+    if let Some(v) = rule_timeout_ms {
+        config.rule_timeout_ms = v;
+    }
+    if let Some(v) = rule_max_depth {
+        config.rule_max_depth = v;
+    }
+    if let Some(v) = rule_max_output_bytes {
+        config.rule_max_output_bytes = v;
+    }
+    engine::set_limits(Limits::from_config(&config));
+    *state.retry.write().unwrap_or_else(|e| e.into_inner()) = RetryPolicy::from_config(&config);
+    *state.pricing.write().unwrap_or_else(|e| e.into_inner()) = CachePricing::from_config(&config);
 
     Ok(config.clone())
 }
@@ -18,14 +18,14 @@ pub async fn list_request_logs(
 
     let (items, total) = if let Some(model) = model {
         let items = sqlx::query_as::<_, RequestLog>(
-            "SELECT * FROM request_logs WHERE model = ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM request_logs WHERE parent_id IS NULL AND model = ? ORDER BY created_at DESC LIMIT ? OFFSET ?"
         )
         .bind(&model).bind(limit).bind(offset)
         .fetch_all(&state.db)
         .await?;
 
         let (total,): (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM request_logs WHERE model = ?"
+            "SELECT COUNT(*) FROM request_logs WHERE parent_id IS NULL AND model = ?"
         )
         .bind(&model)
         .fetch_one(&state.db)
@@ -34,14 +34,14 @@ pub async fn list_request_logs(
         (items, total)
     } else {
         let items = sqlx::query_as::<_, RequestLog>(
-            "SELECT * FROM request_logs ORDER BY created_at DESC LIMIT ? OFFSET ?"
+            "SELECT * FROM request_logs WHERE parent_id IS NULL ORDER BY created_at DESC LIMIT ? OFFSET ?"
         )
         .bind(limit).bind(offset)
         .fetch_all(&state.db)
         .await?;
 
         let (total,): (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM request_logs"
+            "SELECT COUNT(*) FROM request_logs WHERE parent_id IS NULL"
         )
         .fetch_one(&state.db)
         .await?;
@@ -63,6 +63,20 @@ pub async fn get_request_log(
         .await?)
 }
 
+/// List the failed upstream attempts that preceded a request's final outcome.
+#[tauri::command]
+pub async fn list_request_log_attempts(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<RequestLog>, IpcError> {
+    Ok(sqlx::query_as::<_, RequestLog>(
+        "SELECT * FROM request_logs WHERE parent_id = ? ORDER BY attempt ASC"
+    )
+    .bind(&id)
+    .fetch_all(&state.db)
+    .await?)
+}
+
 #[tauri::command]
 pub async fn clear_request_logs(state: State<'_, AppState>) -> Result<(), IpcError> {
     sqlx::query("DELETE FROM request_logs")
@@ -81,7 +95,7 @@ pub async fn get_usage_stats(
     let since_str = since.to_rfc3339();
 
     let daily_stats: Vec<(String, i64, i64, i64)> = sqlx::query_as(
-        "SELECT DATE(created_at) as date, COUNT(*) as count, COALESCE(SUM(prompt_tokens), 0) as prompt_tokens, COALESCE(SUM(completion_tokens), 0) as completion_tokens FROM request_logs WHERE created_at >= ? GROUP BY DATE(created_at) ORDER BY date ASC"
+        "SELECT DATE(created_at) as date, COUNT(*) as count, COALESCE(SUM(prompt_tokens), 0) as prompt_tokens, COALESCE(SUM(completion_tokens), 0) as completion_tokens FROM request_logs WHERE created_at >= ? AND parent_id IS NULL GROUP BY DATE(created_at) ORDER BY date ASC"
     )
     .bind(&since_str)
     .fetch_all(&state.db)
@@ -97,7 +111,7 @@ pub async fn get_usage_stats(
     }).collect();
 
     let model_stats: Vec<(String, i64)> = sqlx::query_as(
-        "SELECT COALESCE(model, 'unknown') as model, COUNT(*) as count FROM request_logs WHERE created_at >= ? GROUP BY model ORDER BY count DESC"
+        "SELECT COALESCE(model, 'unknown') as model, COUNT(*) as count FROM request_logs WHERE created_at >= ? AND parent_id IS NULL GROUP BY model ORDER BY count DESC"
     )
     .bind(&since_str)
     .fetch_all(&state.db)
@@ -147,14 +161,31 @@ pub async fn retry_request_log(
     .await?
     .ok_or_else(|| IpcError::not_found("Token not found"))?;
 
-    // 3. Determine endpoint path from input_format
+    // 3. Determine endpoint path from input_format. Gemini takes the model
+    // and whether to stream from the path rather than the body.
     let path = match input_format.as_str() {
-        "openai_chat" => "/v1/chat/completions",
-        "anthropic" => "/v1/messages",
-        "openai_responses" => "/v1/responses",
-        "moonshot" => "/v1/chat/completions",
+        "openai-chat" => "/v1/chat/completions".to_string(),
+        "anthropic" => "/v1/messages".to_string(),
+        "openai-responses" => "/v1/responses".to_string(),
+        "moonshot" => "/v1/chat/completions".to_string(),
+        "openai-embeddings" => "/v1/embeddings".to_string(),
+        "openai-images" => "/v1/images/generations".to_string(),
+        "gemini" => {
+            let model = log.model.ok_or_else(|| IpcError::validation("No model"))?;
+            let model = urlencoding::encode(&model);
+            if log.stream {
+                format!("/v1beta/models/{}:streamGenerateContent?alt=sse", model)
+            } else {
+                format!("/v1beta/models/{}:generateContent", model)
+            }
+        }
         other => return Err(IpcError::validation(format!("Unknown input format: {}", other))),
     };
+    // Each API's own SDKs send the key where its route expects it
+    let (auth_header, auth_value) = match input_format.as_str() {
+        "gemini" => ("x-goog-api-key", token.key_value),
+        _ => ("Authorization", format!("Bearer {}", token.key_value)),
+    };
 
     // 4. Send request to local proxy
     let port = state.config.read().await.server_port;
@@ -164,7 +195,7 @@ pub async fn retry_request_log(
     let resp = client
         .post(&url)
         .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", token.key_value))
+        .header(auth_header, auth_value)
         .body(request_body)
         .send()
         .await?;
@@ -1,8 +1,97 @@
 use crate::db::models::{Channel, ConversionRule};
-use crate::error::IpcError;
+use crate::error::{AppError, IpcError};
+use crate::modality::chat::ChatFormat;
+use crate::rules::fixtures::{self, RuleTestReport};
+use crate::rules::registry::{self, CodecProvider};
 use crate::AppState;
 use serde::Serialize;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+
+/// Emitted after any rule change so open editors can refresh.
+const RULES_CHANGED_EVENT: &str = "rules-changed";
+
+fn notify_rules_changed(app: &AppHandle) {
+    let _ = app.emit(RULES_CHANGED_EVENT, ());
+}
+
+fn validate_fixtures(test_fixtures: Option<&str>) -> Result<(), IpcError> {
+    fixtures::parse(test_fixtures).map(|_| ()).map_err(IpcError::validation)
+}
+
+/// Run a rule's own fixtures, plus those of a built-in codec if one is named.
+fn run_fixtures(rule: &ConversionRule, builtin: Option<ChatFormat>) -> Result<RuleTestReport, IpcError> {
+    let mut suite = fixtures::parse(rule.test_fixtures.as_deref()).map_err(IpcError::validation)?;
+    if let Some(format) = builtin {
+        suite.extend(fixtures::builtin(format));
+    }
+    let (_, provider) = registry::provider_for(rule.clone())
+        .ok_or_else(|| IpcError::validation(format!("Rule {} has no codec", rule.slug)))?;
+    Ok(fixtures::run(&provider, &suite))
+}
+
+/// The built-in codec a system rule overlays, whose shipped fixtures the
+/// overlay must keep passing.
+fn overlaid_format(rule: &ConversionRule) -> Option<ChatFormat> {
+    match registry::provider_for(rule.clone()) {
+        Some((_, CodecProvider::Overlay(format, _))) => Some(format),
+        _ => None,
+    }
+}
+
+/// Only enable a rule that passes its fixtures, so a broken rule never
+/// serves traffic. A failing rule is switched off before it is stored and
+/// the report is returned with it.
+fn gate_on_fixtures(rule: &mut ConversionRule) -> Result<Option<RuleTestReport>, IpcError> {
+    if !rule.enabled {
+        return Ok(None);
+    }
+    let report = run_fixtures(rule, overlaid_format(rule))?;
+    if report.all_passed() {
+        return Ok(None);
+    }
+    log::warn!("Rule {} stored disabled, failing fixtures: {}", rule.slug, report.failures().join(", "));
+    rule.enabled = false;
+    Ok(Some(report))
+}
+
+/// A stored rule. `test_report` is set when the rule was asked to be enabled
+/// but was stored disabled because its fixtures fail.
+#[derive(Debug, Serialize)]
+pub struct SavedRule {
+    #[serde(flatten)]
+    pub rule: ConversionRule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_report: Option<RuleTestReport>,
+}
+
+/// Store a new user rule.
+async fn insert_user_rule(state: &AppState, rule: &ConversionRule) -> Result<(), IpcError> {
+    sqlx::query(
+        "INSERT INTO conversion_rules (id, slug, name, description, author, version, tags, rule_type, modality, decode_request, encode_request, decode_response, encode_response, decode_stream_chunk, encode_stream_chunk, http_config, test_fixtures, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, 'user', ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&rule.id)
+    .bind(&rule.slug)
+    .bind(&rule.name)
+    .bind(&rule.description)
+    .bind(&rule.author)
+    .bind(&rule.version)
+    .bind(&rule.tags)
+    .bind(&rule.modality)
+    .bind(&rule.decode_request)
+    .bind(&rule.encode_request)
+    .bind(&rule.decode_response)
+    .bind(&rule.encode_response)
+    .bind(&rule.decode_stream_chunk)
+    .bind(&rule.encode_stream_chunk)
+    .bind(&rule.http_config)
+    .bind(&rule.test_fixtures)
+    .bind(rule.enabled)
+    .bind(&rule.created_at)
+    .bind(&rule.updated_at)
+    .execute(&state.db)
+    .await?;
+    Ok(())
+}
 
 #[tauri::command]
 pub async fn list_conversion_rules(
@@ -30,7 +119,9 @@ pub async fn get_conversion_rule(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_conversion_rule(
+    app: AppHandle,
     state: State<'_, AppState>,
     slug: String,
     name: String,
@@ -46,45 +137,48 @@ pub async fn create_conversion_rule(
     decode_stream_chunk: Option<String>,
     encode_stream_chunk: Option<String>,
     http_config: Option<String>,
-) -> Result<ConversionRule, IpcError> {
+    test_fixtures: Option<String>,
+) -> Result<SavedRule, IpcError> {
+    validate_fixtures(test_fixtures.as_deref())?;
     let id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
-    let version = version.unwrap_or_else(|| "1.0.0".to_string());
-    let modality = modality.unwrap_or_else(|| "chat".to_string());
-
-    sqlx::query(
-        "INSERT INTO conversion_rules (id, slug, name, description, author, version, tags, rule_type, modality, decode_request, encode_request, decode_response, encode_response, decode_stream_chunk, encode_stream_chunk, http_config, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, 'user', ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"
-    )
-    .bind(&id)
-    .bind(&slug)
-    .bind(&name)
-    .bind(&description)
-    .bind(&author)
-    .bind(&version)
-    .bind(&tags)
-    .bind(&modality)
-    .bind(&decode_request)
-    .bind(&encode_request)
-    .bind(&decode_response)
-    .bind(&encode_response)
-    .bind(&decode_stream_chunk)
-    .bind(&encode_stream_chunk)
-    .bind(&http_config)
-    .bind(&now)
-    .bind(&now)
-    .execute(&state.db)
-    .await?;
+    let mut rule = ConversionRule {
+        id,
+        slug,
+        name,
+        description,
+        author,
+        version: version.unwrap_or_else(|| "1.0.0".to_string()),
+        tags,
+        rule_type: "user".to_string(),
+        modality: modality.unwrap_or_else(|| "chat".to_string()),
+        decode_request,
+        encode_request,
+        decode_response,
+        encode_response,
+        decode_stream_chunk,
+        encode_stream_chunk,
+        http_config,
+        test_fixtures,
+        enabled: true,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    let test_report = gate_on_fixtures(&mut rule)?;
+    insert_user_rule(&state, &rule).await?;
+
+    if rule.enabled {
+        state.registry.register_rule(rule.clone()).await;
+    }
+    notify_rules_changed(&app);
 
-    Ok(
-        sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
-            .bind(&id)
-            .fetch_one(&state.db)
-            .await?,
-    )
+    Ok(SavedRule { rule, test_report })
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_conversion_rule(
+    app: AppHandle,
     state: State<'_, AppState>,
     id: String,
     slug: String,
@@ -101,8 +195,10 @@ pub async fn update_conversion_rule(
     decode_stream_chunk: Option<String>,
     encode_stream_chunk: Option<String>,
     http_config: Option<String>,
+    test_fixtures: Option<String>,
     enabled: bool,
-) -> Result<(), IpcError> {
+) -> Result<SavedRule, IpcError> {
+    validate_fixtures(test_fixtures.as_deref())?;
     let existing =
         sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
             .bind(&id)
@@ -110,39 +206,104 @@ pub async fn update_conversion_rule(
             .await?
             .ok_or_else(|| IpcError::not_found("Conversion rule not found"))?;
 
-    if existing.rule_type == "system" {
-        return Err(IpcError::validation("Cannot modify system conversion rules"));
+    let now = chrono::Utc::now().to_rfc3339();
+    let is_system = existing.rule_type == "system";
+    let mut candidate = ConversionRule {
+        decode_request: decode_request.clone(),
+        encode_request: encode_request.clone(),
+        decode_response: decode_response.clone(),
+        encode_response: encode_response.clone(),
+        decode_stream_chunk: decode_stream_chunk.clone(),
+        encode_stream_chunk: encode_stream_chunk.clone(),
+        test_fixtures: test_fixtures.clone(),
+        enabled,
+        ..existing
+    };
+    if !is_system {
+        candidate.slug = slug.clone();
+        candidate.modality = modality.clone().unwrap_or(candidate.modality);
+        candidate.http_config = http_config.clone();
     }
 
-    let now = chrono::Utc::now().to_rfc3339();
-    sqlx::query(
-        "UPDATE conversion_rules SET slug = ?, name = ?, description = ?, author = ?, version = ?, tags = ?, modality = ?, decode_request = ?, encode_request = ?, decode_response = ?, encode_response = ?, decode_stream_chunk = ?, encode_stream_chunk = ?, http_config = ?, enabled = ?, updated_at = ? WHERE id = ?"
-    )
-    .bind(&slug)
-    .bind(&name)
-    .bind(&description)
-    .bind(&author)
-    .bind(&version)
-    .bind(&tags)
-    .bind(&modality)
-    .bind(&decode_request)
-    .bind(&encode_request)
-    .bind(&decode_response)
-    .bind(&encode_response)
-    .bind(&decode_stream_chunk)
-    .bind(&encode_stream_chunk)
-    .bind(&http_config)
-    .bind(enabled)
-    .bind(&now)
-    .bind(&id)
-    .execute(&state.db)
-    .await?;
+    let test_report;
+    if is_system {
+        // A system rule stays bound to its built-in codec; only the overlay
+        // expressions, their fixtures and the switch that applies them can
+        // change
+        let overlays = [
+            ("decode_request", Some(&decode_request)),
+            ("encode_request", Some(&encode_request)),
+            ("decode_response", Some(&decode_response)),
+            ("encode_response", Some(&encode_response)),
+            ("decode_stream_chunk", decode_stream_chunk.as_ref()),
+            ("encode_stream_chunk", encode_stream_chunk.as_ref()),
+        ];
+        for (name, expr) in overlays {
+            if let Some(expr) = expr.filter(|e| !e.trim().is_empty()) {
+                crate::rules::engine::validate(expr)
+                    .map_err(|e| IpcError::validation(format!("{}: {}", name, e)))?;
+            }
+        }
+        test_report = gate_on_fixtures(&mut candidate)?;
 
-    Ok(())
+        sqlx::query(
+            "UPDATE conversion_rules SET decode_request = ?, encode_request = ?, decode_response = ?, encode_response = ?, decode_stream_chunk = ?, encode_stream_chunk = ?, test_fixtures = ?, enabled = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(&decode_request)
+        .bind(&encode_request)
+        .bind(&decode_response)
+        .bind(&encode_response)
+        .bind(&decode_stream_chunk)
+        .bind(&encode_stream_chunk)
+        .bind(&test_fixtures)
+        .bind(candidate.enabled)
+        .bind(&now)
+        .bind(&id)
+        .execute(&state.db)
+        .await?;
+    } else {
+        test_report = gate_on_fixtures(&mut candidate)?;
+        sqlx::query(
+            "UPDATE conversion_rules SET slug = ?, name = ?, description = ?, author = ?, version = ?, tags = ?, modality = ?, decode_request = ?, encode_request = ?, decode_response = ?, encode_response = ?, decode_stream_chunk = ?, encode_stream_chunk = ?, http_config = ?, test_fixtures = ?, enabled = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(&slug)
+        .bind(&name)
+        .bind(&description)
+        .bind(&author)
+        .bind(&version)
+        .bind(&tags)
+        .bind(&modality)
+        .bind(&decode_request)
+        .bind(&encode_request)
+        .bind(&decode_response)
+        .bind(&encode_response)
+        .bind(&decode_stream_chunk)
+        .bind(&encode_stream_chunk)
+        .bind(&http_config)
+        .bind(&test_fixtures)
+        .bind(candidate.enabled)
+        .bind(&now)
+        .bind(&id)
+        .execute(&state.db)
+        .await?;
+    }
+
+    let rule =
+        sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&state.db)
+            .await?;
+
+    // The slug or enabled flag may have changed, so rebuild rather than patch
+    state.registry.reload_from_db(&state.db).await?;
+    notify_rules_changed(&app);
+
+    Ok(SavedRule { rule, test_report })
 }
 
 #[tauri::command]
 pub async fn delete_conversion_rule(
+    app: AppHandle,
     state: State<'_, AppState>,
     id: String,
 ) -> Result<(), IpcError> {
@@ -162,14 +323,18 @@ pub async fn delete_conversion_rule(
         .execute(&state.db)
         .await?;
 
+    state.registry.remove_rule(&existing.slug).await;
+    notify_rules_changed(&app);
+
     Ok(())
 }
 
 #[tauri::command]
 pub async fn duplicate_conversion_rule(
+    app: AppHandle,
     state: State<'_, AppState>,
     id: String,
-) -> Result<ConversionRule, IpcError> {
+) -> Result<SavedRule, IpcError> {
     let source =
         sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
             .bind(&id)
@@ -177,40 +342,26 @@ pub async fn duplicate_conversion_rule(
             .await?
             .ok_or_else(|| IpcError::not_found("Conversion rule not found"))?;
 
-    let new_id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
-    let new_slug = format!("{}-copy", source.slug);
-    let new_name = format!("{} (Copy)", source.name);
-
-    sqlx::query(
-        "INSERT INTO conversion_rules (id, slug, name, description, author, version, tags, rule_type, modality, decode_request, encode_request, decode_response, encode_response, decode_stream_chunk, encode_stream_chunk, http_config, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, 'user', ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"
-    )
-    .bind(&new_id)
-    .bind(&new_slug)
-    .bind(&new_name)
-    .bind(&source.description)
-    .bind(&source.author)
-    .bind(&source.version)
-    .bind(&source.tags)
-    .bind(&source.modality)
-    .bind(&source.decode_request)
-    .bind(&source.encode_request)
-    .bind(&source.decode_response)
-    .bind(&source.encode_response)
-    .bind(&source.decode_stream_chunk)
-    .bind(&source.encode_stream_chunk)
-    .bind(&source.http_config)
-    .bind(&now)
-    .bind(&now)
-    .execute(&state.db)
-    .await?;
+    let mut rule = ConversionRule {
+        id: uuid::Uuid::new_v4().to_string(),
+        slug: format!("{}-copy", source.slug),
+        name: format!("{} (Copy)", source.name),
+        rule_type: "user".to_string(),
+        enabled: true,
+        created_at: now.clone(),
+        updated_at: now,
+        ..source
+    };
+    let test_report = gate_on_fixtures(&mut rule)?;
+    insert_user_rule(&state, &rule).await?;
+
+    if rule.enabled {
+        state.registry.register_rule(rule.clone()).await;
+    }
+    notify_rules_changed(&app);
 
-    Ok(
-        sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
-            .bind(&new_id)
-            .fetch_one(&state.db)
-            .await?,
-    )
+    Ok(SavedRule { rule, test_report })
 }
 
 // ---------------------------------------------------------------------------
@@ -360,16 +511,16 @@ pub async fn generate_rule_with_ai(
     }
 
     let resp = req.send().await.map_err(|e| {
-        IpcError::internal(&format!("Failed to call AI: {}", e))
+        IpcError::internal(format!("Failed to call AI: {}", e))
     })?;
 
     let status = resp.status();
     let resp_text = resp.text().await.map_err(|e| {
-        IpcError::internal(&format!("Failed to read AI response: {}", e))
+        IpcError::internal(format!("Failed to read AI response: {}", e))
     })?;
 
     if !status.is_success() {
-        return Err(IpcError::internal(&format!(
+        return Err(IpcError::internal(format!(
             "AI returned status {}: {}",
             status.as_u16(),
             resp_text
@@ -378,7 +529,7 @@ pub async fn generate_rule_with_ai(
 
     // Parse response — extract content from OpenAI-format response
     let resp_json: serde_json::Value = serde_json::from_str(&resp_text)
-        .map_err(|e| IpcError::internal(&format!("Invalid AI response JSON: {}", e)))?;
+        .map_err(|e| IpcError::internal(format!("Invalid AI response JSON: {}", e)))?;
 
     let content = resp_json["choices"][0]["message"]["content"]
         .as_str()
@@ -386,7 +537,7 @@ pub async fn generate_rule_with_ai(
 
     // Parse the generated rule JSON
     let rule: serde_json::Value = serde_json::from_str(content)
-        .map_err(|e| IpcError::internal(&format!("AI returned invalid JSON: {}", e)))?;
+        .map_err(|e| IpcError::internal(format!("AI returned invalid JSON: {}", e)))?;
 
     Ok(GeneratedRule {
         name: rule["name"].as_str().unwrap_or("").to_string(),
@@ -402,7 +553,9 @@ pub async fn generate_rule_with_ai(
     })
 }
 
-/// Validate JSONata expressions without saving the rule.
+/// Validate JSONata expressions without saving the rule. Blank templates are
+/// skipped: they mean "no overlay" on system rules and are rejected as
+/// missing by the editor on user rules.
 #[tauri::command]
 pub async fn validate_rule_templates(
     decode_request: String,
@@ -418,17 +571,17 @@ pub async fn validate_rule_templates(
         ("decode_response", &decode_response),
         ("encode_response", &encode_response),
     ];
-    for (name, expr) in &required {
+    for (name, expr) in required.iter().filter(|(_, e)| !e.trim().is_empty()) {
         crate::rules::engine::validate(expr)
-            .map_err(|e| IpcError::validation(&format!("{}: {}", name, e)))?;
+            .map_err(|e| IpcError::validation(format!("{}: {}", name, e)))?;
     }
     if let Some(ref expr) = decode_stream_chunk {
         crate::rules::engine::validate(expr)
-            .map_err(|e| IpcError::validation(&format!("decode_stream_chunk: {}", e)))?;
+            .map_err(|e| IpcError::validation(format!("decode_stream_chunk: {}", e)))?;
     }
     if let Some(ref expr) = encode_stream_chunk {
         crate::rules::engine::validate(expr)
-            .map_err(|e| IpcError::validation(&format!("encode_stream_chunk: {}", e)))?;
+            .map_err(|e| IpcError::validation(format!("encode_stream_chunk: {}", e)))?;
     }
     Ok(())
 }
@@ -440,27 +593,53 @@ pub async fn test_rule_template(
     input_json: String,
 ) -> Result<String, IpcError> {
     let input: serde_json::Value = serde_json::from_str(&input_json)
-        .map_err(|e| IpcError::validation(&format!("Invalid input JSON: {}", e)))?;
-    let result = crate::rules::engine::evaluate(&expression, &input)
-        .map_err(|e| IpcError::validation(&format!("{}", e)))?;
+        .map_err(|e| IpcError::validation(format!("Invalid input JSON: {}", e)))?;
+    let result = crate::rules::engine::evaluate(&expression, &input).map_err(|e| match e {
+        AppError::CodecLimit(_) => IpcError::rule_limit(e.to_string()),
+        e => IpcError::validation(format!("{}", e)),
+    })?;
     serde_json::to_string_pretty(&result)
-        .map_err(|e| IpcError::internal(&format!("Serialize error: {}", e)))
+        .map_err(|e| IpcError::internal(format!("Serialize error: {}", e)))
+}
+
+/// Run a rule's fixtures and report a diff for each failure. `builtin` names
+/// a built-in codec whose shipped fixtures are run against the rule too.
+#[tauri::command]
+pub async fn run_rule_tests(
+    state: State<'_, AppState>,
+    id: String,
+    builtin: Option<String>,
+) -> Result<RuleTestReport, IpcError> {
+    let rule =
+        sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
+            .bind(&id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| IpcError::not_found("Conversion rule not found"))?;
+    let builtin = builtin
+        .map(|slug| {
+            ChatFormat::from_str_loose(&slug)
+                .ok_or_else(|| IpcError::validation(format!("Unknown built-in codec: {}", slug)))
+        })
+        .transpose()?;
+    run_fixtures(&rule, builtin)
 }
 
 #[tauri::command]
 pub async fn fetch_rule_store_index() -> Result<serde_json::Value, IpcError> {
     match crate::rules::repository::fetch_index().await {
         Some(index) => serde_json::to_value(index)
-            .map_err(|e| IpcError::internal(&e.to_string())),
+            .map_err(|e| IpcError::internal(e.to_string())),
         None => Ok(serde_json::json!({ "rules": [] })),
     }
 }
 
 #[tauri::command]
 pub async fn install_rule_from_store(
+    app: AppHandle,
     state: State<'_, AppState>,
     slug: String,
-) -> Result<ConversionRule, IpcError> {
+) -> Result<SavedRule, IpcError> {
     let rule_data = crate::rules::repository::fetch_rule(&slug)
         .await
         .ok_or_else(|| IpcError::internal("Failed to fetch rule from store"))?;
@@ -469,39 +648,41 @@ pub async fn install_rule_from_store(
     let id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().to_rfc3339();
 
-    let rule_slug = rule_data["slug"].as_str().unwrap_or(&slug).to_string();
-    let name = rule_data["name"].as_str().unwrap_or(&slug).to_string();
-    let description = rule_data["description"].as_str().map(|s| s.to_string());
-    let author = rule_data["author"].as_str().map(|s| s.to_string());
-    let version = rule_data["version"].as_str().unwrap_or("1.0.0").to_string();
-    let tags = rule_data.get("tags").map(|v| v.to_string());
-    let modality = rule_data["modality"].as_str().unwrap_or("chat").to_string();
-    let decode_request = rule_data["decode_request"].as_str().unwrap_or("").to_string();
-    let encode_request = rule_data["encode_request"].as_str().unwrap_or("").to_string();
-    let decode_response = rule_data["decode_response"].as_str().unwrap_or("").to_string();
-    let encode_response = rule_data["encode_response"].as_str().unwrap_or("").to_string();
-    let decode_stream_chunk = rule_data["decode_stream_chunk"].as_str().map(|s| s.to_string());
-    let encode_stream_chunk = rule_data["encode_stream_chunk"].as_str().map(|s| s.to_string());
-    let http_config = rule_data.get("http_config").map(|v| v.to_string());
-
-    sqlx::query(
-        "INSERT INTO conversion_rules (id, slug, name, description, author, version, tags, rule_type, modality, decode_request, encode_request, decode_response, encode_response, decode_stream_chunk, encode_stream_chunk, http_config, enabled, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, 'user', ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"
-    )
-    .bind(&id).bind(&rule_slug).bind(&name).bind(&description)
-    .bind(&author).bind(&version).bind(&tags)
-    .bind(&modality)
-    .bind(&decode_request).bind(&encode_request)
-    .bind(&decode_response).bind(&encode_response)
-    .bind(&decode_stream_chunk).bind(&encode_stream_chunk)
-    .bind(&http_config)
-    .bind(&now).bind(&now)
-    .execute(&state.db)
-    .await?;
+    let test_fixtures = rule_data
+        .get("test_fixtures")
+        .filter(|v| !v.is_null())
+        .map(|v| v.to_string());
+    validate_fixtures(test_fixtures.as_deref())?;
+
+    let mut rule = ConversionRule {
+        id,
+        slug: rule_data["slug"].as_str().unwrap_or(&slug).to_string(),
+        name: rule_data["name"].as_str().unwrap_or(&slug).to_string(),
+        description: rule_data["description"].as_str().map(|s| s.to_string()),
+        author: rule_data["author"].as_str().map(|s| s.to_string()),
+        version: rule_data["version"].as_str().unwrap_or("1.0.0").to_string(),
+        tags: rule_data.get("tags").map(|v| v.to_string()),
+        rule_type: "user".to_string(),
+        modality: rule_data["modality"].as_str().unwrap_or("chat").to_string(),
+        decode_request: rule_data["decode_request"].as_str().unwrap_or("").to_string(),
+        encode_request: rule_data["encode_request"].as_str().unwrap_or("").to_string(),
+        decode_response: rule_data["decode_response"].as_str().unwrap_or("").to_string(),
+        encode_response: rule_data["encode_response"].as_str().unwrap_or("").to_string(),
+        decode_stream_chunk: rule_data["decode_stream_chunk"].as_str().map(|s| s.to_string()),
+        encode_stream_chunk: rule_data["encode_stream_chunk"].as_str().map(|s| s.to_string()),
+        http_config: rule_data.get("http_config").map(|v| v.to_string()),
+        test_fixtures,
+        enabled: true,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    let test_report = gate_on_fixtures(&mut rule)?;
+    insert_user_rule(&state, &rule).await?;
+
+    if rule.enabled {
+        state.registry.register_rule(rule.clone()).await;
+    }
+    notify_rules_changed(&app);
 
-    Ok(
-        sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE id = ?")
-            .bind(&id)
-            .fetch_one(&state.db)
-            .await?,
-    )
+    Ok(SavedRule { rule, test_report })
 }
@@ -10,6 +10,7 @@ pub async fn parse_video_url(url: String) -> Result<VideoInfo, IpcError> {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn download_video(
     app: AppHandle,
     manager: State<'_, DownloadManager>,
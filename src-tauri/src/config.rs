@@ -1,10 +1,24 @@
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
+use crate::rules::engine::Limits;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub server_port: u16,
     pub log_retention_days: u32,
+    pub retry_max_attempts: u32,
+    pub retry_status_codes: Vec<u16>,
+    pub retry_backoff_ms: u64,
+    /// Quota charged per prompt token read from a provider's prompt cache,
+    /// relative to an uncached prompt token.
+    pub cache_read_price_ratio: f64,
+    /// Quota charged per prompt token written to a provider's prompt cache.
+    pub cache_write_price_ratio: f64,
+    /// Execution limits for JSONata conversion rules.
+    pub rule_timeout_ms: u64,
+    pub rule_max_depth: usize,
+    pub rule_max_output_bytes: usize,
 }
 
 impl Default for AppConfig {
@@ -12,6 +26,14 @@ impl Default for AppConfig {
         Self {
             server_port: 9000,
             log_retention_days: 30,
+            retry_max_attempts: 3,
+            retry_status_codes: vec![429, 500, 502, 503, 504, 529],
+            retry_backoff_ms: 200,
+            cache_read_price_ratio: 0.1,
+            cache_write_price_ratio: 1.25,
+            rule_timeout_ms: Limits::DEFAULT.timeout_ms,
+            rule_max_depth: Limits::DEFAULT.max_depth,
+            rule_max_output_bytes: Limits::DEFAULT.max_output_bytes,
         }
     }
 }
@@ -37,6 +59,44 @@ impl AppConfig {
                         config.log_retention_days = days;
                     }
                 }
+                "retry_max_attempts" => {
+                    if let Ok(attempts) = value.parse::<u32>() {
+                        config.retry_max_attempts = attempts;
+                    }
+                }
+                "retry_status_codes" => {
+                    config.retry_status_codes = parse_status_codes(value);
+                }
+                "retry_backoff_ms" => {
+                    if let Ok(ms) = value.parse::<u64>() {
+                        config.retry_backoff_ms = ms;
+                    }
+                }
+                "cache_read_price_ratio" => {
+                    if let Ok(ratio) = value.parse::<f64>() {
+                        config.cache_read_price_ratio = ratio;
+                    }
+                }
+                "cache_write_price_ratio" => {
+                    if let Ok(ratio) = value.parse::<f64>() {
+                        config.cache_write_price_ratio = ratio;
+                    }
+                }
+                "rule_timeout_ms" => {
+                    if let Ok(ms) = value.parse::<u64>() {
+                        config.rule_timeout_ms = ms;
+                    }
+                }
+                "rule_max_depth" => {
+                    if let Ok(depth) = value.parse::<usize>() {
+                        config.rule_max_depth = depth;
+                    }
+                }
+                "rule_max_output_bytes" => {
+                    if let Ok(bytes) = value.parse::<usize>() {
+                        config.rule_max_output_bytes = bytes;
+                    }
+                }
                 _ => {}
             }
         }
@@ -44,3 +104,11 @@ impl AppConfig {
         Ok(config)
     }
 }
+
+/// Parse a comma-separated list of HTTP status codes, ignoring invalid entries.
+pub fn parse_status_codes(value: &str) -> Vec<u16> {
+    value
+        .split(',')
+        .filter_map(|s| s.trim().parse::<u16>().ok())
+        .collect()
+}
@@ -10,6 +10,7 @@ pub struct Channel {
     pub weight: i32,
     pub enabled: bool,
     pub key_rotation: bool,
+    pub key_strategy: String,
     pub rate_limit: Option<String>,
     pub test_url: Option<String>,
     pub test_headers: Option<String>,
@@ -61,9 +62,15 @@ pub struct RequestLog {
     pub latency_ms: Option<i64>,
     pub prompt_tokens: Option<i64>,
     pub completion_tokens: Option<i64>,
+    pub cache_read_tokens: Option<i64>,
+    pub cache_write_tokens: Option<i64>,
     pub request_body: Option<String>,
     pub response_body: Option<String>,
     pub created_at: String,
+    pub parent_id: Option<String>,
+    pub attempt: i64,
+    pub error_message: Option<String>,
+    pub stream: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -110,6 +117,8 @@ pub struct ConversionRule {
     pub decode_stream_chunk: Option<String>,
     pub encode_stream_chunk: Option<String>,
     pub http_config: Option<String>,
+    /// JSON array of golden fixtures (see `rules::fixtures`).
+    pub test_fixtures: Option<String>,
     pub enabled: bool,
     pub created_at: String,
     pub updated_at: String,
@@ -1,7 +1,10 @@
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Json, Response};
+use crate::modality::chat::ir::IrError;
+use crate::modality::chat::{self, ChatFormat};
+use crate::rules::engine::RuleLimit;
 use serde::Serialize;
-use serde_json::json;
+use std::time::Duration;
 use thiserror::Error;
 
 // === IPC Error type for Tauri commands ===
@@ -13,16 +16,24 @@ pub struct IpcError {
 }
 
 impl IpcError {
+    fn new(code: &str, msg: impl Into<String>) -> Self {
+        Self { code: code.into(), message: msg.into() }
+    }
+
     pub fn not_found(msg: impl Into<String>) -> Self {
-        Self { code: "NOT_FOUND".into(), message: msg.into() }
+        Self::new("NOT_FOUND", msg)
     }
 
     pub fn validation(msg: impl Into<String>) -> Self {
-        Self { code: "VALIDATION".into(), message: msg.into() }
+        Self::new("VALIDATION", msg)
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
-        Self { code: "INTERNAL".into(), message: msg.into() }
+        Self::new("INTERNAL", msg)
+    }
+
+    pub fn rule_limit(msg: impl Into<String>) -> Self {
+        Self::new("RULE_LIMIT", msg)
     }
 }
 
@@ -30,22 +41,22 @@ impl From<sqlx::Error> for IpcError {
     fn from(e: sqlx::Error) -> Self {
         if let sqlx::Error::Database(ref db_err) = e {
             if db_err.code().as_deref() == Some("2067") {
-                return Self { code: "CONFLICT".into(), message: db_err.message().to_string() };
+                return Self::new("CONFLICT", db_err.message());
             }
         }
-        Self { code: "DB_ERROR".into(), message: e.to_string() }
+        Self::new("DB_ERROR", e.to_string())
     }
 }
 
 impl From<reqwest::Error> for IpcError {
     fn from(e: reqwest::Error) -> Self {
-        Self { code: "INTERNAL".into(), message: e.to_string() }
+        Self::internal(e.to_string())
     }
 }
 
 impl From<serde_json::Error> for IpcError {
     fn from(e: serde_json::Error) -> Self {
-        Self { code: "VALIDATION".into(), message: e.to_string() }
+        Self::validation(e.to_string())
     }
 }
 
@@ -59,18 +70,31 @@ pub enum AppError {
     #[error("Authentication failed: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("Channel not found for model: {0}")]
     NoChannel(String),
 
     #[error("All channels failed for model: {0}")]
     AllChannelsFailed(String),
 
+    #[error("All channels are rate limited for model: {model}")]
+    RateLimited { model: String, retry_after: Duration },
+
     #[error("Upstream error: {status} {body}")]
     Upstream { status: u16, body: String },
 
     #[error("Codec error: {0}")]
     Codec(String),
 
+    /// A JSONata rule ran into one of its execution limits.
+    #[error("Codec error: JSONata rule {0}")]
+    CodecLimit(RuleLimit),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -84,31 +108,64 @@ pub enum AppError {
     Internal(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, message) = match &self {
+impl AppError {
+    fn status_and_message(&self) -> (StatusCode, String) {
+        match self {
             AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, self.to_string()),
+            AppError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            AppError::QuotaExceeded(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
             AppError::NoChannel(_) => (StatusCode::NOT_FOUND, self.to_string()),
             AppError::AllChannelsFailed(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
+            AppError::RateLimited { .. } => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
             AppError::Upstream { status, .. } => (
                 StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY),
                 self.to_string(),
             ),
             AppError::Codec(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            AppError::CodecLimit(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
             AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database error".into()),
             AppError::HttpClient(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
             AppError::Json(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-        };
+        }
+    }
 
-        let body = Json(json!({
-            "error": {
-                "message": message,
-                "type": format!("{:?}", status),
-            }
-        }));
+    /// Attach a `retry-after` header (whole seconds, at least 1) for rate-limit errors.
+    fn with_retry_after(&self, mut response: Response) -> Response {
+        if let AppError::RateLimited { retry_after, .. } = self {
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(secs.max(1)),
+            );
+        }
+        response
+    }
+
+    /// The error in IR form. Upstream errors are translated from whichever
+    /// format the upstream answered in.
+    pub fn to_ir_error(&self) -> IrError {
+        if let AppError::Upstream { status, body } = self {
+            return chat::decode_upstream_error(*status, body);
+        }
+        let (status, message) = self.status_and_message();
+        IrError::new(status.as_u16(), message)
+    }
 
-        (status, body).into_response()
+    /// Render the error in the wire format of the API the caller used,
+    /// identified by its codec slug. Unknown slugs get the OpenAI shape.
+    pub fn into_response_for(self, format_slug: &str) -> Response {
+        let format = ChatFormat::from_str_loose(format_slug).unwrap_or(ChatFormat::OpenaiChat);
+        let (status, body) = chat::get_encoder(format).encode_error(&self.to_ir_error());
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+
+        self.with_retry_after((status, Json(body)).into_response())
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.into_response_for(ChatFormat::OpenaiChat.as_str())
     }
 }
@@ -6,15 +6,37 @@ mod modality;
 mod routing;
 mod rules;
 mod server;
+mod sse;
+mod tokenizer;
 mod video;
 
+use routing::retry::RetryPolicy;
+use rules::registry::RuleRegistry;
+use server::access::CachePricing;
 use sqlx::SqlitePool;
+use std::sync::Arc;
 use tauri::Manager;
 use tokio::sync::RwLock;
 
+/// Codec entry points for the Criterion benchmarks in `benches/`, which can
+/// only reach public items.
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::db::models::ConversionRule;
+    pub use crate::modality::chat::{get_decoder, get_encoder, ChatFormat, Decoder};
+    pub use crate::rules::engine;
+    pub use crate::rules::registry::JsonataDecoder;
+}
+
 pub struct AppState {
     pub db: SqlitePool,
     pub config: RwLock<config::AppConfig>,
+    /// Codec registry shared with the proxy server.
+    pub registry: Arc<RuleRegistry>,
+    /// Failover policy shared with the proxy server, refreshed on config updates.
+    pub retry: Arc<std::sync::RwLock<RetryPolicy>>,
+    /// Prompt-cache pricing shared with the proxy server, refreshed on config updates.
+    pub pricing: Arc<std::sync::RwLock<CachePricing>>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -51,6 +73,7 @@ pub fn run() {
             commands::model_mappings::delete_model_mapping,
             commands::request_logs::list_request_logs,
             commands::request_logs::get_request_log,
+            commands::request_logs::list_request_log_attempts,
             commands::request_logs::clear_request_logs,
             commands::request_logs::get_usage_stats,
             commands::request_logs::retry_request_log,
@@ -68,6 +91,7 @@ pub fn run() {
             commands::rules::delete_conversion_rule,
             commands::rules::duplicate_conversion_rule,
             commands::rules::validate_rule_templates,
+            commands::rules::run_rule_tests,
             commands::rules::test_rule_template,
             commands::rules::fetch_rule_store_index,
             commands::rules::install_rule_from_store,
@@ -99,17 +123,26 @@ pub fn run() {
                     .await
                     .unwrap_or_default();
                 let server_port = config.server_port;
+                rules::engine::set_limits(rules::engine::Limits::from_config(&config));
+
+                let registry = Arc::new(RuleRegistry::new());
+                registry.load_from_db(&pool).await;
+                let retry = Arc::new(std::sync::RwLock::new(RetryPolicy::from_config(&config)));
+                let pricing = Arc::new(std::sync::RwLock::new(CachePricing::from_config(&config)));
 
                 let state = AppState {
                     db: pool.clone(),
                     config: RwLock::new(config),
+                    registry: registry.clone(),
+                    retry: retry.clone(),
+                    pricing: pricing.clone(),
                 };
                 app_handle.manage(state);
                 app_handle.manage(video::downloader::DownloadManager::new());
 
                 // Start Axum HTTP server in background
                 tauri::async_runtime::spawn(async move {
-                    if let Err(e) = server::start(pool, server_port).await {
+                    if let Err(e) = server::start(pool, registry, retry, pricing, server_port).await {
                         log::error!("Axum server error: {}", e);
                     }
                 });
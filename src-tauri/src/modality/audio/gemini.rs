@@ -0,0 +1,171 @@
+use super::ir::*;
+use crate::error::AppError;
+use crate::modality::chat::ir::{
+    IrChatRequest, IrChatResponse, IrContent, IrContentPart, IrMessage, IrRole,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+/// Gemini has no dedicated speech-to-text endpoint; transcription and
+/// translation are chat requests with the audio attached inline, sent through
+/// the Gemini chat codec.
+pub struct GeminiAudioCodec;
+
+impl GeminiAudioCodec {
+    /// Build the chat request that asks the model for a transcript.
+    /// Subtitle formats (`srt`, `vtt`) need timestamps and are rejected.
+    pub fn transcription_to_chat(
+        &self,
+        ir: &IrTranscriptionRequest,
+    ) -> Result<IrChatRequest, AppError> {
+        if let Some(format @ ("srt" | "vtt")) = ir.response_format.as_deref() {
+            return Err(AppError::Codec(format!(
+                "response_format '{}' is not supported by this channel",
+                format
+            )));
+        }
+
+        let mut instruction = match (ir.task, ir.language.as_deref()) {
+            (IrTranscriptionTask::Translate, _) => {
+                "Translate the speech in this audio into English.".to_string()
+            }
+            (IrTranscriptionTask::Transcribe, Some(lang)) => format!(
+                "Transcribe the speech in this audio verbatim. The spoken language is '{}'.",
+                lang
+            ),
+            (IrTranscriptionTask::Transcribe, None) => {
+                "Transcribe the speech in this audio verbatim.".to_string()
+            }
+        };
+        instruction.push_str(" Reply with the text only, without any commentary.");
+        if let Some(prompt) = &ir.prompt {
+            instruction.push_str(&format!(
+                "\nContext that may help with spelling and style: {}",
+                prompt
+            ));
+        }
+
+        Ok(IrChatRequest {
+            model: ir.model.clone(),
+            messages: vec![IrMessage {
+                role: IrRole::User,
+                content: IrContent::Parts(vec![
+                    IrContentPart::Text { text: instruction },
+                    IrContentPart::Audio {
+                        media_type: ir.audio.mime_type.clone(),
+                        data: BASE64.encode(&ir.audio.data),
+                    },
+                ]),
+                ..Default::default()
+            }],
+            temperature: ir.temperature,
+            ..Default::default()
+        })
+    }
+
+    /// Read the transcript out of the chat response.
+    pub fn transcription_from_chat(&self, resp: IrChatResponse) -> IrTranscriptionResponse {
+        IrTranscriptionResponse {
+            text: resp.message.content.to_text().trim().to_string(),
+            language: None,
+            usage: resp.usage.map(|u| IrAudioUsage {
+                input_tokens: u.prompt_tokens,
+                output_tokens: u.completion_tokens,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modality::chat::ir::IrUsage;
+
+    fn request(task: IrTranscriptionTask) -> IrTranscriptionRequest {
+        IrTranscriptionRequest {
+            model: "gemini-2.0-flash".into(),
+            task,
+            audio: IrAudioData {
+                filename: Some("clip.wav".into()),
+                mime_type: "audio/wav".into(),
+                data: b"RIFF".to_vec(),
+            },
+            language: Some("de".into()),
+            prompt: Some("Names: Jörg".into()),
+            response_format: None,
+            temperature: Some(0.0),
+        }
+    }
+
+    fn instruction(chat: &IrChatRequest) -> &str {
+        let IrContent::Parts(parts) = &chat.messages[0].content else {
+            panic!("expected parts");
+        };
+        let IrContentPart::Text { text } = &parts[0] else {
+            panic!("expected the instruction first");
+        };
+        text
+    }
+
+    #[test]
+    fn test_transcription_to_chat() {
+        let chat = GeminiAudioCodec.transcription_to_chat(&request(IrTranscriptionTask::Transcribe)).unwrap();
+        assert_eq!(chat.model, "gemini-2.0-flash");
+        assert_eq!(chat.temperature, Some(0.0));
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.messages[0].role, IrRole::User);
+        let text = instruction(&chat);
+        assert!(text.starts_with("Transcribe the speech in this audio verbatim. The spoken language is 'de'."));
+        assert!(text.ends_with("Context that may help with spelling and style: Names: Jörg"));
+        let IrContent::Parts(parts) = &chat.messages[0].content else {
+            panic!("expected parts");
+        };
+        assert!(matches!(
+            &parts[1],
+            IrContentPart::Audio { media_type, data } if media_type == "audio/wav" && data == "UklGRg=="
+        ));
+
+        // Translations are always into English, whatever the spoken language
+        let chat = GeminiAudioCodec.transcription_to_chat(&request(IrTranscriptionTask::Translate)).unwrap();
+        assert!(instruction(&chat).starts_with("Translate the speech in this audio into English."));
+    }
+
+    #[test]
+    fn test_subtitle_formats_are_rejected() {
+        for format in ["srt", "vtt"] {
+            let ir = IrTranscriptionRequest {
+                response_format: Some(format.into()),
+                ..request(IrTranscriptionTask::Transcribe)
+            };
+            let err = GeminiAudioCodec.transcription_to_chat(&ir).unwrap_err();
+            assert!(matches!(err, AppError::Codec(message) if message.contains(format)));
+        }
+        for format in ["text", "json", "verbose_json"] {
+            let ir = IrTranscriptionRequest {
+                response_format: Some(format.into()),
+                ..request(IrTranscriptionTask::Transcribe)
+            };
+            assert!(GeminiAudioCodec.transcription_to_chat(&ir).is_ok(), "{format}");
+        }
+    }
+
+    #[test]
+    fn test_transcription_from_chat() {
+        let resp = IrChatResponse {
+            id: "1".into(),
+            model: "gemini-2.0-flash".into(),
+            message: IrMessage {
+                role: IrRole::Assistant,
+                content: IrContent::Text("  Hallo Welt\n".into()),
+                ..Default::default()
+            },
+            finish_reason: None,
+            usage: Some(IrUsage { prompt_tokens: 40, completion_tokens: 4, ..Default::default() }),
+        };
+        let ir = GeminiAudioCodec.transcription_from_chat(resp);
+        assert_eq!(ir.text, "Hallo Welt");
+        assert!(ir.language.is_none());
+        let usage = ir.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (40, 4));
+    }
+}
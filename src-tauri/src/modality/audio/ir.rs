@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+/// IR Transcription Request — speech to text, optionally translated to English.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrTranscriptionRequest {
+    pub model: String,
+    pub task: IrTranscriptionTask,
+    pub audio: IrAudioData,
+    /// Input language as ISO-639-1, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Text to guide the model's style or vocabulary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// `json`, `text`, `srt`, `verbose_json` or `vtt`; defaults to `json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IrTranscriptionTask {
+    Transcribe,
+    /// Transcribe and translate into English.
+    Translate,
+}
+
+/// An uploaded audio file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrAudioData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub mime_type: String,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// IR Transcription Response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrTranscriptionResponse {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<IrAudioUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrAudioUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// IR Speech Request — text to speech.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrSpeechRequest {
+    pub model: String,
+    pub input: String,
+    pub voice: String,
+    /// Audio container: `mp3`, `opus`, `aac`, `flac`, `wav` or `pcm`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+    /// Voice direction (tone, accent) for models that support it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logged_requests_leave_out_the_audio() {
+        let ir = IrTranscriptionRequest {
+            model: "whisper-1".into(),
+            task: IrTranscriptionTask::Translate,
+            audio: IrAudioData {
+                filename: Some("clip.wav".into()),
+                mime_type: "audio/wav".into(),
+                data: vec![0; 1024],
+            },
+            language: None,
+            prompt: None,
+            response_format: Some("text".into()),
+            temperature: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&ir).unwrap(),
+            serde_json::json!({
+                "model": "whisper-1",
+                "task": "translate",
+                "audio": {"filename": "clip.wav", "mime_type": "audio/wav"},
+                "response_format": "text"
+            })
+        );
+    }
+}
@@ -0,0 +1,24 @@
+pub mod ir;
+pub mod openai;
+pub mod gemini;
+
+/// Identifies the upstream wire format for audio requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioFormat {
+    /// OpenAI Audio API (`/v1/audio/*`).
+    OpenAi,
+    /// Gemini audio understanding via `generateContent`, built on the chat IR.
+    /// Transcription and translation only.
+    Gemini,
+}
+
+impl AudioFormat {
+    /// The audio format spoken by a channel's provider.
+    pub fn from_provider(provider: &str) -> Self {
+        if super::is_gemini_provider(provider) {
+            Self::Gemini
+        } else {
+            Self::OpenAi
+        }
+    }
+}
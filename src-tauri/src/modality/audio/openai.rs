@@ -0,0 +1,364 @@
+use super::ir::*;
+use crate::error::AppError;
+use crate::modality::MultipartField;
+use serde::{Deserialize, Serialize};
+
+/// OpenAI Audio API codec: transcriptions, translations and speech.
+pub struct OpenAiAudioCodec;
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiTranscription {
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<OaiAudioUsage>,
+}
+
+/// Token usage reported by `gpt-4o-transcribe` style models; whisper reports
+/// `{"type": "duration", "seconds": …}` instead, which has no token counts.
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiAudioUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiSpeechRequest {
+    #[serde(default)]
+    model: String,
+    input: String,
+    voice: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    speed: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    instructions: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Transcription / translation
+// ---------------------------------------------------------------------------
+
+impl OpenAiAudioCodec {
+    /// Decode a `/v1/audio/transcriptions` or `/v1/audio/translations`
+    /// multipart body. The audio is sent as `file`.
+    pub fn decode_transcription_request(
+        &self,
+        fields: Vec<MultipartField>,
+        task: IrTranscriptionTask,
+    ) -> Result<IrTranscriptionRequest, AppError> {
+        let mut model = String::new();
+        let mut audio = None;
+        let mut language = None;
+        let mut prompt = None;
+        let mut response_format = None;
+        let mut temperature = None;
+
+        for field in fields {
+            match field.name.as_str() {
+                "file" => {
+                    audio = Some(IrAudioData {
+                        mime_type: field
+                            .content_type
+                            .clone()
+                            .filter(|t| t != "application/octet-stream")
+                            .or_else(|| field.filename.as_deref().and_then(mime_from_filename))
+                            .unwrap_or_else(|| "application/octet-stream".to_string()),
+                        filename: field.filename,
+                        data: field.data.to_vec(),
+                    })
+                }
+                "model" => model = field.text(),
+                "language" => language = Some(field.text()),
+                "prompt" => prompt = Some(field.text()),
+                "response_format" => response_format = Some(field.text()),
+                "temperature" => {
+                    let value = field.text();
+                    temperature = Some(value.parse().map_err(|_| {
+                        AppError::Codec(format!("Invalid temperature: {}", value))
+                    })?)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(IrTranscriptionRequest {
+            model,
+            task,
+            audio: audio.ok_or_else(|| AppError::Codec("Missing audio file".to_string()))?,
+            language,
+            prompt,
+            response_format,
+            temperature,
+        })
+    }
+
+    /// Encode a transcription request as multipart for upstream.
+    pub fn encode_transcription_request(
+        &self,
+        ir: &IrTranscriptionRequest,
+        model: &str,
+    ) -> Result<reqwest::multipart::Form, AppError> {
+        let file = reqwest::multipart::Part::bytes(ir.audio.data.clone())
+            .file_name(ir.audio.filename.clone().unwrap_or_else(|| "audio".to_string()))
+            .mime_str(&ir.audio.mime_type)
+            .map_err(|e| AppError::Codec(format!("Invalid audio type: {}", e)))?;
+
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", model.to_string())
+            .part("file", file);
+        // Translations are always into English and take no language
+        if let (Some(language), IrTranscriptionTask::Transcribe) = (&ir.language, ir.task) {
+            form = form.text("language", language.clone());
+        }
+        if let Some(prompt) = &ir.prompt {
+            form = form.text("prompt", prompt.clone());
+        }
+        if let Some(format) = &ir.response_format {
+            form = form.text("response_format", format.clone());
+        }
+        if let Some(temperature) = ir.temperature {
+            form = form.text("temperature", temperature.to_string());
+        }
+        Ok(form)
+    }
+
+    /// Decode an upstream transcription. JSON bodies (`json`, `verbose_json`)
+    /// are parsed; `text`, `srt` and `vtt` bodies are taken as the text.
+    pub fn decode_transcription_response(
+        &self,
+        body: &[u8],
+    ) -> Result<IrTranscriptionResponse, AppError> {
+        match serde_json::from_slice::<OaiTranscription>(body) {
+            Ok(resp) => Ok(IrTranscriptionResponse {
+                text: resp.text,
+                language: resp.language,
+                usage: resp.usage.map(|u| IrAudioUsage {
+                    input_tokens: u.input_tokens,
+                    output_tokens: u.output_tokens,
+                }),
+            }),
+            Err(_) => Ok(IrTranscriptionResponse {
+                text: String::from_utf8_lossy(body).to_string(),
+                language: None,
+                usage: None,
+            }),
+        }
+    }
+
+    /// Encode a transcription for the client in the requested
+    /// `response_format`. Returns the content type and body.
+    pub fn encode_transcription_response(
+        &self,
+        ir: &IrTranscriptionResponse,
+        response_format: Option<&str>,
+    ) -> Result<(&'static str, Vec<u8>), AppError> {
+        match response_format.unwrap_or("json") {
+            "text" => Ok(("text/plain; charset=utf-8", ir.text.clone().into_bytes())),
+            "json" | "verbose_json" => {
+                let resp = OaiTranscription {
+                    text: ir.text.clone(),
+                    language: ir.language.clone(),
+                    usage: ir.usage.as_ref().map(|u| OaiAudioUsage {
+                        input_tokens: u.input_tokens,
+                        output_tokens: u.output_tokens,
+                    }),
+                };
+                let body = serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))?;
+                Ok(("application/json", body))
+            }
+            other => Err(AppError::Codec(format!(
+                "response_format '{}' is not supported by this channel",
+                other
+            ))),
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Speech
+    // -----------------------------------------------------------------------
+
+    pub fn decode_speech_request(&self, body: &[u8]) -> Result<IrSpeechRequest, AppError> {
+        let req: OaiSpeechRequest =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(IrSpeechRequest {
+            model: req.model,
+            input: req.input,
+            voice: req.voice,
+            response_format: req.response_format,
+            speed: req.speed,
+            instructions: req.instructions,
+        })
+    }
+
+    pub fn encode_speech_request(&self, ir: &IrSpeechRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        let req = OaiSpeechRequest {
+            model: model.to_string(),
+            input: ir.input.clone(),
+            voice: ir.voice.clone(),
+            response_format: ir.response_format.clone(),
+            speed: ir.speed,
+            instructions: ir.instructions.clone(),
+        };
+        serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+/// Guess an audio MIME type from a file extension.
+pub fn mime_from_filename(filename: &str) -> Option<String> {
+    let ext = filename.rsplit_once('.')?.1.to_lowercase();
+    let mime = match ext.as_str() {
+        "mp3" | "mpga" | "mpeg" => "audio/mpeg",
+        "mp4" | "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "webm" => "audio/webm",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "aac" => "audio/aac",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn field(name: &str, value: &str) -> MultipartField {
+        MultipartField {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            data: Bytes::from(value.to_string()),
+        }
+    }
+
+    fn file(filename: Option<&str>, content_type: Option<&str>) -> MultipartField {
+        MultipartField {
+            name: "file".into(),
+            filename: filename.map(Into::into),
+            content_type: content_type.map(Into::into),
+            data: Bytes::from_static(b"RIFF"),
+        }
+    }
+
+    fn decode(fields: Vec<MultipartField>) -> Result<IrTranscriptionRequest, AppError> {
+        OpenAiAudioCodec.decode_transcription_request(fields, IrTranscriptionTask::Transcribe)
+    }
+
+    fn transcript() -> IrTranscriptionResponse {
+        IrTranscriptionResponse {
+            text: "Hello there".into(),
+            language: Some("en".into()),
+            usage: Some(IrAudioUsage { input_tokens: 12, output_tokens: 3 }),
+        }
+    }
+
+    #[test]
+    fn test_decode_transcription_request() {
+        let ir = decode(vec![
+            field("model", " whisper-1 "),
+            file(Some("clip.wav"), Some("audio/x-wav")),
+            field("language", "de"),
+            field("prompt", "Names: Jörg"),
+            field("response_format", "verbose_json"),
+            field("temperature", "0.2"),
+            field("unknown", "ignored"),
+        ])
+        .unwrap();
+        assert_eq!(ir.model, "whisper-1");
+        assert_eq!(ir.audio.mime_type, "audio/x-wav");
+        assert_eq!(ir.audio.filename.as_deref(), Some("clip.wav"));
+        assert_eq!(ir.audio.data, b"RIFF");
+        assert_eq!(ir.language.as_deref(), Some("de"));
+        assert_eq!(ir.prompt.as_deref(), Some("Names: Jörg"));
+        assert_eq!(ir.response_format.as_deref(), Some("verbose_json"));
+        assert_eq!(ir.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn test_file_type_falls_back_to_the_filename() {
+        let generic = decode(vec![file(Some("clip.MP3"), Some("application/octet-stream"))]).unwrap();
+        assert_eq!(generic.audio.mime_type, "audio/mpeg");
+        let untyped = decode(vec![file(Some("clip.m4a"), None)]).unwrap();
+        assert_eq!(untyped.audio.mime_type, "audio/mp4");
+        let unknown = decode(vec![file(Some("clip.xyz"), None)]).unwrap();
+        assert_eq!(unknown.audio.mime_type, "application/octet-stream");
+        let nameless = decode(vec![file(None, None)]).unwrap();
+        assert_eq!(nameless.audio.mime_type, "application/octet-stream");
+
+        assert_eq!(mime_from_filename("a.b.flac").as_deref(), Some("audio/flac"));
+        assert_eq!(mime_from_filename("noextension"), None);
+    }
+
+    #[test]
+    fn test_invalid_transcription_requests_are_rejected() {
+        let err = decode(vec![file(Some("clip.wav"), None), field("temperature", "warm")]).unwrap_err();
+        assert!(matches!(err, AppError::Codec(message) if message == "Invalid temperature: warm"));
+
+        let err = decode(vec![field("model", "whisper-1")]).unwrap_err();
+        assert!(matches!(err, AppError::Codec(message) if message == "Missing audio file"));
+    }
+
+    #[test]
+    fn test_decode_transcription_response() {
+        let json = br#"{"text":"Hi","language":"en","usage":{"type":"tokens","input_tokens":7,"output_tokens":2}}"#;
+        let ir = OpenAiAudioCodec.decode_transcription_response(json).unwrap();
+        assert_eq!(ir.text, "Hi");
+        assert_eq!(ir.language.as_deref(), Some("en"));
+        let usage = ir.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (7, 2));
+
+        let srt = b"1\n00:00:00,000 --> 00:00:01,000\nHi\n";
+        let ir = OpenAiAudioCodec.decode_transcription_response(srt).unwrap();
+        assert_eq!(ir.text.as_bytes(), srt);
+        assert!(ir.usage.is_none());
+    }
+
+    #[test]
+    fn test_encode_transcription_response_formats() {
+        let ir = transcript();
+
+        let (content_type, body) = OpenAiAudioCodec.encode_transcription_response(&ir, Some("text")).unwrap();
+        assert_eq!(content_type, "text/plain; charset=utf-8");
+        assert_eq!(body, b"Hello there");
+
+        let expected = serde_json::json!({
+            "text": "Hello there",
+            "language": "en",
+            "usage": {"input_tokens": 12, "output_tokens": 3}
+        });
+        for format in [None, Some("json"), Some("verbose_json")] {
+            let (content_type, body) = OpenAiAudioCodec.encode_transcription_response(&ir, format).unwrap();
+            assert_eq!(content_type, "application/json");
+            assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(), expected);
+        }
+
+        let err = OpenAiAudioCodec.encode_transcription_response(&ir, Some("srt")).unwrap_err();
+        assert!(matches!(err, AppError::Codec(message) if message.contains("'srt'")));
+    }
+
+    #[test]
+    fn test_speech_request_round_trip() {
+        let body = br#"{"model":"tts","input":"Hello","voice":"alloy","response_format":"opus","speed":1.5}"#;
+        let ir = OpenAiAudioCodec.decode_speech_request(body).unwrap();
+        assert_eq!((ir.input.as_str(), ir.voice.as_str(), ir.speed), ("Hello", "alloy", Some(1.5)));
+
+        let encoded = OpenAiAudioCodec.encode_speech_request(&ir, "tts-1-hd").unwrap();
+        let encoded: serde_json::Value = serde_json::from_slice(&encoded).unwrap();
+        assert_eq!(
+            encoded,
+            serde_json::json!({"model": "tts-1-hd", "input": "Hello", "voice": "alloy", "response_format": "opus", "speed": 1.5})
+        );
+        assert!(OpenAiAudioCodec.decode_speech_request(br#"{"input":"Hello"}"#).is_err());
+    }
+}
@@ -1,9 +1,19 @@
 use super::ir::*;
-use super::{Decoder, Encoder};
+use super::{structured_output, ChatFormat, Decoder, Encoder};
 use crate::error::AppError;
+use crate::sse::SseEvent;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-
-pub struct AnthropicCodec;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Anthropic Messages codec. An instance encodes at most one stream: it
+/// tracks the open content block so every block gets its own index.
+#[derive(Default)]
+pub struct AnthropicCodec {
+    stream: Mutex<StreamBlocks>,
+}
 
 // --- Anthropic Wire Types (Request) ---
 
@@ -11,14 +21,18 @@ pub struct AnthropicCodec;
 pub struct AnthropicRequest {
     pub model: String,
     pub messages: Vec<AnthropicMessage>,
+    /// Required by the Messages API, absent in count_tokens requests (decoded as 0).
+    #[serde(default)]
     pub max_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<String>,
+    pub system: Option<AnthropicSystem>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequences: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
@@ -26,6 +40,37 @@ pub struct AnthropicRequest {
     pub tools: Option<Vec<AnthropicTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<AnthropicToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<AnthropicThinking>,
+    /// Fields without an IR counterpart (`metadata`, `service_tier`, ...).
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// The system prompt is a plain string or a list of text blocks, which may
+/// carry cache breakpoints.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicSystem {
+    Text(String),
+    Blocks(Vec<AnthropicSystemBlock>),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnthropicSystemBlock {
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<AnthropicCacheControl>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnthropicCacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,6 +85,8 @@ pub struct AnthropicTool {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub input_schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<AnthropicCacheControl>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +97,15 @@ pub struct AnthropicToolChoice {
     pub name: Option<String>,
 }
 
+/// Extended thinking configuration; `budget_tokens` is required when enabled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnthropicThinking {
+    #[serde(rename = "type")]
+    pub thinking_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
+}
+
 // --- Anthropic Wire Types (Response) ---
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -76,12 +132,26 @@ pub enum AnthropicContentBlock {
         name: String,
         input: serde_json::Value,
     },
+    #[serde(rename = "thinking")]
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnthropicUsage {
+    /// Input tokens after the last cache breakpoint; cached ones are counted
+    /// separately below.
     pub input_tokens: u32,
     pub output_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_input_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_input_tokens: Option<u32>,
 }
 
 // --- Streaming event types ---
@@ -113,6 +183,8 @@ pub struct StreamContentBlockDelta {
     pub delta: StreamDelta,
 }
 
+/// Variant names mirror the wire `type` values.
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum StreamDelta {
@@ -120,6 +192,10 @@ pub enum StreamDelta {
     TextDelta { text: String },
     #[serde(rename = "input_json_delta")]
     InputJsonDelta { partial_json: String },
+    #[serde(rename = "thinking_delta")]
+    ThinkingDelta { thinking: String },
+    #[serde(rename = "signature_delta")]
+    SignatureDelta { signature: String },
 }
 
 #[derive(Debug, Deserialize)]
@@ -159,6 +235,151 @@ fn ir_finish_to_anthropic(reason: &Option<IrFinishReason>) -> Option<String> {
     })
 }
 
+/// Thinking budget sent when only "reasoning on" is known.
+const DEFAULT_THINKING_BUDGET: u32 = 8192;
+
+/// Minimum `budget_tokens` accepted by the Messages API.
+const MIN_THINKING_BUDGET: u32 = 1024;
+
+fn anthropic_thinking_to_ir(thinking: &AnthropicThinking) -> IrReasoningConfig {
+    match thinking.thinking_type.as_str() {
+        "disabled" => IrReasoningConfig {
+            effort: Some(IrReasoningEffort::None),
+            budget_tokens: None,
+        },
+        _ => IrReasoningConfig {
+            effort: None,
+            budget_tokens: thinking.budget_tokens,
+        },
+    }
+}
+
+fn ir_reasoning_to_anthropic(config: &IrReasoningConfig) -> AnthropicThinking {
+    if config.is_disabled() {
+        return AnthropicThinking {
+            thinking_type: "disabled".to_string(),
+            budget_tokens: None,
+        };
+    }
+    AnthropicThinking {
+        thinking_type: "enabled".to_string(),
+        budget_tokens: Some(
+            config
+                .budget_tokens()
+                .unwrap_or(DEFAULT_THINKING_BUDGET)
+                .max(MIN_THINKING_BUDGET),
+        ),
+    }
+}
+
+/// Collect thinking and redacted_thinking blocks of a message.
+fn anthropic_reasoning_to_ir(content: &serde_json::Value) -> Option<Vec<IrReasoning>> {
+    let blocks = content.as_array()?;
+    let reasoning: Vec<IrReasoning> = blocks
+        .iter()
+        .filter_map(|block| {
+            let str_field = |key: &str| block.get(key).and_then(|v| v.as_str()).map(String::from);
+            match block.get("type").and_then(|t| t.as_str())? {
+                "thinking" => Some(IrReasoning {
+                    text: str_field("thinking").unwrap_or_default(),
+                    signature: str_field("signature"),
+                    source: Some(ChatFormat::Anthropic),
+                    ..Default::default()
+                }),
+                "redacted_thinking" => Some(IrReasoning {
+                    encrypted: str_field("data"),
+                    source: Some(ChatFormat::Anthropic),
+                    ..Default::default()
+                }),
+                _ => None,
+            }
+        })
+        .collect();
+    (!reasoning.is_empty()).then_some(reasoning)
+}
+
+/// Thinking blocks to send back upstream. Blocks without a signature issued by
+/// Anthropic would be rejected and are dropped.
+fn ir_reasoning_to_anthropic_blocks(reasoning: &Option<Vec<IrReasoning>>) -> Vec<serde_json::Value> {
+    reasoning
+        .iter()
+        .flatten()
+        .filter_map(|r| {
+            if let Some(data) = r.encrypted_for(ChatFormat::Anthropic) {
+                return Some(serde_json::json!({"type": "redacted_thinking", "data": data}));
+            }
+            let signature = r.signature_for(ChatFormat::Anthropic).filter(|s| !s.is_empty())?;
+            Some(serde_json::json!({
+                "type": "thinking",
+                "thinking": r.text,
+                "signature": signature,
+            }))
+        })
+        .collect()
+}
+
+fn anthropic_usage_to_ir(u: &AnthropicUsage) -> IrUsage {
+    let cache_read = u.cache_read_input_tokens.unwrap_or(0);
+    let cache_write = u.cache_creation_input_tokens.unwrap_or(0);
+    let prompt_tokens = u.input_tokens + cache_read + cache_write;
+    IrUsage {
+        prompt_tokens,
+        completion_tokens: u.output_tokens,
+        total_tokens: Some(prompt_tokens + u.output_tokens),
+        reasoning_tokens: None,
+        cache_read_tokens: u.cache_read_input_tokens,
+        cache_write_tokens: u.cache_creation_input_tokens,
+    }
+}
+
+fn ir_usage_to_anthropic(u: &IrUsage) -> AnthropicUsage {
+    let cached = u.cache_read_tokens.unwrap_or(0) + u.cache_write_tokens.unwrap_or(0);
+    AnthropicUsage {
+        input_tokens: u.prompt_tokens.saturating_sub(cached),
+        output_tokens: u.completion_tokens,
+        cache_creation_input_tokens: u.cache_write_tokens,
+        cache_read_input_tokens: u.cache_read_tokens,
+    }
+}
+
+fn anthropic_cache_control_to_ir(cc: &AnthropicCacheControl) -> IrCacheControl {
+    IrCacheControl { ttl: cc.ttl.clone() }
+}
+
+fn ir_cache_control_to_anthropic(cc: &IrCacheControl) -> AnthropicCacheControl {
+    AnthropicCacheControl {
+        cache_type: "ephemeral".to_string(),
+        ttl: cc.ttl.clone(),
+    }
+}
+
+/// Cache breakpoint of a content block.
+fn block_cache_control(block: &serde_json::Value) -> Option<IrCacheControl> {
+    let cc: AnthropicCacheControl = serde_json::from_value(block.get("cache_control")?.clone()).ok()?;
+    Some(anthropic_cache_control_to_ir(&cc))
+}
+
+/// The last cache breakpoint among a message's blocks (except tool results,
+/// which become messages of their own). The IR keeps one per message, at its end.
+fn message_cache_control(content: &serde_json::Value) -> Option<IrCacheControl> {
+    content
+        .as_array()?
+        .iter()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) != Some("tool_result"))
+        .filter_map(block_cache_control)
+        .next_back()
+}
+
+/// Put a cache breakpoint on the last block of a message.
+fn set_cache_control(blocks: &mut [serde_json::Value], cache_control: &Option<IrCacheControl>) {
+    if let (Some(cc), Some(serde_json::Value::Object(block))) = (cache_control, blocks.last_mut()) {
+        block.insert(
+            "cache_control".to_string(),
+            serde_json::json!(ir_cache_control_to_anthropic(cc)),
+        );
+    }
+}
+
 /// Convert Anthropic content (string or array of blocks) to IR content + tool_calls.
 fn anthropic_content_to_ir(
     content: &serde_json::Value,
@@ -181,19 +402,20 @@ fn anthropic_content_to_ir(
                     }
                     "image" => {
                         if let Some(source) = block.get("source") {
+                            let str_field =
+                                |key: &str| source.get(key).and_then(|v| v.as_str()).map(String::from);
                             parts.push(IrContentPart::Image {
-                                url: None,
-                                media_type: source
-                                    .get("media_type")
-                                    .and_then(|m| m.as_str())
-                                    .map(String::from),
-                                data: source
-                                    .get("data")
-                                    .and_then(|d| d.as_str())
-                                    .map(String::from),
+                                url: str_field("url"),
+                                media_type: str_field("media_type"),
+                                data: str_field("data"),
                             });
                         }
                     }
+                    "document" => {
+                        if let Some(source) = block.get("source") {
+                            parts.push(anthropic_document_to_ir(block, source));
+                        }
+                    }
                     "tool_use" => {
                         let id = block
                             .get("id")
@@ -246,48 +468,135 @@ fn anthropic_content_to_ir(
     }
 }
 
+/// Convert a `document` block. Plain-text sources are base64-encoded so that
+/// IR file data is always base64.
+fn anthropic_document_to_ir(block: &serde_json::Value, source: &serde_json::Value) -> IrContentPart {
+    let str_field = |key: &str| source.get(key).and_then(|v| v.as_str()).map(String::from);
+    let source_type = source.get("type").and_then(|t| t.as_str()).unwrap_or("");
+    let (media_type, data) = match source_type {
+        "base64" => (str_field("media_type"), str_field("data")),
+        "text" => (
+            Some(str_field("media_type").unwrap_or_else(|| "text/plain".to_string())),
+            str_field("data").map(|text| BASE64.encode(text)),
+        ),
+        _ => (None, None),
+    };
+    IrContentPart::File {
+        media_type,
+        data,
+        url: str_field("url"),
+        file_id: str_field("file_id"),
+        filename: block.get("title").and_then(|t| t.as_str()).map(String::from),
+    }
+}
+
+/// Convert an IR file part to a `document` block. Anthropic reads PDFs and
+/// plain text only.
+fn ir_file_to_anthropic(
+    media_type: &Option<String>,
+    data: &Option<String>,
+    url: &Option<String>,
+    file_id: &Option<String>,
+    filename: &Option<String>,
+) -> Result<serde_json::Value, AppError> {
+    let source = match (file_id, data, url) {
+        (Some(id), _, _) => serde_json::json!({"type": "file", "file_id": id}),
+        (None, Some(data), _) => match media_type.as_deref().unwrap_or("application/pdf") {
+            "application/pdf" => serde_json::json!({
+                "type": "base64",
+                "media_type": "application/pdf",
+                "data": data,
+            }),
+            "text/plain" => {
+                let text = BASE64
+                    .decode(data)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| {
+                        AppError::BadRequest("Text file data is not base64-encoded UTF-8".to_string())
+                    })?;
+                serde_json::json!({"type": "text", "media_type": "text/plain", "data": text})
+            }
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Anthropic does not accept {} files",
+                    other
+                )))
+            }
+        },
+        (None, None, Some(url)) => serde_json::json!({"type": "url", "url": url}),
+        (None, None, None) => {
+            return Err(AppError::BadRequest(
+                "File part has neither data, a URL nor a file id".to_string(),
+            ))
+        }
+    };
+    let mut block = serde_json::json!({"type": "document", "source": source});
+    if let Some(name) = filename {
+        block["title"] = serde_json::Value::String(name.clone());
+    }
+    Ok(block)
+}
+
 /// Convert IR content to Anthropic content blocks array.
-fn ir_content_to_anthropic(content: &IrContent) -> Vec<serde_json::Value> {
+fn ir_content_to_anthropic(content: &IrContent) -> Result<Vec<serde_json::Value>, AppError> {
     match content {
         IrContent::Text(s) => {
             if s.is_empty() {
-                vec![]
+                Ok(vec![])
             } else {
-                vec![serde_json::json!({"type": "text", "text": s})]
+                Ok(vec![serde_json::json!({"type": "text", "text": s})])
             }
         }
         IrContent::Parts(parts) => parts
             .iter()
             .map(|p| match p {
                 IrContentPart::Text { text } => {
-                    serde_json::json!({"type": "text", "text": text})
+                    Ok(serde_json::json!({"type": "text", "text": text}))
                 }
                 IrContentPart::Image {
                     url,
                     media_type,
                     data,
                 } => {
-                    if let Some(data) = data {
-                        serde_json::json!({
+                    // Data URLs are sent as base64 sources
+                    let inline = match (data, url) {
+                        (Some(data), _) => Some((
+                            media_type.clone().unwrap_or_else(|| "image/png".to_string()),
+                            data.clone(),
+                        )),
+                        (None, Some(url)) => parse_data_url(url),
+                        (None, None) => None,
+                    };
+                    if let Some((media_type, data)) = inline {
+                        Ok(serde_json::json!({
                             "type": "image",
                             "source": {
                                 "type": "base64",
-                                "media_type": media_type.as_deref().unwrap_or("image/png"),
+                                "media_type": media_type,
                                 "data": data,
                             }
-                        })
+                        }))
                     } else if let Some(url) = url {
-                        serde_json::json!({
+                        Ok(serde_json::json!({
                             "type": "image",
                             "source": {
                                 "type": "url",
                                 "url": url,
                             }
-                        })
+                        }))
                     } else {
-                        serde_json::json!({"type": "text", "text": "[image]"})
+                        Err(AppError::BadRequest(
+                            "Image part has neither data nor a URL".to_string(),
+                        ))
                     }
                 }
+                IrContentPart::Audio { .. } => Err(AppError::BadRequest(
+                    "Anthropic does not accept audio input".to_string(),
+                )),
+                IrContentPart::File { media_type, data, url, file_id, filename } => {
+                    ir_file_to_anthropic(media_type, data, url, file_id, filename)
+                }
             })
             .collect(),
     }
@@ -295,6 +604,88 @@ fn ir_content_to_anthropic(content: &IrContent) -> Vec<serde_json::Value> {
 
 // --- Decoder impl ---
 
+// --- Stream Encoding State ---
+
+/// Kinds of content block a stream can open. Tool blocks are keyed by the
+/// IR tool call index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Thinking,
+    RedactedThinking,
+    Text,
+    ToolUse(u32),
+}
+
+/// Content blocks of the stream being encoded. Anthropic clients expect each
+/// block to be started, receive its deltas and be stopped before the next one
+/// starts, with indices counting up from 0.
+#[derive(Debug, Default)]
+struct StreamBlocks {
+    /// The block currently receiving deltas and its index.
+    current: Option<(Block, u32)>,
+    next_index: u32,
+}
+
+impl StreamBlocks {
+    /// Index of `block`, starting it (with empty content) if it is not the
+    /// block currently open.
+    fn open(&mut self, block: Block, events: &mut Vec<SseEvent>) -> Result<u32, AppError> {
+        match self.current {
+            Some((current, index)) if current == block => Ok(index),
+            _ => {
+                let content_block = match block {
+                    Block::Thinking => serde_json::json!({ "type": "thinking", "thinking": "" }),
+                    Block::Text => serde_json::json!({ "type": "text", "text": "" }),
+                    Block::RedactedThinking => serde_json::json!({ "type": "redacted_thinking", "data": "" }),
+                    Block::ToolUse(_) => serde_json::json!({ "type": "tool_use", "id": "", "name": "", "input": {} }),
+                };
+                self.start(block, content_block, events)
+            }
+        }
+    }
+
+    fn is_open(&self, block: Block) -> bool {
+        self.current.is_some_and(|(current, _)| current == block)
+    }
+
+    /// Stop the open block, if any, and start a new one.
+    fn start(&mut self, block: Block, content_block: serde_json::Value, events: &mut Vec<SseEvent>) -> Result<u32, AppError> {
+        self.close(events)?;
+        let index = self.next_index;
+        self.next_index += 1;
+        self.current = Some((block, index));
+        events.push(named_event("content_block_start", serde_json::json!({
+            "type": "content_block_start",
+            "index": index,
+            "content_block": content_block,
+        }))?);
+        Ok(index)
+    }
+
+    fn close(&mut self, events: &mut Vec<SseEvent>) -> Result<(), AppError> {
+        if let Some((_, index)) = self.current.take() {
+            events.push(named_event("content_block_stop", serde_json::json!({
+                "type": "content_block_stop",
+                "index": index,
+            }))?);
+        }
+        Ok(())
+    }
+}
+
+fn block_delta(index: u32, delta: serde_json::Value) -> Result<SseEvent, AppError> {
+    named_event("content_block_delta", serde_json::json!({
+        "type": "content_block_delta",
+        "index": index,
+        "delta": delta,
+    }))
+}
+
+fn named_event(name: &str, data: serde_json::Value) -> Result<SseEvent, AppError> {
+    let data = serde_json::to_string(&data).map_err(|e| AppError::Codec(e.to_string()))?;
+    Ok(SseEvent::named(name, data))
+}
+
 impl Decoder for AnthropicCodec {
     fn decode_request(&self, body: &[u8]) -> Result<IrChatRequest, AppError> {
         let req: AnthropicRequest =
@@ -333,6 +724,8 @@ impl Decoder for AnthropicCodec {
                                     tool_calls: None,
                                     tool_call_id: Some(tool_use_id),
                                     name: None,
+                                    reasoning: None,
+                                    cache_control: block_cache_control(block),
                                 });
                             }
                         }
@@ -348,6 +741,8 @@ impl Decoder for AnthropicCodec {
                                 tool_calls: None,
                                 tool_call_id: None,
                                 name: None,
+                                reasoning: None,
+                                cache_control: message_cache_control(&msg.content),
                             });
                         }
                         continue;
@@ -366,6 +761,8 @@ impl Decoder for AnthropicCodec {
                     tool_calls,
                     tool_call_id: None,
                     name: None,
+                    reasoning: anthropic_reasoning_to_ir(&msg.content),
+                    cache_control: message_cache_control(&msg.content),
                 });
             }
         }
@@ -376,6 +773,7 @@ impl Decoder for AnthropicCodec {
                     name: t.name,
                     description: t.description,
                     parameters: t.input_schema,
+                    cache_control: t.cache_control.as_ref().map(anthropic_cache_control_to_ir),
                 })
                 .collect()
         });
@@ -389,18 +787,43 @@ impl Decoder for AnthropicCodec {
             _ => IrToolChoice::Auto,
         });
 
+        // System blocks are joined; the last breakpoint moves to the end
+        let (system, system_cache_control) = match req.system {
+            Some(AnthropicSystem::Text(text)) => (Some(text), None),
+            Some(AnthropicSystem::Blocks(blocks)) => {
+                let cache_control = blocks
+                    .iter()
+                    .filter_map(|b| b.cache_control.as_ref())
+                    .next_back()
+                    .map(anthropic_cache_control_to_ir);
+                let text = blocks.into_iter().map(|b| b.text).collect::<Vec<_>>().join("\n\n");
+                (Some(text), cache_control)
+            }
+            None => (None, None),
+        };
+
+        let extra = (!req.extra.is_empty()).then_some(req.extra);
+
         Ok(IrChatRequest {
             model: req.model,
             messages,
-            system: req.system,
+            system,
+            system_cache_control,
             temperature: req.temperature,
             top_p: req.top_p,
-            max_tokens: Some(req.max_tokens),
+            top_k: req.top_k,
+            max_tokens: (req.max_tokens > 0).then_some(req.max_tokens),
             stream: req.stream.unwrap_or(false),
             stop: req.stop_sequences,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
             tools,
             tool_choice,
-            extra: None,
+            reasoning: req.thinking.as_ref().map(anthropic_thinking_to_ir),
+            response_format: None,
+            extra_source: extra.as_ref().map(|_| ChatFormat::Anthropic),
+            extra,
         })
     }
 
@@ -410,12 +833,28 @@ impl Decoder for AnthropicCodec {
 
         let mut text_parts = Vec::new();
         let mut tool_calls = Vec::new();
+        let mut reasoning = Vec::new();
 
         for block in &resp.content {
             match block {
                 AnthropicContentBlock::Text { text } => {
                     text_parts.push(text.clone());
                 }
+                AnthropicContentBlock::Thinking { thinking, signature } => {
+                    reasoning.push(IrReasoning {
+                        text: thinking.clone(),
+                        signature: Some(signature.clone()),
+                        source: Some(ChatFormat::Anthropic),
+                        ..Default::default()
+                    });
+                }
+                AnthropicContentBlock::RedactedThinking { data } => {
+                    reasoning.push(IrReasoning {
+                        encrypted: Some(data.clone()),
+                        source: Some(ChatFormat::Anthropic),
+                        ..Default::default()
+                    });
+                }
                 AnthropicContentBlock::ToolUse { id, name, input } => {
                     tool_calls.push(IrToolCall {
                         id: id.clone(),
@@ -442,17 +881,15 @@ impl Decoder for AnthropicCodec {
                 tool_calls: tc,
                 tool_call_id: None,
                 name: None,
+                reasoning: (!reasoning.is_empty()).then_some(reasoning),
+                cache_control: None,
             },
             finish_reason: anthropic_stop_to_ir(&resp.stop_reason),
-            usage: resp.usage.map(|u| IrUsage {
-                prompt_tokens: u.input_tokens,
-                completion_tokens: u.output_tokens,
-                total_tokens: Some(u.input_tokens + u.output_tokens),
-            }),
+            usage: resp.usage.as_ref().map(anthropic_usage_to_ir),
         })
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+    fn decode_stream_chunk(&self, event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
         if data.trim().is_empty() || self.is_stream_done(data) {
             return Ok(None);
         }
@@ -461,10 +898,11 @@ impl Decoder for AnthropicCodec {
         let v: serde_json::Value =
             serde_json::from_str(data).map_err(|e| AppError::Codec(e.to_string()))?;
 
-        let event_type = v
-            .get("type")
-            .and_then(|t| t.as_str())
-            .unwrap_or("");
+        // Relays that drop `event:` lines still have the type in the data
+        let event_type = match event {
+            "message" => v.get("type").and_then(|t| t.as_str()).unwrap_or(""),
+            name => name,
+        };
 
         match event_type {
             "message_start" => {
@@ -476,12 +914,9 @@ impl Decoder for AnthropicCodec {
                     delta_role: Some(IrRole::Assistant),
                     delta_content: None,
                     delta_tool_calls: None,
+                    delta_reasoning: None,
                     finish_reason: None,
-                    usage: evt.message.usage.map(|u| IrUsage {
-                        prompt_tokens: u.input_tokens,
-                        completion_tokens: u.output_tokens,
-                        total_tokens: Some(u.input_tokens + u.output_tokens),
-                    }),
+                    usage: evt.message.usage.as_ref().map(anthropic_usage_to_ir),
                 }))
             }
             "content_block_start" => {
@@ -500,10 +935,18 @@ impl Decoder for AnthropicCodec {
                                 name: Some(name.clone()),
                                 arguments: None,
                             }]),
+                            delta_reasoning: None,
                             finish_reason: None,
                             usage: None,
                         }))
                     }
+                    AnthropicContentBlock::RedactedThinking { data } => {
+                        Ok(Some(reasoning_chunk(IrReasoningDelta {
+                            encrypted: Some(data.clone()),
+                            source: Some(ChatFormat::Anthropic),
+                            ..Default::default()
+                        })))
+                    }
                     _ => Ok(None),
                 }
             }
@@ -517,6 +960,7 @@ impl Decoder for AnthropicCodec {
                         delta_role: None,
                         delta_content: Some(text.clone()),
                         delta_tool_calls: None,
+                        delta_reasoning: None,
                         finish_reason: None,
                         usage: None,
                     })),
@@ -532,10 +976,25 @@ impl Decoder for AnthropicCodec {
                                 name: None,
                                 arguments: Some(partial_json.clone()),
                             }]),
+                            delta_reasoning: None,
                             finish_reason: None,
                             usage: None,
                         }))
                     }
+                    StreamDelta::ThinkingDelta { thinking } => {
+                        Ok(Some(reasoning_chunk(IrReasoningDelta {
+                            text: Some(thinking.clone()),
+                            source: Some(ChatFormat::Anthropic),
+                            ..Default::default()
+                        })))
+                    }
+                    StreamDelta::SignatureDelta { signature } => {
+                        Ok(Some(reasoning_chunk(IrReasoningDelta {
+                            signature: Some(signature.clone()),
+                            source: Some(ChatFormat::Anthropic),
+                            ..Default::default()
+                        })))
+                    }
                 }
             }
             "message_delta" => {
@@ -547,11 +1006,15 @@ impl Decoder for AnthropicCodec {
                     delta_role: None,
                     delta_content: None,
                     delta_tool_calls: None,
+                    delta_reasoning: None,
                     finish_reason: anthropic_stop_to_ir(&evt.delta.stop_reason),
                     usage: evt.usage.map(|u| IrUsage {
                         prompt_tokens: 0,
                         completion_tokens: u.output_tokens,
                         total_tokens: None,
+                        reasoning_tokens: None,
+                        cache_read_tokens: None,
+                        cache_write_tokens: None,
                     }),
                 }))
             }
@@ -566,10 +1029,28 @@ impl Decoder for AnthropicCodec {
     }
 }
 
+fn reasoning_chunk(delta: IrReasoningDelta) -> IrStreamChunk {
+    IrStreamChunk {
+        id: String::new(),
+        model: None,
+        delta_role: None,
+        delta_content: None,
+        delta_tool_calls: None,
+        delta_reasoning: Some(delta),
+        finish_reason: None,
+        usage: None,
+    }
+}
+
 // --- Encoder impl ---
 
 impl Encoder for AnthropicCodec {
     fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        // No native JSON mode: ask for the reply as a forced tool call instead
+        if let Some(emulated) = structured_output::emulate_request(ir) {
+            return self.encode_request(&emulated, model);
+        }
+
         let mut messages = Vec::new();
 
         for msg in &ir.messages {
@@ -579,14 +1060,17 @@ impl Encoder for AnthropicCodec {
                     continue;
                 }
                 IrRole::User => {
-                    let content_blocks = ir_content_to_anthropic(&msg.content);
+                    let mut content_blocks = ir_content_to_anthropic(&msg.content)?;
+                    set_cache_control(&mut content_blocks, &msg.cache_control);
                     messages.push(AnthropicMessage {
                         role: "user".to_string(),
                         content: serde_json::Value::Array(content_blocks),
                     });
                 }
                 IrRole::Assistant => {
-                    let mut content_blocks = ir_content_to_anthropic(&msg.content);
+                    // Thinking blocks must precede the text and tool_use blocks
+                    let mut content_blocks = ir_reasoning_to_anthropic_blocks(&msg.reasoning);
+                    content_blocks.extend(ir_content_to_anthropic(&msg.content)?);
 
                     // Add tool_use blocks
                     if let Some(tcs) = &msg.tool_calls {
@@ -605,6 +1089,7 @@ impl Encoder for AnthropicCodec {
                     if content_blocks.is_empty() {
                         content_blocks.push(serde_json::json!({"type": "text", "text": ""}));
                     }
+                    set_cache_control(&mut content_blocks, &msg.cache_control);
 
                     messages.push(AnthropicMessage {
                         role: "assistant".to_string(),
@@ -614,11 +1099,12 @@ impl Encoder for AnthropicCodec {
                 IrRole::Tool => {
                     // Tool results become tool_result content blocks in a user message
                     let result_content = msg.content.to_text();
-                    let block = serde_json::json!({
+                    let mut block = serde_json::json!({
                         "type": "tool_result",
                         "tool_use_id": msg.tool_call_id.as_deref().unwrap_or(""),
                         "content": result_content,
                     });
+                    set_cache_control(std::slice::from_mut(&mut block), &msg.cache_control);
 
                     // Try to merge with previous user message containing tool_results
                     let merged = if let Some(last) = messages.last_mut() {
@@ -659,6 +1145,7 @@ impl Encoder for AnthropicCodec {
                     name: t.name.clone(),
                     description: t.description.clone(),
                     input_schema: t.parameters.clone(),
+                    cache_control: t.cache_control.as_ref().map(ir_cache_control_to_anthropic),
                 })
                 .collect::<Vec<_>>()
         });
@@ -682,17 +1169,36 @@ impl Encoder for AnthropicCodec {
             },
         });
 
+        // max_tokens includes the thinking budget and must exceed it
+        let thinking = ir.reasoning.as_ref().map(ir_reasoning_to_anthropic);
+        let mut max_tokens = ir.max_tokens.unwrap_or(4096);
+        if let Some(budget) = thinking.as_ref().and_then(|t| t.budget_tokens) {
+            if budget >= max_tokens {
+                max_tokens += budget;
+            }
+        }
+
         let req = AnthropicRequest {
             model: model.to_string(),
             messages,
-            max_tokens: ir.max_tokens.unwrap_or(4096),
-            system: ir.system.clone(),
+            max_tokens,
+            system: ir.system.clone().map(|text| match &ir.system_cache_control {
+                Some(cc) => AnthropicSystem::Blocks(vec![AnthropicSystemBlock {
+                    block_type: "text".to_string(),
+                    text,
+                    cache_control: Some(ir_cache_control_to_anthropic(cc)),
+                }]),
+                None => AnthropicSystem::Text(text),
+            }),
             temperature: ir.temperature,
             top_p: ir.top_p,
+            top_k: ir.top_k,
             stop_sequences: ir.stop.clone(),
             stream: if ir.stream { Some(true) } else { None },
             tools,
             tool_choice,
+            thinking,
+            extra: ir.extra_for(ChatFormat::Anthropic).cloned().unwrap_or_default(),
         };
 
         serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
@@ -701,6 +1207,22 @@ impl Encoder for AnthropicCodec {
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
         let mut content = Vec::new();
 
+        for r in ir.message.reasoning.iter().flatten() {
+            match r.encrypted_for(ChatFormat::Anthropic) {
+                Some(data) => content.push(AnthropicContentBlock::RedactedThinking {
+                    data: data.to_string(),
+                }),
+                None if !r.text.is_empty() => content.push(AnthropicContentBlock::Thinking {
+                    thinking: r.text.clone(),
+                    signature: r
+                        .signature_for(ChatFormat::Anthropic)
+                        .unwrap_or_default()
+                        .to_string(),
+                }),
+                None => {}
+            }
+        }
+
         let text = ir.message.content.to_text();
         if !text.is_empty() {
             content.push(AnthropicContentBlock::Text { text });
@@ -731,25 +1253,27 @@ impl Encoder for AnthropicCodec {
             content,
             model: ir.model.clone(),
             stop_reason: ir_finish_to_anthropic(&ir.finish_reason),
-            usage: ir.usage.as_ref().map(|u| AnthropicUsage {
-                input_tokens: u.prompt_tokens,
-                output_tokens: u.completion_tokens,
-            }),
+            usage: ir.usage.as_ref().map(ir_usage_to_anthropic),
         };
 
         serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
         let mut events = Vec::new();
 
         // message_start event (when we have role + id)
         if chunk.delta_role.is_some() && !chunk.id.is_empty() {
-            let input_tokens = chunk
+            let usage = chunk
                 .usage
                 .as_ref()
-                .map(|u| u.prompt_tokens)
-                .unwrap_or(0);
+                .map(|u| ir_usage_to_anthropic(&IrUsage { completion_tokens: 0, ..u.clone() }))
+                .unwrap_or(AnthropicUsage {
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cache_creation_input_tokens: None,
+                    cache_read_input_tokens: None,
+                });
             let msg_start = serde_json::json!({
                 "type": "message_start",
                 "message": {
@@ -759,77 +1283,81 @@ impl Encoder for AnthropicCodec {
                     "content": [],
                     "model": chunk.model.as_deref().unwrap_or(""),
                     "stop_reason": null,
-                    "usage": {
-                        "input_tokens": input_tokens,
-                        "output_tokens": 0,
-                    }
+                    "usage": usage,
                 }
             });
-            events.push(format!(
-                "event: message_start\ndata: {}",
+            events.push(SseEvent::named(
+                "message_start",
                 serde_json::to_string(&msg_start)
                     .map_err(|e| AppError::Codec(e.to_string()))?
             ));
         }
 
+        let mut blocks = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+
+        // thinking deltas
+        if let Some(reasoning) = &chunk.delta_reasoning {
+            let own = reasoning.source == Some(ChatFormat::Anthropic);
+            if let Some(thinking) = &reasoning.text {
+                let index = blocks.open(Block::Thinking, &mut events)?;
+                events.push(block_delta(index, serde_json::json!({
+                    "type": "thinking_delta",
+                    "thinking": thinking,
+                }))?);
+            }
+            // The signature closes the thinking block it belongs to
+            if let Some(signature) = reasoning.signature.as_ref().filter(|_| own) {
+                let index = blocks.open(Block::Thinking, &mut events)?;
+                events.push(block_delta(index, serde_json::json!({
+                    "type": "signature_delta",
+                    "signature": signature,
+                }))?);
+                blocks.close(&mut events)?;
+            }
+            // Redacted thinking arrives whole, as a block of its own
+            if let Some(data) = reasoning.encrypted.as_ref().filter(|_| own) {
+                blocks.start(Block::RedactedThinking, serde_json::json!({
+                    "type": "redacted_thinking",
+                    "data": data,
+                }), &mut events)?;
+                blocks.close(&mut events)?;
+            }
+        }
+
         // content_block_delta for text
         if let Some(text) = &chunk.delta_content {
-            let delta = serde_json::json!({
-                "type": "content_block_delta",
-                "index": 0,
-                "delta": {
-                    "type": "text_delta",
-                    "text": text,
-                }
-            });
-            events.push(format!(
-                "event: content_block_delta\ndata: {}",
-                serde_json::to_string(&delta)
-                    .map_err(|e| AppError::Codec(e.to_string()))?
-            ));
+            let index = blocks.open(Block::Text, &mut events)?;
+            events.push(block_delta(index, serde_json::json!({
+                "type": "text_delta",
+                "text": text,
+            }))?);
         }
 
         // tool call deltas
         if let Some(tcs) = &chunk.delta_tool_calls {
             for tc in tcs {
-                if tc.id.is_some() || tc.name.is_some() {
-                    // content_block_start for tool_use
-                    let block_start = serde_json::json!({
-                        "type": "content_block_start",
-                        "index": tc.index,
-                        "content_block": {
-                            "type": "tool_use",
-                            "id": tc.id.as_deref().unwrap_or(""),
-                            "name": tc.name.as_deref().unwrap_or(""),
-                            "input": {},
-                        }
-                    });
-                    events.push(format!(
-                        "event: content_block_start\ndata: {}",
-                        serde_json::to_string(&block_start)
-                            .map_err(|e| AppError::Codec(e.to_string()))?
-                    ));
+                let block = Block::ToolUse(tc.index);
+                if (tc.id.is_some() || tc.name.is_some()) && !blocks.is_open(block) {
+                    blocks.start(block, serde_json::json!({
+                        "type": "tool_use",
+                        "id": tc.id.as_deref().unwrap_or(""),
+                        "name": tc.name.as_deref().unwrap_or(""),
+                        "input": {},
+                    }), &mut events)?;
                 }
                 if let Some(args) = &tc.arguments {
-                    let delta = serde_json::json!({
-                        "type": "content_block_delta",
-                        "index": tc.index,
-                        "delta": {
-                            "type": "input_json_delta",
-                            "partial_json": args,
-                        }
-                    });
-                    events.push(format!(
-                        "event: content_block_delta\ndata: {}",
-                        serde_json::to_string(&delta)
-                            .map_err(|e| AppError::Codec(e.to_string()))?
-                    ));
+                    let index = blocks.open(block, &mut events)?;
+                    events.push(block_delta(index, serde_json::json!({
+                        "type": "input_json_delta",
+                        "partial_json": args,
+                    }))?);
                 }
             }
         }
 
         // message_delta for finish_reason
         if let Some(reason) = &chunk.finish_reason {
+            blocks.close(&mut events)?;
             let stop_reason = match reason {
                 IrFinishReason::Stop => "end_turn",
                 IrFinishReason::Length => "max_tokens",
@@ -850,26 +1378,147 @@ impl Encoder for AnthropicCodec {
                     "output_tokens": output_tokens,
                 }
             });
-            events.push(format!(
-                "event: message_delta\ndata: {}",
+            events.push(SseEvent::named(
+                "message_delta",
                 serde_json::to_string(&msg_delta)
                     .map_err(|e| AppError::Codec(e.to_string()))?
             ));
         }
 
-        if events.is_empty() {
-            Ok(None)
-        } else {
-            // Join multiple events — each will be sent as a separate SSE block
-            // The proxy SSE layer wraps each line in "data: ...\n\n" so we need
-            // to return the raw content. Since we handle event: lines ourselves,
-            // return as a single string (the proxy will wrap it).
-            Ok(Some(events.join("\n\n")))
-        }
+        Ok(events)
+    }
+
+    fn stream_done_signal(&self) -> Option<SseEvent> {
+        Some(SseEvent::named("message_stop", r#"{"type":"message_stop"}"#))
+    }
+
+    fn encode_error(&self, error: &IrError) -> (u16, serde_json::Value) {
+        let status = match error.kind {
+            IrErrorKind::Overloaded => 529,
+            _ => error.status,
+        };
+        let body = serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": error_type(error.kind),
+                "message": error.message,
+            }
+        });
+        (status, body)
     }
 
-    fn stream_done_signal(&self) -> Option<String> {
-        Some(r#"event: message_stop
-data: {"type":"message_stop"}"#.to_string())
+    fn encode_stream_error(&self, error: &IrError) -> SseEvent {
+        SseEvent::named("error", self.encode_error(error).1.to_string())
+    }
+}
+
+fn error_type(kind: IrErrorKind) -> &'static str {
+    match kind {
+        IrErrorKind::InvalidRequest => "invalid_request_error",
+        IrErrorKind::Authentication => "authentication_error",
+        IrErrorKind::PermissionDenied => "permission_error",
+        IrErrorKind::NotFound => "not_found_error",
+        IrErrorKind::RequestTooLarge => "request_too_large",
+        IrErrorKind::RateLimited => "rate_limit_error",
+        IrErrorKind::Overloaded => "overloaded_error",
+        IrErrorKind::Internal => "api_error",
+    }
+}
+
+/// Error kind for an Anthropic error `type`.
+pub(crate) fn error_kind(error_type: &str) -> Option<IrErrorKind> {
+    match error_type {
+        "invalid_request_error" => Some(IrErrorKind::InvalidRequest),
+        "authentication_error" => Some(IrErrorKind::Authentication),
+        "permission_error" | "billing_error" => Some(IrErrorKind::PermissionDenied),
+        "not_found_error" => Some(IrErrorKind::NotFound),
+        "request_too_large" => Some(IrErrorKind::RequestTooLarge),
+        "rate_limit_error" => Some(IrErrorKind::RateLimited),
+        "overloaded_error" => Some(IrErrorKind::Overloaded),
+        "api_error" => Some(IrErrorKind::Internal),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_parts_are_rejected() {
+        // Errors have no fixture step; the document case is in fixtures/chat
+        let ir = IrChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![IrMessage {
+                content: IrContent::Parts(vec![IrContentPart::Audio {
+                    media_type: "audio/wav".to_string(),
+                    data: "UklGRg==".to_string(),
+                }]),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let err = AnthropicCodec::default().encode_request(&ir, "claude-sonnet-4-5").unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+    }
+
+    #[test]
+    fn test_overloaded_error_round_trip() {
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let error = super::super::decode_upstream_error(529, body);
+        assert_eq!(error.kind, IrErrorKind::Overloaded);
+
+        let (status, anthropic) = AnthropicCodec::default().encode_error(&error);
+        assert_eq!(status, 529);
+        assert_eq!(anthropic["error"]["type"], "overloaded_error");
+
+        let event = AnthropicCodec::default().encode_stream_error(&error);
+        assert_eq!(event.event_name(), "error");
+        assert_eq!(super::super::decode_stream_error(&event.data), Some(error));
+    }
+
+    #[test]
+    fn test_stream_blocks_get_their_own_indices() {
+        let codec = AnthropicCodec::default();
+        let chunks = [
+            serde_json::json!({"id": "msg_1", "delta_reasoning": {"text": "Hmm", "source": "anthropic"}}),
+            serde_json::json!({"id": "msg_1", "delta_reasoning": {"signature": "sig-1", "source": "anthropic"}}),
+            serde_json::json!({"id": "msg_1", "delta_reasoning": {"encrypted": "opaque", "source": "anthropic"}}),
+            serde_json::json!({"id": "msg_1", "delta_content": "Let me check."}),
+            serde_json::json!({"id": "msg_1", "delta_content": " One moment."}),
+            serde_json::json!({"id": "msg_1", "delta_tool_calls": [{"index": 0, "id": "toolu_1", "name": "weather"}]}),
+            serde_json::json!({"id": "msg_1", "delta_tool_calls": [{"index": 0, "arguments": "{}"}]}),
+            serde_json::json!({"id": "msg_1", "finish_reason": "tool_calls"}),
+        ];
+        let events: Vec<(String, Option<u64>, Option<String>)> = chunks
+            .into_iter()
+            .flat_map(|chunk| codec.encode_stream_chunk(&serde_json::from_value(chunk).unwrap()).unwrap())
+            .map(|event| {
+                let data: serde_json::Value = serde_json::from_str(&event.data).unwrap();
+                let block = data["content_block"]["type"].as_str().or(data["delta"]["type"].as_str());
+                (event.event_name().to_string(), data["index"].as_u64(), block.map(str::to_string))
+            })
+            .collect();
+        let expected = [
+            ("content_block_start", Some(0), Some("thinking")),
+            ("content_block_delta", Some(0), Some("thinking_delta")),
+            ("content_block_delta", Some(0), Some("signature_delta")),
+            ("content_block_stop", Some(0), None),
+            ("content_block_start", Some(1), Some("redacted_thinking")),
+            ("content_block_stop", Some(1), None),
+            ("content_block_start", Some(2), Some("text")),
+            ("content_block_delta", Some(2), Some("text_delta")),
+            ("content_block_delta", Some(2), Some("text_delta")),
+            ("content_block_stop", Some(2), None),
+            ("content_block_start", Some(3), Some("tool_use")),
+            ("content_block_delta", Some(3), Some("input_json_delta")),
+            ("content_block_stop", Some(3), None),
+            ("message_delta", None, None),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(name, index, block)| (name.to_string(), index, block.map(str::to_string)))
+            .collect();
+        assert_eq!(events, expected);
     }
 }
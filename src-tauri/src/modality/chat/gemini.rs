@@ -1,7 +1,9 @@
 use super::ir::*;
-use super::{Decoder, Encoder};
+use super::{ChatFormat, Decoder, Encoder};
 use crate::error::AppError;
+use crate::sse::SseEvent;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub struct GeminiCodec;
 
@@ -19,6 +21,9 @@ pub struct GeminiRequest {
     pub tools: Option<Vec<GeminiToolDeclaration>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_config: Option<GeminiToolConfig>,
+    /// Fields without an IR counterpart (`safetySettings`, `cachedContent`, ...).
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,9 +46,18 @@ pub struct GeminiPart {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inline_data: Option<GeminiInlineData>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<GeminiFileData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub function_call: Option<GeminiFunctionCall>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_response: Option<GeminiFunctionResponse>,
+    /// Marks a part as a thought summary rather than answer text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
+    /// Opaque signature of the model's reasoning, to be returned on the part
+    /// it was attached to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +67,16 @@ pub struct GeminiInlineData {
     pub data: String,
 }
 
+/// A file referenced by URI: uploaded through the Files API, in Cloud
+/// Storage, or a public URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFileData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub file_uri: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiFunctionCall {
     pub name: String,
@@ -74,9 +98,47 @@ pub struct GeminiGenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_output_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_config: Option<GeminiThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    /// OpenAPI-subset schema of the older API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    /// Standard JSON Schema, as taken by the other providers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_json_schema: Option<serde_json::Value>,
+    /// Fields without an IR counterpart (`candidateCount`, `responseModalities`, ...),
+    /// kept in the IR extras under [`GENERATION_CONFIG_KEY`].
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Key of the unmapped generation config fields in `IrChatRequest.extra`.
+const GENERATION_CONFIG_KEY: &str = "generationConfig";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiThinkingConfig {
+    /// Token budget; 0 disables thinking, -1 lets the model decide.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<i32>,
+    /// Gemini 3 alternative to a budget (`low` / `high`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_thoughts: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -136,6 +198,12 @@ pub struct GeminiUsageMetadata {
     pub candidates_token_count: u32,
     #[serde(default)]
     pub total_token_count: u32,
+    /// Reasoning tokens, not included in `candidates_token_count`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thoughts_token_count: Option<u32>,
+    /// Part of `prompt_token_count` served from the context cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_content_token_count: Option<u32>,
 }
 
 // --- Conversion helpers ---
@@ -176,14 +244,167 @@ fn ir_finish_to_gemini(reason: &Option<IrFinishReason>) -> Option<String> {
     })
 }
 
+fn gemini_usage_to_ir(u: &GeminiUsageMetadata) -> IrUsage {
+    let thoughts = u.thoughts_token_count.unwrap_or(0);
+    IrUsage {
+        prompt_tokens: u.prompt_token_count,
+        completion_tokens: u.candidates_token_count + thoughts,
+        total_tokens: Some(u.total_token_count),
+        reasoning_tokens: u.thoughts_token_count,
+        cache_read_tokens: u.cached_content_token_count,
+        cache_write_tokens: None,
+    }
+}
+
+fn ir_usage_to_gemini(u: &IrUsage) -> GeminiUsageMetadata {
+    GeminiUsageMetadata {
+        prompt_token_count: u.prompt_tokens,
+        candidates_token_count: u
+            .completion_tokens
+            .saturating_sub(u.reasoning_tokens.unwrap_or(0)),
+        total_token_count: u
+            .total_tokens
+            .unwrap_or(u.prompt_tokens + u.completion_tokens),
+        thoughts_token_count: u.reasoning_tokens,
+        cached_content_token_count: u.cache_read_tokens,
+    }
+}
+
+/// Gemini schemas carry no name; this one is used when translating them.
+const GEMINI_SCHEMA_NAME: &str = "response";
+
+fn gemini_response_format_to_ir(config: &GeminiGenerationConfig) -> Option<IrResponseFormat> {
+    let schema = config
+        .response_json_schema
+        .as_ref()
+        .or(config.response_schema.as_ref());
+    if let Some(schema) = schema {
+        return Some(IrResponseFormat::JsonSchema {
+            name: GEMINI_SCHEMA_NAME.to_string(),
+            description: None,
+            schema: schema.clone(),
+            strict: None,
+        });
+    }
+    match config.response_mime_type.as_deref()? {
+        "application/json" => Some(IrResponseFormat::JsonObject),
+        "text/plain" => Some(IrResponseFormat::Text),
+        _ => None,
+    }
+}
+
+fn gemini_thinking_to_ir(config: &GeminiThinkingConfig) -> IrReasoningConfig {
+    match config.thinking_budget {
+        Some(0) => IrReasoningConfig {
+            effort: Some(IrReasoningEffort::None),
+            budget_tokens: None,
+        },
+        Some(budget) if budget > 0 => IrReasoningConfig {
+            effort: None,
+            budget_tokens: Some(budget as u32),
+        },
+        _ => IrReasoningConfig {
+            effort: config
+                .thinking_level
+                .as_deref()
+                .and_then(IrReasoningEffort::from_str_loose),
+            budget_tokens: None,
+        },
+    }
+}
+
+fn ir_reasoning_to_gemini(config: &IrReasoningConfig) -> GeminiThinkingConfig {
+    if config.is_disabled() {
+        return GeminiThinkingConfig {
+            thinking_budget: Some(0),
+            thinking_level: None,
+            include_thoughts: None,
+        };
+    }
+    GeminiThinkingConfig {
+        thinking_budget: Some(config.budget_tokens().map_or(-1, |b| b as i32)),
+        thinking_level: None,
+        include_thoughts: Some(true),
+    }
+}
+
+/// Collect thought summaries and thought signatures of a content.
+fn gemini_reasoning_to_ir(parts: &[GeminiPart]) -> Option<Vec<IrReasoning>> {
+    let text: String = parts
+        .iter()
+        .filter(|p| p.thought == Some(true))
+        .filter_map(|p| p.text.as_deref())
+        .collect();
+    let signature = parts.iter().find_map(|p| p.thought_signature.clone());
+    if text.is_empty() && signature.is_none() {
+        return None;
+    }
+    Some(vec![IrReasoning {
+        text,
+        signature,
+        source: Some(ChatFormat::Gemini),
+        ..Default::default()
+    }])
+}
+
+fn thought_part(text: String) -> GeminiPart {
+    GeminiPart {
+        text: Some(text),
+        inline_data: None,
+        file_data: None,
+        function_call: None,
+        function_response: None,
+        thought: Some(true),
+        thought_signature: None,
+    }
+}
+
+/// Return a thought signature on the part Gemini expects it: the first
+/// function call, or the last part of a text-only turn.
+fn attach_thought_signature(parts: &mut Vec<GeminiPart>, signature: Option<&str>) {
+    let Some(signature) = signature else {
+        return;
+    };
+    if parts.is_empty() {
+        parts.push(GeminiPart {
+            text: Some(String::new()),
+            inline_data: None,
+            file_data: None,
+            function_call: None,
+            function_response: None,
+            thought: None,
+            thought_signature: None,
+        });
+    }
+    let target = match parts.iter().position(|p| p.function_call.is_some()) {
+        Some(i) => i,
+        None => parts.len() - 1,
+    };
+    parts[target].thought_signature = Some(signature.to_string());
+}
+
+fn gemini_signature(reasoning: &Option<Vec<IrReasoning>>) -> Option<&str> {
+    reasoning
+        .iter()
+        .flatten()
+        .find_map(|r| r.signature_for(ChatFormat::Gemini))
+}
+
 /// Convert Gemini parts into IR content + optional tool_calls.
+/// Thought parts are left to `gemini_reasoning_to_ir`.
 fn gemini_parts_to_ir(parts: &[GeminiPart]) -> (IrContent, Option<Vec<IrToolCall>>) {
-    let mut text_parts = Vec::new();
+    let mut content_parts = Vec::new();
     let mut tool_calls = Vec::new();
 
     for (i, part) in parts.iter().enumerate() {
-        if let Some(text) = &part.text {
-            text_parts.push(text.clone());
+        if let Some(text) = part.text.as_ref().filter(|_| part.thought != Some(true)) {
+            content_parts.push(IrContentPart::Text { text: text.clone() });
+        }
+        if let Some(inline) = &part.inline_data {
+            content_parts.push(gemini_inline_data_to_ir(inline));
+        }
+        if let Some(file) = &part.file_data {
+            content_parts.push(gemini_file_data_to_ir(file));
         }
         if let Some(fc) = &part.function_call {
             tool_calls.push(IrToolCall {
@@ -194,12 +415,11 @@ fn gemini_parts_to_ir(parts: &[GeminiPart]) -> (IrContent, Option<Vec<IrToolCall
         }
     }
 
-    let content = if text_parts.len() == 1 {
-        IrContent::Text(text_parts.into_iter().next().unwrap())
-    } else if text_parts.is_empty() {
-        IrContent::Text(String::new())
+    // Text-only content is joined into a plain string
+    let content = if content_parts.iter().all(|p| matches!(p, IrContentPart::Text { .. })) {
+        IrContent::Text(IrContent::Parts(content_parts).to_text())
     } else {
-        IrContent::Text(text_parts.join(""))
+        IrContent::Parts(content_parts)
     };
 
     let tc = if tool_calls.is_empty() {
@@ -211,57 +431,126 @@ fn gemini_parts_to_ir(parts: &[GeminiPart]) -> (IrContent, Option<Vec<IrToolCall
     (content, tc)
 }
 
+fn gemini_inline_data_to_ir(inline: &GeminiInlineData) -> IrContentPart {
+    let media_type = inline.mime_type.clone();
+    let data = inline.data.clone();
+    if media_type.starts_with("image/") {
+        IrContentPart::Image { url: None, media_type: Some(media_type), data: Some(data) }
+    } else if media_type.starts_with("audio/") {
+        IrContentPart::Audio { media_type, data }
+    } else {
+        IrContentPart::File {
+            media_type: Some(media_type),
+            data: Some(data),
+            url: None,
+            file_id: None,
+            filename: None,
+        }
+    }
+}
+
+fn gemini_file_data_to_ir(file: &GeminiFileData) -> IrContentPart {
+    if file.mime_type.as_deref().is_some_and(|m| m.starts_with("image/")) {
+        IrContentPart::Image {
+            url: Some(file.file_uri.clone()),
+            media_type: file.mime_type.clone(),
+            data: None,
+        }
+    } else {
+        IrContentPart::File {
+            media_type: file.mime_type.clone(),
+            data: None,
+            url: Some(file.file_uri.clone()),
+            file_id: None,
+            filename: None,
+        }
+    }
+}
+
+fn inline_part(mime_type: String, data: String) -> GeminiPart {
+    GeminiPart {
+        text: None,
+        inline_data: Some(GeminiInlineData { mime_type, data }),
+        file_data: None,
+        function_call: None,
+        function_response: None,
+        thought: None,
+        thought_signature: None,
+    }
+}
+
+fn file_part(mime_type: Option<String>, file_uri: String) -> GeminiPart {
+    GeminiPart {
+        text: None,
+        inline_data: None,
+        file_data: Some(GeminiFileData { mime_type, file_uri }),
+        function_call: None,
+        function_response: None,
+        thought: None,
+        thought_signature: None,
+    }
+}
+
 /// Convert IR content into Gemini parts.
-fn ir_content_to_gemini_parts(content: &IrContent) -> Vec<GeminiPart> {
+fn ir_content_to_gemini_parts(content: &IrContent) -> Result<Vec<GeminiPart>, AppError> {
     match content {
         IrContent::Text(s) => {
             if s.is_empty() {
-                vec![]
+                Ok(vec![])
             } else {
-                vec![GeminiPart {
+                Ok(vec![GeminiPart {
                     text: Some(s.clone()),
                     inline_data: None,
+                    file_data: None,
                     function_call: None,
                     function_response: None,
-                }]
+                    thought: None,
+                    thought_signature: None,
+                }])
             }
         }
         IrContent::Parts(parts) => parts
             .iter()
-            .filter_map(|p| match p {
-                IrContentPart::Text { text } => Some(GeminiPart {
+            .map(|p| match p {
+                IrContentPart::Text { text } => Ok(GeminiPart {
                     text: Some(text.clone()),
                     inline_data: None,
+                    file_data: None,
                     function_call: None,
                     function_response: None,
+                    thought: None,
+                    thought_signature: None,
                 }),
-                IrContentPart::Image {
-                    data, media_type, ..
-                } => {
-                    if let Some(data) = data {
-                        Some(GeminiPart {
-                            text: None,
-                            inline_data: Some(GeminiInlineData {
-                                mime_type: media_type
-                                    .as_deref()
-                                    .unwrap_or("image/png")
-                                    .to_string(),
-                                data: data.clone(),
-                            }),
-                            function_call: None,
-                            function_response: None,
-                        })
-                    } else {
-                        // Gemini doesn't support URL-based images directly;
-                        // emit a text placeholder.
-                        Some(GeminiPart {
-                            text: Some("[image]".to_string()),
-                            inline_data: None,
-                            function_call: None,
-                            function_response: None,
-                        })
-                    }
+                IrContentPart::Image { url, media_type, data } => match (data, url) {
+                    (Some(data), _) => Ok(inline_part(
+                        media_type.clone().unwrap_or_else(|| "image/png".to_string()),
+                        data.clone(),
+                    )),
+                    (None, Some(url)) => Ok(match parse_data_url(url) {
+                        Some((media_type, data)) => inline_part(media_type, data),
+                        None => file_part(media_type.clone(), url.clone()),
+                    }),
+                    (None, None) => Err(AppError::BadRequest(
+                        "Image part has neither data nor a URL".to_string(),
+                    )),
+                },
+                IrContentPart::Audio { media_type, data } => {
+                    Ok(inline_part(media_type.clone(), data.clone()))
                 }
+                IrContentPart::File { media_type, data, url, file_id, .. } => match (data, url) {
+                    (Some(data), _) => Ok(inline_part(
+                        media_type.clone().unwrap_or_else(|| "application/pdf".to_string()),
+                        data.clone(),
+                    )),
+                    (None, Some(url)) => Ok(file_part(media_type.clone(), url.clone())),
+                    (None, None) if file_id.is_some() => Err(AppError::BadRequest(
+                        "Gemini does not accept file ids of other providers; send the file inline or as a URI"
+                            .to_string(),
+                    )),
+                    (None, None) => Err(AppError::BadRequest(
+                        "File part has neither data, a URL nor a file id".to_string(),
+                    )),
+                },
             })
             .collect(),
     }
@@ -306,6 +595,8 @@ impl Decoder for GeminiCodec {
                             tool_calls: None,
                             tool_call_id: None,
                             name: Some(fr.name.clone()),
+                            reasoning: None,
+                            cache_control: None,
                         });
                     }
                 }
@@ -322,6 +613,8 @@ impl Decoder for GeminiCodec {
                 tool_calls,
                 tool_call_id: None,
                 name: None,
+                reasoning: gemini_reasoning_to_ir(&content.parts),
+                cache_control: None,
             });
         }
 
@@ -333,6 +626,7 @@ impl Decoder for GeminiCodec {
                         name: fd.name.clone(),
                         description: fd.description.clone(),
                         parameters: fd.parameters.clone().unwrap_or(serde_json::json!({})),
+                        cache_control: None,
                     })
                 })
                 .collect()
@@ -361,18 +655,34 @@ impl Decoder for GeminiCodec {
         // Extract generation config
         let gen = req.generation_config.as_ref();
 
+        let mut extra = req.extra;
+        if let Some(g) = gen.filter(|g| !g.extra.is_empty()) {
+            extra.insert(GENERATION_CONFIG_KEY.to_string(), serde_json::json!(g.extra));
+        }
+        let extra = (!extra.is_empty()).then_some(extra);
+
         Ok(IrChatRequest {
             model: String::new(), // Gemini model is in the URL path, not the body
             messages,
             system,
+            system_cache_control: None,
             temperature: gen.and_then(|g| g.temperature),
             top_p: gen.and_then(|g| g.top_p),
+            top_k: gen.and_then(|g| g.top_k),
             max_tokens: gen.and_then(|g| g.max_output_tokens),
             stream: false, // Gemini stream is determined by the endpoint, not a body field
             stop: gen.and_then(|g| g.stop_sequences.clone()),
+            seed: gen.and_then(|g| g.seed),
+            presence_penalty: gen.and_then(|g| g.presence_penalty),
+            frequency_penalty: gen.and_then(|g| g.frequency_penalty),
             tools,
             tool_choice,
-            extra: None,
+            reasoning: gen
+                .and_then(|g| g.thinking_config.as_ref())
+                .map(gemini_thinking_to_ir),
+            response_format: gen.and_then(gemini_response_format_to_ir),
+            extra_source: extra.as_ref().map(|_| ChatFormat::Gemini),
+            extra,
         })
     }
 
@@ -402,17 +712,15 @@ impl Decoder for GeminiCodec {
                 tool_calls,
                 tool_call_id: None,
                 name: None,
+                reasoning: gemini_reasoning_to_ir(&candidate.content.parts),
+                cache_control: None,
             },
             finish_reason,
-            usage: resp.usage_metadata.map(|u| IrUsage {
-                prompt_tokens: u.prompt_token_count,
-                completion_tokens: u.candidates_token_count,
-                total_tokens: Some(u.total_token_count),
-            }),
+            usage: resp.usage_metadata.as_ref().map(gemini_usage_to_ir),
         })
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+    fn decode_stream_chunk(&self, _event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
         if data.trim().is_empty() {
             return Ok(None);
         }
@@ -432,12 +740,9 @@ impl Decoder for GeminiCodec {
                         delta_role: None,
                         delta_content: None,
                         delta_tool_calls: None,
+                        delta_reasoning: None,
                         finish_reason: None,
-                        usage: Some(IrUsage {
-                            prompt_tokens: usage.prompt_token_count,
-                            completion_tokens: usage.candidates_token_count,
-                            total_tokens: Some(usage.total_token_count),
-                        }),
+                        usage: Some(gemini_usage_to_ir(usage)),
                     }));
                 }
                 return Ok(None);
@@ -446,11 +751,16 @@ impl Decoder for GeminiCodec {
 
         // Extract delta text from parts
         let mut delta_text_parts = Vec::new();
+        let mut delta_thought_parts = Vec::new();
         let mut delta_tool_calls = Vec::new();
 
         for (i, part) in candidate.content.parts.iter().enumerate() {
             if let Some(text) = &part.text {
-                delta_text_parts.push(text.clone());
+                if part.thought == Some(true) {
+                    delta_thought_parts.push(text.clone());
+                } else {
+                    delta_text_parts.push(text.clone());
+                }
             }
             if let Some(fc) = &part.function_call {
                 delta_tool_calls.push(IrToolCallDelta {
@@ -476,6 +786,22 @@ impl Decoder for GeminiCodec {
             Some(delta_tool_calls)
         };
 
+        let signature = candidate
+            .content
+            .parts
+            .iter()
+            .find_map(|p| p.thought_signature.clone());
+        let delta_reasoning = if delta_thought_parts.is_empty() && signature.is_none() {
+            None
+        } else {
+            Some(IrReasoningDelta {
+                text: (!delta_thought_parts.is_empty()).then(|| delta_thought_parts.join("")),
+                signature,
+                source: Some(ChatFormat::Gemini),
+                ..Default::default()
+            })
+        };
+
         // Map finish reason; if tool calls present, override to ToolCalls
         let finish_reason = if delta_tc.is_some() {
             Some(IrFinishReason::ToolCalls)
@@ -495,12 +821,9 @@ impl Decoder for GeminiCodec {
             delta_role: role,
             delta_content,
             delta_tool_calls: delta_tc,
+            delta_reasoning,
             finish_reason,
-            usage: chunk.usage_metadata.map(|u| IrUsage {
-                prompt_tokens: u.prompt_token_count,
-                completion_tokens: u.candidates_token_count,
-                total_tokens: Some(u.total_token_count),
-            }),
+            usage: chunk.usage_metadata.as_ref().map(gemini_usage_to_ir),
         }))
     }
 
@@ -523,7 +846,7 @@ impl Encoder for GeminiCodec {
                     continue;
                 }
                 IrRole::User => {
-                    let parts = ir_content_to_gemini_parts(&msg.content);
+                    let parts = ir_content_to_gemini_parts(&msg.content)?;
                     if !parts.is_empty() {
                         contents.push(GeminiContent {
                             role: Some("user".to_string()),
@@ -532,7 +855,7 @@ impl Encoder for GeminiCodec {
                     }
                 }
                 IrRole::Assistant => {
-                    let mut parts = ir_content_to_gemini_parts(&msg.content);
+                    let mut parts = ir_content_to_gemini_parts(&msg.content)?;
 
                     // Add functionCall parts for tool calls
                     if let Some(tcs) = &msg.tool_calls {
@@ -543,15 +866,21 @@ impl Encoder for GeminiCodec {
                             parts.push(GeminiPart {
                                 text: None,
                                 inline_data: None,
+                                file_data: None,
                                 function_call: Some(GeminiFunctionCall {
                                     name: tc.name.clone(),
                                     args,
                                 }),
                                 function_response: None,
+                                thought: None,
+                                thought_signature: None,
                             });
                         }
                     }
 
+                    // Thought summaries need not be resent, only the signature
+                    attach_thought_signature(&mut parts, gemini_signature(&msg.reasoning));
+
                     if !parts.is_empty() {
                         contents.push(GeminiContent {
                             role: Some("model".to_string()),
@@ -576,11 +905,14 @@ impl Encoder for GeminiCodec {
                         parts: vec![GeminiPart {
                             text: None,
                             inline_data: None,
+                            file_data: None,
                             function_call: None,
                             function_response: Some(GeminiFunctionResponse {
                                 name: func_name,
                                 response: response_value,
                             }),
+                            thought: None,
+                            thought_signature: None,
                         }],
                     });
                 }
@@ -592,22 +924,55 @@ impl Encoder for GeminiCodec {
             parts: vec![GeminiPart {
                 text: Some(s.clone()),
                 inline_data: None,
+                file_data: None,
                 function_call: None,
                 function_response: None,
+                thought: None,
+                thought_signature: None,
             }],
         });
 
+        let mut extra = ir.extra_for(ChatFormat::Gemini).cloned().unwrap_or_default();
+        let generation_extra: HashMap<String, serde_json::Value> = extra
+            .remove(GENERATION_CONFIG_KEY)
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default();
+
         // Generation config
         let generation_config = if ir.temperature.is_some()
             || ir.top_p.is_some()
+            || ir.top_k.is_some()
             || ir.max_tokens.is_some()
             || ir.stop.is_some()
+            || ir.seed.is_some()
+            || ir.presence_penalty.is_some()
+            || ir.frequency_penalty.is_some()
+            || ir.reasoning.is_some()
+            || ir.response_format.is_some()
+            || !generation_extra.is_empty()
         {
+            let response_mime_type = ir.response_format.as_ref().map(|f| match f {
+                IrResponseFormat::Text => "text/plain".to_string(),
+                _ => "application/json".to_string(),
+            });
+            let response_json_schema = match &ir.response_format {
+                Some(IrResponseFormat::JsonSchema { schema, .. }) => Some(schema.clone()),
+                _ => None,
+            };
             Some(GeminiGenerationConfig {
                 temperature: ir.temperature,
                 top_p: ir.top_p,
+                top_k: ir.top_k,
                 max_output_tokens: ir.max_tokens,
                 stop_sequences: ir.stop.clone(),
+                seed: ir.seed,
+                presence_penalty: ir.presence_penalty,
+                frequency_penalty: ir.frequency_penalty,
+                thinking_config: ir.reasoning.as_ref().map(ir_reasoning_to_gemini),
+                response_mime_type,
+                response_schema: None,
+                response_json_schema,
+                extra: generation_extra,
             })
         } else {
             None
@@ -651,13 +1016,18 @@ impl Encoder for GeminiCodec {
             generation_config,
             tools,
             tool_config,
+            extra,
         };
 
         serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
     }
 
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
-        let mut parts = ir_content_to_gemini_parts(&ir.message.content);
+        let mut parts: Vec<GeminiPart> = reasoning_text(&ir.message.reasoning)
+            .map(thought_part)
+            .into_iter()
+            .collect();
+        parts.extend(ir_content_to_gemini_parts(&ir.message.content)?);
 
         // Add functionCall parts for tool calls
         if let Some(tcs) = &ir.message.tool_calls {
@@ -667,21 +1037,29 @@ impl Encoder for GeminiCodec {
                 parts.push(GeminiPart {
                     text: None,
                     inline_data: None,
+                    file_data: None,
                     function_call: Some(GeminiFunctionCall {
                         name: tc.name.clone(),
                         args,
                     }),
                     function_response: None,
+                    thought: None,
+                    thought_signature: None,
                 });
             }
         }
 
+        attach_thought_signature(&mut parts, gemini_signature(&ir.message.reasoning));
+
         if parts.is_empty() {
             parts.push(GeminiPart {
                 text: Some(String::new()),
                 inline_data: None,
+                file_data: None,
                 function_call: None,
                 function_response: None,
+                thought: None,
+                thought_signature: None,
             });
         }
 
@@ -695,28 +1073,30 @@ impl Encoder for GeminiCodec {
                 },
                 finish_reason,
             }],
-            usage_metadata: ir.usage.as_ref().map(|u| GeminiUsageMetadata {
-                prompt_token_count: u.prompt_tokens,
-                candidates_token_count: u.completion_tokens,
-                total_token_count: u
-                    .total_tokens
-                    .unwrap_or(u.prompt_tokens + u.completion_tokens),
-            }),
+            usage_metadata: ir.usage.as_ref().map(ir_usage_to_gemini),
         };
 
         serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
         let mut parts = Vec::new();
 
+        // Thought summary delta
+        if let Some(text) = chunk.delta_reasoning.as_ref().and_then(|r| r.text.clone()) {
+            parts.push(thought_part(text));
+        }
+
         // Text delta
         if let Some(text) = &chunk.delta_content {
             parts.push(GeminiPart {
                 text: Some(text.clone()),
                 inline_data: None,
+                file_data: None,
                 function_call: None,
                 function_response: None,
+                thought: None,
+                thought_signature: None,
             });
         }
 
@@ -729,19 +1109,29 @@ impl Encoder for GeminiCodec {
                     parts.push(GeminiPart {
                         text: None,
                         inline_data: None,
+                        file_data: None,
                         function_call: Some(GeminiFunctionCall {
                             name: name.clone(),
                             args,
                         }),
                         function_response: None,
+                        thought: None,
+                        thought_signature: None,
                     });
                 }
             }
         }
 
+        let signature = chunk
+            .delta_reasoning
+            .as_ref()
+            .filter(|r| r.source == Some(ChatFormat::Gemini))
+            .and_then(|r| r.signature.as_deref());
+        attach_thought_signature(&mut parts, signature);
+
         // If no content parts, still emit chunk with empty parts for finish_reason / usage
         if parts.is_empty() && chunk.finish_reason.is_none() && chunk.usage.is_none() {
-            return Ok(None);
+            return Ok(Vec::new());
         }
 
         let role = chunk
@@ -755,23 +1145,59 @@ impl Encoder for GeminiCodec {
                 content: GeminiContent { role, parts },
                 finish_reason: ir_finish_to_gemini(&chunk.finish_reason),
             }],
-            usage_metadata: chunk.usage.as_ref().map(|u| GeminiUsageMetadata {
-                prompt_token_count: u.prompt_tokens,
-                candidates_token_count: u.completion_tokens,
-                total_token_count: u
-                    .total_tokens
-                    .unwrap_or(u.prompt_tokens + u.completion_tokens),
-            }),
+            usage_metadata: chunk.usage.as_ref().map(ir_usage_to_gemini),
         };
 
         let json = serde_json::to_string(&gemini_chunk)
             .map_err(|e| AppError::Codec(e.to_string()))?;
 
-        Ok(Some(json))
+        Ok(vec![SseEvent::data(json)])
     }
 
-    fn stream_done_signal(&self) -> Option<String> {
+    fn stream_done_signal(&self) -> Option<SseEvent> {
         // Gemini streams end when the connection closes; no explicit done signal.
         None
     }
+
+    /// `fileData` only takes Files API and Cloud Storage URIs.
+    fn accepts_image_urls(&self) -> bool {
+        false
+    }
+
+    fn encode_error(&self, error: &IrError) -> (u16, serde_json::Value) {
+        let body = serde_json::json!({
+            "error": {
+                "code": error.status,
+                "message": error.message,
+                "status": error_status(error.kind),
+            }
+        });
+        (error.status, body)
+    }
+}
+
+fn error_status(kind: IrErrorKind) -> &'static str {
+    match kind {
+        IrErrorKind::InvalidRequest | IrErrorKind::RequestTooLarge => "INVALID_ARGUMENT",
+        IrErrorKind::Authentication => "UNAUTHENTICATED",
+        IrErrorKind::PermissionDenied => "PERMISSION_DENIED",
+        IrErrorKind::NotFound => "NOT_FOUND",
+        IrErrorKind::RateLimited => "RESOURCE_EXHAUSTED",
+        IrErrorKind::Overloaded => "UNAVAILABLE",
+        IrErrorKind::Internal => "INTERNAL",
+    }
+}
+
+/// Error kind for a Gemini (google.rpc) error `status`.
+pub(crate) fn error_kind(status: &str) -> Option<IrErrorKind> {
+    match status {
+        "INVALID_ARGUMENT" | "FAILED_PRECONDITION" | "OUT_OF_RANGE" => Some(IrErrorKind::InvalidRequest),
+        "UNAUTHENTICATED" => Some(IrErrorKind::Authentication),
+        "PERMISSION_DENIED" => Some(IrErrorKind::PermissionDenied),
+        "NOT_FOUND" => Some(IrErrorKind::NotFound),
+        "RESOURCE_EXHAUSTED" => Some(IrErrorKind::RateLimited),
+        "UNAVAILABLE" => Some(IrErrorKind::Overloaded),
+        "INTERNAL" | "UNKNOWN" | "DEADLINE_EXCEEDED" => Some(IrErrorKind::Internal),
+        _ => None,
+    }
 }
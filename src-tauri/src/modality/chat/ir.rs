@@ -1,29 +1,66 @@
+use super::ChatFormat;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// IR Chat Request — the universal intermediate representation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IrChatRequest {
     pub model: String,
     pub messages: Vec<IrMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<String>,
+    /// Cache breakpoint at the end of the system prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_cache_control: Option<IrCacheControl>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<IrTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<IrToolChoice>,
+    /// Extended thinking / reasoning effort requested by the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<IrReasoningConfig>,
+    /// Structured output constraint on the assistant reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<IrResponseFormat>,
     /// Provider-specific fields that don't map to IR fields.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<HashMap<String, serde_json::Value>>,
+    /// Format whose decoder collected `extra`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_source: Option<ChatFormat>,
+}
+
+impl IrChatRequest {
+    /// Provider-specific fields, if they were collected from `format`. Other
+    /// formats don't know what to make of them.
+    pub fn extra_for(&self, format: ChatFormat) -> Option<&HashMap<String, serde_json::Value>> {
+        self.extra.as_ref().filter(|_| self.extra_source == Some(format))
+    }
+
+    /// All prompt cache breakpoints: system prompt, tools and messages.
+    pub fn cache_breakpoints(&self) -> impl Iterator<Item = &IrCacheControl> {
+        self.system_cache_control
+            .iter()
+            .chain(self.tools.iter().flatten().filter_map(|t| t.cache_control.as_ref()))
+            .chain(self.messages.iter().filter_map(|m| m.cache_control.as_ref()))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +76,27 @@ pub struct IrMessage {
     /// Tool name (used by Gemini function responses).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Reasoning produced before the content of assistant messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Vec<IrReasoning>>,
+    /// Cache breakpoint at the end of this message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<IrCacheControl>,
+}
+
+/// An empty user message, for building messages with struct update syntax.
+impl Default for IrMessage {
+    fn default() -> Self {
+        Self {
+            role: IrRole::User,
+            content: IrContent::Text(String::new()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+            reasoning: None,
+            cache_control: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -97,6 +155,49 @@ pub enum IrContentPart {
         #[serde(skip_serializing_if = "Option::is_none")]
         data: Option<String>,
     },
+    /// Inline audio input, base64-encoded.
+    Audio {
+        media_type: String,
+        data: String,
+    },
+    /// A document or other file (PDF, plain text, hosted audio, ...): inline
+    /// base64 `data`, a `url`, or the id of a file uploaded to the provider.
+    File {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        media_type: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        data: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+}
+
+impl IrContentPart {
+    /// Short name of the part kind, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            IrContentPart::Text { .. } => "text",
+            IrContentPart::Image { .. } => "image",
+            IrContentPart::Audio { .. } => "audio",
+            IrContentPart::File { .. } => "file",
+        }
+    }
+}
+
+/// Build a `data:` URL from a media type and base64 data.
+pub fn data_url(media_type: &str, data: &str) -> String {
+    format!("data:{};base64,{}", media_type, data)
+}
+
+/// Split a base64 `data:` URL into its media type and data.
+pub fn parse_data_url(url: &str) -> Option<(String, String)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let media_type = header.strip_suffix(";base64")?;
+    Some((media_type.to_string(), data.to_string()))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +206,18 @@ pub struct IrTool {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub parameters: serde_json::Value,
+    /// Cache breakpoint after this tool definition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<IrCacheControl>,
+}
+
+/// Prompt cache breakpoint: the prompt up to and including the marked
+/// element is cached by providers with explicit caching (Anthropic).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IrCacheControl {
+    /// Cache lifetime such as `5m` or `1h`; the provider default if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +229,161 @@ pub enum IrToolChoice {
     Tool { name: String },
 }
 
+/// Format the assistant reply must follow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IrResponseFormat {
+    Text,
+    /// Any valid JSON object.
+    JsonObject,
+    /// JSON matching the given schema.
+    JsonSchema {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        schema: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+impl IrResponseFormat {
+    /// JSON schema the reply must match, `None` for plain text.
+    pub fn json_schema(&self) -> Option<serde_json::Value> {
+        match self {
+            Self::Text => None,
+            Self::JsonObject => Some(serde_json::json!({ "type": "object" })),
+            Self::JsonSchema { schema, .. } => Some(schema.clone()),
+        }
+    }
+}
+
+/// Reasoning configuration. Providers take either an effort level or a token
+/// budget; whichever is missing is derived from the other.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IrReasoningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<IrReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
+}
+
+impl IrReasoningConfig {
+    pub fn effort(&self) -> Option<IrReasoningEffort> {
+        self.effort
+            .or_else(|| self.budget_tokens.map(IrReasoningEffort::from_budget))
+    }
+
+    pub fn budget_tokens(&self) -> Option<u32> {
+        self.budget_tokens
+            .or_else(|| self.effort.map(IrReasoningEffort::budget_tokens))
+    }
+
+    /// True when reasoning was explicitly turned off.
+    pub fn is_disabled(&self) -> bool {
+        self.effort() == Some(IrReasoningEffort::None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IrReasoningEffort {
+    None,
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl IrReasoningEffort {
+    pub fn from_str_loose(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "minimal" => Some(Self::Minimal),
+            "low" => Some(Self::Low),
+            "medium" => Some(Self::Medium),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Minimal => "minimal",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// Thinking budget used for providers that take a token count.
+    /// Anthropic's minimum budget is 1024.
+    pub fn budget_tokens(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::Minimal => 1024,
+            Self::Low => 2048,
+            Self::Medium => 8192,
+            Self::High => 24576,
+        }
+    }
+
+    pub fn from_budget(budget: u32) -> Self {
+        match budget {
+            0 => Self::None,
+            1..=1024 => Self::Minimal,
+            1025..=2048 => Self::Low,
+            2049..=8192 => Self::Medium,
+            _ => Self::High,
+        }
+    }
+}
+
+/// A block of model reasoning. `signature` and `encrypted` are opaque values
+/// that must be sent back unchanged on the next turn, and are only meaningful
+/// to the provider recorded in `source`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IrReasoning {
+    /// Reasoning text or summary; empty when the provider withholds it.
+    #[serde(default)]
+    pub text: String,
+    /// Anthropic thinking signature or Gemini thought signature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Anthropic redacted thinking data or OpenAI encrypted reasoning content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<String>,
+    /// OpenAI Responses reasoning item id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ChatFormat>,
+}
+
+impl IrReasoning {
+    /// Opaque signature, if it was issued by `format`.
+    pub fn signature_for(&self, format: ChatFormat) -> Option<&str> {
+        self.signature.as_deref().filter(|_| self.source == Some(format))
+    }
+
+    /// Opaque encrypted content, if it was issued by `format`.
+    pub fn encrypted_for(&self, format: ChatFormat) -> Option<&str> {
+        self.encrypted.as_deref().filter(|_| self.source == Some(format))
+    }
+}
+
+/// Join the text of all reasoning blocks of a message.
+pub fn reasoning_text(reasoning: &Option<Vec<IrReasoning>>) -> Option<String> {
+    let text = reasoning
+        .iter()
+        .flatten()
+        .map(|r| r.text.as_str())
+        .collect::<Vec<_>>()
+        .join("");
+    (!text.is_empty()).then_some(text)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IrToolCall {
     pub id: String,
@@ -151,6 +419,15 @@ pub struct IrUsage {
     pub completion_tokens: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_tokens: Option<u32>,
+    /// Part of `completion_tokens` spent on reasoning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
+    /// Part of `prompt_tokens` read from the prompt cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_read_tokens: Option<u32>,
+    /// Part of `prompt_tokens` written to the prompt cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_write_tokens: Option<u32>,
 }
 
 // --- Streaming IR ---
@@ -167,6 +444,8 @@ pub struct IrStreamChunk {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delta_tool_calls: Option<Vec<IrToolCallDelta>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_reasoning: Option<IrReasoningDelta>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub finish_reason: Option<IrFinishReason>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<IrUsage>,
@@ -182,3 +461,83 @@ pub struct IrToolCallDelta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<String>,
 }
+
+/// Incremental reasoning. Text arrives in pieces; signatures and encrypted
+/// content arrive whole, usually once the reasoning block is complete.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IrReasoningDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ChatFormat>,
+}
+
+// --- Error IR ---
+
+/// Provider-neutral error category. Each format maps it to its own error
+/// type names (and Anthropic to its own 529 status for overload).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrErrorKind {
+    InvalidRequest,
+    Authentication,
+    PermissionDenied,
+    NotFound,
+    RequestTooLarge,
+    RateLimited,
+    Overloaded,
+    Internal,
+}
+
+impl IrErrorKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            400 | 422 => Self::InvalidRequest,
+            401 => Self::Authentication,
+            403 => Self::PermissionDenied,
+            404 => Self::NotFound,
+            413 => Self::RequestTooLarge,
+            429 => Self::RateLimited,
+            503 | 529 => Self::Overloaded,
+            _ => Self::Internal,
+        }
+    }
+
+    /// Status used when the error has none of its own, e.g. in-band
+    /// stream errors.
+    pub fn status(self) -> u16 {
+        match self {
+            Self::InvalidRequest => 400,
+            Self::Authentication => 401,
+            Self::PermissionDenied => 403,
+            Self::NotFound => 404,
+            Self::RequestTooLarge => 413,
+            Self::RateLimited => 429,
+            Self::Overloaded => 503,
+            Self::Internal => 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrError {
+    pub kind: IrErrorKind,
+    /// HTTP status to answer with, before format-specific overrides.
+    pub status: u16,
+    pub message: String,
+}
+
+impl IrError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            kind: IrErrorKind::from_status(status),
+            status,
+            message: message.into(),
+        }
+    }
+}
@@ -4,9 +4,12 @@ pub mod anthropic;
 pub mod openai_responses;
 pub mod gemini;
 pub mod moonshot;
+pub mod structured_output;
 
 use crate::error::AppError;
-use ir::{IrChatRequest, IrChatResponse, IrStreamChunk};
+use crate::sse::SseEvent;
+use ir::{IrChatRequest, IrChatResponse, IrError, IrErrorKind, IrStreamChunk};
+use serde_json::Value;
 
 /// Identifies the wire format of a request/response.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -23,7 +26,7 @@ impl ChatFormat {
     /// Parse from string (header value, query param, or provider name).
     pub fn from_str_loose(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
-            "openai-chat" | "openai_chat" | "openai" => Some(Self::OpenaiChat),
+            "openai-chat" | "openai_chat" | "openai" | "ollama" => Some(Self::OpenaiChat),
             "openai-responses" | "openai_responses" => Some(Self::OpenaiResponses),
             "anthropic" | "claude" => Some(Self::Anthropic),
             "gemini" | "google" => Some(Self::Gemini),
@@ -43,6 +46,12 @@ impl ChatFormat {
         }
     }
 
+    /// Whether the format has no native response format, so structured output
+    /// is emulated with a forced tool call (see [`structured_output`]).
+    pub fn emulates_response_format(&self) -> bool {
+        matches!(self, Self::Anthropic)
+    }
+
     /// Map from provider name stored in database channel.
     pub fn from_provider(provider: &str) -> Option<Self> {
         match provider {
@@ -63,9 +72,10 @@ pub trait Decoder: Send + Sync {
     /// Decode a non-streaming upstream response body into IR.
     fn decode_response(&self, body: &[u8]) -> Result<IrChatResponse, AppError>;
 
-    /// Decode a single SSE data line from upstream into an IR stream chunk.
-    /// Returns None if the line is a keep-alive, comment, or terminal signal.
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError>;
+    /// Decode a single SSE event from upstream into an IR stream chunk.
+    /// `event` is the SSE event type (`message` for unnamed events).
+    /// Returns None if the event is a keep-alive or terminal signal.
+    fn decode_stream_chunk(&self, event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError>;
 
     /// Returns true if the given SSE data line signals end-of-stream.
     fn is_stream_done(&self, data: &str) -> bool;
@@ -76,14 +86,34 @@ pub trait Encoder: Send + Sync {
     /// Encode IR request into bytes to send upstream.
     fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError>;
 
+    /// Whether upstream requests may reference images by URL. If not, remote
+    /// images are downloaded and inlined before `encode_request`.
+    fn accepts_image_urls(&self) -> bool {
+        true
+    }
+
     /// Encode IR response into bytes to send downstream.
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError>;
 
-    /// Encode an IR stream chunk into an SSE data line to send downstream.
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError>;
+    /// Encode an IR stream chunk into the SSE events to send downstream
+    /// (possibly none). Chunks of one stream go through the same encoder,
+    /// which may keep state between them.
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError>;
+
+    /// Return the SSE termination event for this format (e.g. "[DONE]").
+    fn stream_done_signal(&self) -> Option<SseEvent>;
 
-    /// Return the SSE termination signal for this format (e.g. "[DONE]").
-    fn stream_done_signal(&self) -> Option<String>;
+    /// Encode an error into the HTTP status and body this format's clients
+    /// expect. Defaults to the OpenAI shape.
+    fn encode_error(&self, error: &IrError) -> (u16, Value) {
+        openai_chat::encode_error(error)
+    }
+
+    /// Encode an error as an in-band SSE event, for failures after the
+    /// response status has already been sent.
+    fn encode_stream_error(&self, error: &IrError) -> SseEvent {
+        SseEvent::data(self.encode_error(error).1.to_string())
+    }
 }
 
 /// Get a decoder for a given format.
@@ -91,7 +121,7 @@ pub fn get_decoder(format: ChatFormat) -> Box<dyn Decoder> {
     match format {
         ChatFormat::OpenaiChat => Box::new(openai_chat::OpenAiChatCodec),
         ChatFormat::Moonshot => Box::new(moonshot::MoonshotCodec),
-        ChatFormat::Anthropic => Box::new(anthropic::AnthropicCodec),
+        ChatFormat::Anthropic => Box::new(anthropic::AnthropicCodec::default()),
         ChatFormat::Gemini => Box::new(gemini::GeminiCodec),
         ChatFormat::OpenaiResponses => Box::new(openai_responses::OpenAiResponsesCodec),
     }
@@ -102,8 +132,57 @@ pub fn get_encoder(format: ChatFormat) -> Box<dyn Encoder> {
     match format {
         ChatFormat::OpenaiChat => Box::new(openai_chat::OpenAiChatCodec),
         ChatFormat::Moonshot => Box::new(moonshot::MoonshotCodec),
-        ChatFormat::Anthropic => Box::new(anthropic::AnthropicCodec),
+        ChatFormat::Anthropic => Box::new(anthropic::AnthropicCodec::default()),
         ChatFormat::Gemini => Box::new(gemini::GeminiCodec),
         ChatFormat::OpenaiResponses => Box::new(openai_responses::OpenAiResponsesCodec),
     }
 }
+
+/// Translate an upstream error response into IR. The body may use any of the
+/// supported formats' error shapes; if it names no known error type, the kind
+/// follows the HTTP status.
+pub fn decode_upstream_error(status: u16, body: &str) -> IrError {
+    let parsed = serde_json::from_str::<Value>(body).ok().and_then(|v| parse_error_value(&v));
+    let (kind, message) = match parsed {
+        Some((kind, message)) if !message.is_empty() => {
+            (kind.unwrap_or_else(|| IrErrorKind::from_status(status)), message)
+        }
+        _ => (IrErrorKind::from_status(status), body.trim().to_string()),
+    };
+    // Only Anthropic answers overload with 529; the encoder restores it
+    let status = if kind == IrErrorKind::Overloaded { 503 } else { status };
+    IrError { kind, status, message }
+}
+
+/// Return the error carried by an SSE data line, if it is an error event.
+pub fn decode_stream_error(data: &str) -> Option<IrError> {
+    if !data.contains("error") {
+        return None;
+    }
+    let value = serde_json::from_str::<Value>(data).ok()?;
+    let (kind, message) = parse_error_value(&value)?;
+    let kind = kind.unwrap_or(IrErrorKind::Internal);
+    Some(IrError { kind, status: kind.status(), message })
+}
+
+/// Find the error object in an error body or stream event and return its
+/// kind, if it names a known error type, and message.
+fn parse_error_value(value: &Value) -> Option<(Option<IrErrorKind>, String)> {
+    // Gemini REST errors may arrive wrapped in a one-element array
+    let value = value.as_array().and_then(|a| a.first()).unwrap_or(value);
+    let error = match value.get("error") {
+        Some(error) if error.is_object() => error,
+        // Responses API `error` events carry the fields at the top level
+        _ if value.get("type").and_then(Value::as_str) == Some("error") => value,
+        // Responses API `response.failed` events
+        _ => value.pointer("/response/error").filter(|e| e.is_object())?,
+    };
+    let field = |key: &str| error.get(key).and_then(Value::as_str);
+
+    let kind = field("code")
+        .and_then(openai_chat::error_kind)
+        .or_else(|| field("status").and_then(gemini::error_kind))
+        .or_else(|| field("type").and_then(anthropic::error_kind));
+    let message = field("message").unwrap_or_default().to_string();
+    Some((kind, message))
+}
@@ -2,9 +2,11 @@ use super::ir::*;
 use super::openai_chat::OpenAiChatCodec;
 use super::{Decoder, Encoder};
 use crate::error::AppError;
+use crate::sse::SseEvent;
 
 /// Moonshot codec — delegates to OpenAI Chat codec.
-/// Moonshot API is OpenAI-compatible with minor additions.
+/// Moonshot API is OpenAI-compatible with minor additions: thinking models
+/// expect earlier `reasoning_content` to be sent back.
 pub struct MoonshotCodec;
 
 impl Decoder for MoonshotCodec {
@@ -16,8 +18,8 @@ impl Decoder for MoonshotCodec {
         OpenAiChatCodec.decode_response(body)
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
-        OpenAiChatCodec.decode_stream_chunk(data)
+    fn decode_stream_chunk(&self, event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+        OpenAiChatCodec.decode_stream_chunk(event, data)
     }
 
     fn is_stream_done(&self, data: &str) -> bool {
@@ -27,18 +29,18 @@ impl Decoder for MoonshotCodec {
 
 impl Encoder for MoonshotCodec {
     fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError> {
-        OpenAiChatCodec.encode_request(ir, model)
+        OpenAiChatCodec.encode_request_with_reasoning(ir, model, true)
     }
 
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
         OpenAiChatCodec.encode_response(ir)
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
         OpenAiChatCodec.encode_stream_chunk(chunk)
     }
 
-    fn stream_done_signal(&self) -> Option<String> {
-        Some("[DONE]".to_string())
+    fn stream_done_signal(&self) -> Option<SseEvent> {
+        OpenAiChatCodec.stream_done_signal()
     }
 }
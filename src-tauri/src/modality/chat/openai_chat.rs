@@ -1,7 +1,9 @@
 use super::ir::*;
-use super::{Decoder, Encoder};
+use super::{ChatFormat, Decoder, Encoder};
 use crate::error::AppError;
+use crate::sse::SseEvent;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub struct OpenAiChatCodec;
 
@@ -22,11 +24,43 @@ pub struct OaiRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<OaiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream_options: Option<OaiStreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<OaiResponseFormat>,
+    /// Fields without an IR counterpart (`n`, `logit_bias`, `user`, ...).
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OaiResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: OaiJsonSchema },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiJsonSchema {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,6 +79,9 @@ pub struct OaiMessage {
     pub tool_call_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Reasoning text returned by DeepSeek, Moonshot and other compatible APIs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -101,6 +138,25 @@ pub struct OaiUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<OaiPromptTokensDetails>,
+    /// Moonshot reports cached prompt tokens at the top level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<OaiCompletionTokensDetails>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiPromptTokensDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiCompletionTokensDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
 }
 
 // --- Streaming types ---
@@ -131,6 +187,8 @@ pub struct OaiStreamDelta {
     pub content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OaiStreamToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -193,6 +251,27 @@ fn oai_content_to_ir(content: &Option<serde_json::Value>) -> IrContent {
                                 data: None,
                             })
                         }
+                        "input_audio" => {
+                            let audio = p.get("input_audio")?;
+                            let format = audio.get("format")?.as_str()?;
+                            Some(IrContentPart::Audio {
+                                media_type: audio_format_to_media_type(format),
+                                data: audio.get("data")?.as_str()?.to_string(),
+                            })
+                        }
+                        "file" => {
+                            let file = p.get("file")?;
+                            let str_field =
+                                |key: &str| file.get(key).and_then(|v| v.as_str()).map(String::from);
+                            let inline = str_field("file_data").and_then(|d| parse_data_url(&d));
+                            Some(IrContentPart::File {
+                                media_type: inline.as_ref().map(|(m, _)| m.clone()),
+                                data: inline.map(|(_, d)| d),
+                                url: None,
+                                file_id: str_field("file_id"),
+                                filename: str_field("filename"),
+                            })
+                        }
                         _ => None,
                     }
                 })
@@ -204,24 +283,153 @@ fn oai_content_to_ir(content: &Option<serde_json::Value>) -> IrContent {
     }
 }
 
-fn ir_content_to_oai(content: &IrContent) -> serde_json::Value {
+fn ir_content_to_oai(content: &IrContent) -> Result<serde_json::Value, AppError> {
     match content {
-        IrContent::Text(s) => serde_json::Value::String(s.clone()),
+        IrContent::Text(s) => Ok(serde_json::Value::String(s.clone())),
         IrContent::Parts(parts) => {
-            let oai_parts: Vec<serde_json::Value> = parts
+            let oai_parts = parts
                 .iter()
                 .map(|p| match p {
-                    IrContentPart::Text { text } => serde_json::json!({
+                    IrContentPart::Text { text } => Ok(serde_json::json!({
                         "type": "text",
                         "text": text,
-                    }),
-                    IrContentPart::Image { url, .. } => serde_json::json!({
-                        "type": "image_url",
-                        "image_url": { "url": url },
-                    }),
+                    })),
+                    IrContentPart::Image { url, media_type, data } => {
+                        let url = match (url, data) {
+                            (Some(url), _) => url.clone(),
+                            (None, Some(data)) => {
+                                data_url(media_type.as_deref().unwrap_or("image/png"), data)
+                            }
+                            (None, None) => {
+                                return Err(AppError::BadRequest(
+                                    "Image part has neither data nor a URL".to_string(),
+                                ))
+                            }
+                        };
+                        Ok(serde_json::json!({
+                            "type": "image_url",
+                            "image_url": { "url": url },
+                        }))
+                    }
+                    IrContentPart::Audio { media_type, data } => Ok(serde_json::json!({
+                        "type": "input_audio",
+                        "input_audio": {
+                            "data": data,
+                            "format": media_type_to_audio_format(media_type),
+                        },
+                    })),
+                    IrContentPart::File { media_type, data, file_id, filename, .. } => {
+                        let mut file = serde_json::Map::new();
+                        if let Some(id) = file_id {
+                            file.insert("file_id".into(), serde_json::json!(id));
+                        } else if let Some(data) = data {
+                            let media_type = media_type.as_deref().unwrap_or("application/pdf");
+                            file.insert("file_data".into(), serde_json::json!(data_url(media_type, data)));
+                        } else {
+                            return Err(AppError::BadRequest(
+                                "OpenAI Chat Completions does not accept file URLs; send the file inline"
+                                    .to_string(),
+                            ));
+                        }
+                        if let Some(name) = filename {
+                            file.insert("filename".into(), serde_json::json!(name));
+                        }
+                        Ok(serde_json::json!({ "type": "file", "file": file }))
+                    }
                 })
-                .collect();
-            serde_json::Value::Array(oai_parts)
+                .collect::<Result<Vec<_>, AppError>>()?;
+            Ok(serde_json::Value::Array(oai_parts))
+        }
+    }
+}
+
+/// `input_audio.format` (`wav`, `mp3`) to a MIME type.
+fn audio_format_to_media_type(format: &str) -> String {
+    match format {
+        "mp3" => "audio/mpeg".to_string(),
+        other => format!("audio/{}", other),
+    }
+}
+
+fn media_type_to_audio_format(media_type: &str) -> &str {
+    match media_type {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        other => other.strip_prefix("audio/").unwrap_or(other),
+    }
+}
+
+fn oai_usage_to_ir(u: &OaiUsage) -> IrUsage {
+    IrUsage {
+        prompt_tokens: u.prompt_tokens,
+        completion_tokens: u.completion_tokens,
+        total_tokens: Some(u.total_tokens),
+        reasoning_tokens: u
+            .completion_tokens_details
+            .as_ref()
+            .and_then(|d| d.reasoning_tokens),
+        cache_read_tokens: u
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens)
+            .or(u.cached_tokens),
+        cache_write_tokens: None,
+    }
+}
+
+fn ir_usage_to_oai(u: &IrUsage) -> OaiUsage {
+    OaiUsage {
+        prompt_tokens: u.prompt_tokens,
+        completion_tokens: u.completion_tokens,
+        total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
+        prompt_tokens_details: u.cache_read_tokens.map(|c| OaiPromptTokensDetails {
+            cached_tokens: Some(c),
+        }),
+        cached_tokens: None,
+        completion_tokens_details: u.reasoning_tokens.map(|r| OaiCompletionTokensDetails {
+            reasoning_tokens: Some(r),
+        }),
+    }
+}
+
+fn oai_reasoning_to_ir(reasoning_content: &Option<String>) -> Option<Vec<IrReasoning>> {
+    reasoning_content
+        .as_ref()
+        .filter(|text| !text.is_empty())
+        .map(|text| {
+            vec![IrReasoning {
+                text: text.clone(),
+                source: Some(ChatFormat::OpenaiChat),
+                ..Default::default()
+            }]
+        })
+}
+
+fn oai_response_format_to_ir(format: OaiResponseFormat) -> IrResponseFormat {
+    match format {
+        OaiResponseFormat::Text => IrResponseFormat::Text,
+        OaiResponseFormat::JsonObject => IrResponseFormat::JsonObject,
+        OaiResponseFormat::JsonSchema { json_schema } => IrResponseFormat::JsonSchema {
+            name: json_schema.name,
+            description: json_schema.description,
+            schema: json_schema.schema.unwrap_or_else(|| serde_json::json!({})),
+            strict: json_schema.strict,
+        },
+    }
+}
+
+fn ir_response_format_to_oai(format: &IrResponseFormat) -> OaiResponseFormat {
+    match format {
+        IrResponseFormat::Text => OaiResponseFormat::Text,
+        IrResponseFormat::JsonObject => OaiResponseFormat::JsonObject,
+        IrResponseFormat::JsonSchema { name, description, schema, strict } => {
+            OaiResponseFormat::JsonSchema {
+                json_schema: OaiJsonSchema {
+                    name: name.clone(),
+                    description: description.clone(),
+                    schema: Some(schema.clone()),
+                    strict: *strict,
+                },
+            }
         }
     }
 }
@@ -266,6 +474,8 @@ impl Decoder for OpenAiChatCodec {
                     tool_calls: None,
                     tool_call_id: msg.tool_call_id.clone(),
                     name: msg.name.clone(),
+                    reasoning: oai_reasoning_to_ir(&msg.reasoning_content),
+                    cache_control: None,
                 };
 
                 if let Some(tcs) = &msg.tool_calls {
@@ -290,6 +500,7 @@ impl Decoder for OpenAiChatCodec {
                     name: t.function.name,
                     description: t.function.description,
                     parameters: t.function.parameters.unwrap_or(serde_json::json!({})),
+                    cache_control: None,
                 })
                 .collect()
         });
@@ -308,12 +519,16 @@ impl Decoder for OpenAiChatCodec {
             }
         });
 
+        let extra = (!req.extra.is_empty()).then_some(req.extra);
+
         Ok(IrChatRequest {
             model: req.model,
             messages,
             system,
+            system_cache_control: None,
             temperature: req.temperature,
             top_p: req.top_p,
+            top_k: None,
             max_tokens: req.max_tokens,
             stream: req.stream.unwrap_or(false),
             stop: req.stop.and_then(|s| {
@@ -331,9 +546,18 @@ impl Decoder for OpenAiChatCodec {
                     None
                 }
             }),
+            seed: req.seed,
+            presence_penalty: req.presence_penalty,
+            frequency_penalty: req.frequency_penalty,
             tools,
             tool_choice,
-            extra: None,
+            reasoning: req.reasoning_effort.as_deref().map(|effort| IrReasoningConfig {
+                effort: IrReasoningEffort::from_str_loose(effort),
+                budget_tokens: None,
+            }),
+            response_format: req.response_format.map(oai_response_format_to_ir),
+            extra_source: extra.as_ref().map(|_| ChatFormat::OpenaiChat),
+            extra,
         })
     }
 
@@ -351,6 +575,8 @@ impl Decoder for OpenAiChatCodec {
             tool_calls: None,
             tool_call_id: None,
             name: None,
+            reasoning: oai_reasoning_to_ir(&choice.message.reasoning_content),
+            cache_control: None,
         };
 
         if let Some(tcs) = &choice.message.tool_calls {
@@ -370,15 +596,11 @@ impl Decoder for OpenAiChatCodec {
             model: resp.model,
             message: ir_msg,
             finish_reason: oai_finish_to_ir(&choice.finish_reason),
-            usage: resp.usage.map(|u| IrUsage {
-                prompt_tokens: u.prompt_tokens,
-                completion_tokens: u.completion_tokens,
-                total_tokens: Some(u.total_tokens),
-            }),
+            usage: resp.usage.as_ref().map(oai_usage_to_ir),
         })
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+    fn decode_stream_chunk(&self, _event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
         if data.trim().is_empty() || self.is_stream_done(data) {
             return Ok(None);
         }
@@ -397,12 +619,9 @@ impl Decoder for OpenAiChatCodec {
                         delta_role: None,
                         delta_content: None,
                         delta_tool_calls: None,
+                        delta_reasoning: None,
                         finish_reason: None,
-                        usage: Some(IrUsage {
-                            prompt_tokens: usage.prompt_tokens,
-                            completion_tokens: usage.completion_tokens,
-                            total_tokens: Some(usage.total_tokens),
-                        }),
+                        usage: Some(oai_usage_to_ir(usage)),
                     }));
                 }
                 return Ok(None);
@@ -426,12 +645,13 @@ impl Decoder for OpenAiChatCodec {
             delta_role: choice.delta.role.as_ref().map(|r| oai_role_to_ir(r)),
             delta_content: choice.delta.content.clone(),
             delta_tool_calls,
-            finish_reason: oai_finish_to_ir(&choice.finish_reason),
-            usage: chunk.usage.map(|u| IrUsage {
-                prompt_tokens: u.prompt_tokens,
-                completion_tokens: u.completion_tokens,
-                total_tokens: Some(u.total_tokens),
+            delta_reasoning: choice.delta.reasoning_content.clone().map(|text| IrReasoningDelta {
+                text: Some(text),
+                source: Some(ChatFormat::OpenaiChat),
+                ..Default::default()
             }),
+            finish_reason: oai_finish_to_ir(&choice.finish_reason),
+            usage: chunk.usage.as_ref().map(oai_usage_to_ir),
         }))
     }
 
@@ -442,8 +662,16 @@ impl Decoder for OpenAiChatCodec {
 
 // --- Encoder impl ---
 
-impl Encoder for OpenAiChatCodec {
-    fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError> {
+impl OpenAiChatCodec {
+    /// Encode a request, optionally echoing assistant reasoning back as
+    /// `reasoning_content`. Thinking models of some compatible APIs require it
+    /// across tool calls; OpenAI itself rejects the field.
+    pub(super) fn encode_request_with_reasoning(
+        &self,
+        ir: &IrChatRequest,
+        model: &str,
+        keep_reasoning: bool,
+    ) -> Result<Vec<u8>, AppError> {
         let mut messages = Vec::new();
 
         // Add system message first if present
@@ -454,16 +682,22 @@ impl Encoder for OpenAiChatCodec {
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
+                reasoning_content: None,
             });
         }
 
         for msg in &ir.messages {
             let mut oai_msg = OaiMessage {
                 role: ir_role_to_oai(&msg.role).to_string(),
-                content: Some(ir_content_to_oai(&msg.content)),
+                content: Some(ir_content_to_oai(&msg.content)?),
                 tool_calls: None,
                 tool_call_id: msg.tool_call_id.clone(),
                 name: msg.name.clone(),
+                reasoning_content: if keep_reasoning {
+                    reasoning_text(&msg.reasoning)
+                } else {
+                    None
+                },
             };
 
             if let Some(tcs) = &msg.tool_calls {
@@ -516,6 +750,9 @@ impl Encoder for OpenAiChatCodec {
             max_tokens: ir.max_tokens,
             stream: if ir.stream { Some(true) } else { None },
             stop: ir.stop.as_ref().map(|s| serde_json::json!(s)),
+            seed: ir.seed,
+            presence_penalty: ir.presence_penalty,
+            frequency_penalty: ir.frequency_penalty,
             tools,
             tool_choice,
             stream_options: if ir.stream {
@@ -523,18 +760,34 @@ impl Encoder for OpenAiChatCodec {
             } else {
                 None
             },
+            // OpenAI has no way to turn reasoning off on every model; omit it instead
+            reasoning_effort: ir
+                .reasoning
+                .as_ref()
+                .and_then(|r| r.effort())
+                .filter(|e| *e != IrReasoningEffort::None)
+                .map(|e| e.as_str().to_string()),
+            response_format: ir.response_format.as_ref().map(ir_response_format_to_oai),
+            extra: ir.extra_for(ChatFormat::OpenaiChat).cloned().unwrap_or_default(),
         };
 
         serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
     }
+}
+
+impl Encoder for OpenAiChatCodec {
+    fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        self.encode_request_with_reasoning(ir, model, false)
+    }
 
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
         let mut oai_msg = OaiMessage {
             role: ir_role_to_oai(&ir.message.role).to_string(),
-            content: Some(ir_content_to_oai(&ir.message.content)),
+            content: Some(ir_content_to_oai(&ir.message.content)?),
             tool_calls: None,
             tool_call_id: None,
             name: None,
+            reasoning_content: reasoning_text(&ir.message.reasoning),
         };
 
         if let Some(tcs) = &ir.message.tool_calls {
@@ -552,11 +805,7 @@ impl Encoder for OpenAiChatCodec {
             );
         }
 
-        let usage = ir.usage.as_ref().map(|u| OaiUsage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
-        });
+        let usage = ir.usage.as_ref().map(ir_usage_to_oai);
 
         let resp = OaiResponse {
             id: ir.id.clone(),
@@ -573,7 +822,7 @@ impl Encoder for OpenAiChatCodec {
         serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
         let delta_tool_calls = chunk.delta_tool_calls.as_ref().map(|tcs| {
             tcs.iter()
                 .map(|tc| OaiStreamToolCall {
@@ -602,23 +851,83 @@ impl Encoder for OpenAiChatCodec {
                     role: chunk.delta_role.as_ref().map(|r| ir_role_to_oai(r).to_string()),
                     content: chunk.delta_content.clone(),
                     tool_calls: delta_tool_calls,
+                    reasoning_content: chunk
+                        .delta_reasoning
+                        .as_ref()
+                        .and_then(|r| r.text.clone()),
                 },
                 finish_reason: ir_finish_to_oai(&chunk.finish_reason),
             }],
-            usage: chunk.usage.as_ref().map(|u| OaiUsage {
-                prompt_tokens: u.prompt_tokens,
-                completion_tokens: u.completion_tokens,
-                total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
-            }),
+            usage: chunk.usage.as_ref().map(ir_usage_to_oai),
         };
 
         let json = serde_json::to_string(&oai_chunk)
             .map_err(|e| AppError::Codec(e.to_string()))?;
 
-        Ok(Some(json))
+        Ok(vec![SseEvent::data(json)])
+    }
+
+    fn stream_done_signal(&self) -> Option<SseEvent> {
+        Some(SseEvent::data("[DONE]"))
+    }
+}
+
+/// OpenAI error `type` and `code` for an error kind.
+fn error_type_and_code(kind: IrErrorKind) -> (&'static str, Option<&'static str>) {
+    match kind {
+        IrErrorKind::InvalidRequest => ("invalid_request_error", None),
+        IrErrorKind::Authentication => ("invalid_request_error", Some("invalid_api_key")),
+        IrErrorKind::PermissionDenied => ("invalid_request_error", Some("permission_denied")),
+        IrErrorKind::NotFound => ("invalid_request_error", Some("not_found")),
+        IrErrorKind::RequestTooLarge => ("invalid_request_error", Some("request_too_large")),
+        IrErrorKind::RateLimited => ("rate_limit_error", Some("rate_limit_exceeded")),
+        IrErrorKind::Overloaded => ("server_error", Some("overloaded")),
+        IrErrorKind::Internal => ("server_error", None),
     }
+}
+
+/// Error kind for an OpenAI error `code`. The `type` is too coarse to use
+/// (401s are `invalid_request_error` too), so unknown codes yield `None`.
+pub(crate) fn error_kind(code: &str) -> Option<IrErrorKind> {
+    match code {
+        "invalid_api_key" => Some(IrErrorKind::Authentication),
+        "permission_denied" | "unsupported_country_region_territory" => Some(IrErrorKind::PermissionDenied),
+        "not_found" | "model_not_found" => Some(IrErrorKind::NotFound),
+        "context_length_exceeded" | "invalid_value" => Some(IrErrorKind::InvalidRequest),
+        "request_too_large" => Some(IrErrorKind::RequestTooLarge),
+        "rate_limit_exceeded" | "insufficient_quota" => Some(IrErrorKind::RateLimited),
+        "overloaded" => Some(IrErrorKind::Overloaded),
+        _ => None,
+    }
+}
+
+/// The OpenAI error shape, which is also the default for formats that don't
+/// define their own.
+pub(crate) fn encode_error(error: &IrError) -> (u16, serde_json::Value) {
+    let (error_type, code) = error_type_and_code(error.kind);
+    let body = serde_json::json!({
+        "error": {
+            "message": error.message,
+            "type": error_type,
+            "param": null,
+            "code": code,
+        }
+    });
+    (error.status, body)
+}
 
-    fn stream_done_signal(&self) -> Option<String> {
-        Some("[DONE]".to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overloaded_error_is_a_server_error() {
+        let error = IrError::new(503, "Overloaded");
+        assert_eq!(error.kind, IrErrorKind::Overloaded);
+        let (status, body) = OpenAiChatCodec.encode_error(&error);
+        assert_eq!(status, 503);
+        assert_eq!(body["error"]["type"], "server_error");
+        assert_eq!(body["error"]["code"], "overloaded");
+        assert_eq!(body["error"]["message"], "Overloaded");
     }
 }
@@ -1,7 +1,9 @@
 use super::ir::*;
-use super::{Decoder, Encoder};
+use super::{ChatFormat, Decoder, Encoder};
 use crate::error::AppError;
+use crate::sse::SseEvent;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub struct OpenAiResponsesCodec;
 
@@ -27,6 +29,48 @@ pub struct OaiRespApiRequest {
     pub tools: Option<Vec<OaiRespApiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<OaiRespApiReasoningConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<OaiRespApiTextConfig>,
+    /// `in_memory` or `24h`. OpenAI caches prompt prefixes automatically, so
+    /// this is the only part of a cache breakpoint it takes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_cache_retention: Option<String>,
+    /// Fields without an IR counterpart (`store`, `include`, `metadata`, ...).
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiRespApiReasoningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiRespApiTextConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<OaiRespApiTextFormat>,
+}
+
+/// Output format of `text.format`; unlike Chat Completions the schema fields
+/// are not nested under `json_schema`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OaiRespApiTextFormat {
+    Text,
+    JsonObject,
+    JsonSchema {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        schema: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
 }
 
 /// Input can be a plain string (shorthand for a single user message) or
@@ -59,6 +103,15 @@ pub enum OaiRespApiInputItem {
         call_id: String,
         output: String,
     },
+    /// Reasoning from an earlier turn, passed back as it was output.
+    Reasoning {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(default)]
+        summary: Vec<OaiRespApiSummaryPart>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -104,6 +157,20 @@ pub enum OaiRespApiOutputItem {
         name: String,
         arguments: String,
     },
+    Reasoning {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(default)]
+        summary: Vec<OaiRespApiSummaryPart>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encrypted_content: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OaiRespApiSummaryPart {
+    SummaryText { text: String },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -121,6 +188,22 @@ pub struct OaiRespApiUsage {
     pub input_tokens: u32,
     pub output_tokens: u32,
     pub total_tokens: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens_details: Option<OaiRespApiInputTokensDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_details: Option<OaiRespApiOutputTokensDetails>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiRespApiInputTokensDetails {
+    #[serde(default)]
+    pub cached_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OaiRespApiOutputTokensDetails {
+    #[serde(default)]
+    pub reasoning_tokens: u32,
 }
 
 // =============================================================================
@@ -131,7 +214,7 @@ pub struct OaiRespApiUsage {
 /// The `type` field determines the event kind.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OaiRespApiStreamEvent {
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default)]
     pub event_type: String,
 
     // Present on response.created / response.completed / response.done
@@ -199,6 +282,18 @@ fn resp_content_to_ir(value: &serde_json::Value) -> IrContent {
                                 data,
                             })
                         }
+                        "input_file" => {
+                            let str_field =
+                                |key: &str| p.get(key).and_then(|v| v.as_str()).map(String::from);
+                            let inline = str_field("file_data").and_then(|d| parse_data_url(&d));
+                            Some(IrContentPart::File {
+                                media_type: inline.as_ref().map(|(m, _)| m.clone()),
+                                data: inline.map(|(_, d)| d),
+                                url: str_field("file_url"),
+                                file_id: str_field("file_id"),
+                                filename: str_field("filename"),
+                            })
+                        }
                         _ => None,
                     }
                 })
@@ -210,33 +305,56 @@ fn resp_content_to_ir(value: &serde_json::Value) -> IrContent {
     }
 }
 
-fn ir_content_to_resp_input(content: &IrContent) -> serde_json::Value {
+fn ir_content_to_resp_input(content: &IrContent) -> Result<serde_json::Value, AppError> {
     match content {
-        IrContent::Text(s) => serde_json::Value::String(s.clone()),
+        IrContent::Text(s) => Ok(serde_json::Value::String(s.clone())),
         IrContent::Parts(parts) => {
-            let resp_parts: Vec<serde_json::Value> = parts
+            let resp_parts = parts
                 .iter()
                 .map(|p| match p {
-                    IrContentPart::Text { text } => serde_json::json!({
+                    IrContentPart::Text { text } => Ok(serde_json::json!({
                         "type": "input_text",
                         "text": text,
-                    }),
+                    })),
                     IrContentPart::Image { url, media_type, data } => {
-                        let mut obj = serde_json::json!({"type": "input_image"});
-                        if let Some(u) = url {
-                            obj["image_url"] = serde_json::Value::String(u.clone());
-                        }
-                        if let Some(d) = data {
-                            obj["data"] = serde_json::Value::String(d.clone());
+                        let url = match (url, data) {
+                            (Some(url), _) => url.clone(),
+                            (None, Some(data)) => {
+                                data_url(media_type.as_deref().unwrap_or("image/png"), data)
+                            }
+                            (None, None) => {
+                                return Err(AppError::BadRequest(
+                                    "Image part has neither data nor a URL".to_string(),
+                                ))
+                            }
+                        };
+                        Ok(serde_json::json!({"type": "input_image", "image_url": url}))
+                    }
+                    IrContentPart::Audio { .. } => Err(AppError::BadRequest(
+                        "The OpenAI Responses API does not accept audio input".to_string(),
+                    )),
+                    IrContentPart::File { media_type, data, url, file_id, filename } => {
+                        let mut obj = serde_json::json!({"type": "input_file"});
+                        if let Some(id) = file_id {
+                            obj["file_id"] = serde_json::Value::String(id.clone());
+                        } else if let Some(data) = data {
+                            let media_type = media_type.as_deref().unwrap_or("application/pdf");
+                            obj["file_data"] = serde_json::Value::String(data_url(media_type, data));
+                        } else if let Some(url) = url {
+                            obj["file_url"] = serde_json::Value::String(url.clone());
+                        } else {
+                            return Err(AppError::BadRequest(
+                                "File part has neither data, a URL nor a file id".to_string(),
+                            ));
                         }
-                        if let Some(m) = media_type {
-                            obj["media_type"] = serde_json::Value::String(m.clone());
+                        if let Some(name) = filename {
+                            obj["filename"] = serde_json::Value::String(name.clone());
                         }
-                        obj
+                        Ok(obj)
                     }
                 })
-                .collect();
-            serde_json::Value::Array(resp_parts)
+                .collect::<Result<Vec<_>, AppError>>()?;
+            Ok(serde_json::Value::Array(resp_parts))
         }
     }
 }
@@ -275,6 +393,88 @@ fn ir_finish_to_resp_status(reason: &Option<IrFinishReason>) -> String {
     }
 }
 
+fn resp_usage_to_ir(u: &OaiRespApiUsage) -> IrUsage {
+    IrUsage {
+        prompt_tokens: u.input_tokens,
+        completion_tokens: u.output_tokens,
+        total_tokens: Some(u.total_tokens),
+        reasoning_tokens: u.output_tokens_details.as_ref().map(|d| d.reasoning_tokens),
+        cache_read_tokens: u.input_tokens_details.as_ref().map(|d| d.cached_tokens),
+        cache_write_tokens: None,
+    }
+}
+
+fn ir_usage_to_resp(u: &IrUsage) -> OaiRespApiUsage {
+    OaiRespApiUsage {
+        input_tokens: u.prompt_tokens,
+        output_tokens: u.completion_tokens,
+        total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
+        input_tokens_details: u
+            .cache_read_tokens
+            .map(|c| OaiRespApiInputTokensDetails { cached_tokens: c }),
+        output_tokens_details: u
+            .reasoning_tokens
+            .map(|r| OaiRespApiOutputTokensDetails { reasoning_tokens: r }),
+    }
+}
+
+fn resp_reasoning_to_ir(
+    id: &Option<String>,
+    summary: &[OaiRespApiSummaryPart],
+    encrypted_content: &Option<String>,
+) -> IrReasoning {
+    IrReasoning {
+        text: summary
+            .iter()
+            .map(|OaiRespApiSummaryPart::SummaryText { text }| text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        signature: None,
+        encrypted: encrypted_content.clone(),
+        id: id.clone(),
+        source: Some(ChatFormat::OpenaiResponses),
+    }
+}
+
+fn ir_reasoning_summary(r: &IrReasoning) -> Vec<OaiRespApiSummaryPart> {
+    if r.text.is_empty() {
+        vec![]
+    } else {
+        vec![OaiRespApiSummaryPart::SummaryText {
+            text: r.text.clone(),
+        }]
+    }
+}
+
+/// Longest Anthropic cache TTL, mapped to OpenAI's extended prompt cache retention.
+const EXTENDED_CACHE_TTL: &str = "1h";
+const EXTENDED_CACHE_RETENTION: &str = "24h";
+
+fn resp_text_format_to_ir(format: OaiRespApiTextFormat) -> IrResponseFormat {
+    match format {
+        OaiRespApiTextFormat::Text => IrResponseFormat::Text,
+        OaiRespApiTextFormat::JsonObject => IrResponseFormat::JsonObject,
+        OaiRespApiTextFormat::JsonSchema { name, description, schema, strict } => {
+            IrResponseFormat::JsonSchema { name, description, schema, strict }
+        }
+    }
+}
+
+fn ir_response_format_to_resp(format: &IrResponseFormat) -> OaiRespApiTextFormat {
+    match format {
+        IrResponseFormat::Text => OaiRespApiTextFormat::Text,
+        IrResponseFormat::JsonObject => OaiRespApiTextFormat::JsonObject,
+        IrResponseFormat::JsonSchema { name, description, schema, strict } => {
+            OaiRespApiTextFormat::JsonSchema {
+                name: name.clone(),
+                description: description.clone(),
+                schema: schema.clone(),
+                strict: *strict,
+            }
+        }
+    }
+}
+
 fn has_tool_calls_in_output(output: &[OaiRespApiOutputItem]) -> bool {
     output.iter().any(|item| matches!(item, OaiRespApiOutputItem::FunctionCall { .. }))
 }
@@ -289,6 +489,11 @@ impl Decoder for OpenAiResponsesCodec {
             serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
 
         let system = req.instructions.clone();
+        // Extended retention becomes a long-lived breakpoint after the system prompt
+        let system_cache_control = (req.prompt_cache_retention.as_deref() == Some(EXTENDED_CACHE_RETENTION))
+            .then(|| IrCacheControl {
+                ttl: Some(EXTENDED_CACHE_TTL.to_string()),
+            });
 
         let mut messages = Vec::new();
 
@@ -300,18 +505,30 @@ impl Decoder for OpenAiResponsesCodec {
                     tool_calls: None,
                     tool_call_id: None,
                     name: None,
+                    reasoning: None,
+                    cache_control: None,
                 });
             }
             OaiRespApiInput::Items(items) => {
+                // Reasoning items belong to the assistant output that follows them
+                let mut pending_reasoning: Vec<IrReasoning> = Vec::new();
                 for item in items {
                     match item {
                         OaiRespApiInputItem::Message { role, content } => {
+                            let role = resp_role_to_ir(role);
+                            let reasoning = if role == IrRole::Assistant && !pending_reasoning.is_empty() {
+                                Some(std::mem::take(&mut pending_reasoning))
+                            } else {
+                                None
+                            };
                             messages.push(IrMessage {
-                                role: resp_role_to_ir(role),
+                                role,
                                 content: resp_content_to_ir(content),
                                 tool_calls: None,
                                 tool_call_id: None,
                                 name: None,
+                                reasoning,
+                                cache_control: None,
                             });
                         }
                         OaiRespApiInputItem::FunctionCall {
@@ -332,6 +549,9 @@ impl Decoder for OpenAiResponsesCodec {
                                 }]),
                                 tool_call_id: None,
                                 name: None,
+                                reasoning: (!pending_reasoning.is_empty())
+                                    .then(|| std::mem::take(&mut pending_reasoning)),
+                                cache_control: None,
                             });
                         }
                         OaiRespApiInputItem::FunctionCallOutput { call_id, output } => {
@@ -342,10 +562,30 @@ impl Decoder for OpenAiResponsesCodec {
                                 tool_calls: None,
                                 tool_call_id: Some(call_id.clone()),
                                 name: None,
+                                reasoning: None,
+                                cache_control: None,
                             });
                         }
+                        OaiRespApiInputItem::Reasoning {
+                            id,
+                            summary,
+                            encrypted_content,
+                        } => {
+                            pending_reasoning.push(resp_reasoning_to_ir(id, summary, encrypted_content));
+                        }
                     }
                 }
+                if !pending_reasoning.is_empty() {
+                    messages.push(IrMessage {
+                        role: IrRole::Assistant,
+                        content: IrContent::Text(String::new()),
+                        tool_calls: None,
+                        tool_call_id: None,
+                        name: None,
+                        reasoning: Some(pending_reasoning),
+                        cache_control: None,
+                    });
+                }
             }
         }
 
@@ -355,6 +595,7 @@ impl Decoder for OpenAiResponsesCodec {
                     name: t.name,
                     description: t.description,
                     parameters: t.parameters.unwrap_or(serde_json::json!({})),
+                    cache_control: None,
                 })
                 .collect()
         });
@@ -373,18 +614,31 @@ impl Decoder for OpenAiResponsesCodec {
             }
         });
 
+        let extra = (!req.extra.is_empty()).then_some(req.extra);
+
         Ok(IrChatRequest {
             model: req.model,
             messages,
             system,
+            system_cache_control,
             temperature: req.temperature,
             top_p: req.top_p,
+            top_k: None,
             max_tokens: req.max_output_tokens,
             stream: req.stream.unwrap_or(false),
             stop: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
             tools,
             tool_choice,
-            extra: None,
+            reasoning: req.reasoning.as_ref().map(|r| IrReasoningConfig {
+                effort: r.effort.as_deref().and_then(IrReasoningEffort::from_str_loose),
+                budget_tokens: None,
+            }),
+            response_format: req.text.and_then(|t| t.format).map(resp_text_format_to_ir),
+            extra_source: extra.as_ref().map(|_| ChatFormat::OpenaiResponses),
+            extra,
         })
     }
 
@@ -395,9 +649,17 @@ impl Decoder for OpenAiResponsesCodec {
         // Collect text content and tool calls from output items.
         let mut text_parts: Vec<String> = Vec::new();
         let mut tool_calls: Vec<IrToolCall> = Vec::new();
+        let mut reasoning: Vec<IrReasoning> = Vec::new();
 
         for item in &resp.output {
             match item {
+                OaiRespApiOutputItem::Reasoning {
+                    id,
+                    summary,
+                    encrypted_content,
+                } => {
+                    reasoning.push(resp_reasoning_to_ir(id, summary, encrypted_content));
+                }
                 OaiRespApiOutputItem::Message { content, .. } => {
                     for part in content {
                         match part {
@@ -439,6 +701,8 @@ impl Decoder for OpenAiResponsesCodec {
             },
             tool_call_id: None,
             name: None,
+            reasoning: (!reasoning.is_empty()).then_some(reasoning),
+            cache_control: None,
         };
 
         Ok(IrChatResponse {
@@ -446,15 +710,11 @@ impl Decoder for OpenAiResponsesCodec {
             model: resp.model,
             message: ir_msg,
             finish_reason,
-            usage: resp.usage.map(|u| IrUsage {
-                prompt_tokens: u.input_tokens,
-                completion_tokens: u.output_tokens,
-                total_tokens: Some(u.total_tokens),
-            }),
+            usage: resp.usage.as_ref().map(resp_usage_to_ir),
         })
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+    fn decode_stream_chunk(&self, event_name: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
         if data.trim().is_empty() || self.is_stream_done(data) {
             return Ok(None);
         }
@@ -462,7 +722,13 @@ impl Decoder for OpenAiResponsesCodec {
         let event: OaiRespApiStreamEvent =
             serde_json::from_str(data).map_err(|e| AppError::Codec(e.to_string()))?;
 
-        match event.event_type.as_str() {
+        // The SSE event name is the event type; relays that drop `event:`
+        // lines still have it in the data
+        let event_type = match event_name {
+            "message" => event.event_type.as_str(),
+            name => name,
+        };
+        match event_type {
             "response.created" => {
                 // Extract id and model from the response object.
                 if let Some(resp) = &event.response {
@@ -472,6 +738,7 @@ impl Decoder for OpenAiResponsesCodec {
                         delta_role: Some(IrRole::Assistant),
                         delta_content: None,
                         delta_tool_calls: None,
+                        delta_reasoning: None,
                         finish_reason: None,
                         usage: None,
                     }));
@@ -488,6 +755,7 @@ impl Decoder for OpenAiResponsesCodec {
                     delta_role: None,
                     delta_content: event.delta,
                     delta_tool_calls: None,
+                    delta_reasoning: None,
                     finish_reason: None,
                     usage: None,
                 }))
@@ -509,6 +777,7 @@ impl Decoder for OpenAiResponsesCodec {
                     delta_role: None,
                     delta_content: None,
                     delta_tool_calls: Some(vec![tc_delta]),
+                    delta_reasoning: None,
                     finish_reason: None,
                     usage: None,
                 }))
@@ -537,6 +806,54 @@ impl Decoder for OpenAiResponsesCodec {
                         delta_role: None,
                         delta_content: None,
                         delta_tool_calls: Some(vec![tc_delta]),
+                        delta_reasoning: None,
+                        finish_reason: None,
+                        usage: None,
+                    }));
+                }
+                Ok(None)
+            }
+
+            "response.reasoning_summary_text.delta" | "response.reasoning_text.delta" => {
+                Ok(Some(IrStreamChunk {
+                    id: extract_event_id(&event),
+                    model: None,
+                    delta_role: None,
+                    delta_content: None,
+                    delta_tool_calls: None,
+                    delta_reasoning: Some(IrReasoningDelta {
+                        text: event.delta,
+                        source: Some(ChatFormat::OpenaiResponses),
+                        ..Default::default()
+                    }),
+                    finish_reason: None,
+                    usage: None,
+                }))
+            }
+
+            "response.output_item.done" => {
+                // The summary has been streamed already; completed reasoning
+                // items add their id and encrypted content.
+                let id = extract_event_id(&event);
+                if let Some(OaiRespApiOutputItem::Reasoning {
+                    id: item_id,
+                    encrypted_content,
+                    ..
+                }) = event.item
+                {
+                    return Ok(Some(IrStreamChunk {
+                        id,
+                        model: None,
+                        delta_role: None,
+                        delta_content: None,
+                        delta_tool_calls: None,
+                        delta_reasoning: Some(IrReasoningDelta {
+                            text: None,
+                            signature: None,
+                            encrypted: encrypted_content,
+                            id: item_id,
+                            source: Some(ChatFormat::OpenaiResponses),
+                        }),
                         finish_reason: None,
                         usage: None,
                     }));
@@ -558,19 +875,16 @@ impl Decoder for OpenAiResponsesCodec {
                         delta_role: None,
                         delta_content: None,
                         delta_tool_calls: None,
+                        delta_reasoning: None,
                         finish_reason,
-                        usage: resp.usage.as_ref().map(|u| IrUsage {
-                            prompt_tokens: u.input_tokens,
-                            completion_tokens: u.output_tokens,
-                            total_tokens: Some(u.total_tokens),
-                        }),
+                        usage: resp.usage.as_ref().map(resp_usage_to_ir),
                     }));
                 }
                 Ok(None)
             }
 
             // Events we consume but produce no IR chunk for:
-            // response.output_item.done, response.content_part.added,
+            // response.content_part.added,
             // response.content_part.done, response.output_text.done,
             // response.function_call_arguments.done, response.done
             _ => Ok(None),
@@ -628,10 +942,22 @@ impl Encoder for OpenAiResponsesCodec {
                     // encode it as a regular message item (the API accepts it).
                     items.push(OaiRespApiInputItem::Message {
                         role: "user".to_string(),
-                        content: ir_content_to_resp_input(&msg.content),
+                        content: ir_content_to_resp_input(&msg.content)?,
                     });
                 }
                 IrRole::User | IrRole::Assistant => {
+                    // Reasoning items must be passed back ahead of the output
+                    // they produced; only items with an id can be referenced.
+                    for r in msg.reasoning.iter().flatten() {
+                        if r.source == Some(ChatFormat::OpenaiResponses) && r.id.is_some() {
+                            items.push(OaiRespApiInputItem::Reasoning {
+                                id: r.id.clone(),
+                                summary: ir_reasoning_summary(r),
+                                encrypted_content: r.encrypted.clone(),
+                            });
+                        }
+                    }
+
                     // If this is an assistant message with tool calls, emit
                     // individual function_call items instead.
                     if let Some(tcs) = &msg.tool_calls {
@@ -646,7 +972,7 @@ impl Encoder for OpenAiResponsesCodec {
                     } else {
                         items.push(OaiRespApiInputItem::Message {
                             role: ir_role_to_resp(&msg.role).to_string(),
-                            content: ir_content_to_resp_input(&msg.content),
+                            content: ir_content_to_resp_input(&msg.content)?,
                         });
                     }
                 }
@@ -692,6 +1018,24 @@ impl Encoder for OpenAiResponsesCodec {
             stream: if ir.stream { Some(true) } else { None },
             tools,
             tool_choice,
+            // Not every model accepts effort "none"; omit it instead
+            reasoning: ir
+                .reasoning
+                .as_ref()
+                .and_then(|r| r.effort())
+                .filter(|e| *e != IrReasoningEffort::None)
+                .map(|e| OaiRespApiReasoningConfig {
+                    effort: Some(e.as_str().to_string()),
+                    summary: None,
+                }),
+            text: ir.response_format.as_ref().map(|f| OaiRespApiTextConfig {
+                format: Some(ir_response_format_to_resp(f)),
+            }),
+            prompt_cache_retention: ir
+                .cache_breakpoints()
+                .any(|cc| cc.ttl.as_deref() == Some(EXTENDED_CACHE_TTL))
+                .then(|| EXTENDED_CACHE_RETENTION.to_string()),
+            extra: ir.extra_for(ChatFormat::OpenaiResponses).cloned().unwrap_or_default(),
         };
 
         serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
@@ -700,6 +1044,19 @@ impl Encoder for OpenAiResponsesCodec {
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
         let mut output: Vec<OaiRespApiOutputItem> = Vec::new();
 
+        // Reasoning items come first, as the model produced them.
+        for (i, r) in ir.message.reasoning.iter().flatten().enumerate() {
+            let own = r.source == Some(ChatFormat::OpenaiResponses);
+            output.push(OaiRespApiOutputItem::Reasoning {
+                id: Some(match r.id.as_ref().filter(|_| own) {
+                    Some(id) => id.clone(),
+                    None => format!("rs_{}_{}", ir.id, i),
+                }),
+                summary: ir_reasoning_summary(r),
+                encrypted_content: r.encrypted_for(ChatFormat::OpenaiResponses).map(String::from),
+            });
+        }
+
         // If there is text content, add a message output item.
         let text = ir.message.content.to_text();
         if !text.is_empty() {
@@ -727,11 +1084,7 @@ impl Encoder for OpenAiResponsesCodec {
 
         let status = ir_finish_to_resp_status(&ir.finish_reason);
 
-        let usage = ir.usage.as_ref().map(|u| OaiRespApiUsage {
-            input_tokens: u.prompt_tokens,
-            output_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
-        });
+        let usage = ir.usage.as_ref().map(ir_usage_to_resp);
 
         let resp = OaiRespApiResponse {
             id: ir.id.clone(),
@@ -745,8 +1098,8 @@ impl Encoder for OpenAiResponsesCodec {
         serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
-        let mut events: Vec<String> = Vec::new();
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
+        let mut events = Vec::new();
 
         // If this chunk carries a role (first chunk), emit response.created.
         if chunk.delta_role.is_some() && chunk.model.is_some() {
@@ -769,9 +1122,7 @@ impl Encoder for OpenAiResponsesCodec {
                 arguments: None,
                 sequence_number: None,
             };
-            events.push(
-                serde_json::to_string(&created).map_err(|e| AppError::Codec(e.to_string()))?,
-            );
+            events.push(stream_event(&created)?);
 
             // Also emit output_item.added for the message and content_part.added.
             let item_added = OaiRespApiStreamEvent {
@@ -790,9 +1141,7 @@ impl Encoder for OpenAiResponsesCodec {
                 arguments: None,
                 sequence_number: None,
             };
-            events.push(
-                serde_json::to_string(&item_added).map_err(|e| AppError::Codec(e.to_string()))?,
-            );
+            events.push(stream_event(&item_added)?);
 
             let part_added = OaiRespApiStreamEvent {
                 event_type: "response.content_part.added".to_string(),
@@ -809,9 +1158,46 @@ impl Encoder for OpenAiResponsesCodec {
                 arguments: None,
                 sequence_number: None,
             };
-            events.push(
-                serde_json::to_string(&part_added).map_err(|e| AppError::Codec(e.to_string()))?,
-            );
+            events.push(stream_event(&part_added)?);
+        }
+
+        // Reasoning deltas.
+        if let Some(reasoning) = &chunk.delta_reasoning {
+            if let Some(text) = &reasoning.text {
+                let summary_delta = OaiRespApiStreamEvent {
+                    event_type: "response.reasoning_summary_text.delta".to_string(),
+                    response: None,
+                    item: None,
+                    part: None,
+                    delta: Some(text.clone()),
+                    text: None,
+                    output_index: Some(0),
+                    content_index: None,
+                    arguments: None,
+                    sequence_number: None,
+                };
+                events.push(stream_event(&summary_delta)?);
+            }
+            let own = reasoning.source == Some(ChatFormat::OpenaiResponses);
+            if own && (reasoning.encrypted.is_some() || reasoning.id.is_some()) {
+                let item_done = OaiRespApiStreamEvent {
+                    event_type: "response.output_item.done".to_string(),
+                    response: None,
+                    item: Some(OaiRespApiOutputItem::Reasoning {
+                        id: reasoning.id.clone(),
+                        summary: vec![],
+                        encrypted_content: reasoning.encrypted.clone(),
+                    }),
+                    part: None,
+                    delta: None,
+                    text: None,
+                    output_index: Some(0),
+                    content_index: None,
+                    arguments: None,
+                    sequence_number: None,
+                };
+                events.push(stream_event(&item_done)?);
+            }
         }
 
         // Text delta.
@@ -828,9 +1214,7 @@ impl Encoder for OpenAiResponsesCodec {
                 arguments: None,
                 sequence_number: None,
             };
-            events.push(
-                serde_json::to_string(&text_delta).map_err(|e| AppError::Codec(e.to_string()))?,
-            );
+            events.push(stream_event(&text_delta)?);
         }
 
         // Tool call deltas.
@@ -857,10 +1241,7 @@ impl Encoder for OpenAiResponsesCodec {
                         arguments: None,
                         sequence_number: None,
                     };
-                    events.push(
-                        serde_json::to_string(&item_added)
-                            .map_err(|e| AppError::Codec(e.to_string()))?,
-                    );
+                    events.push(stream_event(&item_added)?);
                 }
 
                 // Argument delta.
@@ -877,10 +1258,7 @@ impl Encoder for OpenAiResponsesCodec {
                         arguments: None,
                         sequence_number: None,
                     };
-                    events.push(
-                        serde_json::to_string(&args_delta)
-                            .map_err(|e| AppError::Codec(e.to_string()))?,
-                    );
+                    events.push(stream_event(&args_delta)?);
                 }
             }
         }
@@ -888,11 +1266,7 @@ impl Encoder for OpenAiResponsesCodec {
         // Finish reason / usage → response.completed.
         if chunk.finish_reason.is_some() || chunk.usage.is_some() {
             let status = ir_finish_to_resp_status(&chunk.finish_reason);
-            let usage = chunk.usage.as_ref().map(|u| OaiRespApiUsage {
-                input_tokens: u.prompt_tokens,
-                output_tokens: u.completion_tokens,
-                total_tokens: u.total_tokens.unwrap_or(u.prompt_tokens + u.completion_tokens),
-            });
+            let usage = chunk.usage.as_ref().map(ir_usage_to_resp);
 
             let completed = OaiRespApiStreamEvent {
                 event_type: "response.completed".to_string(),
@@ -913,21 +1287,13 @@ impl Encoder for OpenAiResponsesCodec {
                 arguments: None,
                 sequence_number: None,
             };
-            events.push(
-                serde_json::to_string(&completed).map_err(|e| AppError::Codec(e.to_string()))?,
-            );
+            events.push(stream_event(&completed)?);
         }
 
-        if events.is_empty() {
-            Ok(None)
-        } else {
-            // Join multiple events with newline separators; the proxy layer
-            // will wrap each as an individual SSE `data:` line.
-            Ok(Some(events.join("\n")))
-        }
+        Ok(events)
     }
 
-    fn stream_done_signal(&self) -> Option<String> {
+    fn stream_done_signal(&self) -> Option<SseEvent> {
         // Emit a final `response.done` event as the terminal signal.
         let done = OaiRespApiStreamEvent {
             event_type: "response.done".to_string(),
@@ -941,10 +1307,26 @@ impl Encoder for OpenAiResponsesCodec {
             arguments: None,
             sequence_number: None,
         };
-        serde_json::to_string(&done).ok()
+        stream_event(&done).ok()
+    }
+
+    fn encode_stream_error(&self, error: &IrError) -> SseEvent {
+        // Stream errors are an `error` event with the error fields at the top
+        // level, not the wrapped error body
+        let (_, mut body) = self.encode_error(error);
+        let mut event = body["error"].take();
+        event["type"] = "error".into();
+        SseEvent::named("error", event.to_string())
     }
 }
 
+/// Wrap a stream event in an SSE event named after its type, as the
+/// Responses API sends them.
+fn stream_event(event: &OaiRespApiStreamEvent) -> Result<SseEvent, AppError> {
+    let data = serde_json::to_string(event).map_err(|e| AppError::Codec(e.to_string()))?;
+    Ok(SseEvent::named(event.event_type.clone(), data))
+}
+
 // =============================================================================
 // Tests
 // =============================================================================
@@ -953,422 +1335,6 @@ impl Encoder for OpenAiResponsesCodec {
 mod tests {
     use super::*;
 
-    #[test]
-    fn decode_request_string_input() {
-        let body = serde_json::json!({
-            "model": "gpt-4o",
-            "input": "Hello, world!",
-            "temperature": 0.7,
-            "max_output_tokens": 1024
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_request(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        assert_eq!(ir.model, "gpt-4o");
-        assert_eq!(ir.messages.len(), 1);
-        assert_eq!(ir.messages[0].role, IrRole::User);
-        assert_eq!(ir.messages[0].content.to_text(), "Hello, world!");
-        assert_eq!(ir.temperature, Some(0.7));
-        assert_eq!(ir.max_tokens, Some(1024));
-        assert!(ir.system.is_none());
-    }
-
-    #[test]
-    fn decode_request_with_instructions_and_items() {
-        let body = serde_json::json!({
-            "model": "gpt-4o",
-            "instructions": "You are a helpful assistant.",
-            "input": [
-                {"type": "message", "role": "user", "content": "Hi"},
-                {"type": "message", "role": "assistant", "content": "Hello!"},
-                {"type": "message", "role": "user", "content": "What is 2+2?"}
-            ],
-            "stream": true
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_request(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        assert_eq!(ir.system, Some("You are a helpful assistant.".to_string()));
-        assert_eq!(ir.messages.len(), 3);
-        assert_eq!(ir.messages[0].role, IrRole::User);
-        assert_eq!(ir.messages[1].role, IrRole::Assistant);
-        assert_eq!(ir.messages[2].role, IrRole::User);
-        assert!(ir.stream);
-    }
-
-    #[test]
-    fn decode_request_with_function_call_items() {
-        let body = serde_json::json!({
-            "model": "gpt-4o",
-            "input": [
-                {"type": "message", "role": "user", "content": "What is the weather?"},
-                {"type": "function_call", "id": "fc_1", "call_id": "call_1", "name": "get_weather", "arguments": "{\"location\":\"NYC\"}"},
-                {"type": "function_call_output", "call_id": "call_1", "output": "sunny, 72F"}
-            ]
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_request(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        assert_eq!(ir.messages.len(), 3);
-        // First: user message
-        assert_eq!(ir.messages[0].role, IrRole::User);
-        // Second: assistant with tool call
-        assert_eq!(ir.messages[1].role, IrRole::Assistant);
-        let tc = ir.messages[1].tool_calls.as_ref().unwrap();
-        assert_eq!(tc.len(), 1);
-        assert_eq!(tc[0].id, "call_1");
-        assert_eq!(tc[0].name, "get_weather");
-        // Third: tool result
-        assert_eq!(ir.messages[2].role, IrRole::Tool);
-        assert_eq!(ir.messages[2].tool_call_id, Some("call_1".to_string()));
-        assert_eq!(ir.messages[2].content.to_text(), "sunny, 72F");
-    }
-
-    #[test]
-    fn decode_request_with_tools() {
-        let body = serde_json::json!({
-            "model": "gpt-4o",
-            "input": "Use a tool",
-            "tools": [
-                {
-                    "type": "function",
-                    "name": "get_weather",
-                    "description": "Get weather",
-                    "parameters": {"type": "object", "properties": {"location": {"type": "string"}}}
-                }
-            ],
-            "tool_choice": "auto"
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_request(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        let tools = ir.tools.unwrap();
-        assert_eq!(tools.len(), 1);
-        assert_eq!(tools[0].name, "get_weather");
-        assert!(matches!(ir.tool_choice, Some(IrToolChoice::Auto)));
-    }
-
-    #[test]
-    fn decode_response_text_only() {
-        let body = serde_json::json!({
-            "id": "resp_001",
-            "object": "response",
-            "model": "gpt-4o",
-            "output": [
-                {
-                    "type": "message",
-                    "id": "msg_001",
-                    "role": "assistant",
-                    "content": [
-                        {"type": "output_text", "text": "Hello!", "annotations": []}
-                    ]
-                }
-            ],
-            "usage": {
-                "input_tokens": 10,
-                "output_tokens": 5,
-                "total_tokens": 15
-            },
-            "status": "completed"
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_response(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        assert_eq!(ir.id, "resp_001");
-        assert_eq!(ir.model, "gpt-4o");
-        assert_eq!(ir.message.content.to_text(), "Hello!");
-        assert!(ir.message.tool_calls.is_none());
-        assert_eq!(ir.finish_reason, Some(IrFinishReason::Stop));
-        let usage = ir.usage.unwrap();
-        assert_eq!(usage.prompt_tokens, 10);
-        assert_eq!(usage.completion_tokens, 5);
-    }
-
-    #[test]
-    fn decode_response_with_tool_calls() {
-        let body = serde_json::json!({
-            "id": "resp_002",
-            "object": "response",
-            "model": "gpt-4o",
-            "output": [
-                {
-                    "type": "function_call",
-                    "id": "fc_1",
-                    "call_id": "call_1",
-                    "name": "get_weather",
-                    "arguments": "{\"location\":\"NYC\"}"
-                }
-            ],
-            "usage": {
-                "input_tokens": 20,
-                "output_tokens": 15,
-                "total_tokens": 35
-            },
-            "status": "completed"
-        });
-        let codec = OpenAiResponsesCodec;
-        let ir = codec
-            .decode_response(serde_json::to_vec(&body).unwrap().as_slice())
-            .unwrap();
-
-        assert_eq!(ir.finish_reason, Some(IrFinishReason::ToolCalls));
-        let tcs = ir.message.tool_calls.unwrap();
-        assert_eq!(tcs.len(), 1);
-        assert_eq!(tcs[0].id, "call_1");
-        assert_eq!(tcs[0].name, "get_weather");
-    }
-
-    #[test]
-    fn encode_request_basic() {
-        let ir = IrChatRequest {
-            model: "gpt-4o".to_string(),
-            messages: vec![IrMessage {
-                role: IrRole::User,
-                content: IrContent::Text("Hello".to_string()),
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            }],
-            system: Some("Be helpful".to_string()),
-            temperature: Some(0.5),
-            top_p: None,
-            max_tokens: Some(512),
-            stream: false,
-            stop: None,
-            tools: None,
-            tool_choice: None,
-            extra: None,
-        };
-        let codec = OpenAiResponsesCodec;
-        let bytes = codec.encode_request(&ir, "gpt-4o-mini").unwrap();
-        let req: OaiRespApiRequest = serde_json::from_slice(&bytes).unwrap();
-
-        assert_eq!(req.model, "gpt-4o-mini");
-        assert_eq!(req.instructions, Some("Be helpful".to_string()));
-        assert_eq!(req.max_output_tokens, Some(512));
-        assert_eq!(req.temperature, Some(0.5));
-        assert!(req.stream.is_none());
-
-        if let OaiRespApiInput::Items(items) = &req.input {
-            assert_eq!(items.len(), 1);
-        } else {
-            panic!("Expected Items input");
-        }
-    }
-
-    #[test]
-    fn encode_request_with_tool_messages() {
-        let ir = IrChatRequest {
-            model: "gpt-4o".to_string(),
-            messages: vec![
-                IrMessage {
-                    role: IrRole::User,
-                    content: IrContent::Text("Weather?".to_string()),
-                    tool_calls: None,
-                    tool_call_id: None,
-                    name: None,
-                },
-                IrMessage {
-                    role: IrRole::Assistant,
-                    content: IrContent::Text(String::new()),
-                    tool_calls: Some(vec![IrToolCall {
-                        id: "call_1".to_string(),
-                        name: "get_weather".to_string(),
-                        arguments: "{\"location\":\"NYC\"}".to_string(),
-                    }]),
-                    tool_call_id: None,
-                    name: None,
-                },
-                IrMessage {
-                    role: IrRole::Tool,
-                    content: IrContent::Text("sunny".to_string()),
-                    tool_calls: None,
-                    tool_call_id: Some("call_1".to_string()),
-                    name: None,
-                },
-            ],
-            system: None,
-            temperature: None,
-            top_p: None,
-            max_tokens: None,
-            stream: false,
-            stop: None,
-            tools: None,
-            tool_choice: None,
-            extra: None,
-        };
-        let codec = OpenAiResponsesCodec;
-        let bytes = codec.encode_request(&ir, "gpt-4o").unwrap();
-        let req: OaiRespApiRequest = serde_json::from_slice(&bytes).unwrap();
-
-        if let OaiRespApiInput::Items(items) = &req.input {
-            assert_eq!(items.len(), 3);
-            // User message
-            assert!(matches!(&items[0], OaiRespApiInputItem::Message { role, .. } if role == "user"));
-            // Function call
-            assert!(matches!(&items[1], OaiRespApiInputItem::FunctionCall { call_id, name, .. }
-                if call_id == "call_1" && name == "get_weather"));
-            // Function call output
-            assert!(matches!(&items[2], OaiRespApiInputItem::FunctionCallOutput { call_id, output }
-                if call_id == "call_1" && output == "sunny"));
-        } else {
-            panic!("Expected Items input");
-        }
-    }
-
-    #[test]
-    fn encode_response_text() {
-        let ir = IrChatResponse {
-            id: "resp_123".to_string(),
-            model: "gpt-4o".to_string(),
-            message: IrMessage {
-                role: IrRole::Assistant,
-                content: IrContent::Text("Hello there!".to_string()),
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            },
-            finish_reason: Some(IrFinishReason::Stop),
-            usage: Some(IrUsage {
-                prompt_tokens: 10,
-                completion_tokens: 5,
-                total_tokens: Some(15),
-            }),
-        };
-        let codec = OpenAiResponsesCodec;
-        let bytes = codec.encode_response(&ir).unwrap();
-        let resp: OaiRespApiResponse = serde_json::from_slice(&bytes).unwrap();
-
-        assert_eq!(resp.id, "resp_123");
-        assert_eq!(resp.object, "response");
-        assert_eq!(resp.status, Some("completed".to_string()));
-        assert_eq!(resp.output.len(), 1);
-        if let OaiRespApiOutputItem::Message { content, role, .. } = &resp.output[0] {
-            assert_eq!(role, "assistant");
-            assert_eq!(content.len(), 1);
-            if let OaiRespApiContentPart::OutputText { text, .. } = &content[0] {
-                assert_eq!(text, "Hello there!");
-            }
-        } else {
-            panic!("Expected Message output");
-        }
-    }
-
-    #[test]
-    fn encode_response_with_tool_calls() {
-        let ir = IrChatResponse {
-            id: "resp_456".to_string(),
-            model: "gpt-4o".to_string(),
-            message: IrMessage {
-                role: IrRole::Assistant,
-                content: IrContent::Text(String::new()),
-                tool_calls: Some(vec![IrToolCall {
-                    id: "call_1".to_string(),
-                    name: "search".to_string(),
-                    arguments: "{\"q\":\"rust\"}".to_string(),
-                }]),
-                tool_call_id: None,
-                name: None,
-            },
-            finish_reason: Some(IrFinishReason::ToolCalls),
-            usage: None,
-        };
-        let codec = OpenAiResponsesCodec;
-        let bytes = codec.encode_response(&ir).unwrap();
-        let resp: OaiRespApiResponse = serde_json::from_slice(&bytes).unwrap();
-
-        // Empty text should not produce a message output item.
-        assert_eq!(resp.output.len(), 1);
-        if let OaiRespApiOutputItem::FunctionCall {
-            call_id, name, arguments, ..
-        } = &resp.output[0]
-        {
-            assert_eq!(call_id, "call_1");
-            assert_eq!(name, "search");
-            assert_eq!(arguments, "{\"q\":\"rust\"}");
-        } else {
-            panic!("Expected FunctionCall output");
-        }
-    }
-
-    #[test]
-    fn decode_stream_text_delta() {
-        let data = serde_json::json!({
-            "type": "response.output_text.delta",
-            "delta": "Hello",
-            "output_index": 0,
-            "content_index": 0
-        });
-        let codec = OpenAiResponsesCodec;
-        let chunk = codec
-            .decode_stream_chunk(&serde_json::to_string(&data).unwrap())
-            .unwrap()
-            .unwrap();
-
-        assert_eq!(chunk.delta_content, Some("Hello".to_string()));
-        assert!(chunk.delta_tool_calls.is_none());
-    }
-
-    #[test]
-    fn decode_stream_function_call_args_delta() {
-        let data = serde_json::json!({
-            "type": "response.function_call_arguments.delta",
-            "delta": "{\"loc",
-            "output_index": 1
-        });
-        let codec = OpenAiResponsesCodec;
-        let chunk = codec
-            .decode_stream_chunk(&serde_json::to_string(&data).unwrap())
-            .unwrap()
-            .unwrap();
-
-        assert!(chunk.delta_content.is_none());
-        let tcs = chunk.delta_tool_calls.unwrap();
-        assert_eq!(tcs.len(), 1);
-        assert_eq!(tcs[0].index, 1);
-        assert_eq!(tcs[0].arguments, Some("{\"loc".to_string()));
-    }
-
-    #[test]
-    fn decode_stream_completed() {
-        let data = serde_json::json!({
-            "type": "response.completed",
-            "response": {
-                "id": "resp_001",
-                "object": "response",
-                "model": "gpt-4o",
-                "output": [],
-                "usage": {
-                    "input_tokens": 10,
-                    "output_tokens": 20,
-                    "total_tokens": 30
-                },
-                "status": "completed"
-            }
-        });
-        let codec = OpenAiResponsesCodec;
-        let chunk = codec
-            .decode_stream_chunk(&serde_json::to_string(&data).unwrap())
-            .unwrap()
-            .unwrap();
-
-        assert_eq!(chunk.id, "resp_001");
-        assert_eq!(chunk.model, Some("gpt-4o".to_string()));
-        let usage = chunk.usage.unwrap();
-        assert_eq!(usage.prompt_tokens, 10);
-        assert_eq!(usage.completion_tokens, 20);
-    }
-
     #[test]
     fn is_stream_done_checks() {
         let codec = OpenAiResponsesCodec;
@@ -1378,52 +1344,11 @@ mod tests {
         assert!(!codec.is_stream_done("{\"type\":\"response.output_text.delta\",\"delta\":\"hi\"}"));
     }
 
-    #[test]
-    fn encode_stream_chunk_text_delta() {
-        let chunk = IrStreamChunk {
-            id: "resp_001".to_string(),
-            model: None,
-            delta_role: None,
-            delta_content: Some("world".to_string()),
-            delta_tool_calls: None,
-            finish_reason: None,
-            usage: None,
-        };
-        let codec = OpenAiResponsesCodec;
-        let result = codec.encode_stream_chunk(&chunk).unwrap().unwrap();
-
-        let event: OaiRespApiStreamEvent = serde_json::from_str(&result).unwrap();
-        assert_eq!(event.event_type, "response.output_text.delta");
-        assert_eq!(event.delta, Some("world".to_string()));
-    }
-
-    #[test]
-    fn encode_stream_chunk_initial() {
-        let chunk = IrStreamChunk {
-            id: "resp_001".to_string(),
-            model: Some("gpt-4o".to_string()),
-            delta_role: Some(IrRole::Assistant),
-            delta_content: None,
-            delta_tool_calls: None,
-            finish_reason: None,
-            usage: None,
-        };
-        let codec = OpenAiResponsesCodec;
-        let result = codec.encode_stream_chunk(&chunk).unwrap().unwrap();
-
-        // Should contain multiple events separated by newlines.
-        let lines: Vec<&str> = result.split('\n').collect();
-        assert!(lines.len() >= 3); // created, output_item.added, content_part.added
-
-        let first: OaiRespApiStreamEvent = serde_json::from_str(lines[0]).unwrap();
-        assert_eq!(first.event_type, "response.created");
-    }
-
     #[test]
     fn stream_done_signal_is_valid_json() {
         let codec = OpenAiResponsesCodec;
         let signal = codec.stream_done_signal().unwrap();
-        let event: OaiRespApiStreamEvent = serde_json::from_str(&signal).unwrap();
+        let event: OaiRespApiStreamEvent = serde_json::from_str(&signal.data).unwrap();
         assert_eq!(event.event_type, "response.done");
     }
 
@@ -1435,20 +1360,14 @@ mod tests {
                 IrMessage {
                     role: IrRole::User,
                     content: IrContent::Text("Hello".to_string()),
-                    tool_calls: None,
-                    tool_call_id: None,
-                    name: None,
+                    ..Default::default()
                 },
             ],
             system: Some("Be helpful".to_string()),
             temperature: Some(0.7),
             top_p: Some(1.0),
             max_tokens: Some(1024),
-            stream: false,
-            stop: None,
-            tools: None,
-            tool_choice: None,
-            extra: None,
+            ..Default::default()
         };
 
         let codec = OpenAiResponsesCodec;
@@ -1471,15 +1390,16 @@ mod tests {
             message: IrMessage {
                 role: IrRole::Assistant,
                 content: IrContent::Text("Test response".to_string()),
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
+                ..Default::default()
             },
             finish_reason: Some(IrFinishReason::Stop),
             usage: Some(IrUsage {
                 prompt_tokens: 5,
                 completion_tokens: 10,
                 total_tokens: Some(15),
+                reasoning_tokens: None,
+                cache_read_tokens: None,
+                cache_write_tokens: None,
             }),
         };
 
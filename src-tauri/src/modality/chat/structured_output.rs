@@ -0,0 +1,147 @@
+//! Structured output emulation for providers without a native response format.
+//!
+//! The requested schema becomes the input schema of a synthetic tool the model
+//! is forced to call; the arguments of that call are then turned back into the
+//! reply content.
+
+use super::ir::*;
+
+/// Name of the synthetic tool carrying the structured reply.
+pub const RESPONSE_TOOL_NAME: &str = "structured_output";
+
+/// Rewrite a request that asks for JSON output into one that forces a call to
+/// the response tool. Returns `None` if no JSON output was requested.
+pub fn emulate_request(ir: &IrChatRequest) -> Option<IrChatRequest> {
+    let format = ir.response_format.as_ref()?;
+    let schema = format.json_schema()?;
+    let description = match format {
+        IrResponseFormat::JsonSchema { description: Some(d), .. } => d.clone(),
+        _ => "Respond with the final answer by calling this tool.".to_string(),
+    };
+
+    let mut emulated = ir.clone();
+    emulated.response_format = None;
+    let client_tools = emulated.tools.take().unwrap_or_default();
+    let thinking = ir.reasoning.as_ref().is_some_and(|r| !r.is_disabled());
+
+    // Leave the client's own tools callable; forcing a specific tool is not
+    // allowed together with extended thinking.
+    emulated.tool_choice = match &ir.tool_choice {
+        _ if thinking => Some(IrToolChoice::Auto),
+        Some(choice @ IrToolChoice::Tool { .. }) => Some(choice.clone()),
+        Some(IrToolChoice::Auto | IrToolChoice::Any) if !client_tools.is_empty() => {
+            Some(IrToolChoice::Any)
+        }
+        _ => Some(IrToolChoice::Tool {
+            name: RESPONSE_TOOL_NAME.to_string(),
+        }),
+    };
+
+    let mut tools = client_tools;
+    tools.push(IrTool {
+        name: RESPONSE_TOOL_NAME.to_string(),
+        description: Some(description),
+        parameters: schema,
+        cache_control: None,
+    });
+    emulated.tools = Some(tools);
+    Some(emulated)
+}
+
+/// Move the arguments of a response tool call into the message content.
+pub fn unwrap_response(resp: &mut IrChatResponse) {
+    let Some(calls) = resp.message.tool_calls.take() else {
+        return;
+    };
+    let (response, other): (Vec<_>, Vec<_>) = calls
+        .into_iter()
+        .partition(|tc| tc.name == RESPONSE_TOOL_NAME);
+
+    if let Some(call) = response.into_iter().next() {
+        resp.message.content = IrContent::Text(call.arguments);
+        if other.is_empty() && resp.finish_reason == Some(IrFinishReason::ToolCalls) {
+            resp.finish_reason = Some(IrFinishReason::Stop);
+        }
+    }
+    resp.message.tool_calls = (!other.is_empty()).then_some(other);
+}
+
+/// Streaming counterpart of [`unwrap_response`]: argument deltas of the
+/// response tool call are re-emitted as content deltas.
+#[derive(Debug, Default)]
+pub struct StreamUnwrapper {
+    /// Stream index of the response tool call, once it has started.
+    index: Option<u32>,
+    /// Whether the model called any of the client's tools.
+    other_calls: bool,
+}
+
+impl StreamUnwrapper {
+    pub fn apply(&mut self, chunk: &mut IrStreamChunk) {
+        if let Some(deltas) = chunk.delta_tool_calls.take() {
+            let mut kept = Vec::new();
+            for delta in deltas {
+                if delta.name.as_deref() == Some(RESPONSE_TOOL_NAME) {
+                    self.index = Some(delta.index);
+                } else if delta.name.is_some() || self.index != Some(delta.index) {
+                    self.other_calls |= delta.name.is_some();
+                    kept.push(delta);
+                    continue;
+                }
+                if let Some(args) = delta.arguments.filter(|a| !a.is_empty()) {
+                    chunk.delta_content.get_or_insert_with(String::new).push_str(&args);
+                }
+            }
+            chunk.delta_tool_calls = (!kept.is_empty()).then_some(kept);
+        }
+
+        if self.index.is_some()
+            && !self.other_calls
+            && chunk.finish_reason == Some(IrFinishReason::ToolCalls)
+        {
+            chunk.finish_reason = Some(IrFinishReason::Stop);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_unwrapper_turns_arguments_into_content() {
+        let mut unwrapper = StreamUnwrapper::default();
+        let delta = |name: Option<&str>, args: &str| IrStreamChunk {
+            id: String::new(),
+            model: None,
+            delta_role: None,
+            delta_content: None,
+            delta_tool_calls: Some(vec![IrToolCallDelta {
+                index: 0,
+                id: name.map(|_| "toolu_1".to_string()),
+                name: name.map(String::from),
+                arguments: Some(args.to_string()),
+            }]),
+            delta_reasoning: None,
+            finish_reason: None,
+            usage: None,
+        };
+
+        let mut start = delta(Some(RESPONSE_TOOL_NAME), "");
+        unwrapper.apply(&mut start);
+        assert!(start.delta_tool_calls.is_none());
+
+        let mut args = delta(None, "{\"a\":1}");
+        unwrapper.apply(&mut args);
+        assert!(args.delta_tool_calls.is_none());
+        assert_eq!(args.delta_content.as_deref(), Some("{\"a\":1}"));
+
+        let mut finish = IrStreamChunk {
+            delta_tool_calls: None,
+            finish_reason: Some(IrFinishReason::ToolCalls),
+            ..delta(None, "")
+        };
+        unwrapper.apply(&mut finish);
+        assert_eq!(finish.finish_reason, Some(IrFinishReason::Stop));
+    }
+}
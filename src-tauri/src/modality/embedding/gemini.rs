@@ -0,0 +1,277 @@
+use super::ir::*;
+use super::{Decoder, Encoder};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// Gemini embeddings codec.
+///
+/// A single input is sent to `models/{model}:embedContent`, several inputs to
+/// `models/{model}:batchEmbedContents` (see [`GeminiEmbeddingCodec::method`]).
+/// Gemini reports no token usage for embeddings.
+pub struct GeminiEmbeddingCodec;
+
+impl GeminiEmbeddingCodec {
+    /// The API method to call for a request.
+    pub fn method(ir: &IrEmbeddingRequest) -> &'static str {
+        if ir.input.len() == 1 {
+            "embedContent"
+        } else {
+            "batchEmbedContents"
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiEmbedRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    content: GeminiContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_dimensionality: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GeminiBatchRequest {
+    requests: Vec<GeminiEmbedRequest>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GeminiContent {
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GeminiPart {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct GeminiValues {
+    values: Vec<f32>,
+}
+
+/// Both response shapes: `{"embedding": …}` from embedContent and
+/// `{"embeddings": […]}` from batchEmbedContents.
+#[derive(Debug, Deserialize, Serialize)]
+struct GeminiEmbedResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding: Option<GeminiValues>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embeddings: Option<Vec<GeminiValues>>,
+}
+
+fn content_text(content: GeminiContent) -> String {
+    content
+        .parts
+        .into_iter()
+        .map(|p| p.text)
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+fn text_content(text: &str) -> GeminiContent {
+    GeminiContent {
+        parts: vec![GeminiPart { text: text.to_string() }],
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Decoder / Encoder
+// ---------------------------------------------------------------------------
+
+impl Decoder for GeminiEmbeddingCodec {
+    /// The model is taken from `requests[].model` when present; for
+    /// embedContent it is only in the URL and left empty here.
+    fn decode_request(&self, body: &[u8]) -> Result<IrEmbeddingRequest, AppError> {
+        let value: serde_json::Value =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+
+        let requests = if value.get("requests").is_some() {
+            serde_json::from_value::<GeminiBatchRequest>(value)
+                .map_err(|e| AppError::Codec(e.to_string()))?
+                .requests
+        } else {
+            vec![serde_json::from_value::<GeminiEmbedRequest>(value)
+                .map_err(|e| AppError::Codec(e.to_string()))?]
+        };
+
+        let model = requests
+            .iter()
+            .find_map(|r| r.model.as_deref())
+            .map(|m| m.trim_start_matches("models/").to_string())
+            .unwrap_or_default();
+        let dimensions = requests.iter().find_map(|r| r.output_dimensionality);
+
+        Ok(IrEmbeddingRequest {
+            model,
+            input: requests.into_iter().map(|r| content_text(r.content)).collect(),
+            dimensions,
+            encoding_format: IrEncodingFormat::Float,
+            user: None,
+        })
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrEmbeddingResponse, AppError> {
+        let resp: GeminiEmbedResponse =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+
+        let embeddings = match (resp.embedding, resp.embeddings) {
+            (_, Some(batch)) => batch.into_iter().map(|e| e.values).collect(),
+            (Some(single), None) => vec![single.values],
+            (None, None) => {
+                return Err(AppError::Codec("No embeddings in response".to_string()))
+            }
+        };
+
+        Ok(IrEmbeddingResponse {
+            model: None,
+            embeddings,
+            usage: None,
+        })
+    }
+}
+
+impl Encoder for GeminiEmbeddingCodec {
+    fn encode_request(&self, ir: &IrEmbeddingRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        let body = if ir.input.len() == 1 {
+            serde_json::to_vec(&GeminiEmbedRequest {
+                model: None,
+                content: text_content(&ir.input[0]),
+                output_dimensionality: ir.dimensions,
+            })
+        } else {
+            let model = format!("models/{}", model);
+            serde_json::to_vec(&GeminiBatchRequest {
+                requests: ir
+                    .input
+                    .iter()
+                    .map(|text| GeminiEmbedRequest {
+                        model: Some(model.clone()),
+                        content: text_content(text),
+                        output_dimensionality: ir.dimensions,
+                    })
+                    .collect(),
+            })
+        };
+        body.map_err(|e| AppError::Codec(e.to_string()))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrEmbeddingResponse,
+        _encoding: IrEncodingFormat,
+    ) -> Result<Vec<u8>, AppError> {
+        let resp = GeminiEmbedResponse {
+            embedding: None,
+            embeddings: Some(
+                ir.embeddings
+                    .iter()
+                    .map(|v| GeminiValues { values: v.clone() })
+                    .collect(),
+            ),
+        };
+        serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(input: &[&str]) -> IrEmbeddingRequest {
+        IrEmbeddingRequest {
+            model: "text-embedding-004".to_string(),
+            input: input.iter().map(|s| s.to_string()).collect(),
+            dimensions: Some(256),
+            encoding_format: IrEncodingFormat::Float,
+            user: None,
+        }
+    }
+
+    #[test]
+    fn test_batch_request_round_trip() {
+        let ir = request(&["first", "second"]);
+        assert_eq!(GeminiEmbeddingCodec::method(&ir), "batchEmbedContents");
+
+        let body = GeminiEmbeddingCodec
+            .encode_request(&ir, "text-embedding-004")
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"requests": [
+                {
+                    "model": "models/text-embedding-004",
+                    "content": {"parts": [{"text": "first"}]},
+                    "outputDimensionality": 256,
+                },
+                {
+                    "model": "models/text-embedding-004",
+                    "content": {"parts": [{"text": "second"}]},
+                    "outputDimensionality": 256,
+                },
+            ]})
+        );
+
+        let decoded = GeminiEmbeddingCodec.decode_request(&body).unwrap();
+        assert_eq!(decoded.model, "text-embedding-004");
+        assert_eq!(decoded.input, vec!["first", "second"]);
+        assert_eq!(decoded.dimensions, Some(256));
+    }
+
+    #[test]
+    fn test_single_request_leaves_the_model_to_the_url() {
+        let ir = request(&["only"]);
+        assert_eq!(GeminiEmbeddingCodec::method(&ir), "embedContent");
+
+        let body = GeminiEmbeddingCodec
+            .encode_request(&ir, "text-embedding-004")
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "content": {"parts": [{"text": "only"}]},
+                "outputDimensionality": 256,
+            })
+        );
+
+        let decoded = GeminiEmbeddingCodec.decode_request(&body).unwrap();
+        assert_eq!(decoded.model, "");
+        assert_eq!(decoded.input, vec!["only"]);
+    }
+
+    #[test]
+    fn test_batch_response_round_trip() {
+        let body = serde_json::to_vec(&serde_json::json!({
+            "embeddings": [{"values": [0.5, 1.0]}, {"values": [-0.25]}],
+        }))
+        .unwrap();
+        let ir = GeminiEmbeddingCodec.decode_response(&body).unwrap();
+        assert_eq!(ir.embeddings, vec![vec![0.5, 1.0], vec![-0.25]]);
+        assert!(ir.usage.is_none());
+
+        let encoded = GeminiEmbeddingCodec
+            .encode_response(&ir, IrEncodingFormat::Float)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&encoded).unwrap();
+        assert_eq!(value, serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+    }
+
+    #[test]
+    fn test_decode_single_and_empty_responses() {
+        let ir = GeminiEmbeddingCodec
+            .decode_response(br#"{"embedding": {"values": [1.0, 2.0]}}"#)
+            .unwrap();
+        assert_eq!(ir.embeddings, vec![vec![1.0, 2.0]]);
+
+        let err = GeminiEmbeddingCodec.decode_response(b"{}").unwrap_err();
+        assert!(matches!(err, AppError::Codec(msg) if msg == "No embeddings in response"));
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// IR Embedding Request — a batch of texts to embed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrEmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    /// Requested vector size, for models that support shortening.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    /// How the client wants vectors encoded in the response.
+    pub encoding_format: IrEncodingFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IrEncodingFormat {
+    #[default]
+    Float,
+    /// Little-endian f32 values, base64-encoded.
+    Base64,
+}
+
+/// IR Embedding Response — one vector per input, in input order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrEmbeddingResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub embeddings: Vec<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<IrEmbeddingUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrEmbeddingUsage {
+    pub prompt_tokens: u32,
+    pub total_tokens: u32,
+}
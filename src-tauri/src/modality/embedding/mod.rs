@@ -0,0 +1,73 @@
+pub mod ir;
+pub mod openai;
+pub mod gemini;
+pub mod ollama;
+
+use crate::error::AppError;
+use ir::{IrEmbeddingRequest, IrEmbeddingResponse, IrEncodingFormat};
+
+/// Identifies the wire format of an embeddings request/response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmbeddingFormat {
+    /// OpenAI `/v1/embeddings`, also spoken by most compatible providers.
+    OpenAi,
+    /// Gemini `embedContent` / `batchEmbedContents`.
+    Gemini,
+    /// Ollama native `/api/embed`.
+    Ollama,
+}
+
+impl EmbeddingFormat {
+    /// The embeddings format spoken by a channel's provider. Ollama has a
+    /// native endpoint besides its OpenAI-compatible one.
+    pub fn from_provider(provider: &str) -> Self {
+        if super::is_gemini_provider(provider) {
+            Self::Gemini
+        } else if provider.eq_ignore_ascii_case("ollama") {
+            Self::Ollama
+        } else {
+            Self::OpenAi
+        }
+    }
+}
+
+/// Decodes a provider-specific embeddings format into IR.
+pub trait Decoder: Send + Sync {
+    /// Decode an incoming HTTP request body into IR.
+    fn decode_request(&self, body: &[u8]) -> Result<IrEmbeddingRequest, AppError>;
+
+    /// Decode an upstream response body into IR.
+    fn decode_response(&self, body: &[u8]) -> Result<IrEmbeddingResponse, AppError>;
+}
+
+/// Encodes IR into a provider-specific embeddings format.
+pub trait Encoder: Send + Sync {
+    /// Encode IR request into bytes to send upstream.
+    fn encode_request(&self, ir: &IrEmbeddingRequest, model: &str) -> Result<Vec<u8>, AppError>;
+
+    /// Encode IR response into bytes to send downstream, with vectors in the
+    /// encoding the client asked for where the format supports a choice.
+    fn encode_response(
+        &self,
+        ir: &IrEmbeddingResponse,
+        encoding: IrEncodingFormat,
+    ) -> Result<Vec<u8>, AppError>;
+}
+
+/// Get a decoder for a given format.
+pub fn get_decoder(format: EmbeddingFormat) -> Box<dyn Decoder> {
+    match format {
+        EmbeddingFormat::OpenAi => Box::new(openai::OpenAiEmbeddingCodec),
+        EmbeddingFormat::Gemini => Box::new(gemini::GeminiEmbeddingCodec),
+        EmbeddingFormat::Ollama => Box::new(ollama::OllamaEmbeddingCodec),
+    }
+}
+
+/// Get an encoder for a given format.
+pub fn get_encoder(format: EmbeddingFormat) -> Box<dyn Encoder> {
+    match format {
+        EmbeddingFormat::OpenAi => Box::new(openai::OpenAiEmbeddingCodec),
+        EmbeddingFormat::Gemini => Box::new(gemini::GeminiEmbeddingCodec),
+        EmbeddingFormat::Ollama => Box::new(ollama::OllamaEmbeddingCodec),
+    }
+}
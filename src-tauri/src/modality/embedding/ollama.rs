@@ -0,0 +1,153 @@
+use super::ir::*;
+use super::{Decoder, Encoder};
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// Ollama native embeddings codec (`/api/embed`).
+pub struct OllamaEmbeddingCodec;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OllamaEmbedRequest {
+    model: String,
+    input: OllamaInput,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum OllamaInput {
+    Text(String),
+    Texts(Vec<String>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OllamaEmbedResponse {
+    #[serde(default)]
+    model: String,
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prompt_eval_count: Option<u32>,
+}
+
+impl Decoder for OllamaEmbeddingCodec {
+    fn decode_request(&self, body: &[u8]) -> Result<IrEmbeddingRequest, AppError> {
+        let req: OllamaEmbedRequest =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(IrEmbeddingRequest {
+            model: req.model,
+            input: match req.input {
+                OllamaInput::Text(text) => vec![text],
+                OllamaInput::Texts(texts) => texts,
+            },
+            dimensions: req.dimensions,
+            encoding_format: IrEncodingFormat::Float,
+            user: None,
+        })
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrEmbeddingResponse, AppError> {
+        let resp: OllamaEmbedResponse =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(IrEmbeddingResponse {
+            model: Some(resp.model).filter(|m| !m.is_empty()),
+            embeddings: resp.embeddings,
+            usage: resp.prompt_eval_count.map(|n| IrEmbeddingUsage {
+                prompt_tokens: n,
+                total_tokens: n,
+            }),
+        })
+    }
+}
+
+impl Encoder for OllamaEmbeddingCodec {
+    fn encode_request(&self, ir: &IrEmbeddingRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        let req = OllamaEmbedRequest {
+            model: model.to_string(),
+            input: OllamaInput::Texts(ir.input.clone()),
+            dimensions: ir.dimensions,
+        };
+        serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrEmbeddingResponse,
+        _encoding: IrEncodingFormat,
+    ) -> Result<Vec<u8>, AppError> {
+        let resp = OllamaEmbedResponse {
+            model: ir.model.clone().unwrap_or_default(),
+            embeddings: ir.embeddings.clone(),
+            prompt_eval_count: ir.usage.as_ref().map(|u| u.prompt_tokens),
+        };
+        serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_request_always_sends_a_list() {
+        let ir = IrEmbeddingRequest {
+            model: "client-model".to_string(),
+            input: vec!["hello".to_string()],
+            dimensions: None,
+            encoding_format: IrEncodingFormat::Base64,
+            user: Some("u".to_string()),
+        };
+        let body = OllamaEmbeddingCodec.encode_request(&ir, "nomic-embed-text").unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"model": "nomic-embed-text", "input": ["hello"]})
+        );
+    }
+
+    #[test]
+    fn test_decode_request_accepts_a_single_string() {
+        let ir = OllamaEmbeddingCodec
+            .decode_request(br#"{"model": "nomic-embed-text", "input": "hello", "dimensions": 64}"#)
+            .unwrap();
+        assert_eq!(ir.model, "nomic-embed-text");
+        assert_eq!(ir.input, vec!["hello"]);
+        assert_eq!(ir.dimensions, Some(64));
+        assert_eq!(ir.encoding_format, IrEncodingFormat::Float);
+    }
+
+    #[test]
+    fn test_usage_comes_from_prompt_eval_count() {
+        let ir = OllamaEmbeddingCodec
+            .decode_response(
+                br#"{"model": "nomic-embed-text", "embeddings": [[0.5], [1.5]], "prompt_eval_count": 7}"#,
+            )
+            .unwrap();
+        assert_eq!(ir.model.as_deref(), Some("nomic-embed-text"));
+        assert_eq!(ir.embeddings, vec![vec![0.5], vec![1.5]]);
+        let usage = ir.usage.as_ref().unwrap();
+        assert_eq!((usage.prompt_tokens, usage.total_tokens), (7, 7));
+
+        let body = OllamaEmbeddingCodec
+            .encode_response(&ir, IrEncodingFormat::Float)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "model": "nomic-embed-text",
+                "embeddings": [[0.5], [1.5]],
+                "prompt_eval_count": 7,
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_prompt_eval_count_means_no_usage() {
+        let ir = OllamaEmbeddingCodec
+            .decode_response(br#"{"embeddings": [[0.5]]}"#)
+            .unwrap();
+        assert!(ir.model.is_none());
+        assert!(ir.usage.is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use super::ir::*;
+use super::{Decoder, Encoder};
+use crate::error::AppError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// OpenAI Embeddings API codec.
+pub struct OpenAiEmbeddingCodec;
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiEmbeddingRequest {
+    model: String,
+    input: OaiInput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding_format: Option<IrEncodingFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+/// `input` is a string, an array of strings, or pre-tokenized input
+/// (an array of token IDs or of token ID arrays).
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum OaiInput {
+    Text(String),
+    Texts(Vec<String>),
+    Tokens(Vec<serde_json::Value>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiEmbeddingResponse {
+    #[serde(default = "list_object")]
+    object: String,
+    data: Vec<OaiEmbedding>,
+    #[serde(default)]
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<OaiUsage>,
+}
+
+fn list_object() -> String {
+    "list".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiEmbedding {
+    #[serde(default = "embedding_object")]
+    object: String,
+    #[serde(default)]
+    index: usize,
+    embedding: OaiVector,
+}
+
+fn embedding_object() -> String {
+    "embedding".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum OaiVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiUsage {
+    prompt_tokens: u32,
+    total_tokens: u32,
+}
+
+// ---------------------------------------------------------------------------
+// Vector encoding
+// ---------------------------------------------------------------------------
+
+/// Encode a vector as base64 of its little-endian f32 bytes, as OpenAI does
+/// for `encoding_format: "base64"`.
+pub fn vector_to_base64(vector: &[f32]) -> String {
+    let bytes: Vec<u8> = vector.iter().flat_map(|v| v.to_le_bytes()).collect();
+    BASE64.encode(bytes)
+}
+
+/// Inverse of [`vector_to_base64`].
+pub fn vector_from_base64(encoded: &str) -> Result<Vec<f32>, AppError> {
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| AppError::Codec(format!("Invalid base64 embedding: {}", e)))?;
+    if bytes.len() % 4 != 0 {
+        return Err(AppError::Codec(
+            "Base64 embedding is not a whole number of f32 values".to_string(),
+        ));
+    }
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect())
+}
+
+// ---------------------------------------------------------------------------
+// Decoder / Encoder
+// ---------------------------------------------------------------------------
+
+impl Decoder for OpenAiEmbeddingCodec {
+    fn decode_request(&self, body: &[u8]) -> Result<IrEmbeddingRequest, AppError> {
+        let req: OaiEmbeddingRequest =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+
+        let input = match req.input {
+            OaiInput::Text(text) => vec![text],
+            OaiInput::Texts(texts) => texts,
+            OaiInput::Tokens(_) => {
+                return Err(AppError::Codec(
+                    "Token ID input is not supported; send text input".to_string(),
+                ))
+            }
+        };
+        if input.is_empty() {
+            return Err(AppError::Codec("Embedding input must not be empty".to_string()));
+        }
+
+        Ok(IrEmbeddingRequest {
+            model: req.model,
+            input,
+            dimensions: req.dimensions,
+            encoding_format: req.encoding_format.unwrap_or_default(),
+            user: req.user,
+        })
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrEmbeddingResponse, AppError> {
+        let mut resp: OaiEmbeddingResponse =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        resp.data.sort_by_key(|d| d.index);
+
+        let embeddings = resp
+            .data
+            .into_iter()
+            .map(|d| match d.embedding {
+                OaiVector::Float(values) => Ok(values),
+                OaiVector::Base64(encoded) => vector_from_base64(&encoded),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(IrEmbeddingResponse {
+            model: Some(resp.model).filter(|m| !m.is_empty()),
+            embeddings,
+            usage: resp.usage.map(|u| IrEmbeddingUsage {
+                prompt_tokens: u.prompt_tokens,
+                total_tokens: u.total_tokens,
+            }),
+        })
+    }
+}
+
+impl Encoder for OpenAiEmbeddingCodec {
+    fn encode_request(&self, ir: &IrEmbeddingRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        let req = OaiEmbeddingRequest {
+            model: model.to_string(),
+            input: OaiInput::Texts(ir.input.clone()),
+            dimensions: ir.dimensions,
+            // Only forwarded when asked for: not every compatible server supports
+            // base64, and the response decoder handles either encoding
+            encoding_format: Some(ir.encoding_format).filter(|f| *f == IrEncodingFormat::Base64),
+            user: ir.user.clone(),
+        };
+        serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrEmbeddingResponse,
+        encoding: IrEncodingFormat,
+    ) -> Result<Vec<u8>, AppError> {
+        let resp = OaiEmbeddingResponse {
+            object: list_object(),
+            data: ir
+                .embeddings
+                .iter()
+                .enumerate()
+                .map(|(index, vector)| OaiEmbedding {
+                    object: embedding_object(),
+                    index,
+                    embedding: match encoding {
+                        IrEncodingFormat::Float => OaiVector::Float(vector.clone()),
+                        IrEncodingFormat::Base64 => OaiVector::Base64(vector_to_base64(vector)),
+                    },
+                })
+                .collect(),
+            model: ir.model.clone().unwrap_or_default(),
+            usage: ir.usage.as_ref().map(|u| OaiUsage {
+                prompt_tokens: u.prompt_tokens,
+                total_tokens: u.total_tokens,
+            }),
+        };
+        serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_roundtrip() {
+        let vector = vec![0.5, -1.25, 3.0];
+        let encoded = vector_to_base64(&vector);
+        assert_eq!(vector_from_base64(&encoded).unwrap(), vector);
+    }
+
+    #[test]
+    fn test_decode_response_mixed_encodings() {
+        let body = serde_json::json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": vector_to_base64(&[2.0])},
+                {"object": "embedding", "index": 0, "embedding": [1.0]},
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 4, "total_tokens": 4},
+        });
+        let ir = OpenAiEmbeddingCodec
+            .decode_response(&serde_json::to_vec(&body).unwrap())
+            .unwrap();
+        assert_eq!(ir.embeddings, vec![vec![1.0], vec![2.0]]);
+        assert_eq!(ir.usage.unwrap().prompt_tokens, 4);
+    }
+}
@@ -0,0 +1,282 @@
+use super::ir::*;
+use super::{Decoder, Encoder, ImageRequestBody};
+use crate::error::AppError;
+use crate::modality::chat::gemini::{
+    GeminiCandidate, GeminiContent, GeminiInlineData, GeminiPart, GeminiResponse,
+    GeminiUsageMetadata,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Gemini image codec: image-capable models (e.g. `gemini-2.5-flash-image`)
+/// called through `generateContent` with image output enabled. Source images
+/// for edits are sent inline; masks are not supported by Gemini and dropped.
+pub struct GeminiImageCodec;
+
+/// Aspect ratios accepted by `imageConfig.aspectRatio`.
+const ASPECT_RATIOS: [(&str, f64); 10] = [
+    ("1:1", 1.0),
+    ("2:3", 2.0 / 3.0),
+    ("3:2", 3.0 / 2.0),
+    ("3:4", 3.0 / 4.0),
+    ("4:3", 4.0 / 3.0),
+    ("4:5", 4.0 / 5.0),
+    ("5:4", 5.0 / 4.0),
+    ("9:16", 9.0 / 16.0),
+    ("16:9", 16.0 / 9.0),
+    ("21:9", 21.0 / 9.0),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiImageRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GeminiImageGenerationConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiImageGenerationConfig {
+    #[serde(default)]
+    response_modalities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_config: Option<GeminiImageConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiImageConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aspect_ratio: Option<String>,
+}
+
+/// Map an OpenAI `WIDTHxHEIGHT` size to the closest supported aspect ratio.
+pub fn size_to_aspect_ratio(size: &str) -> Option<&'static str> {
+    let (w, h) = size.split_once('x')?;
+    let (w, h) = (w.trim().parse::<f64>().ok()?, h.trim().parse::<f64>().ok()?);
+    if w <= 0.0 || h <= 0.0 {
+        return None;
+    }
+    let ratio = (w / h).ln();
+    ASPECT_RATIOS
+        .iter()
+        .min_by(|a, b| {
+            (a.1.ln() - ratio)
+                .abs()
+                .total_cmp(&(b.1.ln() - ratio).abs())
+        })
+        .map(|(name, _)| *name)
+}
+
+fn text_part(text: String) -> GeminiPart {
+    GeminiPart {
+        text: Some(text),
+        inline_data: None,
+        file_data: None,
+        function_call: None,
+        function_response: None,
+        thought: None,
+        thought_signature: None,
+    }
+}
+
+fn inline_part(mime_type: String, data: String) -> GeminiPart {
+    GeminiPart {
+        text: None,
+        inline_data: Some(GeminiInlineData { mime_type, data }),
+        file_data: None,
+        function_call: None,
+        function_response: None,
+        thought: None,
+        thought_signature: None,
+    }
+}
+
+impl Decoder for GeminiImageCodec {
+    /// The model is only in the URL for Gemini and left empty here.
+    fn decode_request(&self, body: &[u8]) -> Result<IrImageRequest, AppError> {
+        let req: GeminiImageRequest =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+
+        let mut prompt = Vec::new();
+        let mut images = Vec::new();
+        for part in req.contents.into_iter().flat_map(|c| c.parts) {
+            if let Some(text) = part.text {
+                prompt.push(text);
+            }
+            if let Some(inline) = part.inline_data {
+                images.push(IrImageData {
+                    filename: None,
+                    mime_type: inline.mime_type,
+                    data: BASE64
+                        .decode(&inline.data)
+                        .map_err(|e| AppError::Codec(format!("Invalid inline image: {}", e)))?,
+                });
+            }
+        }
+
+        Ok(IrImageRequest {
+            model: String::new(),
+            prompt: prompt.join("\n"),
+            n: req
+                .generation_config
+                .as_ref()
+                .and_then(|c| c.candidate_count)
+                .unwrap_or(1),
+            size: None,
+            quality: None,
+            response_format: Some(IrImageResponseFormat::B64Json),
+            images,
+            mask: None,
+            user: None,
+        })
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrImageResponse, AppError> {
+        let resp: GeminiResponse =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+
+        let mut images = Vec::new();
+        let mut texts = Vec::new();
+        for candidate in resp.candidates {
+            let mut text = String::new();
+            let first_image = images.len();
+            for part in candidate.content.parts {
+                // Thinking models may return draft images and text as thoughts
+                if part.thought == Some(true) {
+                    continue;
+                }
+                if let Some(t) = part.text {
+                    text.push_str(&t);
+                }
+                if let Some(inline) = part.inline_data {
+                    images.push(IrGeneratedImage {
+                        b64_json: Some(inline.data),
+                        url: None,
+                        mime_type: Some(inline.mime_type),
+                        revised_prompt: None,
+                    });
+                }
+            }
+            // Text accompanying an image is the model's description of it
+            if !text.is_empty() {
+                if let Some(img) = images.get_mut(first_image) {
+                    img.revised_prompt = Some(text.clone());
+                }
+                texts.push(text);
+            }
+        }
+
+        if images.is_empty() {
+            return Err(AppError::Codec(format!(
+                "No image in upstream response{}",
+                if texts.is_empty() { String::new() } else { format!(": {}", texts.join(" ")) }
+            )));
+        }
+
+        Ok(IrImageResponse {
+            created: chrono::Utc::now().timestamp(),
+            images,
+            usage: resp.usage_metadata.map(|u| IrImageUsage {
+                input_tokens: u.prompt_token_count,
+                output_tokens: u.candidates_token_count,
+            }),
+        })
+    }
+}
+
+impl Encoder for GeminiImageCodec {
+    fn encode_request(&self, ir: &IrImageRequest, _model: &str) -> Result<ImageRequestBody, AppError> {
+        if ir.mask.is_some() {
+            log::warn!("Gemini does not support edit masks; ignoring the mask");
+        }
+
+        let mut parts = vec![text_part(ir.prompt.clone())];
+        parts.extend(
+            ir.images
+                .iter()
+                .map(|img| inline_part(img.mime_type.clone(), BASE64.encode(&img.data))),
+        );
+
+        let req = GeminiImageRequest {
+            contents: vec![GeminiContent {
+                role: Some("user".to_string()),
+                parts,
+            }],
+            generation_config: Some(GeminiImageGenerationConfig {
+                response_modalities: vec!["TEXT".to_string(), "IMAGE".to_string()],
+                candidate_count: Some(ir.n).filter(|n| *n > 1),
+                image_config: ir
+                    .size
+                    .as_deref()
+                    .and_then(size_to_aspect_ratio)
+                    .map(|ratio| GeminiImageConfig {
+                        aspect_ratio: Some(ratio.to_string()),
+                    }),
+            }),
+        };
+        let body = serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(ImageRequestBody::Json(body))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrImageResponse,
+        _format: Option<IrImageResponseFormat>,
+    ) -> Result<Vec<u8>, AppError> {
+        let candidates = ir
+            .images
+            .iter()
+            .map(|img| {
+                let mut parts = Vec::new();
+                if let Some(text) = &img.revised_prompt {
+                    parts.push(text_part(text.clone()));
+                }
+                match (&img.b64_json, &img.url) {
+                    (Some(b64), _) => parts.push(inline_part(
+                        img.mime_type.clone().unwrap_or_else(|| "image/png".to_string()),
+                        b64.clone(),
+                    )),
+                    (None, Some(url)) => parts.push(text_part(url.clone())),
+                    (None, None) => {}
+                }
+                GeminiCandidate {
+                    content: GeminiContent {
+                        role: Some("model".to_string()),
+                        parts,
+                    },
+                    finish_reason: Some("STOP".to_string()),
+                }
+            })
+            .collect();
+
+        let resp = GeminiResponse {
+            candidates,
+            usage_metadata: ir.usage.as_ref().map(|u| GeminiUsageMetadata {
+                prompt_token_count: u.input_tokens,
+                candidates_token_count: u.output_tokens,
+                total_token_count: u.input_tokens + u.output_tokens,
+                thoughts_token_count: None,
+                cached_content_token_count: None,
+            }),
+        };
+        serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_to_aspect_ratio() {
+        assert_eq!(size_to_aspect_ratio("1024x1024"), Some("1:1"));
+        assert_eq!(size_to_aspect_ratio("1792x1024"), Some("16:9"));
+        assert_eq!(size_to_aspect_ratio("1024x1536"), Some("2:3"));
+        assert_eq!(size_to_aspect_ratio("auto"), None);
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// IR Image Request — covers both generation and edits. A request with
+/// source `images` is an edit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrImageRequest {
+    pub model: String,
+    pub prompt: String,
+    /// Number of images to generate.
+    pub n: u32,
+    /// Requested size as `WIDTHxHEIGHT` (or a provider keyword such as `auto`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<String>,
+    /// How the client wants images returned; `None` leaves the provider default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<IrImageResponseFormat>,
+    /// Source images for edits; empty for generation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<IrImageData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<IrImageData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl IrImageRequest {
+    pub fn is_edit(&self) -> bool {
+        !self.images.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IrImageResponseFormat {
+    Url,
+    B64Json,
+}
+
+/// An uploaded image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrImageData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub mime_type: String,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// IR Image Response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrImageResponse {
+    /// Unix timestamp in seconds.
+    pub created: i64,
+    pub images: Vec<IrGeneratedImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<IrImageUsage>,
+}
+
+/// A generated image, returned inline or as a link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrGeneratedImage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// MIME type of `b64_json`, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrImageUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
@@ -0,0 +1,71 @@
+pub mod ir;
+pub mod openai;
+pub mod gemini;
+
+use crate::error::AppError;
+use ir::{IrImageRequest, IrImageResponse, IrImageResponseFormat};
+
+/// Identifies the wire format of an image generation request/response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// OpenAI Images API (`/v1/images/generations`, `/v1/images/edits`).
+    OpenAi,
+    /// Gemini image-capable models via `generateContent`.
+    Gemini,
+}
+
+impl ImageFormat {
+    /// The image format spoken by a channel's provider.
+    pub fn from_provider(provider: &str) -> Self {
+        if super::is_gemini_provider(provider) {
+            Self::Gemini
+        } else {
+            Self::OpenAi
+        }
+    }
+}
+
+/// An encoded upstream request body.
+pub enum ImageRequestBody {
+    Json(Vec<u8>),
+    Multipart(reqwest::multipart::Form),
+}
+
+/// Decodes a provider-specific image format into IR.
+pub trait Decoder: Send + Sync {
+    /// Decode an incoming JSON request body into IR.
+    fn decode_request(&self, body: &[u8]) -> Result<IrImageRequest, AppError>;
+
+    /// Decode an upstream response body into IR.
+    fn decode_response(&self, body: &[u8]) -> Result<IrImageResponse, AppError>;
+}
+
+/// Encodes IR into a provider-specific image format.
+pub trait Encoder: Send + Sync {
+    /// Encode IR request into a body to send upstream.
+    fn encode_request(&self, ir: &IrImageRequest, model: &str) -> Result<ImageRequestBody, AppError>;
+
+    /// Encode IR response into bytes to send downstream. `format` is what the
+    /// client asked for; images are converted where possible.
+    fn encode_response(
+        &self,
+        ir: &IrImageResponse,
+        format: Option<IrImageResponseFormat>,
+    ) -> Result<Vec<u8>, AppError>;
+}
+
+/// Get a decoder for a given format.
+pub fn get_decoder(format: ImageFormat) -> Box<dyn Decoder> {
+    match format {
+        ImageFormat::OpenAi => Box::new(openai::OpenAiImageCodec),
+        ImageFormat::Gemini => Box::new(gemini::GeminiImageCodec),
+    }
+}
+
+/// Get an encoder for a given format.
+pub fn get_encoder(format: ImageFormat) -> Box<dyn Encoder> {
+    match format {
+        ImageFormat::OpenAi => Box::new(openai::OpenAiImageCodec),
+        ImageFormat::Gemini => Box::new(gemini::GeminiImageCodec),
+    }
+}
@@ -0,0 +1,442 @@
+use super::ir::*;
+use super::{Decoder, Encoder, ImageRequestBody};
+use crate::error::AppError;
+use crate::modality::chat::ir::data_url;
+use crate::modality::MultipartField;
+use serde::{Deserialize, Serialize};
+
+/// OpenAI Images API codec.
+pub struct OpenAiImageCodec;
+
+// ---------------------------------------------------------------------------
+// Wire types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiImageRequest {
+    #[serde(default)]
+    model: String,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<IrImageResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiImageResponse {
+    created: i64,
+    #[serde(default)]
+    data: Vec<OaiImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<OaiImageUsage>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiImage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    b64_json: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revised_prompt: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct OaiImageUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    total_tokens: u32,
+}
+
+// ---------------------------------------------------------------------------
+// Multipart (edits)
+// ---------------------------------------------------------------------------
+
+impl OpenAiImageCodec {
+    /// Decode a `/v1/images/edits` multipart body. Source images are sent as
+    /// `image` (or `image[]` for several), the optional mask as `mask`.
+    pub fn decode_multipart_request(
+        &self,
+        fields: Vec<MultipartField>,
+    ) -> Result<IrImageRequest, AppError> {
+        let mut ir = IrImageRequest {
+            model: String::new(),
+            prompt: String::new(),
+            n: 1,
+            size: None,
+            quality: None,
+            response_format: None,
+            images: Vec::new(),
+            mask: None,
+            user: None,
+        };
+
+        for field in fields {
+            match field.name.as_str() {
+                "image" | "image[]" => ir.images.push(field_to_image(field)),
+                "mask" => ir.mask = Some(field_to_image(field)),
+                name => {
+                    let value = field.text();
+                    match name {
+                        "model" => ir.model = value,
+                        "prompt" => ir.prompt = value,
+                        "n" => {
+                            ir.n = value
+                                .parse()
+                                .map_err(|_| AppError::Codec(format!("Invalid n: {}", value)))?
+                        }
+                        "size" => ir.size = Some(value),
+                        "quality" => ir.quality = Some(value),
+                        "response_format" => {
+                            ir.response_format = Some(
+                                serde_json::from_value(serde_json::Value::String(value.clone()))
+                                    .map_err(|_| {
+                                        AppError::Codec(format!("Invalid response_format: {}", value))
+                                    })?,
+                            )
+                        }
+                        "user" => ir.user = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if ir.images.is_empty() {
+            return Err(AppError::Codec("Missing image to edit".to_string()));
+        }
+        if ir.prompt.is_empty() {
+            return Err(AppError::Codec("Missing prompt".to_string()));
+        }
+        Ok(ir)
+    }
+}
+
+fn field_to_image(field: MultipartField) -> IrImageData {
+    IrImageData {
+        filename: field.filename,
+        mime_type: field.content_type.unwrap_or_else(|| "image/png".to_string()),
+        data: field.data.to_vec(),
+    }
+}
+
+fn image_part(image: &IrImageData) -> Result<reqwest::multipart::Part, AppError> {
+    reqwest::multipart::Part::bytes(image.data.clone())
+        .file_name(image.filename.clone().unwrap_or_else(|| "image.png".to_string()))
+        .mime_str(&image.mime_type)
+        .map_err(|e| AppError::Codec(format!("Invalid image type: {}", e)))
+}
+
+// ---------------------------------------------------------------------------
+// Decoder / Encoder
+// ---------------------------------------------------------------------------
+
+impl Decoder for OpenAiImageCodec {
+    fn decode_request(&self, body: &[u8]) -> Result<IrImageRequest, AppError> {
+        let req: OaiImageRequest =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(IrImageRequest {
+            model: req.model,
+            prompt: req.prompt,
+            n: req.n.unwrap_or(1),
+            size: req.size,
+            quality: req.quality,
+            response_format: req.response_format,
+            images: Vec::new(),
+            mask: None,
+            user: req.user,
+        })
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrImageResponse, AppError> {
+        let resp: OaiImageResponse =
+            serde_json::from_slice(body).map_err(|e| AppError::Codec(e.to_string()))?;
+        Ok(IrImageResponse {
+            created: resp.created,
+            images: resp
+                .data
+                .into_iter()
+                .map(|img| IrGeneratedImage {
+                    b64_json: img.b64_json,
+                    url: img.url,
+                    mime_type: None,
+                    revised_prompt: img.revised_prompt,
+                })
+                .collect(),
+            usage: resp.usage.map(|u| IrImageUsage {
+                input_tokens: u.input_tokens,
+                output_tokens: u.output_tokens,
+            }),
+        })
+    }
+}
+
+impl Encoder for OpenAiImageCodec {
+    fn encode_request(&self, ir: &IrImageRequest, model: &str) -> Result<ImageRequestBody, AppError> {
+        if !ir.is_edit() {
+            let req = OaiImageRequest {
+                model: model.to_string(),
+                prompt: ir.prompt.clone(),
+                n: Some(ir.n),
+                size: ir.size.clone(),
+                quality: ir.quality.clone(),
+                response_format: ir.response_format,
+                user: ir.user.clone(),
+            };
+            let body = serde_json::to_vec(&req).map_err(|e| AppError::Codec(e.to_string()))?;
+            return Ok(ImageRequestBody::Json(body));
+        }
+
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", model.to_string())
+            .text("prompt", ir.prompt.clone())
+            .text("n", ir.n.to_string());
+        if let Some(size) = &ir.size {
+            form = form.text("size", size.clone());
+        }
+        if let Some(quality) = &ir.quality {
+            form = form.text("quality", quality.clone());
+        }
+        if let Some(format) = ir.response_format {
+            let value = match format {
+                IrImageResponseFormat::Url => "url",
+                IrImageResponseFormat::B64Json => "b64_json",
+            };
+            form = form.text("response_format", value);
+        }
+        if let Some(user) = &ir.user {
+            form = form.text("user", user.clone());
+        }
+        let image_field = if ir.images.len() > 1 { "image[]" } else { "image" };
+        for image in &ir.images {
+            form = form.part(image_field, image_part(image)?);
+        }
+        if let Some(mask) = &ir.mask {
+            form = form.part("mask", image_part(mask)?);
+        }
+        Ok(ImageRequestBody::Multipart(form))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrImageResponse,
+        format: Option<IrImageResponseFormat>,
+    ) -> Result<Vec<u8>, AppError> {
+        let data = ir
+            .images
+            .iter()
+            .map(|img| {
+                let (b64_json, url) = match format {
+                    // No hosting here: inline images are returned as data URLs
+                    Some(IrImageResponseFormat::Url) => match (&img.url, &img.b64_json) {
+                        (Some(url), _) => (None, Some(url.clone())),
+                        (None, Some(b64)) => (None, Some(data_url(img.mime_type.as_deref().unwrap_or("image/png"), b64))),
+                        (None, None) => (None, None),
+                    },
+                    Some(IrImageResponseFormat::B64Json) if img.b64_json.is_some() => {
+                        (img.b64_json.clone(), None)
+                    }
+                    _ => (img.b64_json.clone(), img.url.clone()),
+                };
+                OaiImage {
+                    b64_json,
+                    url,
+                    revised_prompt: img.revised_prompt.clone(),
+                }
+            })
+            .collect();
+
+        let resp = OaiImageResponse {
+            created: ir.created,
+            data,
+            usage: ir.usage.as_ref().map(|u| OaiImageUsage {
+                input_tokens: u.input_tokens,
+                output_tokens: u.output_tokens,
+                total_tokens: u.input_tokens + u.output_tokens,
+            }),
+        };
+        serde_json::to_vec(&resp).map_err(|e| AppError::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, filename: Option<&str>, content_type: Option<&str>, data: &[u8]) -> MultipartField {
+        MultipartField {
+            name: name.into(),
+            filename: filename.map(Into::into),
+            content_type: content_type.map(Into::into),
+            data: bytes::Bytes::copy_from_slice(data),
+        }
+    }
+
+    fn response() -> IrImageResponse {
+        IrImageResponse {
+            created: 1700000000,
+            images: vec![
+                IrGeneratedImage {
+                    b64_json: Some("aW1n".into()),
+                    url: None,
+                    mime_type: Some("image/webp".into()),
+                    revised_prompt: Some("a red fox".into()),
+                },
+                IrGeneratedImage {
+                    b64_json: None,
+                    url: Some("https://cdn.example/fox.png".into()),
+                    mime_type: None,
+                    revised_prompt: None,
+                },
+            ],
+            usage: Some(IrImageUsage { input_tokens: 10, output_tokens: 100 }),
+        }
+    }
+
+    fn encode(format: Option<IrImageResponseFormat>) -> serde_json::Value {
+        let body = OpenAiImageCodec.encode_response(&response(), format).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_decode_generation_request() {
+        let ir = OpenAiImageCodec
+            .decode_request(br#"{"model": "gpt-image-1", "prompt": "a fox", "size": "1024x1024", "response_format": "b64_json"}"#)
+            .unwrap();
+        assert_eq!(ir.model, "gpt-image-1");
+        assert_eq!(ir.n, 1);
+        assert_eq!(ir.size.as_deref(), Some("1024x1024"));
+        assert_eq!(ir.response_format, Some(IrImageResponseFormat::B64Json));
+        assert!(!ir.is_edit());
+
+        let ImageRequestBody::Json(body) = OpenAiImageCodec.encode_request(&ir, "dall-e-3").unwrap() else {
+            panic!("generation requests are sent as JSON");
+        };
+        let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "model": "dall-e-3",
+                "prompt": "a fox",
+                "n": 1,
+                "size": "1024x1024",
+                "response_format": "b64_json",
+            })
+        );
+    }
+
+    #[test]
+    fn test_b64_json_response_format() {
+        let value = encode(Some(IrImageResponseFormat::B64Json));
+        assert_eq!(value["data"][0], serde_json::json!({"b64_json": "aW1n", "revised_prompt": "a red fox"}));
+        // Linked images can't be inlined and are passed on as links
+        assert_eq!(value["data"][1], serde_json::json!({"url": "https://cdn.example/fox.png"}));
+        assert_eq!(
+            value["usage"],
+            serde_json::json!({"input_tokens": 10, "output_tokens": 100, "total_tokens": 110})
+        );
+    }
+
+    #[test]
+    fn test_url_response_format() {
+        let value = encode(Some(IrImageResponseFormat::Url));
+        assert_eq!(
+            value["data"][0],
+            serde_json::json!({"url": "data:image/webp;base64,aW1n", "revised_prompt": "a red fox"})
+        );
+        assert_eq!(value["data"][1], serde_json::json!({"url": "https://cdn.example/fox.png"}));
+
+        let value = encode(None);
+        assert_eq!(value["data"][0]["b64_json"], "aW1n");
+        assert_eq!(value["data"][1]["url"], "https://cdn.example/fox.png");
+    }
+
+    #[test]
+    fn test_decode_response() {
+        let ir = OpenAiImageCodec
+            .decode_response(br#"{"created": 1, "data": [{"url": "https://cdn.example/a.png"}, {"b64_json": "aW1n"}]}"#)
+            .unwrap();
+        assert_eq!(ir.images[0].url.as_deref(), Some("https://cdn.example/a.png"));
+        assert_eq!(ir.images[1].b64_json.as_deref(), Some("aW1n"));
+        assert!(ir.usage.is_none());
+    }
+
+    #[test]
+    fn test_decode_edit_request() {
+        let ir = OpenAiImageCodec
+            .decode_multipart_request(vec![
+                field("model", None, None, b"gpt-image-1"),
+                field("prompt", None, None, b" add a hat "),
+                field("n", None, None, b"2"),
+                field("response_format", None, None, b"url"),
+                field("image[]", Some("a.png"), Some("image/png"), b"png"),
+                field("image[]", Some("b.jpg"), Some("image/jpeg"), b"jpg"),
+                field("mask", Some("mask"), None, b"mask"),
+            ])
+            .unwrap();
+        assert_eq!(ir.prompt, "add a hat");
+        assert_eq!(ir.n, 2);
+        assert_eq!(ir.response_format, Some(IrImageResponseFormat::Url));
+        assert!(ir.is_edit());
+        assert_eq!(ir.images.len(), 2);
+        assert_eq!(ir.images[1].filename.as_deref(), Some("b.jpg"));
+        assert_eq!(ir.images[1].mime_type, "image/jpeg");
+        assert_eq!(ir.images[1].data, b"jpg");
+        let mask = ir.mask.unwrap();
+        assert_eq!((mask.mime_type.as_str(), mask.data.as_slice()), ("image/png", &b"mask"[..]));
+    }
+
+    #[test]
+    fn test_invalid_edit_requests_are_rejected() {
+        let rejected = |fields: Vec<MultipartField>| match OpenAiImageCodec.decode_multipart_request(fields) {
+            Err(AppError::Codec(msg)) => msg,
+            other => panic!("expected a codec error, got {:?}", other),
+        };
+        let image = || field("image", Some("a.png"), Some("image/png"), b"png");
+        let prompt = || field("prompt", None, None, b"add a hat");
+
+        assert_eq!(rejected(vec![prompt()]), "Missing image to edit");
+        assert_eq!(rejected(vec![image()]), "Missing prompt");
+        assert_eq!(rejected(vec![image(), prompt(), field("n", None, None, b"two")]), "Invalid n: two");
+        assert_eq!(
+            rejected(vec![image(), prompt(), field("response_format", None, None, b"png")]),
+            "Invalid response_format: png"
+        );
+    }
+
+    #[test]
+    fn test_edit_requests_are_sent_as_multipart() {
+        let ir = IrImageRequest {
+            model: "gpt-image-1".into(),
+            prompt: "add a hat".into(),
+            n: 1,
+            size: None,
+            quality: None,
+            response_format: None,
+            images: vec![IrImageData { filename: None, mime_type: "image/png".into(), data: b"png".to_vec() }],
+            mask: None,
+            user: None,
+        };
+        assert!(matches!(
+            OpenAiImageCodec.encode_request(&ir, "gpt-image-1").unwrap(),
+            ImageRequestBody::Multipart(_)
+        ));
+
+        let mut ir = ir;
+        ir.images[0].mime_type = "not a mime type".into();
+        assert!(matches!(
+            OpenAiImageCodec.encode_request(&ir, "gpt-image-1"),
+            Err(AppError::Codec(msg)) if msg.starts_with("Invalid image type")
+        ));
+    }
+}
@@ -1 +1,28 @@
+pub mod audio;
 pub mod chat;
+pub mod embedding;
+pub mod image;
+
+use bytes::Bytes;
+
+/// Whether a channel's provider (codec slug) speaks Google's Gemini API.
+/// The media modalities treat every other provider as OpenAI-compatible.
+pub fn is_gemini_provider(provider: &str) -> bool {
+    matches!(provider.to_lowercase().as_str(), "gemini" | "google")
+}
+
+/// One part of a `multipart/form-data` request body.
+#[derive(Debug, Clone)]
+pub struct MultipartField {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Bytes,
+}
+
+impl MultipartField {
+    /// The field value as text, for non-file fields.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data).trim().to_string()
+    }
+}
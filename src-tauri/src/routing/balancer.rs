@@ -1,42 +1,64 @@
 use crate::db::models::{Channel, ModelMapping};
 use crate::error::AppError;
 use crate::routing::circuit::CircuitBreaker;
+use crate::routing::keys::{KeyScheduler, KeyStrategy};
+use crate::routing::limiter::{RateLimitConfig, RateLimitPermit, RateLimiter};
 use rand::Rng;
 use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Result of channel selection: the channel, model mapping, and API key to use.
 pub struct SelectedChannel {
     pub channel: Channel,
     pub mapping: ModelMapping,
+    pub api_key_id: String,
     pub api_key: String,
+    /// Held for the lifetime of the request on rate-limited channels.
+    pub permit: Option<RateLimitPermit>,
 }
 
 /// Select the best available channel for a given model.
 ///
 /// Algorithm:
-/// 1. Find all enabled channels with a mapping for the requested model
+/// 1. Find all enabled channels with a mapping for the requested model and
+///    modality (`chat`, `embedding`, ...)
 /// 2. Group by priority (lower number = higher priority)
 /// 3. Within each priority group, filter out channels with open circuit breakers
+///    and channels listed in `exclude` (already tried for this request)
 /// 4. Select by weighted random from available channels
-/// 5. If no channels available in current priority, try next priority group
-/// 6. If all exhausted, return AllChannelsFailed
+/// 5. Pick an API key via the key scheduler; a channel with no usable key
+///    (all benched or parked) is dropped and the selection repeated
+/// 6. Admit the request against the channel's rate limit (`cost` is the
+///    estimated token count); a saturated channel is dropped and the selection repeated.
+///    Only an admitted request stamps its key's `last_used`
+/// 7. If no channels available in current priority, try next priority group
+/// 8. If all exhausted, return RateLimited when any candidate was only
+///    saturated, AllChannelsFailed otherwise
+#[allow(clippy::too_many_arguments)]
 pub async fn select_channel(
     model: &str,
+    modality: &str,
     db: &SqlitePool,
     circuit: &CircuitBreaker,
+    keys: &KeyScheduler,
+    limiter: &Arc<RateLimiter>,
+    exclude: &[String],
+    cost: i64,
 ) -> Result<SelectedChannel, AppError> {
     // Fetch all candidate channels with their mappings, ordered by priority
     let rows = sqlx::query_as::<_, ChannelWithMapping>(
         "SELECT c.id as channel_id, c.name, c.provider, c.base_url,
-                c.priority, c.weight, c.enabled, c.key_rotation,
+                c.priority, c.weight, c.enabled, c.key_rotation, c.key_strategy,
                 c.rate_limit, c.created_at, c.updated_at,
                 m.id as mapping_id, m.public_name, m.actual_name, m.modality
          FROM model_mappings m
          JOIN channels c ON m.channel_id = c.id
-         WHERE m.public_name = ? AND c.enabled = 1
+         WHERE m.public_name = ? AND m.modality = ? AND c.enabled = 1
          ORDER BY c.priority ASC",
     )
     .bind(model)
+    .bind(modality)
     .fetch_all(db)
     .await?;
 
@@ -56,63 +78,86 @@ pub async fn select_channel(
         priority_groups.push((row.priority, vec![row]));
     }
 
+    // Shortest wait among channels skipped for being rate limited
+    let mut saturated_wait: Option<Duration> = None;
+
     // Try each priority group
     for (_priority, group) in &priority_groups {
-        // Filter by circuit breaker
-        let available: Vec<&&ChannelWithMapping> = group
+        // Filter by circuit breaker and exclusion list
+        let mut available: Vec<&&ChannelWithMapping> = group
             .iter()
+            .filter(|r| !exclude.contains(&r.channel_id))
             .filter(|r| circuit.is_available(&r.channel_id))
             .collect();
 
-        if available.is_empty() {
-            continue;
-        }
+        while !available.is_empty() {
+            // Weighted random selection
+            let selected = weighted_random_select(&available);
+
+            // Pick an API key
+            let strategy = KeyStrategy::from_str_loose(&selected.key_strategy);
+            let Some(key) = keys
+                .select_key(db, &selected.channel_id, selected.key_rotation, strategy)
+                .await?
+            else {
+                log::warn!("No usable API key for channel '{}'", selected.name);
+                available.retain(|r| r.channel_id != selected.channel_id);
+                continue;
+            };
 
-        // Weighted random selection
-        let selected = weighted_random_select(&available);
-
-        // Fetch API key
-        let api_key = sqlx::query_scalar::<_, String>(
-            "SELECT key_value FROM channel_api_keys WHERE channel_id = ? AND enabled = 1 LIMIT 1",
-        )
-        .bind(&selected.channel_id)
-        .fetch_optional(db)
-        .await?
-        .ok_or_else(|| {
-            AppError::Internal(format!(
-                "No API key for channel '{}'",
-                selected.name
-            ))
-        })?;
-
-        return Ok(SelectedChannel {
-            channel: Channel {
-                id: selected.channel_id.clone(),
-                name: selected.name.clone(),
-                provider: selected.provider.clone(),
-                base_url: selected.base_url.clone(),
-                priority: selected.priority,
-                weight: selected.weight,
-                enabled: selected.enabled,
-                key_rotation: selected.key_rotation,
-                rate_limit: selected.rate_limit.clone(),
-                test_url: None,
-                test_headers: None,
-                created_at: selected.created_at.clone(),
-                updated_at: selected.updated_at.clone(),
-            },
-            mapping: ModelMapping {
-                id: selected.mapping_id.clone(),
-                public_name: selected.public_name.clone(),
-                channel_id: selected.channel_id.clone(),
-                actual_name: selected.actual_name.clone(),
-                modality: selected.modality.clone(),
-            },
-            api_key,
-        });
+            // Admit against the channel's rate limit. This comes last, as the
+            // permit consumes RPM/TPM budget that is not refunded.
+            let permit = match RateLimitConfig::parse(selected.rate_limit.as_deref()) {
+                Some(config) => match limiter.try_acquire(&selected.channel_id, &config, cost) {
+                    Ok(permit) => Some(permit),
+                    Err(wait) => {
+                        saturated_wait = Some(saturated_wait.map_or(wait, |w| w.min(wait)));
+                        available.retain(|r| r.channel_id != selected.channel_id);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            keys.mark_used(db, &key.id).await?;
+
+            return Ok(SelectedChannel {
+                channel: Channel {
+                    id: selected.channel_id.clone(),
+                    name: selected.name.clone(),
+                    provider: selected.provider.clone(),
+                    base_url: selected.base_url.clone(),
+                    priority: selected.priority,
+                    weight: selected.weight,
+                    enabled: selected.enabled,
+                    key_rotation: selected.key_rotation,
+                    key_strategy: selected.key_strategy.clone(),
+                    rate_limit: selected.rate_limit.clone(),
+                    test_url: None,
+                    test_headers: None,
+                    created_at: selected.created_at.clone(),
+                    updated_at: selected.updated_at.clone(),
+                },
+                mapping: ModelMapping {
+                    id: selected.mapping_id.clone(),
+                    public_name: selected.public_name.clone(),
+                    channel_id: selected.channel_id.clone(),
+                    actual_name: selected.actual_name.clone(),
+                    modality: selected.modality.clone(),
+                },
+                api_key_id: key.id,
+                api_key: key.value,
+                permit,
+            });
+        }
     }
 
-    Err(AppError::AllChannelsFailed(model.to_string()))
+    match saturated_wait {
+        Some(retry_after) => Err(AppError::RateLimited {
+            model: model.to_string(),
+            retry_after,
+        }),
+        None => Err(AppError::AllChannelsFailed(model.to_string())),
+    }
 }
 
 fn weighted_random_select<'a>(channels: &[&'a &ChannelWithMapping]) -> &'a ChannelWithMapping {
@@ -145,6 +190,7 @@ struct ChannelWithMapping {
     weight: i32,
     enabled: bool,
     key_rotation: bool,
+    key_strategy: String,
     rate_limit: Option<String>,
     created_at: String,
     updated_at: String,
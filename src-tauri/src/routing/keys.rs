@@ -0,0 +1,279 @@
+use rand::Rng;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a key that was rejected with 401/403 stays out of rotation.
+const BENCH_DURATION: Duration = Duration::from_secs(30 * 60);
+/// Park duration for a 429 response without a usable `retry-after` header.
+const DEFAULT_PARK_DURATION: Duration = Duration::from_secs(30);
+
+/// Strategy for picking among a channel's enabled API keys when
+/// `Channel.key_rotation` is on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyStrategy {
+    RoundRobin,
+    LeastRecentlyUsed,
+    Random,
+}
+
+impl KeyStrategy {
+    /// Parse a strategy name as stored in `channels.key_strategy`.
+    /// Unknown values fall back to round-robin.
+    pub fn from_str_loose(s: &str) -> Self {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "lru" | "least_recently_used" => Self::LeastRecentlyUsed,
+            "random" => Self::Random,
+            _ => Self::RoundRobin,
+        }
+    }
+}
+
+/// A channel API key picked by the scheduler.
+#[derive(Debug, Clone)]
+pub struct SelectedKey {
+    pub id: String,
+    pub value: String,
+}
+
+#[derive(sqlx::FromRow)]
+struct KeyRow {
+    id: String,
+    key_value: String,
+    last_used: Option<String>,
+}
+
+/// Schedules API keys within a channel and tracks keys that are temporarily
+/// unusable (benched after auth failures, parked after rate limiting).
+pub struct KeyScheduler {
+    /// key ID → instant until which the key is unavailable
+    unavailable: Mutex<HashMap<String, Instant>>,
+    /// channel ID → round-robin cursor
+    cursors: Mutex<HashMap<String, usize>>,
+}
+
+impl Default for KeyScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyScheduler {
+    pub fn new() -> Self {
+        Self {
+            unavailable: Mutex::new(HashMap::new()),
+            cursors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pick a key for the channel. Its `last_used` is left alone until the
+    /// caller commits to it with [`mark_used`](Self::mark_used).
+    ///
+    /// Without rotation the first available key is used, so the next key only
+    /// takes over while the first one is benched or parked. Returns `None` if
+    /// the channel has no usable key right now.
+    pub async fn select_key(
+        &self,
+        db: &SqlitePool,
+        channel_id: &str,
+        rotation: bool,
+        strategy: KeyStrategy,
+    ) -> Result<Option<SelectedKey>, sqlx::Error> {
+        let keys = sqlx::query_as::<_, KeyRow>(
+            "SELECT id, key_value, last_used FROM channel_api_keys
+             WHERE channel_id = ? AND enabled = 1 ORDER BY rowid",
+        )
+        .bind(channel_id)
+        .fetch_all(db)
+        .await?;
+
+        let available: Vec<&KeyRow> = {
+            let mut unavailable = self.unavailable.lock().unwrap();
+            let now = Instant::now();
+            unavailable.retain(|_, until| *until > now);
+            keys.iter()
+                .filter(|k| !unavailable.contains_key(&k.id))
+                .collect()
+        };
+
+        if available.is_empty() {
+            return Ok(None);
+        }
+
+        let picked = if !rotation {
+            available[0]
+        } else {
+            match strategy {
+                KeyStrategy::RoundRobin => {
+                    let mut cursors = self.cursors.lock().unwrap();
+                    let cursor = cursors.entry(channel_id.to_string()).or_insert(0);
+                    let picked = available[*cursor % available.len()];
+                    *cursor = cursor.wrapping_add(1);
+                    picked
+                }
+                // Never-used keys (NULL) sort first; RFC 3339 timestamps compare lexically
+                KeyStrategy::LeastRecentlyUsed => available
+                    .iter()
+                    .min_by(|a, b| a.last_used.cmp(&b.last_used))
+                    .copied()
+                    .unwrap(),
+                KeyStrategy::Random => available[rand::rng().random_range(0..available.len())],
+            }
+        };
+
+        Ok(Some(SelectedKey {
+            id: picked.id.clone(),
+            value: picked.key_value.clone(),
+        }))
+    }
+
+    /// Stamp the `last_used` of a key a request is going out with.
+    pub async fn mark_used(&self, db: &SqlitePool, key_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE channel_api_keys SET last_used = ? WHERE id = ?")
+            .bind(chrono::Utc::now().to_rfc3339())
+            .bind(key_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Take a key out of rotation after the upstream rejected it (401/403).
+    pub fn bench(&self, key_id: &str) {
+        log::warn!("Benching API key {} after auth failure", key_id);
+        self.unavailable
+            .lock()
+            .unwrap()
+            .insert(key_id.to_string(), Instant::now() + BENCH_DURATION);
+    }
+
+    /// Park a rate-limited key (429) until the upstream's `retry-after` time.
+    pub fn park(&self, key_id: &str, retry_after: Option<Duration>) {
+        let duration = retry_after.unwrap_or(DEFAULT_PARK_DURATION);
+        self.unavailable
+            .lock()
+            .unwrap()
+            .insert(key_id.to_string(), Instant::now() + duration);
+    }
+}
+
+/// Parse a `retry-after` header value, given either as delay-seconds or as an
+/// HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delta = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delta.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    /// In-memory database with one channel holding the keys `k0`, `k1`, `k2`.
+    async fn pool_with_keys() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        sqlx::query("INSERT INTO channels (id, name, provider, base_url) VALUES ('ch', 'ch', 'openai', '')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for i in 0..3 {
+            sqlx::query("INSERT INTO channel_api_keys (id, channel_id, key_value) VALUES (?1, 'ch', ?1)")
+                .bind(format!("k{i}"))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        pool
+    }
+
+    /// Select a key and use it, as the balancer does once the channel admits the request.
+    async fn pick(scheduler: &KeyScheduler, db: &SqlitePool, rotation: bool, strategy: KeyStrategy) -> Option<String> {
+        let key = scheduler.select_key(db, "ch", rotation, strategy).await.unwrap()?;
+        scheduler.mark_used(db, &key.id).await.unwrap();
+        Some(key.id)
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(" 120 "), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&date).unwrap();
+        assert!(wait > Duration::from_secs(85) && wait <= Duration::from_secs(90), "{wait:?}");
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        assert_eq!(KeyStrategy::from_str_loose("least-recently-used"), KeyStrategy::LeastRecentlyUsed);
+        assert_eq!(KeyStrategy::from_str_loose("LRU"), KeyStrategy::LeastRecentlyUsed);
+        assert_eq!(KeyStrategy::from_str_loose("random"), KeyStrategy::Random);
+        assert_eq!(KeyStrategy::from_str_loose("unknown"), KeyStrategy::RoundRobin);
+    }
+
+    #[tokio::test]
+    async fn test_strategies_pick_keys_in_order() {
+        let db = pool_with_keys().await;
+        let scheduler = KeyScheduler::new();
+
+        // Without rotation the first key sticks
+        for _ in 0..3 {
+            assert_eq!(pick(&scheduler, &db, false, KeyStrategy::RoundRobin).await.as_deref(), Some("k0"));
+        }
+
+        let mut round_robin = Vec::new();
+        for _ in 0..4 {
+            round_robin.push(pick(&scheduler, &db, true, KeyStrategy::RoundRobin).await.unwrap());
+        }
+        assert_eq!(round_robin, ["k0", "k1", "k2", "k0"]);
+
+        // k0 was stamped last, so the other two are older
+        sqlx::query("UPDATE channel_api_keys SET last_used = NULL WHERE id = 'k2'")
+            .execute(&db)
+            .await
+            .unwrap();
+        // A key that was selected but never used (its channel was saturated) keeps its place
+        let unused = scheduler.select_key(&db, "ch", true, KeyStrategy::LeastRecentlyUsed).await.unwrap();
+        assert_eq!(unused.map(|k| k.id).as_deref(), Some("k2"));
+        assert_eq!(pick(&scheduler, &db, true, KeyStrategy::LeastRecentlyUsed).await.as_deref(), Some("k2"));
+        assert_eq!(pick(&scheduler, &db, true, KeyStrategy::LeastRecentlyUsed).await.as_deref(), Some("k1"));
+
+        let random = pick(&scheduler, &db, true, KeyStrategy::Random).await.unwrap();
+        assert!(["k0", "k1", "k2"].contains(&random.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_benched_and_parked_keys_return_when_expired() {
+        let db = pool_with_keys().await;
+        let scheduler = KeyScheduler::new();
+
+        scheduler.bench("k0");
+        scheduler.park("k1", Some(Duration::from_millis(50)));
+        assert_eq!(pick(&scheduler, &db, false, KeyStrategy::RoundRobin).await.as_deref(), Some("k2"));
+
+        scheduler.park("k2", None);
+        assert_eq!(pick(&scheduler, &db, false, KeyStrategy::RoundRobin).await, None);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(pick(&scheduler, &db, false, KeyStrategy::RoundRobin).await.as_deref(), Some("k1"));
+
+        // Expire the bench without waiting half an hour
+        scheduler
+            .unavailable
+            .lock()
+            .unwrap()
+            .insert("k0".to_string(), Instant::now() - Duration::from_secs(1));
+        assert_eq!(pick(&scheduler, &db, false, KeyStrategy::RoundRobin).await.as_deref(), Some("k0"));
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Retry hint when a channel is saturated only by its concurrency limit.
+const CONCURRENCY_RETRY_HINT: Duration = Duration::from_secs(1);
+
+/// Per-channel limits stored as JSON in `channels.rate_limit`, e.g.
+/// `{"rpm":60,"tpm":100000,"concurrency":4}`. Every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Requests per minute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm: Option<u32>,
+    /// Tokens (prompt + completion) per minute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tpm: Option<u32>,
+    /// Maximum number of in-flight requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<u32>,
+}
+
+impl RateLimitConfig {
+    /// Parse the `rate_limit` column. Empty or invalid values mean "no limit".
+    pub fn parse(raw: Option<&str>) -> Option<Self> {
+        let raw = raw?.trim();
+        if raw.is_empty() {
+            return None;
+        }
+        match serde_json::from_str::<Self>(raw) {
+            Ok(config) if config != Self::default() => Some(config),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Ignoring invalid channel rate_limit '{}': {}", raw, e);
+                None
+            }
+        }
+    }
+}
+
+/// Classic token bucket refilled continuously at `capacity` per minute.
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: per_minute as f64,
+            available: per_minute as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn rate_per_sec(&self) -> f64 {
+        self.capacity / 60.0
+    }
+
+    /// Refill for elapsed time and apply a changed limit.
+    fn refill(&mut self, per_minute: u32, now: Instant) {
+        let capacity = per_minute as f64;
+        if capacity != self.capacity {
+            self.available = self.available.min(capacity);
+            self.capacity = capacity;
+        }
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.rate_per_sec()).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Time until `amount` is available, or zero if it already is.
+    /// Requests larger than the whole bucket only need a full bucket.
+    fn wait_for(&self, amount: f64) -> Duration {
+        let amount = amount.min(self.capacity);
+        if self.available >= amount || self.rate_per_sec() <= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((amount - self.available) / self.rate_per_sec())
+    }
+}
+
+#[derive(Default)]
+struct ChannelState {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    in_flight: u32,
+}
+
+/// In-process RPM/TPM/concurrency limiter shared by all proxy requests.
+#[derive(Default)]
+pub struct RateLimiter {
+    channels: Mutex<HashMap<String, ChannelState>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try to admit one request with an estimated `cost` in tokens on a channel.
+    ///
+    /// On success the request and token budgets are consumed and a permit is
+    /// returned that holds a concurrency slot until dropped. If the channel is
+    /// saturated, returns how long until it is expected to have capacity.
+    pub fn try_acquire(
+        self: &Arc<Self>,
+        channel_id: &str,
+        config: &RateLimitConfig,
+        cost: i64,
+    ) -> Result<RateLimitPermit, Duration> {
+        let mut channels = self.channels.lock().unwrap();
+        let state = channels.entry(channel_id.to_string()).or_default();
+        let now = Instant::now();
+        let cost = cost.max(0) as f64;
+
+        sync_bucket(&mut state.requests, config.rpm, now);
+        sync_bucket(&mut state.tokens, config.tpm, now);
+
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = &state.requests {
+            wait = wait.max(bucket.wait_for(1.0));
+        }
+        if let Some(bucket) = &state.tokens {
+            wait = wait.max(bucket.wait_for(cost));
+        }
+        if let Some(max) = config.concurrency {
+            if state.in_flight >= max {
+                wait = wait.max(CONCURRENCY_RETRY_HINT);
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        if let Some(bucket) = &mut state.requests {
+            bucket.available -= 1.0;
+        }
+        if let Some(bucket) = &mut state.tokens {
+            bucket.available -= cost.min(bucket.capacity);
+        }
+        state.in_flight += 1;
+
+        Ok(RateLimitPermit {
+            limiter: Arc::clone(self),
+            channel_id: channel_id.to_string(),
+            reserved_tokens: cost as i64,
+        })
+    }
+
+    fn adjust_tokens(&self, channel_id: &str, delta: i64) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(bucket) = channels.get_mut(channel_id).and_then(|s| s.tokens.as_mut()) {
+            // May go negative: overspending delays the next requests
+            bucket.available = (bucket.available - delta as f64).min(bucket.capacity);
+        }
+    }
+
+    fn release(&self, channel_id: &str) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(state) = channels.get_mut(channel_id) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+fn sync_bucket(bucket: &mut Option<TokenBucket>, limit: Option<u32>, now: Instant) {
+    match (bucket.as_mut(), limit) {
+        (Some(b), Some(limit)) => b.refill(limit, now),
+        (None, Some(limit)) => *bucket = Some(TokenBucket::new(limit)),
+        (_, None) => *bucket = None,
+    }
+}
+
+/// An admitted request on a rate-limited channel. Holds a concurrency slot
+/// until dropped.
+pub struct RateLimitPermit {
+    limiter: Arc<RateLimiter>,
+    channel_id: String,
+    reserved_tokens: i64,
+}
+
+impl RateLimitPermit {
+    /// Correct the TPM budget once the actual token usage is known.
+    pub fn record_usage(&mut self, actual_tokens: i64) {
+        self.limiter
+            .adjust_tokens(&self.channel_id, actual_tokens - self.reserved_tokens);
+        self.reserved_tokens = actual_tokens;
+    }
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.channel_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(raw: &str) -> RateLimitConfig {
+        RateLimitConfig::parse(Some(raw)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        assert_eq!(config(r#"{"rpm":60}"#).rpm, Some(60));
+        assert_eq!(RateLimitConfig::parse(Some("{}")), None);
+        assert_eq!(RateLimitConfig::parse(Some("  ")), None);
+        assert_eq!(RateLimitConfig::parse(Some(r#"{"rps":1}"#)), None);
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let mut bucket = TokenBucket::new(60);
+        let start = bucket.last_refill;
+        bucket.available = 0.0;
+        assert_eq!(bucket.wait_for(1.0), Duration::from_secs(1));
+
+        bucket.refill(60, start + Duration::from_secs(30));
+        assert_eq!(bucket.available, 30.0);
+        bucket.refill(60, start + Duration::from_secs(120));
+        assert_eq!(bucket.available, 60.0, "refill is capped at capacity");
+
+        // A lowered limit shrinks the balance; requests over capacity only need a full bucket
+        bucket.refill(10, start + Duration::from_secs(120));
+        assert_eq!(bucket.available, 10.0);
+        assert_eq!(bucket.wait_for(1000.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rpm_and_tpm_budgets() {
+        let limiter = Arc::new(RateLimiter::new());
+
+        let rpm = config(r#"{"rpm":2}"#);
+        let _first = limiter.try_acquire("a", &rpm, 0).unwrap();
+        let _second = limiter.try_acquire("a", &rpm, 0).unwrap();
+        assert!(limiter.try_acquire("a", &rpm, 0).is_err());
+
+        let tpm = config(r#"{"tpm":1000}"#);
+        let mut permit = limiter.try_acquire("b", &tpm, 800).unwrap();
+        let wait = limiter.try_acquire("b", &tpm, 300).err().unwrap();
+        assert!(wait > Duration::ZERO);
+
+        // Reporting less usage than estimated returns the difference
+        permit.record_usage(100);
+        drop(permit);
+        assert!(limiter.try_acquire("b", &tpm, 800).is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_slot_released_on_drop() {
+        let limiter = Arc::new(RateLimiter::new());
+        let limit = config(r#"{"concurrency":1}"#);
+
+        let permit = limiter.try_acquire("a", &limit, 0).unwrap();
+        assert_eq!(limiter.try_acquire("a", &limit, 0).err(), Some(CONCURRENCY_RETRY_HINT));
+        drop(permit);
+        assert!(limiter.try_acquire("a", &limit, 0).is_ok());
+    }
+}
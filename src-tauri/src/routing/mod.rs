@@ -1,2 +1,5 @@
 pub mod balancer;
 pub mod circuit;
+pub mod keys;
+pub mod limiter;
+pub mod retry;
@@ -0,0 +1,84 @@
+use crate::config::AppConfig;
+use std::time::Duration;
+
+/// Controls how `proxy_chat` fails over to other channels when an upstream
+/// attempt fails.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of upstream attempts per request (1 = no retry).
+    pub max_attempts: u32,
+    /// Upstream HTTP status codes that trigger a failover.
+    pub retryable_statuses: Vec<u16>,
+    /// Base delay before the second attempt; doubled for every further attempt.
+    pub backoff_base: Duration,
+    /// Upper bound for the exponential backoff delay.
+    pub backoff_max: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_config(&AppConfig::default())
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_attempts: config.retry_max_attempts.max(1),
+            retryable_statuses: config.retry_status_codes.clone(),
+            backoff_base: Duration::from_millis(config.retry_backoff_ms),
+            backoff_max: Duration::from_millis(config.retry_backoff_ms.saturating_mul(16)),
+        }
+    }
+
+    /// Returns true if another attempt is allowed after `attempt` attempts have been made.
+    pub fn has_attempts_left(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Returns true if an upstream response with this status should be retried
+    /// on another channel.
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    /// Delay to wait before making attempt number `attempt + 1`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            retryable_statuses: vec![429, 503],
+            backoff_base: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(350),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(350));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(350));
+
+        assert!(policy.has_attempts_left(2));
+        assert!(!policy.has_attempts_left(3));
+        assert!(policy.is_retryable_status(503));
+        assert!(!policy.is_retryable_status(400));
+    }
+
+    #[test]
+    fn test_at_least_one_attempt() {
+        let config = AppConfig { retry_max_attempts: 0, ..AppConfig::default() };
+        let policy = RetryPolicy::from_config(&config);
+        assert_eq!(policy.max_attempts, 1);
+        assert!(!policy.has_attempts_left(1));
+    }
+}
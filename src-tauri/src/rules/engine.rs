@@ -1,26 +1,510 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
 use bumpalo::Bump;
-use jsonata_rs::JsonAta;
+use jsonata_rs::{JsonAta, Value};
+use self_cell::self_cell;
 
+use crate::config::AppConfig;
+use crate::db::models::ConversionRule;
 use crate::error::AppError;
 
+/// Bounds on a single evaluation, so a pathological rule fails instead of
+/// hanging a worker or exhausting memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout_ms: u64,
+    /// Maximum nesting of evaluation steps, which bounds recursion.
+    pub max_depth: usize,
+    /// Maximum size of the result, measured as compact JSON.
+    pub max_output_bytes: usize,
+}
+
+impl Limits {
+    pub const DEFAULT: Limits = Limits {
+        timeout_ms: 1000,
+        // Each level costs several native frames; this stays well inside an
+        // evaluator thread's stack even in debug builds
+        max_depth: 150,
+        // Requests may carry inlined images
+        max_output_bytes: 32 * 1024 * 1024,
+    };
+
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            timeout_ms: config.rule_timeout_ms,
+            max_depth: config.rule_max_depth,
+            max_output_bytes: config.rule_max_output_bytes,
+        }
+    }
+}
+
+/// The limit an evaluation ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleLimit {
+    Timeout(u64),
+    Depth(usize),
+    OutputSize(usize),
+}
+
+impl fmt::Display for RuleLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleLimit::Timeout(ms) => write!(f, "exceeded the {ms} ms time limit"),
+            RuleLimit::Depth(depth) => write!(f, "exceeded the recursion depth limit of {depth}"),
+            RuleLimit::OutputSize(bytes) => write!(f, "produced more than {bytes} bytes"),
+        }
+    }
+}
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits::DEFAULT);
+
+/// Apply new limits to all later evaluations.
+pub fn set_limits(limits: Limits) {
+    *LIMITS.write().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+fn limits() -> Limits {
+    *LIMITS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Parsed programs kept per evaluator thread, keyed by the rule and version they
+/// belong to, so an edited rule misses the cache and two rules never share
+/// a program. Past this many, the least recently used program is dropped.
+const MAX_CACHED_PROGRAMS: usize = 64;
+
+/// Evaluations allocate into the program's arena, which is only freed with
+/// the program, so a program is re-parsed once its arena grows past this.
+const ARENA_RECYCLE_BYTES: usize = 512 * 1024;
+
+type Compiled<'a> = JsonAta<'a>;
+
+self_cell!(
+    /// A parsed expression together with the arena it evaluates in.
+    struct Program {
+        owner: Bump,
+        #[not_covariant]
+        dependent: Compiled,
+    }
+);
+
+impl Program {
+    fn parse(expression: &str) -> Result<Self, AppError> {
+        Program::try_new(Bump::new(), |arena| JsonAta::new(expression, arena))
+            .map_err(|e| AppError::Codec(format!("JSONata parse error: {e}")))
+    }
+
+    /// Parse an expression for reuse. `evaluate_timeboxed` binds variables
+    /// into the program's root frame, which outlives the evaluation, so the
+    /// expression is wrapped in a block: each evaluation then gets a fresh
+    /// frame for its `:=` bindings and nothing leaks into the next one.
+    fn parse_reusable(expression: &str) -> Result<Self, AppError> {
+        Self::parse(&format!("(\n{expression}\n)"))
+    }
+}
+
+/// One expression of one version of a rule. A rule's `updated_at` changes
+/// with every edit, so it serves as the version.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ProgramKey {
+    rule_id: String,
+    version: String,
+    expression: String,
+}
+
+impl ProgramKey {
+    fn new(rule: &ConversionRule, expression: &str) -> Self {
+        Self {
+            rule_id: rule.id.clone(),
+            version: rule.updated_at.clone(),
+            expression: expression.to_string(),
+        }
+    }
+}
+
+struct CachedProgram {
+    program: Program,
+    /// Value of the cache's clock when the program was last used.
+    last_used: u64,
+    /// Value of `GENERATION` when the program was parsed.
+    generation: u64,
+}
+
+/// Bumped each time rules are forgotten, so every thread drops their
+/// programs the next time it evaluates.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Rules whose programs are stale, by the generation they were retired at.
+static RETIRED: Mutex<Retired> = Mutex::new(Retired { all_before: 0, rules: None });
+
+struct Retired {
+    /// Programs of any rule parsed before this generation are stale.
+    all_before: u64,
+    rules: Option<HashMap<String, u64>>,
+}
+
+impl Retired {
+    fn is_stale(&self, key: &ProgramKey, cached: &CachedProgram) -> bool {
+        cached.generation < self.all_before
+            || self
+                .rules
+                .as_ref()
+                .and_then(|rules| rules.get(&key.rule_id))
+                .is_some_and(|&retired| cached.generation < retired)
+    }
+}
+
+fn retired() -> std::sync::MutexGuard<'static, Retired> {
+    RETIRED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Drop the cached programs of a rule that was changed or removed.
+pub fn forget_rule(rule_id: &str) {
+    let mut retired = retired();
+    let generation = GENERATION.fetch_add(1, Ordering::AcqRel) + 1;
+    retired.rules.get_or_insert_with(HashMap::new).insert(rule_id.to_string(), generation);
+}
+
+/// Drop the cached programs of every rule, such as when all rules are
+/// reloaded.
+pub fn forget_all_rules() {
+    let mut retired = retired();
+    let generation = GENERATION.fetch_add(1, Ordering::AcqRel) + 1;
+    retired.all_before = generation;
+    retired.rules = None;
+}
+
+/// A least-recently-used cache of parsed programs.
+#[derive(Default)]
+struct ProgramCache {
+    programs: HashMap<ProgramKey, CachedProgram>,
+    clock: u64,
+    /// The generation whose retired rules were last swept out.
+    synced: u64,
+}
+
+impl ProgramCache {
+    /// The program for `key`, parsed on a miss.
+    fn get_or_parse(&mut self, key: &ProgramKey) -> Result<&Program, AppError> {
+        self.drop_retired();
+        self.clock += 1;
+        if !self.programs.contains_key(key) {
+            if self.programs.len() >= MAX_CACHED_PROGRAMS {
+                self.evict_least_recently_used();
+            }
+            let generation = GENERATION.load(Ordering::Acquire);
+            let program = Program::parse_reusable(&key.expression)?;
+            self.programs.insert(key.clone(), CachedProgram { program, last_used: 0, generation });
+        }
+        let cached = self.programs.get_mut(key).expect("program was just cached");
+        cached.last_used = self.clock;
+        Ok(&cached.program)
+    }
+
+    fn drop_retired(&mut self) {
+        let generation = GENERATION.load(Ordering::Acquire);
+        if generation == self.synced {
+            return;
+        }
+        let retired = retired();
+        self.programs.retain(|key, cached| !retired.is_stale(key, cached));
+        self.synced = generation;
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .programs
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.programs.remove(&key);
+        }
+    }
+}
+
 /// Evaluate a JSONata expression against the given JSON input and return the result.
+/// The expression is parsed for this evaluation only.
 pub fn evaluate(expression: &str, input: &serde_json::Value) -> Result<serde_json::Value, AppError> {
-    let arena = Bump::new();
-    let jsonata = JsonAta::new(expression, &arena)
-        .map_err(|e| AppError::Codec(format!("JSONata parse error: {e}")))?;
+    let input_str = serde_json::to_string(input)
+        .map_err(|e| AppError::Codec(format!("Failed to serialize input: {e}")))?;
+    evaluate_str(None, expression, input_str)
+}
 
+/// Evaluate one of a rule's expressions, reusing its parsed program until
+/// the rule changes.
+pub fn evaluate_rule(
+    rule: &ConversionRule,
+    expression: &str,
+    input: &serde_json::Value,
+) -> Result<serde_json::Value, AppError> {
     let input_str = serde_json::to_string(input)
         .map_err(|e| AppError::Codec(format!("Failed to serialize input: {e}")))?;
+    evaluate_str(Some(ProgramKey::new(rule, expression)), expression, input_str)
+}
+
+/// Evaluate one of a rule's expressions against input that is already JSON
+/// text, such as a request body, without building a `serde_json::Value` first.
+pub fn evaluate_rule_json(
+    rule: &ConversionRule,
+    expression: &str,
+    input: &[u8],
+) -> Result<serde_json::Value, AppError> {
+    // jsonata-rs parses its input with the expression parser, so anything
+    // that is not plain JSON must be rejected before it gets there
+    serde_json::from_slice::<serde::de::IgnoredAny>(input)
+        .map_err(|e| AppError::Codec(format!("Invalid JSON: {e}")))?;
+    let input = std::str::from_utf8(input).map_err(|e| AppError::Codec(format!("Invalid JSON: {e}")))?;
+    evaluate_str(Some(ProgramKey::new(rule, expression)), expression, input.to_string())
+}
+
+/// How long past its time limit an evaluation may run before the caller
+/// gives up on it, which leaves the evaluator time to report the limit itself.
+const TIMEOUT_GRACE: Duration = Duration::from_millis(100);
 
-    let result = jsonata
-        .evaluate(Some(&input_str), None)
-        .map_err(|e| AppError::Codec(format!("JSONata evaluation error: {e}")))?;
+/// Stack of each evaluator thread, which bounds how deep `max_depth` can go.
+const EVALUATOR_STACK_BYTES: usize = 8 * 1024 * 1024;
 
-    let result_str = result.serialize(false);
-    let value: serde_json::Value = serde_json::from_str(&result_str)
-        .map_err(|e| AppError::Codec(format!("Failed to parse JSONata result: {e}")))?;
+/// Most evaluator threads to start, however many cores there are.
+const MAX_EVALUATORS: usize = 8;
 
-    Ok(value)
+/// An evaluation handed to an evaluator thread.
+struct Job {
+    key: Option<ProgramKey>,
+    expression: String,
+    input: String,
+    limits: Limits,
+    /// When the caller stops waiting; a job still queued by then is skipped.
+    deadline: Instant,
+    reply: mpsc::SyncSender<Result<serde_json::Value, AppError>>,
+}
+
+/// Long-lived threads that own the parsed programs. `JsonAta` is neither
+/// `Send` nor `Sync`, so each program lives on one thread: a rule expression
+/// is always sent to the same evaluator and is parsed once, while ad-hoc
+/// expressions are spread over all of them.
+struct Evaluators {
+    queues: Vec<mpsc::Sender<Job>>,
+    next: AtomicUsize,
+}
+
+impl Evaluators {
+    fn start() -> Self {
+        let count = std::thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_EVALUATORS);
+        let queues = (0..count)
+            .map(|i| {
+                let (tx, rx) = mpsc::channel();
+                std::thread::Builder::new()
+                    .name(format!("jsonata-{i}"))
+                    .stack_size(EVALUATOR_STACK_BYTES)
+                    .spawn(move || serve(rx))
+                    .expect("failed to start a JSONata evaluator thread");
+                tx
+            })
+            .collect();
+        Self { queues, next: AtomicUsize::new(0) }
+    }
+
+    fn queue_for(&self, key: Option<&ProgramKey>) -> &mpsc::Sender<Job> {
+        let index = match key {
+            Some(key) => {
+                let mut hasher = std::hash::DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish() as usize
+            }
+            None => self.next.fetch_add(1, Ordering::Relaxed),
+        };
+        &self.queues[index % self.queues.len()]
+    }
+}
+
+static EVALUATORS: OnceLock<Evaluators> = OnceLock::new();
+
+/// Run jobs until the process exits, keeping this thread's programs cached.
+fn serve(jobs: mpsc::Receiver<Job>) {
+    let mut programs = ProgramCache::default();
+    for job in jobs {
+        if Instant::now() >= job.deadline {
+            continue;
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            evaluate_program(&mut programs, job.key.as_ref(), &job.expression, &job.input, job.limits)
+        }))
+        .unwrap_or_else(|_| {
+            // The cache may have been caught halfway through an update
+            programs = ProgramCache::default();
+            Err(AppError::Codec("JSONata evaluation panicked".into()))
+        });
+        let _ = job.reply.send(result);
+    }
+}
+
+/// Evaluation is synchronous, so it runs on an evaluator thread rather than
+/// a runtime worker. The caller waits at most the time limit plus a grace
+/// period: an evaluation that overruns fails the request even while its
+/// evaluator is still busy.
+fn evaluate_str(key: Option<ProgramKey>, expression: &str, input: String) -> Result<serde_json::Value, AppError> {
+    let limits = limits();
+    let wait = Duration::from_millis(limits.timeout_ms).saturating_add(TIMEOUT_GRACE);
+    let (reply, result) = mpsc::sync_channel(1);
+    let evaluators = EVALUATORS.get_or_init(Evaluators::start);
+    let job = Job {
+        deadline: Instant::now() + wait,
+        expression: expression.to_string(),
+        input,
+        limits,
+        reply,
+        key,
+    };
+    evaluators
+        .queue_for(job.key.as_ref())
+        .send(job)
+        .map_err(|_| AppError::Codec("JSONata evaluator stopped".into()))?;
+
+    let result = match wait_for(&result, wait) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(AppError::CodecLimit(RuleLimit::Timeout(limits.timeout_ms))),
+        Err(RecvTimeoutError::Disconnected) => Err(AppError::Codec("JSONata evaluator stopped".into())),
+    };
+    if let Err(AppError::CodecLimit(limit)) = &result {
+        let head: String = expression.chars().take(80).collect();
+        log::warn!("JSONata rule {limit}: {head}");
+    }
+    result
+}
+
+/// Wait at most `wait` for a reply. On the multi-threaded runtime the other
+/// tasks of this worker move elsewhere meanwhile.
+fn wait_for<T>(reply: &mpsc::Receiver<T>, wait: Duration) -> Result<T, RecvTimeoutError> {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| reply.recv_timeout(wait))
+        }
+        _ => reply.recv_timeout(wait),
+    }
+}
+
+/// Evaluate a rule expression through an evaluator's program cache, or an
+/// ad-hoc expression through a program of its own.
+fn evaluate_program(
+    programs: &mut ProgramCache,
+    key: Option<&ProgramKey>,
+    expression: &str,
+    input: &str,
+    limits: Limits,
+) -> Result<serde_json::Value, AppError> {
+    let Some(key) = key else {
+        return run(&Program::parse(expression)?, input, limits);
+    };
+    let program = programs.get_or_parse(key)?;
+    let result = run(program, input, limits);
+    if program.borrow_owner().allocated_bytes() > ARENA_RECYCLE_BYTES {
+        programs.programs.remove(key);
+    }
+    result
+}
+
+/// jsonata-rs only accepts input as text, so that side of the conversion
+/// stays; the result is converted straight from its arena value.
+fn run(program: &Program, input: &str, limits: Limits) -> Result<serde_json::Value, AppError> {
+    program.with_dependent(|_, jsonata| {
+        let time_limit = usize::try_from(limits.timeout_ms).unwrap_or(usize::MAX);
+        let value = jsonata
+            .evaluate_timeboxed(Some(input), Some(limits.max_depth), Some(time_limit))
+            .map_err(|e| match e {
+                jsonata_rs::Error::U1001Timeout => AppError::CodecLimit(RuleLimit::Timeout(limits.timeout_ms)),
+                jsonata_rs::Error::U1001StackOverflow => AppError::CodecLimit(RuleLimit::Depth(limits.max_depth)),
+                e => AppError::Codec(format!("JSONata evaluation error: {e}")),
+            })?;
+        let mut budget = limits.max_output_bytes;
+        to_json(value, &mut budget)
+            .map_err(|_| AppError::CodecLimit(RuleLimit::OutputSize(limits.max_output_bytes)))?
+            .ok_or_else(|| AppError::Codec("JSONata expression produced no result".into()))
+    })
+}
+
+/// The output size budget ran out.
+struct OverBudget;
+
+/// Take roughly the compact JSON size of a value out of the budget.
+fn spend(budget: &mut usize, bytes: usize) -> Result<(), OverBudget> {
+    *budget = budget.checked_sub(bytes).ok_or(OverBudget)?;
+    Ok(())
+}
+
+/// Convert an evaluation result the way jsonata-rs serializes it: numbers
+/// keep 15 significant digits, functions become empty strings and undefined
+/// values are dropped (`None` at the top level).
+fn to_json<'a>(value: &'a Value<'a>, budget: &mut usize) -> Result<Option<serde_json::Value>, OverBudget> {
+    Ok(Some(match value {
+        Value::Undefined => return Ok(None),
+        Value::Null => {
+            spend(budget, 4)?;
+            serde_json::Value::Null
+        }
+        Value::Bool(b) => {
+            spend(budget, 5)?;
+            serde_json::Value::Bool(*b)
+        }
+        Value::Number(n) => {
+            spend(budget, 8)?;
+            number_to_json(*n)
+        }
+        Value::String(s) => {
+            spend(budget, s.len() + 2)?;
+            serde_json::Value::String(s.to_string())
+        }
+        Value::Array(..) | Value::Range(..) => {
+            spend(budget, 2)?;
+            let mut items = Vec::new();
+            for member in value.members() {
+                if let Some(item) = to_json(member, budget)? {
+                    spend(budget, 1)?;
+                    items.push(item);
+                }
+            }
+            serde_json::Value::Array(items)
+        }
+        Value::Object(..) => {
+            spend(budget, 2)?;
+            let mut map = serde_json::Map::new();
+            for (key, member) in value.entries() {
+                spend(budget, key.len() + 4)?;
+                if let Some(item) = to_json(member, budget)? {
+                    map.insert(key.to_string(), item);
+                }
+            }
+            serde_json::Value::Object(map)
+        }
+        Value::Regex(regex) => {
+            spend(budget, regex.as_pattern().len() + 2)?;
+            serde_json::Value::String(regex.as_pattern().to_string())
+        }
+        Value::Lambda { .. } | Value::NativeFn { .. } | Value::Transformer { .. } => {
+            spend(budget, 2)?;
+            serde_json::Value::String(String::new())
+        }
+    }))
+}
+
+fn number_to_json(n: f64) -> serde_json::Value {
+    if !n.is_finite() {
+        return serde_json::Value::Null;
+    }
+    // Same precision as JSONata's `Number.toPrecision(15)`
+    let n: f64 = format!("{n:.14e}").parse().unwrap_or(n);
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        serde_json::Value::from(n as i64)
+    } else {
+        serde_json::Number::from_f64(n).map_or(serde_json::Value::Null, serde_json::Value::Number)
+    }
 }
 
 /// Validate that a JSONata expression is syntactically correct.
@@ -30,3 +514,128 @@ pub fn validate(expression: &str) -> Result<(), String> {
         .map_err(|e| format!("Invalid JSONata expression: {e}"))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(rule_id: &str, expression: &str) -> ProgramKey {
+        ProgramKey { rule_id: rule_id.into(), version: "1".into(), expression: expression.into() }
+    }
+
+    #[test]
+    fn test_cached_programs_match_fresh_evaluation() {
+        let expression = r#"{"model": model, "n": $count(messages), "ratio": 0.1 + 0.2, "gone": missing}"#;
+        let input = json!({"model": "m", "messages": [1, 2, 3]});
+        let expected = json!({"model": "m", "n": 3, "ratio": 0.3});
+        let key = key("cached", expression);
+        let body = serde_json::to_string(&input).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(evaluate_str(Some(key.clone()), expression, body.clone()).unwrap(), expected);
+        }
+        assert_eq!(evaluate(expression, &input).unwrap(), expected);
+        assert!(matches!(evaluate("missing", &input), Err(AppError::Codec(_))));
+    }
+
+    #[test]
+    fn test_bindings_do_not_survive_into_the_next_evaluation() {
+        let expression = r#"($leak := $exists($leak) ? "leaked" : "fresh"; $leak)"#;
+        let top_level = r#"$leak := $exists($leak) ? "leaked" : "fresh""#;
+
+        for expression in [expression, top_level] {
+            let key = key("bindings", expression);
+            let mut cache = ProgramCache::default();
+            for _ in 0..2 {
+                let result = evaluate_program(&mut cache, Some(&key), expression, "{}", Limits::DEFAULT);
+                assert_eq!(result.unwrap(), "fresh");
+            }
+        }
+    }
+
+    #[test]
+    fn test_full_cache_evicts_least_recently_used_program() {
+        let mut cache = ProgramCache::default();
+        for i in 0..MAX_CACHED_PROGRAMS {
+            cache.get_or_parse(&key("lru", &i.to_string())).unwrap();
+        }
+        cache.get_or_parse(&key("lru", "0")).unwrap();
+        cache.get_or_parse(&key("lru", "new")).unwrap();
+
+        assert_eq!(cache.programs.len(), MAX_CACHED_PROGRAMS);
+        assert!(cache.programs.contains_key(&key("lru", "0")));
+        assert!(!cache.programs.contains_key(&key("lru", "1")));
+        assert!(cache.programs.contains_key(&key("lru", "new")));
+    }
+
+    #[test]
+    fn test_forgotten_rules_are_dropped_from_the_cache() {
+        let mut cache = ProgramCache::default();
+        cache.get_or_parse(&key("forgotten", "1")).unwrap();
+        cache.get_or_parse(&key("kept", "1")).unwrap();
+
+        forget_rule("forgotten");
+        cache.get_or_parse(&key("kept", "2")).unwrap();
+
+        assert!(!cache.programs.contains_key(&key("forgotten", "1")));
+        assert!(cache.programs.contains_key(&key("kept", "1")));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_callers_stop_waiting_at_the_deadline() {
+        let (_reply, result) = mpsc::sync_channel::<()>(1);
+        let start = Instant::now();
+        assert_eq!(wait_for(&result, Duration::from_millis(50)), Err(RecvTimeoutError::Timeout));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_evaluators_skip_jobs_whose_caller_gave_up() {
+        let (queue, jobs) = mpsc::channel();
+        std::thread::spawn(move || serve(jobs));
+        let job = |deadline| {
+            let (reply, result) = mpsc::sync_channel(1);
+            let job = Job {
+                key: Some(key("queued", "1 + 1")),
+                expression: "1 + 1".into(),
+                input: "{}".into(),
+                limits: Limits::DEFAULT,
+                deadline,
+                reply,
+            };
+            queue.send(job).unwrap();
+            result
+        };
+
+        let abandoned = job(Instant::now());
+        let waiting = job(Instant::now() + Duration::from_secs(5));
+        assert_eq!(waiting.recv().unwrap().unwrap(), 2);
+        assert!(abandoned.recv().is_err());
+    }
+
+    #[test]
+    fn test_limits_surface_as_codec_limit_errors() {
+        let limits = Limits { timeout_ms: 200, max_depth: 100, max_output_bytes: 1024 };
+        let input = "{}";
+
+        let recursion = "($f := function($n) { $n = 0 ? 0 : 1 + $f($n - 1) }; $f(100000))";
+        assert!(matches!(
+            evaluate_program(&mut ProgramCache::default(), None, recursion, input, limits),
+            Err(AppError::CodecLimit(RuleLimit::Depth(100)))
+        ));
+
+        let large = "$map([1..1000], function($i) { \"item\" })";
+        assert!(matches!(
+            evaluate_program(&mut ProgramCache::default(), None, large, input, limits),
+            Err(AppError::CodecLimit(RuleLimit::OutputSize(1024)))
+        ));
+
+        let slow = "$count($map([1..2000000], function($i) { $i * 2 }))";
+        let limits = Limits { max_depth: 10_000, ..limits };
+        assert!(matches!(
+            evaluate_program(&mut ProgramCache::default(), None, slow, input, limits),
+            Err(AppError::CodecLimit(RuleLimit::Timeout(200)))
+        ));
+    }
+}
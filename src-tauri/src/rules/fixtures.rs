@@ -0,0 +1,273 @@
+//! Golden fixtures for conversion rules: sample wire JSON paired with the IR
+//! a codec should decode it into, or IR paired with the wire JSON it should
+//! encode to. A rule carries its own fixtures, and the built-in codecs ship
+//! theirs so JSONata rewrites of a format can be checked against them.
+
+use crate::error::AppError;
+use crate::modality::chat::ir::{IrChatRequest, IrChatResponse, IrStreamChunk};
+use crate::modality::chat::ChatFormat;
+use crate::rules::registry::CodecProvider;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The codec step a fixture exercises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixtureStep {
+    DecodeRequest,
+    EncodeRequest,
+    DecodeResponse,
+    EncodeResponse,
+    DecodeStreamChunk,
+    EncodeStreamChunk,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleFixture {
+    pub name: String,
+    pub step: FixtureStep,
+    /// Wire JSON for decode steps, IR for encode steps. A string input to
+    /// `decode_stream_chunk` is passed as raw SSE data (e.g. `[DONE]`).
+    pub input: Value,
+    /// IR for decode steps (`null` for a stream event that yields no chunk),
+    /// wire JSON for encode steps. `encode_stream_chunk` expects a list of
+    /// `{"event", "data"}` events.
+    pub expected: Value,
+    /// Target model for `encode_request`; defaults to the IR's model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// SSE event type for `decode_stream_chunk`; defaults to `message`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+}
+
+/// One place where the actual output differs from the expected one. A
+/// missing side means the value is absent there.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FixtureDiff {
+    /// JSON Pointer into the output, `""` for the whole value.
+    pub path: String,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FixtureResult {
+    pub name: String,
+    pub step: FixtureStep,
+    pub passed: bool,
+    /// Set when the codec failed instead of producing output.
+    pub error: Option<String>,
+    pub diffs: Vec<FixtureDiff>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleTestReport {
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<FixtureResult>,
+}
+
+impl RuleTestReport {
+    pub fn all_passed(&self) -> bool {
+        self.failed == 0
+    }
+
+    /// Names of the failing fixtures, for logs and error messages.
+    pub fn failures(&self) -> Vec<&str> {
+        self.results.iter().filter(|r| !r.passed).map(|r| r.name.as_str()).collect()
+    }
+}
+
+/// Parse a rule's `test_fixtures` column. An empty column has no fixtures.
+pub fn parse(json: Option<&str>) -> Result<Vec<RuleFixture>, String> {
+    match json.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(json) => serde_json::from_str(json).map_err(|e| format!("Invalid test fixtures: {e}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The fixtures shipped with a built-in codec. They are the codecs' tests
+/// for single steps; Rust tests only cover what a fixture can't express, such
+/// as errors, state across stream chunks and round trips.
+pub fn builtin(format: ChatFormat) -> Vec<RuleFixture> {
+    let json = match format {
+        ChatFormat::OpenaiChat => include_str!("../../fixtures/chat/openai-chat.json"),
+        ChatFormat::OpenaiResponses => include_str!("../../fixtures/chat/openai-responses.json"),
+        ChatFormat::Anthropic => include_str!("../../fixtures/chat/anthropic.json"),
+        ChatFormat::Gemini => include_str!("../../fixtures/chat/gemini.json"),
+        ChatFormat::Moonshot => include_str!("../../fixtures/chat/moonshot.json"),
+    };
+    // Checked by the tests below, so a bad file never ships
+    parse(Some(json)).unwrap_or_default()
+}
+
+/// Run fixtures through a codec and collect the results.
+pub fn run(provider: &CodecProvider, fixtures: &[RuleFixture]) -> RuleTestReport {
+    let results: Vec<FixtureResult> = fixtures
+        .iter()
+        .map(|fixture| {
+            let (error, diffs) = match run_one(provider, fixture) {
+                Ok((expected, actual)) => (None, diff(&expected, &actual)),
+                Err(e) => (Some(e.to_string()), Vec::new()),
+            };
+            FixtureResult {
+                name: fixture.name.clone(),
+                step: fixture.step,
+                passed: error.is_none() && diffs.is_empty(),
+                error,
+                diffs,
+            }
+        })
+        .collect();
+    let passed = results.iter().filter(|r| r.passed).count();
+    RuleTestReport { passed, failed: results.len() - passed, results }
+}
+
+/// Produce the (expected, actual) pair for a fixture. Expected IR is passed
+/// through its type first, so fixtures may leave out empty fields.
+fn run_one(provider: &CodecProvider, fixture: &RuleFixture) -> Result<(Value, Value), AppError> {
+    let input = &fixture.input;
+    let expected = &fixture.expected;
+    match fixture.step {
+        FixtureStep::DecodeRequest => {
+            let ir = provider.decoder().decode_request(&to_bytes(input)?)?;
+            Ok((normalize::<IrChatRequest>(expected)?, to_value(&ir)?))
+        }
+        FixtureStep::DecodeResponse => {
+            let ir = provider.decoder().decode_response(&to_bytes(input)?)?;
+            Ok((normalize::<IrChatResponse>(expected)?, to_value(&ir)?))
+        }
+        FixtureStep::DecodeStreamChunk => {
+            let data = match input {
+                Value::String(data) => data.clone(),
+                input => input.to_string(),
+            };
+            let event = fixture.event.as_deref().unwrap_or("message");
+            let chunk = provider.decoder().decode_stream_chunk(event, &data)?;
+            Ok((normalize::<Option<IrStreamChunk>>(expected)?, to_value(&chunk)?))
+        }
+        FixtureStep::EncodeRequest => {
+            let ir: IrChatRequest = from_value(input)?;
+            let model = fixture.model.clone().unwrap_or_else(|| ir.model.clone());
+            let body = provider.encoder().encode_request(&ir, &model)?;
+            Ok((expected.clone(), from_slice(&body)?))
+        }
+        FixtureStep::EncodeResponse => {
+            let ir: IrChatResponse = from_value(input)?;
+            let body = provider.encoder().encode_response(&ir)?;
+            Ok((expected.clone(), from_slice(&body)?))
+        }
+        FixtureStep::EncodeStreamChunk => {
+            let chunk: IrStreamChunk = from_value(input)?;
+            let events = provider.encoder().encode_stream_chunk(&chunk)?;
+            let events = events
+                .into_iter()
+                .map(|event| {
+                    let data = serde_json::from_str(&event.data).unwrap_or(Value::String(event.data));
+                    let mut value = serde_json::json!({ "data": data });
+                    if let Some(name) = event.event {
+                        value["event"] = Value::String(name);
+                    }
+                    value
+                })
+                .collect();
+            Ok((expected.clone(), Value::Array(events)))
+        }
+    }
+}
+
+fn normalize<T: Serialize + DeserializeOwned>(expected: &Value) -> Result<Value, AppError> {
+    to_value(&from_value::<T>(expected)?)
+}
+
+fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T, AppError> {
+    T::deserialize(value).map_err(|e| AppError::Codec(format!("Invalid fixture IR: {e}")))
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, AppError> {
+    serde_json::to_value(value).map_err(|e| AppError::Codec(format!("Failed to serialize output: {e}")))
+}
+
+fn to_bytes(value: &Value) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec(value).map_err(|e| AppError::Codec(format!("Failed to serialize input: {e}")))
+}
+
+fn from_slice(body: &[u8]) -> Result<Value, AppError> {
+    serde_json::from_slice(body).map_err(|e| AppError::Codec(format!("Encoded output is not JSON: {e}")))
+}
+
+/// Structural differences between two JSON values. Numbers compare by value,
+/// so `1` and `1.0` are equal.
+pub fn diff(expected: &Value, actual: &Value) -> Vec<FixtureDiff> {
+    let mut diffs = Vec::new();
+    diff_at(&mut String::new(), Some(expected), Some(actual), &mut diffs);
+    diffs
+}
+
+fn diff_at(path: &mut String, expected: Option<&Value>, actual: Option<&Value>, diffs: &mut Vec<FixtureDiff>) {
+    match (expected, actual) {
+        (Some(Value::Object(e)), Some(Value::Object(a))) => {
+            let keys = e.keys().chain(a.keys().filter(|k| !e.contains_key(*k)));
+            for key in keys {
+                with_segment(path, key, |path| diff_at(path, e.get(key), a.get(key), diffs));
+            }
+        }
+        (Some(Value::Array(e)), Some(Value::Array(a))) => {
+            for i in 0..e.len().max(a.len()) {
+                with_segment(path, &i.to_string(), |path| diff_at(path, e.get(i), a.get(i), diffs));
+            }
+        }
+        (Some(Value::Number(e)), Some(Value::Number(a))) if e.as_f64() == a.as_f64() => {}
+        (e, a) if e == a => {}
+        (e, a) => diffs.push(FixtureDiff {
+            path: path.clone(),
+            expected: e.cloned(),
+            actual: a.cloned(),
+        }),
+    }
+}
+
+/// Run `f` with `segment` appended to the pointer, escaped per RFC 6901.
+fn with_segment(path: &mut String, segment: &str, f: impl FnOnce(&mut String)) {
+    let len = path.len();
+    path.push('/');
+    path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    f(path);
+    path.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const FORMATS: [ChatFormat; 5] = [
+        ChatFormat::OpenaiChat,
+        ChatFormat::OpenaiResponses,
+        ChatFormat::Anthropic,
+        ChatFormat::Gemini,
+        ChatFormat::Moonshot,
+    ];
+
+    #[test]
+    fn test_builtin_codecs_pass_their_fixtures() {
+        for format in FORMATS {
+            let fixtures = builtin(format);
+            assert!(!fixtures.is_empty(), "{} has no fixtures", format.as_str());
+            let report = run(&CodecProvider::Builtin(format), &fixtures);
+            if let Some(result) = report.results.iter().find(|r| !r.passed) {
+                panic!("{} / {}: {:?} {:#?}", format.as_str(), result.name, result.error, result.diffs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_diffs_point_at_the_mismatch() {
+        let expected = json!({"a": {"b/c": [1, 2]}, "n": 1, "gone": true});
+        let actual = json!({"a": {"b/c": [1, 3, 4]}, "n": 1.0, "extra": null});
+        let paths: Vec<_> = diff(&expected, &actual).into_iter().map(|d| d.path).collect();
+        assert_eq!(paths, ["/a/b~1c/1", "/a/b~1c/2", "/gone", "/extra"]);
+    }
+}
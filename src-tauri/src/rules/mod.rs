@@ -1,4 +1,5 @@
 pub mod engine;
+pub mod fixtures;
 pub mod registry;
 pub mod repository;
 
@@ -37,6 +38,7 @@ pub async fn seed_system_rules(db: &sqlx::SqlitePool) -> Result<(), sqlx::Error>
         ("anthropic", "Anthropic Messages", "Built-in Anthropic Messages codec"),
         ("gemini", "Gemini", "Built-in Google Gemini codec"),
         ("moonshot", "Moonshot (Kimi)", "Built-in Moonshot codec"),
+        ("ollama", "Ollama", "Built-in Ollama codec (OpenAI-compatible chat, native embeddings)"),
     ];
 
     let now = chrono::Utc::now().to_rfc3339();
@@ -1,8 +1,11 @@
 use crate::db::models::ConversionRule;
 use crate::error::AppError;
-use crate::modality::chat::ir::{IrChatRequest, IrChatResponse, IrStreamChunk};
-use crate::modality::chat::{ChatFormat, Decoder, Encoder};
+use crate::modality::chat::ir::{IrChatRequest, IrChatResponse, IrError, IrStreamChunk};
+use crate::modality::chat::{self, ChatFormat, Decoder, Encoder};
 use crate::rules::engine;
+use crate::sse::SseEvent;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -13,9 +16,37 @@ use tokio::sync::RwLock;
 pub enum CodecProvider {
     Builtin(ChatFormat),
     Jsonata(Arc<ConversionRule>),
+    /// A built-in format whose output is post-processed by the JSONata
+    /// expressions of its system rule.
+    Overlay(ChatFormat, Arc<ConversionRule>),
 }
 
-/// Concurrent registry of slug → CodecProvider mappings.
+impl CodecProvider {
+    pub fn decoder(&self) -> Box<dyn Decoder> {
+        match self {
+            CodecProvider::Builtin(format) => chat::get_decoder(*format),
+            CodecProvider::Jsonata(rule) => Box::new(JsonataDecoder { rule: rule.clone() }),
+            CodecProvider::Overlay(format, rule) => Box::new(OverlayDecoder {
+                inner: chat::get_decoder(*format),
+                rule: rule.clone(),
+            }),
+        }
+    }
+
+    pub fn encoder(&self) -> Box<dyn Encoder> {
+        match self {
+            CodecProvider::Builtin(format) => chat::get_encoder(*format),
+            CodecProvider::Jsonata(rule) => Box::new(JsonataEncoder { rule: rule.clone() }),
+            CodecProvider::Overlay(format, rule) => Box::new(OverlayEncoder {
+                inner: chat::get_encoder(*format),
+                rule: rule.clone(),
+            }),
+        }
+    }
+}
+
+/// Concurrent registry of slug → CodecProvider mappings. It is shared by the
+/// proxy and the rule commands, which keep it in sync with the database.
 pub struct RuleRegistry {
     entries: RwLock<HashMap<String, CodecProvider>>,
 }
@@ -23,34 +54,21 @@ pub struct RuleRegistry {
 impl RuleRegistry {
     /// Create a new registry pre-populated with built-in codecs.
     pub fn new() -> Self {
-        let mut map = HashMap::new();
-        map.insert("openai-chat".to_string(), CodecProvider::Builtin(ChatFormat::OpenaiChat));
-        map.insert("openai".to_string(), CodecProvider::Builtin(ChatFormat::OpenaiChat));
-        map.insert(
-            "openai-responses".to_string(),
-            CodecProvider::Builtin(ChatFormat::OpenaiResponses),
-        );
-        map.insert("anthropic".to_string(), CodecProvider::Builtin(ChatFormat::Anthropic));
-        map.insert("gemini".to_string(), CodecProvider::Builtin(ChatFormat::Gemini));
-        map.insert("moonshot".to_string(), CodecProvider::Builtin(ChatFormat::Moonshot));
         Self {
-            entries: RwLock::new(map),
+            entries: RwLock::new(builtin_entries()),
         }
     }
 
-    /// Load all enabled user rules from the conversion_rules table.
+    /// Load all enabled rules from the conversion_rules table. `system`
+    /// rows are handles onto the built-in codecs and only replace them when
+    /// they carry an overlay.
     pub async fn load_from_db(&self, db: &SqlitePool) {
-        let rows = sqlx::query_as::<_, ConversionRule>(
-            "SELECT * FROM conversion_rules WHERE enabled = true",
-        )
-        .fetch_all(db)
-        .await;
-
-        if let Ok(rules) = rows {
+        if let Ok(rules) = fetch_enabled_rules(db).await {
             let mut entries = self.entries.write().await;
             for rule in rules {
-                let slug = rule.slug.clone();
-                entries.insert(slug, CodecProvider::Jsonata(Arc::new(rule)));
+                if let Some((slug, provider)) = provider_for(rule) {
+                    entries.insert(slug, provider);
+                }
             }
         }
     }
@@ -61,28 +79,219 @@ impl RuleRegistry {
         entries.get(slug).cloned()
     }
 
-    /// Register a single user rule into the registry.
+    /// Register a single rule into the registry, dropping the programs of
+    /// the rule it replaces.
     pub async fn register_rule(&self, rule: ConversionRule) {
-        let slug = rule.slug.clone();
-        let mut entries = self.entries.write().await;
-        entries.insert(slug, CodecProvider::Jsonata(Arc::new(rule)));
+        engine::forget_rule(&rule.id);
+        if let Some((slug, provider)) = provider_for(rule) {
+            if let Some(previous) = self.entries.write().await.insert(slug, provider) {
+                forget_programs(&previous);
+            }
+        }
     }
 
-    /// Remove a rule by slug, but only if it is a Jsonata entry (not Builtin).
+    /// Remove a rule by slug, but only if it is a rule entry (not Builtin).
+    /// A built-in codec the rule was shadowing becomes active again.
     pub async fn remove_rule(&self, slug: &str) {
         let mut entries = self.entries.write().await;
-        if let Some(CodecProvider::Jsonata(_)) = entries.get(slug) {
-            entries.remove(slug);
+        if let Some(provider @ (CodecProvider::Jsonata(_) | CodecProvider::Overlay(..))) = entries.get(slug) {
+            forget_programs(provider);
+            match builtin_entries().remove(slug) {
+                Some(builtin) => entries.insert(slug.to_string(), builtin),
+                None => entries.remove(slug),
+            };
         }
     }
 
-    /// Clear all Jsonata entries and reload from the database.
-    pub async fn reload_from_db(&self, db: &SqlitePool) {
-        {
-            let mut entries = self.entries.write().await;
-            entries.retain(|_, v| matches!(v, CodecProvider::Builtin(_)));
+    /// Rebuild the registry from the built-in codecs and the database. The
+    /// new entries are swapped in at once, so lookups never miss a rule
+    /// halfway through a reload.
+    pub async fn reload_from_db(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let mut map = builtin_entries();
+        for rule in fetch_enabled_rules(db).await? {
+            if let Some((slug, provider)) = provider_for(rule) {
+                map.insert(slug, provider);
+            }
+        }
+        *self.entries.write().await = map;
+        engine::forget_all_rules();
+        Ok(())
+    }
+}
+
+/// Drop the cached programs of a rule that leaves the registry.
+fn forget_programs(provider: &CodecProvider) {
+    if let CodecProvider::Jsonata(rule) | CodecProvider::Overlay(_, rule) = provider {
+        engine::forget_rule(&rule.id);
+    }
+}
+
+async fn fetch_enabled_rules(db: &SqlitePool) -> Result<Vec<ConversionRule>, sqlx::Error> {
+    sqlx::query_as::<_, ConversionRule>("SELECT * FROM conversion_rules WHERE enabled = true")
+        .fetch_all(db)
+        .await
+}
+
+/// The registry entry for a rule. A system rule without overlay expressions
+/// maps back to its plain built-in codec; one for an unknown slug is ignored.
+pub fn provider_for(rule: ConversionRule) -> Option<(String, CodecProvider)> {
+    let slug = rule.slug.clone();
+    if rule.rule_type != "system" {
+        return Some((slug, CodecProvider::Jsonata(Arc::new(rule))));
+    }
+    let builtin = builtin_entries().remove(&slug)?;
+    match builtin {
+        CodecProvider::Builtin(format) if has_overlay(&rule) => {
+            Some((slug, CodecProvider::Overlay(format, Arc::new(rule))))
         }
-        self.load_from_db(db).await;
+        builtin => Some((slug, builtin)),
+    }
+}
+
+fn has_overlay(rule: &ConversionRule) -> bool {
+    [
+        Some(&rule.decode_request),
+        Some(&rule.encode_request),
+        Some(&rule.decode_response),
+        Some(&rule.encode_response),
+        rule.decode_stream_chunk.as_ref(),
+        rule.encode_stream_chunk.as_ref(),
+    ]
+    .into_iter()
+    .any(|expression| overlay_expression(expression).is_some())
+}
+
+/// A blank overlay expression leaves that stage of the built-in codec alone.
+fn overlay_expression(expression: Option<&String>) -> Option<&str> {
+    expression.map(|e| e.trim()).filter(|e| !e.is_empty())
+}
+
+/// Run an overlay expression over a value, or return it unchanged when the
+/// expression is blank.
+fn apply_overlay<T: Serialize + DeserializeOwned>(
+    rule: &ConversionRule,
+    expression: Option<&String>,
+    value: T,
+    what: &str,
+) -> Result<T, AppError> {
+    let Some(expression) = overlay_expression(expression) else {
+        return Ok(value);
+    };
+    let input = serde_json::to_value(&value)
+        .map_err(|e| AppError::Codec(format!("Failed to serialize {what}: {e}")))?;
+    let result = engine::evaluate_rule(rule, expression, &input)?;
+    serde_json::from_value(result)
+        .map_err(|e| AppError::Codec(format!("Failed to deserialize overlaid {what}: {e}")))
+}
+
+/// Run an overlay expression over an encoded JSON body.
+fn apply_overlay_bytes(
+    rule: &ConversionRule,
+    expression: Option<&String>,
+    body: Vec<u8>,
+    what: &str,
+) -> Result<Vec<u8>, AppError> {
+    if overlay_expression(expression).is_none() {
+        return Ok(body);
+    }
+    let value: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| AppError::Codec(format!("Invalid JSON in {what}: {e}")))?;
+    let value = apply_overlay(rule, expression, value, what)?;
+    serde_json::to_vec(&value).map_err(|e| AppError::Codec(format!("Failed to serialize {what}: {e}")))
+}
+
+fn builtin_entries() -> HashMap<String, CodecProvider> {
+    let mut map = HashMap::new();
+    map.insert("openai-chat".to_string(), CodecProvider::Builtin(ChatFormat::OpenaiChat));
+    map.insert("openai".to_string(), CodecProvider::Builtin(ChatFormat::OpenaiChat));
+    map.insert("ollama".to_string(), CodecProvider::Builtin(ChatFormat::OpenaiChat));
+    map.insert(
+        "openai-responses".to_string(),
+        CodecProvider::Builtin(ChatFormat::OpenaiResponses),
+    );
+    map.insert("anthropic".to_string(), CodecProvider::Builtin(ChatFormat::Anthropic));
+    map.insert("gemini".to_string(), CodecProvider::Builtin(ChatFormat::Gemini));
+    map.insert("moonshot".to_string(), CodecProvider::Builtin(ChatFormat::Moonshot));
+    map
+}
+
+/// A built-in decoder whose IR output is post-processed by a system rule's
+/// overlay expressions.
+pub struct OverlayDecoder {
+    pub inner: Box<dyn Decoder>,
+    pub rule: Arc<ConversionRule>,
+}
+
+impl Decoder for OverlayDecoder {
+    fn decode_request(&self, body: &[u8]) -> Result<IrChatRequest, AppError> {
+        let ir = self.inner.decode_request(body)?;
+        apply_overlay(&self.rule, Some(&self.rule.decode_request), ir, "IrChatRequest")
+    }
+
+    fn decode_response(&self, body: &[u8]) -> Result<IrChatResponse, AppError> {
+        let ir = self.inner.decode_response(body)?;
+        apply_overlay(&self.rule, Some(&self.rule.decode_response), ir, "IrChatResponse")
+    }
+
+    fn decode_stream_chunk(&self, event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+        self.inner
+            .decode_stream_chunk(event, data)?
+            .map(|chunk| apply_overlay(&self.rule, self.rule.decode_stream_chunk.as_ref(), chunk, "IrStreamChunk"))
+            .transpose()
+    }
+
+    fn is_stream_done(&self, data: &str) -> bool {
+        self.inner.is_stream_done(data)
+    }
+}
+
+/// A built-in encoder whose wire output is post-processed by a system rule's
+/// overlay expressions. Stream events whose data is not JSON (such as
+/// `[DONE]`) pass through untouched.
+pub struct OverlayEncoder {
+    pub inner: Box<dyn Encoder>,
+    pub rule: Arc<ConversionRule>,
+}
+
+impl Encoder for OverlayEncoder {
+    fn encode_request(&self, ir: &IrChatRequest, model: &str) -> Result<Vec<u8>, AppError> {
+        let body = self.inner.encode_request(ir, model)?;
+        apply_overlay_bytes(&self.rule, Some(&self.rule.encode_request), body, "encoded request")
+    }
+
+    fn accepts_image_urls(&self) -> bool {
+        self.inner.accepts_image_urls()
+    }
+
+    fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
+        let body = self.inner.encode_response(ir)?;
+        apply_overlay_bytes(&self.rule, Some(&self.rule.encode_response), body, "encoded response")
+    }
+
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
+        let expression = self.rule.encode_stream_chunk.as_ref();
+        self.inner
+            .encode_stream_chunk(chunk)?
+            .into_iter()
+            .map(|mut event| {
+                if let Ok(value) = serde_json::from_str::<serde_json::Value>(&event.data) {
+                    event.data = apply_overlay(&self.rule, expression, value, "encoded stream chunk")?.to_string();
+                }
+                Ok(event)
+            })
+            .collect()
+    }
+
+    fn stream_done_signal(&self) -> Option<SseEvent> {
+        self.inner.stream_done_signal()
+    }
+
+    fn encode_error(&self, error: &IrError) -> (u16, serde_json::Value) {
+        self.inner.encode_error(error)
+    }
+
+    fn encode_stream_error(&self, error: &IrError) -> SseEvent {
+        self.inner.encode_stream_error(error)
     }
 }
 
@@ -94,32 +303,26 @@ pub struct JsonataDecoder {
 
 impl Decoder for JsonataDecoder {
     fn decode_request(&self, body: &[u8]) -> Result<IrChatRequest, AppError> {
-        let input: serde_json::Value =
-            serde_json::from_slice(body).map_err(|e| AppError::Codec(format!("Invalid JSON: {e}")))?;
-        let result = engine::evaluate(&self.rule.decode_request, &input)?;
+        let result = engine::evaluate_rule_json(&self.rule, &self.rule.decode_request, body)?;
         let ir: IrChatRequest = serde_json::from_value(result)
             .map_err(|e| AppError::Codec(format!("Failed to deserialize IrChatRequest: {e}")))?;
         Ok(ir)
     }
 
     fn decode_response(&self, body: &[u8]) -> Result<IrChatResponse, AppError> {
-        let input: serde_json::Value =
-            serde_json::from_slice(body).map_err(|e| AppError::Codec(format!("Invalid JSON: {e}")))?;
-        let result = engine::evaluate(&self.rule.decode_response, &input)?;
+        let result = engine::evaluate_rule_json(&self.rule, &self.rule.decode_response, body)?;
         let ir: IrChatResponse = serde_json::from_value(result)
             .map_err(|e| AppError::Codec(format!("Failed to deserialize IrChatResponse: {e}")))?;
         Ok(ir)
     }
 
-    fn decode_stream_chunk(&self, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
+    fn decode_stream_chunk(&self, _event: &str, data: &str) -> Result<Option<IrStreamChunk>, AppError> {
         let expression = self
             .rule
             .decode_stream_chunk
             .as_deref()
             .unwrap_or(&self.rule.decode_response);
-        let input: serde_json::Value = serde_json::from_str(data)
-            .map_err(|e| AppError::Codec(format!("Invalid JSON in stream chunk: {e}")))?;
-        let result = engine::evaluate(expression, &input)?;
+        let result = engine::evaluate_rule_json(&self.rule, expression, data.as_bytes())?;
         let chunk: IrStreamChunk = serde_json::from_value(result)
             .map_err(|e| AppError::Codec(format!("Failed to deserialize IrStreamChunk: {e}")))?;
         Ok(Some(chunk))
@@ -144,7 +347,7 @@ impl Encoder for JsonataEncoder {
         if let serde_json::Value::Object(ref mut map) = input {
             map.insert("model".to_string(), serde_json::Value::String(model.to_string()));
         }
-        let result = engine::evaluate(&self.rule.encode_request, &input)?;
+        let result = engine::evaluate_rule(&self.rule, &self.rule.encode_request, &input)?;
         let bytes = serde_json::to_vec(&result)
             .map_err(|e| AppError::Codec(format!("Failed to serialize encoded request: {e}")))?;
         Ok(bytes)
@@ -153,13 +356,13 @@ impl Encoder for JsonataEncoder {
     fn encode_response(&self, ir: &IrChatResponse) -> Result<Vec<u8>, AppError> {
         let input = serde_json::to_value(ir)
             .map_err(|e| AppError::Codec(format!("Failed to serialize IrChatResponse: {e}")))?;
-        let result = engine::evaluate(&self.rule.encode_response, &input)?;
+        let result = engine::evaluate_rule(&self.rule, &self.rule.encode_response, &input)?;
         let bytes = serde_json::to_vec(&result)
             .map_err(|e| AppError::Codec(format!("Failed to serialize encoded response: {e}")))?;
         Ok(bytes)
     }
 
-    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Option<String>, AppError> {
+    fn encode_stream_chunk(&self, chunk: &IrStreamChunk) -> Result<Vec<SseEvent>, AppError> {
         let expression = self
             .rule
             .encode_stream_chunk
@@ -167,13 +370,65 @@ impl Encoder for JsonataEncoder {
             .unwrap_or(&self.rule.encode_response);
         let input = serde_json::to_value(chunk)
             .map_err(|e| AppError::Codec(format!("Failed to serialize IrStreamChunk: {e}")))?;
-        let result = engine::evaluate(expression, &input)?;
+        let result = engine::evaluate_rule(&self.rule, expression, &input)?;
         let s = serde_json::to_string(&result)
             .map_err(|e| AppError::Codec(format!("Failed to serialize encoded stream chunk: {e}")))?;
-        Ok(Some(s))
+        Ok(vec![SseEvent::data(s)])
+    }
+
+    fn stream_done_signal(&self) -> Option<SseEvent> {
+        Some(SseEvent::data("[DONE]"))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system_rule(slug: &str, encode_request: &str) -> ConversionRule {
+        ConversionRule {
+            id: "1".into(),
+            slug: slug.into(),
+            name: slug.into(),
+            description: None,
+            author: None,
+            version: "1.0.0".into(),
+            tags: None,
+            rule_type: "system".into(),
+            modality: "chat".into(),
+            decode_request: String::new(),
+            encode_request: encode_request.into(),
+            decode_response: String::new(),
+            encode_response: String::new(),
+            decode_stream_chunk: None,
+            encode_stream_chunk: Some(" ".into()),
+            http_config: None,
+            test_fixtures: None,
+            enabled: true,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_system_rules_map_onto_builtins() {
+        let plain = provider_for(system_rule("anthropic", ""));
+        assert!(matches!(plain, Some((_, CodecProvider::Builtin(ChatFormat::Anthropic)))));
+        assert!(provider_for(system_rule("not-a-builtin", "$")).is_none());
 
-    fn stream_done_signal(&self) -> Option<String> {
-        Some("[DONE]".to_string())
+        let Some((_, CodecProvider::Overlay(format, rule))) =
+            provider_for(system_rule("openai-chat", r#"$merge([$, {"user": "omnikit"}])"#))
+        else {
+            panic!("expected an overlay");
+        };
+        let ir = chat::get_decoder(format)
+            .decode_request(br#"{"model":"m","messages":[{"role":"user","content":"hi"}]}"#)
+            .unwrap();
+        let encoder = OverlayEncoder { inner: chat::get_encoder(format), rule };
+        let body: serde_json::Value =
+            serde_json::from_slice(&encoder.encode_request(&ir, "gpt").unwrap()).unwrap();
+        assert_eq!(body["user"], "omnikit");
+        assert_eq!(body["model"], "gpt");
+        assert_eq!(body["messages"][0]["content"], "hi");
     }
 }
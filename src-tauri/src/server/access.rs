@@ -0,0 +1,204 @@
+use crate::config::AppConfig;
+use crate::db::models::Token;
+use crate::error::AppError;
+use crate::modality::chat::ir::IrChatRequest;
+use crate::tokenizer;
+use sqlx::SqlitePool;
+
+/// Check the requested model against the token's `allowed_models` list.
+///
+/// `allowed_models` is a comma-separated list of model names or glob patterns
+/// (`*` matches any sequence, `?` a single character). An empty or missing list
+/// allows every model.
+pub fn check_model_allowed(token: &Token, model: &str) -> Result<(), AppError> {
+    let Some(allowed) = token.allowed_models.as_deref() else {
+        return Ok(());
+    };
+
+    let mut patterns = allowed
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .peekable();
+
+    if patterns.peek().is_none() {
+        return Ok(());
+    }
+
+    if patterns.any(|p| glob_match(p, model)) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!(
+            "Model '{}' is not allowed for this API key",
+            model
+        )))
+    }
+}
+
+/// Match `text` against a glob pattern supporting `*` and `?`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Position of the last `*` in the pattern and the text index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = backtrack {
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            backtrack = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Quota held for an in-flight request.
+///
+/// The estimated cost is added to `quota_used` up front so that concurrent
+/// requests cannot overshoot `quota_limit`; it is replaced by the actual usage
+/// via [`QuotaReservation::settle`] or returned via [`QuotaReservation::release`].
+#[derive(Clone)]
+pub struct QuotaReservation {
+    token_id: String,
+    reserved: i64,
+}
+
+impl QuotaReservation {
+    /// Replace the reserved amount with the actual token usage.
+    pub async fn settle(&self, db: &SqlitePool, actual: i64) {
+        let result = sqlx::query(
+            "UPDATE tokens SET quota_used = MAX(quota_used - ? + ?, 0) WHERE id = ?",
+        )
+        .bind(self.reserved)
+        .bind(actual)
+        .bind(&self.token_id)
+        .execute(db)
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Failed to settle token quota: {}", e);
+        }
+    }
+
+    /// Return the reserved amount (the request produced no billable usage).
+    pub async fn release(&self, db: &SqlitePool) {
+        self.settle(db, 0).await;
+    }
+}
+
+/// Atomically reserve `amount` tokens of the token's quota.
+///
+/// The check and the increment happen in a single UPDATE, so the limit holds
+/// under concurrent requests. Tokens without a `quota_limit` always succeed.
+pub async fn reserve_quota(
+    db: &SqlitePool,
+    token: &Token,
+    amount: i64,
+) -> Result<QuotaReservation, AppError> {
+    let amount = amount.max(1);
+    let result = sqlx::query(
+        "UPDATE tokens SET quota_used = quota_used + ?1
+         WHERE id = ?2 AND (quota_limit IS NULL OR quota_used + ?1 <= quota_limit)",
+    )
+    .bind(amount)
+    .bind(&token.id)
+    .execute(db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        // The token passed in may be stale under concurrent requests
+        let (used, limit): (i64, Option<i64>) =
+            sqlx::query_as("SELECT quota_used, quota_limit FROM tokens WHERE id = ?")
+                .bind(&token.id)
+                .fetch_optional(db)
+                .await?
+                .unwrap_or((token.quota_used, token.quota_limit));
+        return Err(AppError::QuotaExceeded(format!(
+            "Token quota exceeded: requested {}, {} of {} used",
+            amount,
+            used,
+            limit.unwrap_or_default()
+        )));
+    }
+
+    Ok(QuotaReservation {
+        token_id: token.id.clone(),
+        reserved: amount,
+    })
+}
+
+/// Quota price of prompt tokens served from or written to a provider's prompt
+/// cache, relative to a regular token.
+#[derive(Debug, Clone, Copy)]
+pub struct CachePricing {
+    pub read_ratio: f64,
+    pub write_ratio: f64,
+}
+
+impl CachePricing {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            read_ratio: config.cache_read_price_ratio.max(0.0),
+            write_ratio: config.cache_write_price_ratio.max(0.0),
+        }
+    }
+
+    /// Quota charged for a request's usage. `prompt_tokens` includes the
+    /// cached tokens, which are priced at their own ratio.
+    pub fn charge(
+        &self,
+        prompt_tokens: i64,
+        completion_tokens: i64,
+        cache_read_tokens: Option<i64>,
+        cache_write_tokens: Option<i64>,
+    ) -> i64 {
+        let read = cache_read_tokens.unwrap_or(0);
+        let write = cache_write_tokens.unwrap_or(0);
+        let uncached = (prompt_tokens - read - write).max(0);
+        uncached
+            + completion_tokens
+            + (read as f64 * self.read_ratio).ceil() as i64
+            + (write as f64 * self.write_ratio).ceil() as i64
+    }
+}
+
+/// Upper-bound cost of a request: locally counted prompt tokens plus the
+/// requested completion budget.
+pub fn estimate_request_cost(ir: &IrChatRequest) -> i64 {
+    tokenizer::count_request_tokens(ir) as i64 + ir.max_tokens.map_or(0, i64::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gpt-4o", "gpt-4o"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(glob_match("gpt-4*", "gpt-4o-mini"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("claude-*-sonnet*", "claude-3-5-sonnet-latest"));
+        assert!(glob_match("gemini-?.5-pro", "gemini-2.5-pro"));
+        assert!(!glob_match("gemini-?.5-pro", "gemini-2.0-pro"));
+        assert!(!glob_match("gpt-*", "o1-mini"));
+    }
+
+    #[test]
+    fn test_cache_pricing() {
+        let pricing = CachePricing::from_config(&AppConfig::default());
+        assert_eq!(pricing.charge(100, 10, None, None), 110);
+        // 20 uncached + 60 read at 0.1 + 20 written at 1.25
+        assert_eq!(pricing.charge(100, 10, Some(60), Some(20)), 20 + 10 + 6 + 25);
+    }
+}
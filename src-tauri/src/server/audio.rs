@@ -0,0 +1,364 @@
+use crate::error::AppError;
+use crate::modality::audio::gemini::GeminiAudioCodec;
+use crate::modality::audio::ir::{
+    IrSpeechRequest, IrTranscriptionRequest, IrTranscriptionResponse, IrTranscriptionTask,
+};
+use crate::modality::audio::openai::OpenAiAudioCodec;
+use crate::modality::audio::AudioFormat;
+use crate::modality::chat::{self, ChatFormat};
+use crate::server::middleware::{self, ApiKey};
+use crate::server::modality_proxy::{self, ModalityCodec, ModalityOutput};
+use crate::server::proxy::{ProxyState, UpstreamTarget};
+use crate::tokenizer;
+use axum::body::Body;
+use axum::extract::{Multipart, State};
+use axum::response::Response;
+use bytes::Bytes;
+
+/// Input format slugs of the `/v1/audio/*` endpoints, as recorded in request
+/// logs. Transcriptions are logged without their uploads and cannot be replayed.
+const TRANSCRIPTION_FORMAT: &str = "openai-transcriptions";
+const TRANSLATION_FORMAT: &str = "openai-translations";
+const SPEECH_FORMAT: &str = "openai-speech";
+
+/// Proxy handler for OpenAI-compatible `/v1/audio/transcriptions` requests.
+pub async fn proxy_transcription(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    proxy_transcription_task(state, api_key, multipart, IrTranscriptionTask::Transcribe).await
+}
+
+/// Proxy handler for OpenAI-compatible `/v1/audio/translations` requests.
+pub async fn proxy_translation(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    proxy_transcription_task(state, api_key, multipart, IrTranscriptionTask::Translate).await
+}
+
+async fn proxy_transcription_task(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    multipart: Multipart,
+    task: IrTranscriptionTask,
+) -> Result<Response, AppError> {
+    let input_format = match task {
+        IrTranscriptionTask::Transcribe => TRANSCRIPTION_FORMAT,
+        IrTranscriptionTask::Translate => TRANSLATION_FORMAT,
+    };
+    let request = middleware::read_multipart(multipart).await.and_then(|fields| {
+        let ir = OpenAiAudioCodec.decode_transcription_request(fields, task)?;
+        // The audio itself is not serialized
+        let logged = serde_json::to_string(&ir)?;
+        Ok((ir, logged))
+    });
+    modality_proxy::proxy_modality(state, api_key, Transcriptions, request, input_format).await
+}
+
+struct Transcriptions;
+
+/// A decoded transcript. OpenAI-compatible responses are already in the
+/// requested format and keep their content type and body.
+struct Transcript {
+    ir: IrTranscriptionResponse,
+    passthrough: Option<(String, Bytes)>,
+}
+
+#[async_trait::async_trait]
+impl ModalityCodec for Transcriptions {
+    type Request = IrTranscriptionRequest;
+    type Response = Transcript;
+
+    fn modality(&self) -> &'static str {
+        "asr"
+    }
+
+    fn model<'r>(&self, ir: &'r IrTranscriptionRequest) -> &'r str {
+        &ir.model
+    }
+
+    fn estimate_cost(&self, ir: &IrTranscriptionRequest) -> i64 {
+        let prompt = ir.prompt.as_deref().unwrap_or_default();
+        (tokenizer::TOKENS_PER_AUDIO + tokenizer::count_text_tokens(&ir.model, prompt)) as i64
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        ir: &IrTranscriptionRequest,
+        target: &UpstreamTarget,
+    ) -> Result<(String, reqwest::RequestBuilder), AppError> {
+        let base = target.base_url.trim_end_matches('/');
+        match AudioFormat::from_provider(&target.provider) {
+            AudioFormat::OpenAi => {
+                let form = OpenAiAudioCodec.encode_transcription_request(ir, &target.model)?;
+                let endpoint = match ir.task {
+                    IrTranscriptionTask::Transcribe => "transcriptions",
+                    IrTranscriptionTask::Translate => "translations",
+                };
+                let url = format!("{}/v1/audio/{}", base, endpoint);
+                let builder = client
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", target.api_key))
+                    .multipart(form);
+                Ok((url, builder))
+            }
+            AudioFormat::Gemini => {
+                let chat_ir = GeminiAudioCodec.transcription_to_chat(ir)?;
+                let body = chat::get_encoder(ChatFormat::Gemini).encode_request(&chat_ir, &target.model)?;
+                let url = format!("{}/v1beta/models/{}:generateContent", base, target.model);
+                let builder = client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .header("x-goog-api-key", &target.api_key)
+                    .body(body);
+                Ok((url, builder))
+            }
+        }
+    }
+
+    async fn decode_response(
+        &self,
+        _: &IrTranscriptionRequest,
+        provider: &str,
+        upstream: reqwest::Response,
+    ) -> Result<Transcript, AppError> {
+        let upstream_type = upstream
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/json")
+            .to_string();
+        let resp_bytes = upstream.bytes().await?;
+        match AudioFormat::from_provider(provider) {
+            AudioFormat::OpenAi => Ok(Transcript {
+                ir: OpenAiAudioCodec.decode_transcription_response(&resp_bytes)?,
+                passthrough: Some((upstream_type, resp_bytes)),
+            }),
+            AudioFormat::Gemini => {
+                let chat_response = chat::get_decoder(ChatFormat::Gemini).decode_response(&resp_bytes)?;
+                Ok(Transcript {
+                    ir: GeminiAudioCodec.transcription_from_chat(chat_response),
+                    passthrough: None,
+                })
+            }
+        }
+    }
+
+    fn usage(&self, _: &IrTranscriptionRequest, transcript: &Transcript) -> Option<(i64, i64)> {
+        transcript
+            .ir
+            .usage
+            .as_ref()
+            .map(|usage| (usage.input_tokens as i64, usage.output_tokens as i64))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrTranscriptionRequest,
+        transcript: Transcript,
+    ) -> Result<ModalityOutput, AppError> {
+        let (content_type, bytes) = match transcript.passthrough {
+            Some((content_type, bytes)) => (content_type, bytes.to_vec()),
+            None => {
+                let (content_type, bytes) = OpenAiAudioCodec
+                    .encode_transcription_response(&transcript.ir, ir.response_format.as_deref())?;
+                (content_type.to_string(), bytes)
+            }
+        };
+        Ok(ModalityOutput {
+            content_type,
+            logged: String::from_utf8_lossy(&bytes).to_string(),
+            body: Body::from(bytes),
+        })
+    }
+}
+
+/// Proxy handler for OpenAI-compatible `/v1/audio/speech` requests. The
+/// generated audio is streamed back as it arrives.
+pub async fn proxy_speech(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let request = OpenAiAudioCodec
+        .decode_speech_request(&body)
+        .map(|ir| (ir, String::from_utf8_lossy(&body).to_string()));
+    modality_proxy::proxy_modality(state, api_key, Speech, request, SPEECH_FORMAT).await
+}
+
+struct Speech;
+
+#[async_trait::async_trait]
+impl ModalityCodec for Speech {
+    type Request = IrSpeechRequest;
+    /// The upstream response, relayed as it streams in.
+    type Response = reqwest::Response;
+
+    fn modality(&self) -> &'static str {
+        "tts"
+    }
+
+    fn model<'r>(&self, ir: &'r IrSpeechRequest) -> &'r str {
+        &ir.model
+    }
+
+    fn estimate_cost(&self, ir: &IrSpeechRequest) -> i64 {
+        tokenizer::count_text_tokens(&ir.model, &ir.input) as i64
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        ir: &IrSpeechRequest,
+        target: &UpstreamTarget,
+    ) -> Result<(String, reqwest::RequestBuilder), AppError> {
+        if AudioFormat::from_provider(&target.provider) != AudioFormat::OpenAi {
+            return Err(AppError::Codec(format!(
+                "Speech is not supported for provider '{}'",
+                target.provider
+            )));
+        }
+        let body = OpenAiAudioCodec.encode_speech_request(ir, &target.model)?;
+        let url = format!("{}/v1/audio/speech", target.base_url.trim_end_matches('/'));
+        let builder = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", target.api_key))
+            .body(body);
+        Ok((url, builder))
+    }
+
+    async fn decode_response(
+        &self,
+        _: &IrSpeechRequest,
+        _: &str,
+        upstream: reqwest::Response,
+    ) -> Result<reqwest::Response, AppError> {
+        Ok(upstream)
+    }
+
+    /// Speech APIs report no usage, so the input token count is what gets
+    /// charged.
+    fn usage(&self, ir: &IrSpeechRequest, _: &reqwest::Response) -> Option<(i64, i64)> {
+        Some((self.estimate_cost(ir), 0))
+    }
+
+    fn encode_response(
+        &self,
+        _: &IrSpeechRequest,
+        upstream: reqwest::Response,
+    ) -> Result<ModalityOutput, AppError> {
+        let content_type = upstream
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("audio/mpeg")
+            .to_string();
+        // The audio itself is not stored
+        let logged = serde_json::json!({ "content_type": content_type }).to_string();
+        Ok(ModalityOutput {
+            content_type,
+            body: Body::from_stream(upstream.bytes_stream()),
+            logged,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modality::audio::ir::IrAudioData;
+
+    fn request(response_format: Option<&str>) -> IrTranscriptionRequest {
+        IrTranscriptionRequest {
+            model: "asr".into(),
+            task: IrTranscriptionTask::Transcribe,
+            audio: IrAudioData { filename: None, mime_type: "audio/wav".into(), data: b"RIFF".to_vec() },
+            language: None,
+            prompt: None,
+            response_format: response_format.map(Into::into),
+            temperature: None,
+        }
+    }
+
+    fn upstream(content_type: &str, body: &str) -> reqwest::Response {
+        axum::http::Response::builder()
+            .header("content-type", content_type)
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    async fn relay(ir: &IrTranscriptionRequest, provider: &str, upstream: reqwest::Response) -> (String, String) {
+        let transcript = Transcriptions.decode_response(ir, provider, upstream).await.unwrap();
+        let output = Transcriptions.encode_response(ir, transcript).unwrap();
+        let body = axum::body::to_bytes(output.body, usize::MAX).await.unwrap();
+        assert_eq!(output.logged.as_bytes(), &body[..]);
+        (output.content_type, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_openai_transcripts_pass_through_unchanged() {
+        let srt = "1\n00:00:00,000 --> 00:00:01,000\nHi\n";
+        let ir = request(Some("srt"));
+        let (content_type, body) = relay(&ir, "openai", upstream("application/x-subrip", srt)).await;
+        assert_eq!((content_type.as_str(), body.as_str()), ("application/x-subrip", srt));
+
+        let json = r#"{"text":"Hi","usage":{"type":"tokens","input_tokens":7,"output_tokens":2}}"#;
+        let transcript = Transcriptions.decode_response(&ir, "openai", upstream("application/json", json)).await.unwrap();
+        assert_eq!(Transcriptions.usage(&ir, &transcript), Some((7, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_gemini_transcripts_are_encoded_in_the_requested_format() {
+        let gemini = r#"{
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "Hallo Welt\n"}]}, "finishReason": "STOP"}],
+            "usageMetadata": {"promptTokenCount": 40, "candidatesTokenCount": 4}
+        }"#;
+
+        let ir = request(Some("text"));
+        let (content_type, body) = relay(&ir, "gemini", upstream("application/json", gemini)).await;
+        assert_eq!((content_type.as_str(), body.as_str()), ("text/plain; charset=utf-8", "Hallo Welt"));
+
+        let ir = request(None);
+        let transcript = Transcriptions.decode_response(&ir, "gemini", upstream("application/json", gemini)).await.unwrap();
+        assert_eq!(Transcriptions.usage(&ir, &transcript), Some((40, 4)));
+        let (content_type, body) = relay(&ir, "gemini", upstream("application/json", gemini)).await;
+        assert_eq!(content_type, "application/json");
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["text"], "Hallo Welt");
+    }
+
+    #[test]
+    fn test_transcriptions_are_sent_to_the_provider_endpoint() {
+        let client = reqwest::Client::new();
+        let target = |provider: &str| UpstreamTarget {
+            provider: provider.into(),
+            base_url: "http://upstream/".into(),
+            model: "m".into(),
+            api_key: "key".into(),
+        };
+
+        let mut ir = request(None);
+        let (url, _) = Transcriptions.build_request(&client, &ir, &target("openai")).unwrap();
+        assert_eq!(url, "http://upstream/v1/audio/transcriptions");
+        ir.task = IrTranscriptionTask::Translate;
+        let (url, _) = Transcriptions.build_request(&client, &ir, &target("openai")).unwrap();
+        assert_eq!(url, "http://upstream/v1/audio/translations");
+        let (url, _) = Transcriptions.build_request(&client, &ir, &target("gemini")).unwrap();
+        assert_eq!(url, "http://upstream/v1beta/models/m:generateContent");
+
+        let speech = IrSpeechRequest {
+            model: "tts".into(),
+            input: "Hello".into(),
+            voice: "alloy".into(),
+            response_format: None,
+            speed: None,
+            instructions: None,
+        };
+        assert!(Speech.build_request(&client, &speech, &target("gemini")).is_err());
+    }
+}
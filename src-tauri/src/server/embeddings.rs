@@ -0,0 +1,149 @@
+use crate::error::AppError;
+use crate::modality::embedding::gemini::GeminiEmbeddingCodec;
+use crate::modality::embedding::ir::{IrEmbeddingRequest, IrEmbeddingResponse, IrEmbeddingUsage};
+use crate::modality::embedding::{self, EmbeddingFormat};
+use crate::server::middleware::ApiKey;
+use crate::server::modality_proxy::{self, ModalityCodec, ModalityOutput};
+use crate::server::proxy::{ProxyState, UpstreamTarget};
+use crate::tokenizer;
+use axum::extract::State;
+use axum::response::Response;
+use bytes::Bytes;
+
+/// Input format slug of `/v1/embeddings`, as recorded in request logs.
+const INPUT_FORMAT: &str = "openai-embeddings";
+
+/// Proxy handler for OpenAI-compatible `/v1/embeddings` requests.
+/// Upstream channels may speak OpenAI, Gemini or Ollama embeddings.
+pub async fn proxy_embeddings(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let request = embedding::get_decoder(EmbeddingFormat::OpenAi)
+        .decode_request(&body)
+        .map(|ir| (ir, String::from_utf8_lossy(&body).to_string()));
+    modality_proxy::proxy_modality(state, api_key, Embeddings, request, INPUT_FORMAT).await
+}
+
+struct Embeddings;
+
+#[async_trait::async_trait]
+impl ModalityCodec for Embeddings {
+    type Request = IrEmbeddingRequest;
+    type Response = IrEmbeddingResponse;
+
+    fn modality(&self) -> &'static str {
+        "embedding"
+    }
+
+    fn model<'r>(&self, ir: &'r IrEmbeddingRequest) -> &'r str {
+        &ir.model
+    }
+
+    fn estimate_cost(&self, ir: &IrEmbeddingRequest) -> i64 {
+        ir.input
+            .iter()
+            .map(|text| tokenizer::count_text_tokens(&ir.model, text) as i64)
+            .sum()
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        ir: &IrEmbeddingRequest,
+        target: &UpstreamTarget,
+    ) -> Result<(String, reqwest::RequestBuilder), AppError> {
+        let format = EmbeddingFormat::from_provider(&target.provider);
+        let upstream_body = embedding::get_encoder(format).encode_request(ir, &target.model)?;
+        let url = build_upstream_url(&target.base_url, format, &target.model, ir);
+        let builder = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(upstream_body);
+        Ok((url, apply_auth(builder, format, &target.api_key)))
+    }
+
+    async fn decode_response(
+        &self,
+        ir: &IrEmbeddingRequest,
+        provider: &str,
+        upstream: reqwest::Response,
+    ) -> Result<IrEmbeddingResponse, AppError> {
+        let format = EmbeddingFormat::from_provider(provider);
+        let resp_bytes = upstream.bytes().await?;
+        let mut ir_response = embedding::get_decoder(format).decode_response(&resp_bytes)?;
+        if ir_response.embeddings.len() != ir.input.len() {
+            return Err(AppError::Codec(format!(
+                "Upstream returned {} embeddings for {} inputs",
+                ir_response.embeddings.len(),
+                ir.input.len()
+            )));
+        }
+        ir_response.model.get_or_insert_with(|| ir.model.clone());
+        // Gemini reports no usage; fall back to the local count
+        let estimated = self.estimate_cost(ir) as u32;
+        ir_response.usage.get_or_insert(IrEmbeddingUsage {
+            prompt_tokens: estimated,
+            total_tokens: estimated,
+        });
+        Ok(ir_response)
+    }
+
+    fn usage(&self, _: &IrEmbeddingRequest, response: &IrEmbeddingResponse) -> Option<(i64, i64)> {
+        response.usage.as_ref().map(|usage| (usage.prompt_tokens as i64, 0))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrEmbeddingRequest,
+        response: IrEmbeddingResponse,
+    ) -> Result<ModalityOutput, AppError> {
+        let output_bytes = embedding::get_encoder(EmbeddingFormat::OpenAi)
+            .encode_response(&response, ir.encoding_format)?;
+
+        // Log without the vectors themselves, which would only bloat the database
+        let dimensions = response.embeddings.first().map_or(0, Vec::len);
+        let summary = serde_json::json!({
+            "model": response.model,
+            "embeddings": response.embeddings.len(),
+            "dimensions": dimensions,
+            "usage": response.usage,
+        })
+        .to_string();
+        Ok(ModalityOutput::json(output_bytes, summary))
+    }
+}
+
+fn build_upstream_url(
+    base_url: &str,
+    format: EmbeddingFormat,
+    model: &str,
+    ir: &IrEmbeddingRequest,
+) -> String {
+    let base = base_url.trim_end_matches('/');
+    match format {
+        EmbeddingFormat::OpenAi => format!("{}/v1/embeddings", base),
+        EmbeddingFormat::Gemini => format!(
+            "{}/v1beta/models/{}:{}",
+            base,
+            model,
+            GeminiEmbeddingCodec::method(ir)
+        ),
+        EmbeddingFormat::Ollama => format!("{}/api/embed", base),
+    }
+}
+
+fn apply_auth(
+    builder: reqwest::RequestBuilder,
+    format: EmbeddingFormat,
+    api_key: &str,
+) -> reqwest::RequestBuilder {
+    match format {
+        EmbeddingFormat::OpenAi => builder.header("Authorization", format!("Bearer {}", api_key)),
+        EmbeddingFormat::Gemini => builder.header("x-goog-api-key", api_key),
+        // Local Ollama needs no key; one is only sent when configured
+        EmbeddingFormat::Ollama if api_key.is_empty() => builder,
+        EmbeddingFormat::Ollama => builder.header("Authorization", format!("Bearer {}", api_key)),
+    }
+}
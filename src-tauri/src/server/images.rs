@@ -0,0 +1,239 @@
+use crate::error::AppError;
+use crate::modality::image::ir::{IrImageRequest, IrImageResponse};
+use crate::modality::image::openai::OpenAiImageCodec;
+use crate::modality::image::{self, ImageFormat, ImageRequestBody};
+use crate::server::middleware::{self, ApiKey};
+use crate::server::modality_proxy::{self, ModalityCodec, ModalityOutput};
+use crate::server::proxy::{self, ProxyState, UpstreamTarget};
+use crate::tokenizer;
+use axum::extract::{Multipart, State};
+use axum::response::Response;
+use bytes::Bytes;
+
+/// Input format slugs of the `/v1/images/*` endpoints, as recorded in request
+/// logs. Edits are logged without their uploads and cannot be replayed.
+const INPUT_FORMAT: &str = "openai-images";
+const EDIT_INPUT_FORMAT: &str = "openai-image-edits";
+
+/// Proxy handler for OpenAI-compatible `/v1/images/generations` requests.
+pub async fn proxy_image_generation(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let request = image::get_decoder(ImageFormat::OpenAi)
+        .decode_request(&body)
+        .map(|ir| (ir, proxy::truncate_log_payloads(&body)));
+    modality_proxy::proxy_modality(state, api_key, Images, request, INPUT_FORMAT).await
+}
+
+/// Proxy handler for OpenAI-compatible `/v1/images/edits` multipart requests.
+pub async fn proxy_image_edit(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    multipart: Multipart,
+) -> Result<Response, AppError> {
+    let request = middleware::read_multipart(multipart).await.and_then(|fields| {
+        let ir = OpenAiImageCodec.decode_multipart_request(fields)?;
+        // Uploaded image bytes are not serialized, only their metadata
+        let logged = serde_json::to_string(&ir)?;
+        Ok((ir, logged))
+    });
+    modality_proxy::proxy_modality(state, api_key, Images, request, EDIT_INPUT_FORMAT).await
+}
+
+struct Images;
+
+#[async_trait::async_trait]
+impl ModalityCodec for Images {
+    type Request = IrImageRequest;
+    type Response = IrImageResponse;
+
+    fn modality(&self) -> &'static str {
+        "image"
+    }
+
+    fn model<'r>(&self, ir: &'r IrImageRequest) -> &'r str {
+        &ir.model
+    }
+
+    fn estimate_cost(&self, ir: &IrImageRequest) -> i64 {
+        let images = (ir.n as usize + ir.images.len()) * tokenizer::TOKENS_PER_IMAGE;
+        (tokenizer::count_text_tokens(&ir.model, &ir.prompt) + images) as i64
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        ir: &IrImageRequest,
+        target: &UpstreamTarget,
+    ) -> Result<(String, reqwest::RequestBuilder), AppError> {
+        let format = ImageFormat::from_provider(&target.provider);
+        let upstream_body = image::get_encoder(format).encode_request(ir, &target.model)?;
+        let url = build_upstream_url(&target.base_url, format, &target.model, ir);
+        let builder = client.post(&url);
+        let builder = match upstream_body {
+            ImageRequestBody::Json(body) => builder
+                .header("Content-Type", "application/json")
+                .body(body),
+            ImageRequestBody::Multipart(form) => builder.multipart(form),
+        };
+        Ok((url, apply_auth(builder, format, &target.api_key)))
+    }
+
+    async fn decode_response(
+        &self,
+        _: &IrImageRequest,
+        provider: &str,
+        upstream: reqwest::Response,
+    ) -> Result<IrImageResponse, AppError> {
+        let resp_bytes = upstream.bytes().await?;
+        image::get_decoder(ImageFormat::from_provider(provider)).decode_response(&resp_bytes)
+    }
+
+    fn usage(&self, _: &IrImageRequest, response: &IrImageResponse) -> Option<(i64, i64)> {
+        response
+            .usage
+            .as_ref()
+            .map(|usage| (usage.input_tokens as i64, usage.output_tokens as i64))
+    }
+
+    fn encode_response(
+        &self,
+        ir: &IrImageRequest,
+        response: IrImageResponse,
+    ) -> Result<ModalityOutput, AppError> {
+        let output_bytes = image::get_encoder(ImageFormat::OpenAi)
+            .encode_response(&response, ir.response_format)?;
+        let logged = proxy::truncate_log_payloads(&output_bytes);
+        Ok(ModalityOutput::json(output_bytes, logged))
+    }
+}
+
+fn build_upstream_url(
+    base_url: &str,
+    format: ImageFormat,
+    model: &str,
+    ir: &IrImageRequest,
+) -> String {
+    let base = base_url.trim_end_matches('/');
+    match format {
+        ImageFormat::OpenAi if ir.is_edit() => format!("{}/v1/images/edits", base),
+        ImageFormat::OpenAi => format!("{}/v1/images/generations", base),
+        ImageFormat::Gemini => format!("{}/v1beta/models/{}:generateContent", base, model),
+    }
+}
+
+fn apply_auth(
+    builder: reqwest::RequestBuilder,
+    format: ImageFormat,
+    api_key: &str,
+) -> reqwest::RequestBuilder {
+    match format {
+        ImageFormat::OpenAi => builder.header("Authorization", format!("Bearer {}", api_key)),
+        ImageFormat::Gemini => builder.header("x-goog-api-key", api_key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modality::image::ir::{IrImageData, IrImageResponseFormat};
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::{Arc, Mutex};
+
+    fn target(provider: &str, base_url: &str) -> UpstreamTarget {
+        UpstreamTarget {
+            provider: provider.into(),
+            base_url: base_url.into(),
+            model: "gpt-image-1".into(),
+            api_key: "key".into(),
+        }
+    }
+
+    fn edit() -> IrImageRequest {
+        IrImageRequest {
+            model: "client-model".into(),
+            prompt: "add a hat".into(),
+            n: 2,
+            size: Some("512x512".into()),
+            quality: None,
+            response_format: Some(IrImageResponseFormat::B64Json),
+            images: vec![
+                IrImageData { filename: Some("a.png".into()), mime_type: "image/png".into(), data: b"png".to_vec() },
+                IrImageData { filename: None, mime_type: "image/jpeg".into(), data: b"jpg".to_vec() },
+            ],
+            mask: Some(IrImageData { filename: Some("mask.png".into()), mime_type: "image/png".into(), data: b"mask".to_vec() }),
+            user: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_edit_request_reaches_the_upstream_as_multipart() {
+        let received = Arc::new(Mutex::new(None));
+        let recorded = received.clone();
+        let app = Router::new().route(
+            "/v1/images/edits",
+            post(move |headers: axum::http::HeaderMap, multipart: Multipart| async move {
+                let fields = middleware::read_multipart(multipart).await.unwrap();
+                let ir = OpenAiImageCodec.decode_multipart_request(fields).unwrap();
+                *recorded.lock().unwrap() = Some((headers["authorization"].to_str().unwrap().to_string(), ir));
+                axum::Json(serde_json::json!({"created": 1, "data": [{"b64_json": "aW1n"}]}))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let ir = edit();
+        let (url, builder) = Images
+            .build_request(&reqwest::Client::new(), &ir, &target("openai", &base_url))
+            .unwrap();
+        assert!(url.ends_with("/v1/images/edits"));
+        let upstream = builder.send().await.unwrap();
+        let response = Images.decode_response(&ir, "openai", upstream).await.unwrap();
+        assert_eq!(response.images[0].b64_json.as_deref(), Some("aW1n"));
+
+        let (auth, sent) = received.lock().unwrap().take().unwrap();
+        assert_eq!(auth, "Bearer key");
+        assert_eq!(sent.model, "gpt-image-1");
+        assert_eq!(sent.prompt, "add a hat");
+        assert_eq!(sent.n, 2);
+        assert_eq!(sent.size.as_deref(), Some("512x512"));
+        assert_eq!(sent.response_format, Some(IrImageResponseFormat::B64Json));
+        let images: Vec<_> = sent
+            .images
+            .iter()
+            .map(|i| (i.filename.as_deref(), i.mime_type.as_str(), i.data.as_slice()))
+            .collect();
+        assert_eq!(
+            images,
+            vec![
+                (Some("a.png"), "image/png", &b"png"[..]),
+                (Some("image.png"), "image/jpeg", &b"jpg"[..]),
+            ]
+        );
+        assert_eq!(sent.mask.unwrap().data, b"mask");
+    }
+
+    #[test]
+    fn test_requests_are_sent_to_the_provider_endpoint() {
+        let client = reqwest::Client::new();
+        let mut ir = edit();
+
+        let (url, _) = Images.build_request(&client, &ir, &target("openai", "http://upstream/")).unwrap();
+        assert_eq!(url, "http://upstream/v1/images/edits");
+
+        ir.images.clear();
+        let (url, builder) = Images.build_request(&client, &ir, &target("openai", "http://upstream")).unwrap();
+        assert_eq!(url, "http://upstream/v1/images/generations");
+        let request = builder.build().unwrap();
+        assert_eq!(request.headers()["content-type"], "application/json");
+        assert_eq!(request.headers()["authorization"], "Bearer key");
+
+        let (url, builder) = Images.build_request(&client, &ir, &target("gemini", "http://upstream")).unwrap();
+        assert_eq!(url, "http://upstream/v1beta/models/gpt-image-1:generateContent");
+        assert_eq!(builder.build().unwrap().headers()["x-goog-api-key"], "key");
+    }
+}
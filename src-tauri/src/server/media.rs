@@ -0,0 +1,417 @@
+//! Inlining of remote images for providers that only take base64 data.
+
+use crate::error::AppError;
+use crate::modality::chat::ir::{IrChatRequest, IrContent, IrContentPart};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::ImageFormat;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+
+/// Largest image that is downloaded (Anthropic and Gemini cap inline images
+/// at 5 MB and 20 MB per request).
+const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+/// Images with a longer side are downscaled before inlining; providers
+/// resize larger ones anyway.
+const MAX_IMAGE_DIMENSION: u32 = 2048;
+const CACHE_CAPACITY: usize = 64;
+const CACHE_TTL: Duration = Duration::from_secs(600);
+/// Download limits, so a slow or looping image host fails the request
+/// instead of holding it.
+const FETCH_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 3;
+
+#[derive(Clone)]
+struct CachedImage {
+    media_type: String,
+    data: String,
+    fetched_at: Instant,
+}
+
+/// Downloads remote images and caches them by URL hash, so the images of
+/// earlier turns are not fetched again on every request of a conversation.
+///
+/// Downloads use their own client with timeouts and a redirect limit, and
+/// never connect to loopback, private or link-local addresses.
+pub struct ImageInliner {
+    http_client: reqwest::Client,
+    cache: Mutex<HashMap<u64, CachedImage>>,
+}
+
+impl Default for ImageInliner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageInliner {
+    pub fn new() -> Self {
+        let http_client = reqwest::Client::builder()
+            .connect_timeout(FETCH_CONNECT_TIMEOUT)
+            .timeout(FETCH_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if is_local_url(attempt.url()) {
+                    attempt.error("redirect to a local address")
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("failed to build the image download client");
+        Self {
+            http_client,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return a copy of the request with every `http(s)` image URL replaced by
+    /// inline base64 data, or `None` if it has no remote images.
+    pub async fn inline_request(&self, ir: &IrChatRequest) -> Result<Option<IrChatRequest>, AppError> {
+        let has_remote = ir.messages.iter().any(|m| match &m.content {
+            IrContent::Parts(parts) => parts.iter().any(|p| remote_image_url(p).is_some()),
+            IrContent::Text(_) => false,
+        });
+        if !has_remote {
+            return Ok(None);
+        }
+
+        let mut inlined = ir.clone();
+        for msg in &mut inlined.messages {
+            let IrContent::Parts(parts) = &mut msg.content else {
+                continue;
+            };
+            for part in parts {
+                let Some(url) = remote_image_url(part).map(String::from) else {
+                    continue;
+                };
+                let image = self.get(&url).await?;
+                *part = IrContentPart::Image {
+                    url: None,
+                    media_type: Some(image.media_type),
+                    data: Some(image.data),
+                };
+            }
+        }
+        Ok(Some(inlined))
+    }
+
+    async fn get(&self, url: &str) -> Result<CachedImage, AppError> {
+        let key = url_hash(url);
+        if let Some(image) = self.cache.lock().unwrap().get(&key) {
+            if image.fetched_at.elapsed() < CACHE_TTL {
+                return Ok(image.clone());
+            }
+        }
+
+        let image = self.fetch(url).await?;
+        self.store(key, image.clone());
+        Ok(image)
+    }
+
+    /// Cache an image, dropping expired entries and, when full, the oldest one.
+    fn store(&self, key: u64, image: CachedImage) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.fetched_at.elapsed() < CACHE_TTL);
+        if cache.len() >= CACHE_CAPACITY {
+            if let Some(oldest) = cache.iter().min_by_key(|(_, c)| c.fetched_at).map(|(k, _)| *k) {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, image);
+    }
+
+    async fn fetch(&self, url: &str) -> Result<CachedImage, AppError> {
+        let fetch_error = |reason: String| AppError::BadRequest(format!("Failed to fetch image {}: {}", url, reason));
+
+        // Host names are checked by the resolver; IP literals never reach it
+        let parsed = Url::parse(url).map_err(|e| fetch_error(e.to_string()))?;
+        if is_local_url(&parsed) {
+            return Err(fetch_error("local addresses are not fetched".to_string()));
+        }
+
+        let resp = self
+            .http_client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| fetch_error(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(fetch_error(format!("HTTP {}", resp.status().as_u16())));
+        }
+        let too_large = || fetch_error(format!("larger than {} bytes", MAX_IMAGE_BYTES));
+        if resp.content_length().is_some_and(|len| len > MAX_IMAGE_BYTES as u64) {
+            return Err(too_large());
+        }
+        let header_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.split(';').next().unwrap_or(v).trim().to_string());
+
+        let mut bytes = Vec::new();
+        let mut stream = resp.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk.map_err(|e| fetch_error(e.to_string()))?);
+            if bytes.len() > MAX_IMAGE_BYTES {
+                return Err(too_large());
+            }
+        }
+
+        // Trust the content over the header, which is often generic
+        let format = image::guess_format(&bytes).ok();
+        let media_type = match (format, header_type) {
+            (Some(format), _) => format.to_mime_type().to_string(),
+            (None, Some(header)) if header.starts_with("image/") => header,
+            _ => return Err(fetch_error("not an image".to_string())),
+        };
+
+        let (media_type, bytes) = match format {
+            Some(format) => tokio::task::spawn_blocking(move || downscale(format, media_type, bytes))
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?,
+            None => (media_type, bytes),
+        };
+
+        Ok(CachedImage {
+            media_type,
+            data: BASE64.encode(bytes),
+            fetched_at: Instant::now(),
+        })
+    }
+}
+
+fn remote_image_url(part: &IrContentPart) -> Option<&str> {
+    match part {
+        IrContentPart::Image { url: Some(url), data: None, .. }
+            if url.starts_with("http://") || url.starts_with("https://") =>
+        {
+            Some(url)
+        }
+        _ => None,
+    }
+}
+
+/// Whether a URL points at this machine or the local network by IP literal
+/// or `localhost` name.
+fn is_local_url(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return true;
+    };
+    // IPv6 hosts keep their brackets
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => !is_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+    }
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => {
+                let first = ip.segments()[0];
+                let unique_local = (first & 0xfe00) == 0xfc00;
+                let link_local = (first & 0xffc0) == 0xfe80;
+                !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+            }
+        },
+    }
+}
+
+/// System DNS resolution without the non-public addresses, so neither an
+/// image URL nor a redirect can reach the local network by name.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn url_hash(url: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Shrink an image whose longer side exceeds [`MAX_IMAGE_DIMENSION`]. PNGs
+/// stay PNG, everything else is re-encoded as JPEG. The original is kept if
+/// it is small enough or can't be decoded.
+fn downscale(format: ImageFormat, media_type: String, bytes: Vec<u8>) -> (String, Vec<u8>) {
+    let fits = image::ImageReader::with_format(Cursor::new(&bytes), format)
+        .into_dimensions()
+        .map(|(w, h)| w.max(h) <= MAX_IMAGE_DIMENSION)
+        .unwrap_or(true);
+    if fits {
+        return (media_type, bytes);
+    }
+
+    let decoded = match image::load_from_memory_with_format(&bytes, format) {
+        Ok(img) => img,
+        Err(e) => {
+            log::warn!("Failed to decode image for downscaling: {}", e);
+            return (media_type, bytes);
+        }
+    };
+    let resized = decoded.resize(
+        MAX_IMAGE_DIMENSION,
+        MAX_IMAGE_DIMENSION,
+        image::imageops::FilterType::Triangle,
+    );
+
+    // JPEG has no alpha channel
+    let (target, resized) = match format {
+        ImageFormat::Png => (ImageFormat::Png, resized),
+        _ => (ImageFormat::Jpeg, image::DynamicImage::ImageRgb8(resized.to_rgb8())),
+    };
+    let mut out = Cursor::new(Vec::new());
+    match resized.write_to(&mut out, target) {
+        Ok(()) => (target.to_mime_type().to_string(), out.into_inner()),
+        Err(e) => {
+            log::warn!("Failed to encode downscaled image: {}", e);
+            (media_type, bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modality::chat::ir::IrMessage;
+
+    fn cached(data: &str, age: Duration) -> CachedImage {
+        CachedImage {
+            media_type: "image/png".to_string(),
+            data: data.to_string(),
+            fetched_at: Instant::now() - age,
+        }
+    }
+
+    fn image_part(url: &str) -> IrContentPart {
+        IrContentPart::Image { url: Some(url.to_string()), media_type: None, data: None }
+    }
+
+    #[tokio::test]
+    async fn test_inline_request_replaces_remote_images() {
+        let inliner = ImageInliner::new();
+        inliner.store(url_hash("https://example.com/cat.png"), cached("Y2F0", Duration::ZERO));
+
+        let text_only = IrChatRequest {
+            messages: vec![IrMessage { content: IrContent::Text("Hi".into()), ..Default::default() }],
+            ..Default::default()
+        };
+        assert!(inliner.inline_request(&text_only).await.unwrap().is_none());
+
+        let parts = vec![
+            IrContentPart::Text { text: "What is this?".into() },
+            image_part("https://example.com/cat.png"),
+            image_part("data:image/png;base64,ZG9n"),
+        ];
+        let ir = IrChatRequest {
+            messages: vec![IrMessage { content: IrContent::Parts(parts.clone()), ..Default::default() }],
+            ..Default::default()
+        };
+        let inlined = inliner.inline_request(&ir).await.unwrap().unwrap();
+        let IrContent::Parts(inlined_parts) = &inlined.messages[0].content else {
+            panic!("expected parts");
+        };
+        assert!(matches!(
+            &inlined_parts[1],
+            IrContentPart::Image { url: None, media_type: Some(m), data: Some(d) } if m == "image/png" && d == "Y2F0"
+        ));
+        // Text and data URLs are left alone
+        assert!(matches!(&inlined_parts[0], IrContentPart::Text { text } if text == "What is this?"));
+        assert!(matches!(&inlined_parts[2], IrContentPart::Image { url: Some(u), .. } if u.starts_with("data:")));
+    }
+
+    #[tokio::test]
+    async fn test_expired_images_are_fetched_again() {
+        let inliner = ImageInliner::new();
+        let url = "http://127.0.0.1/cat.png";
+        inliner.store(url_hash(url), cached("fresh", CACHE_TTL / 2));
+        assert_eq!(inliner.get(url).await.unwrap().data, "fresh");
+
+        // Refetching a loopback URL fails, which shows the cache was bypassed
+        inliner.cache.lock().unwrap().insert(url_hash(url), cached("stale", CACHE_TTL * 2));
+        assert!(inliner.get(url).await.is_err());
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest_when_full() {
+        let inliner = ImageInliner::new();
+        inliner.store(1000, cached("expired", CACHE_TTL * 2));
+        for key in 0..CACHE_CAPACITY as u64 {
+            inliner.store(key, cached("image", Duration::from_secs(CACHE_CAPACITY as u64 - key)));
+        }
+        {
+            let cache = inliner.cache.lock().unwrap();
+            assert_eq!(cache.len(), CACHE_CAPACITY);
+            assert!(!cache.contains_key(&1000), "expired entries are dropped first");
+        }
+
+        inliner.store(CACHE_CAPACITY as u64, cached("newest", Duration::ZERO));
+        let cache = inliner.cache.lock().unwrap();
+        assert_eq!(cache.len(), CACHE_CAPACITY);
+        assert!(!cache.contains_key(&0), "the oldest entry is evicted");
+        assert!(cache.contains_key(&1));
+    }
+
+    #[test]
+    fn test_local_addresses_are_not_public() {
+        for ip in ["127.0.0.1", "10.1.2.3", "192.168.0.1", "169.254.169.254", "100.64.0.1", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        assert!(is_local_url(&Url::parse("http://LOCALHOST:8080/a.png").unwrap()));
+        assert!(is_local_url(&Url::parse("http://[::1]/a.png").unwrap()));
+        assert!(!is_local_url(&Url::parse("https://example.com/a.png").unwrap()));
+    }
+
+    #[test]
+    fn test_downscale_limits_longer_side() {
+        let img = image::DynamicImage::new_rgb8(MAX_IMAGE_DIMENSION * 2, 100);
+        let mut png = Cursor::new(Vec::new());
+        img.write_to(&mut png, ImageFormat::Png).unwrap();
+
+        let (media_type, bytes) = downscale(ImageFormat::Png, "image/png".to_string(), png.into_inner());
+        assert_eq!(media_type, "image/png");
+        let resized = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(resized.width(), MAX_IMAGE_DIMENSION);
+        assert_eq!(resized.height(), 50);
+    }
+}
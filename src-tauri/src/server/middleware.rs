@@ -1,19 +1,85 @@
 use crate::error::AppError;
+use crate::modality::MultipartField;
+use axum::extract::{FromRequestParts, Multipart};
+use axum::http::request::Parts;
 use axum::http::HeaderMap;
+use axum::response::Response;
 
-/// Extract and validate the Bearer token from request headers.
-/// Returns the raw token string (without "Bearer " prefix).
-pub fn extract_bearer_token(headers: &HeaderMap) -> Result<String, AppError> {
-    let auth = headers
-        .get("authorization")
-        .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| AppError::Unauthorized("Missing Authorization header".into()))?;
+/// The caller's gateway API key, read from every place the native SDK of the
+/// inbound route sends it: `x-api-key` on Anthropic routes, `x-goog-api-key`
+/// or the `key` query param on Gemini routes, and `Authorization: Bearer` on
+/// all of them. A missing key is rejected with a 401 in the route's format.
+pub struct ApiKey(pub String);
 
-    if !auth.starts_with("Bearer ") {
-        return Err(AppError::Unauthorized("Invalid Authorization format".into()));
+impl<S: Send + Sync> FromRequestParts<S> for ApiKey {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let format = route_format(parts.uri.path());
+        extract_api_key(&parts.headers, parts.uri.query(), format)
+            .map(ApiKey)
+            .map_err(|e| e.into_response_for(format))
+    }
+}
+
+/// Codec slug of the API an inbound route belongs to, which decides where
+/// its clients send credentials and how its errors are shaped.
+pub fn route_format(path: &str) -> &'static str {
+    if path.starts_with("/v1/messages") {
+        "anthropic"
+    } else if path.starts_with("/v1beta/") {
+        "gemini"
+    } else if path.starts_with("/v1/responses") {
+        "openai-responses"
+    } else {
+        "openai-chat"
     }
+}
+
+fn extract_api_key(headers: &HeaderMap, query: Option<&str>, format: &str) -> Result<String, AppError> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+
+    let native = match format {
+        "anthropic" => header("x-api-key"),
+        "gemini" => header("x-goog-api-key").or_else(|| query_param(query, "key")),
+        _ => None,
+    };
+    if let Some(key) = native {
+        return Ok(key);
+    }
+
+    if let Some(auth) = header("authorization") {
+        return match auth.strip_prefix("Bearer ") {
+            Some(key) => Ok(key.trim().to_string()),
+            None => Err(AppError::Unauthorized(
+                "Invalid Authorization header, expected \"Bearer <key>\"".into(),
+            )),
+        };
+    }
+
+    let locations = match format {
+        "anthropic" => "the x-api-key header or an Authorization: Bearer header",
+        "gemini" => "the x-goog-api-key header, the key query parameter or an Authorization: Bearer header",
+        _ => "an Authorization: Bearer header",
+    };
+    Err(AppError::Unauthorized(format!("Missing API key, send it in {}", locations)))
+}
 
-    Ok(auth[7..].to_string())
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+        .filter(|value| !value.is_empty())
 }
 
 /// Determine desired output format from headers or query params.
@@ -35,3 +101,41 @@ pub fn extract_output_format(headers: &HeaderMap, query: Option<&str>) -> Option
 
     None
 }
+
+/// Read every field of a `multipart/form-data` body into memory.
+pub async fn read_multipart(mut multipart: Multipart) -> Result<Vec<MultipartField>, AppError> {
+    let invalid = |e: axum::extract::multipart::MultipartError| {
+        AppError::Codec(format!("Invalid multipart body: {}", e))
+    };
+
+    let mut fields = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        let name = field.name().unwrap_or_default().to_string();
+        let filename = field.file_name().map(str::to_string);
+        let content_type = field.content_type().map(str::to_string);
+        let data = field.bytes().await.map_err(invalid)?;
+        fields.push(MultipartField { name, filename, content_type, data });
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_locations_follow_the_route() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-goog-api-key", "goog".parse().unwrap());
+        headers.insert("x-api-key", "anthropic".parse().unwrap());
+        assert_eq!(extract_api_key(&headers, None, "gemini").unwrap(), "goog");
+        assert_eq!(extract_api_key(&headers, None, "anthropic").unwrap(), "anthropic");
+        assert!(matches!(extract_api_key(&headers, None, "openai-chat"), Err(AppError::Unauthorized(_))));
+
+        let empty = HeaderMap::new();
+        assert_eq!(extract_api_key(&empty, Some("alt=sse&key=sk%2Dq"), "gemini").unwrap(), "sk-q");
+
+        headers.insert("authorization", "Bearer sk-bearer".parse().unwrap());
+        assert_eq!(extract_api_key(&headers, None, "openai-chat").unwrap(), "sk-bearer");
+    }
+}
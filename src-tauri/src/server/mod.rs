@@ -1,16 +1,30 @@
+pub mod access;
+pub mod audio;
+pub mod embeddings;
 pub mod generic_proxy;
+pub mod images;
+pub mod media;
+pub mod modality_proxy;
 pub mod middleware;
 pub mod proxy;
 pub mod router;
+pub mod usage;
 
+use crate::routing::retry::RetryPolicy;
+use crate::server::access::CachePricing;
+use crate::rules::registry::RuleRegistry;
 use sqlx::SqlitePool;
 use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
 
 pub async fn start(
     pool: SqlitePool,
+    registry: Arc<RuleRegistry>,
+    retry: Arc<RwLock<RetryPolicy>>,
+    pricing: Arc<RwLock<CachePricing>>,
     port: u16,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let app = router::create_router(pool).await;
+    let app = router::create_router(pool, registry, retry, pricing).await;
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
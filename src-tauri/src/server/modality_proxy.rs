@@ -0,0 +1,201 @@
+use crate::error::AppError;
+use crate::server::access;
+use crate::server::middleware::ApiKey;
+use crate::server::proxy::{
+    self, AttemptLog, ProxyState, RequestLogEntry, UpstreamSuccess, UpstreamTarget,
+};
+use axum::body::Body;
+use axum::http::{header, StatusCode};
+use axum::response::Response;
+use tokio_stream::StreamExt;
+
+/// The parts of a non-chat endpoint (embeddings, images, audio) that differ
+/// by modality. [`proxy_modality`] runs the rest of the request: quota,
+/// failover, logging and settlement.
+#[async_trait::async_trait]
+pub(super) trait ModalityCodec: Sync {
+    /// The decoded client request.
+    type Request: Send + Sync;
+    /// The upstream's response, decoded far enough to read its usage.
+    type Response: Send;
+
+    /// Value of `request_logs.modality`.
+    fn modality(&self) -> &'static str;
+
+    fn model<'r>(&self, request: &'r Self::Request) -> &'r str;
+
+    /// Tokens reserved against the quota before anything is sent.
+    fn estimate_cost(&self, request: &Self::Request) -> i64;
+
+    /// Encode the request for the target's provider. Returns the URL, for
+    /// logging, and the request to send.
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        request: &Self::Request,
+        target: &UpstreamTarget,
+    ) -> Result<(String, reqwest::RequestBuilder), AppError>;
+
+    /// Read the successful upstream response of a channel with this provider.
+    async fn decode_response(
+        &self,
+        request: &Self::Request,
+        provider: &str,
+        upstream: reqwest::Response,
+    ) -> Result<Self::Response, AppError>;
+
+    /// Prompt and completion tokens to charge. `None` leaves the estimate
+    /// charged.
+    fn usage(&self, request: &Self::Request, response: &Self::Response) -> Option<(i64, i64)>;
+
+    /// Build the client's response.
+    fn encode_response(
+        &self,
+        request: &Self::Request,
+        response: Self::Response,
+    ) -> Result<ModalityOutput, AppError>;
+}
+
+/// A response ready for the client.
+pub(super) struct ModalityOutput {
+    pub content_type: String,
+    pub body: Body,
+    /// What `request_logs` keeps of the body.
+    pub logged: String,
+}
+
+impl ModalityOutput {
+    pub fn json(bytes: Vec<u8>, logged: String) -> Self {
+        Self {
+            content_type: "application/json".to_string(),
+            body: Body::from(bytes),
+            logged,
+        }
+    }
+}
+
+/// Proxy a request of a non-chat modality. `request` is the decoded client
+/// request and its body as it should be logged; errors are rendered as
+/// `input_format` errors.
+pub(super) async fn proxy_modality<C: ModalityCodec>(
+    state: ProxyState,
+    api_key: ApiKey,
+    codec: C,
+    request: Result<(C::Request, String), AppError>,
+    input_format: &str,
+) -> Result<Response, AppError> {
+    match handle_modality(state, api_key, codec, request, input_format).await {
+        Ok(resp) => Ok(resp),
+        Err(e) => Ok(e.into_response_for(input_format)),
+    }
+}
+
+async fn handle_modality<C: ModalityCodec>(
+    state: ProxyState,
+    api_key: ApiKey,
+    codec: C,
+    request: Result<(C::Request, String), AppError>,
+    input_format: &str,
+) -> Result<Response, AppError> {
+    let start = std::time::Instant::now();
+
+    // 1. Authenticate and decode
+    let token = proxy::authenticate(&state, &api_key).await?;
+    let (request, request_body_str) = request?;
+    let model = codec.model(&request);
+
+    // 2. Enforce token model restrictions and reserve quota
+    access::check_model_allowed(&token, model)?;
+    let estimated_cost = codec.estimate_cost(&request);
+    let reservation = access::reserve_quota(&state.db, &token, estimated_cost).await?;
+
+    // Save context for logging
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let base_entry = RequestLogEntry {
+        token_id: &token.id,
+        model,
+        modality: codec.modality(),
+        input_format,
+        request_body: Some(&request_body_str),
+        ..Default::default()
+    };
+
+    // 3. Select a channel, encode for its provider and send. Nothing was
+    // served if this fails, so the whole reservation is returned.
+    let attempt_log = AttemptLog { base: base_entry, request_id: &request_id, start };
+    let (state_ref, codec_ref, request_ref) = (&state, &codec, &request);
+    let build_request = move |target: UpstreamTarget| async move {
+        codec_ref.build_request(&state_ref.http_client, request_ref, &target)
+    };
+    let sent = proxy::send_with_failover(&state, model, estimated_cost, attempt_log, build_request).await;
+    let UpstreamSuccess { mut selected, response: upstream_resp, attempt } = match sent {
+        Ok(success) => success,
+        Err(e) => {
+            reservation.release(&state.db).await;
+            return Err(e);
+        }
+    };
+
+    // 4. Convert the response. The upstream has done the work by now, so the
+    // attempt is logged and charged even if its response cannot be converted.
+    let provider = selected.channel.provider.clone();
+    let decoded = codec.decode_response(&request, &provider, upstream_resp).await;
+    let usage = decoded.as_ref().ok().and_then(|response| codec.usage(&request, response));
+    let output = decoded.and_then(|response| codec.encode_response(&request, response));
+
+    let entry = RequestLogEntry {
+        id: Some(&request_id),
+        attempt,
+        channel_id: &selected.channel.id,
+        output_format: &provider,
+        latency_ms: proxy::elapsed_ms(start),
+        prompt_tokens: usage.map(|(prompt, _)| prompt),
+        completion_tokens: usage.map(|(_, completion)| completion),
+        ..base_entry
+    };
+    match &output {
+        Ok(output) => {
+            proxy::log_request(&state.db, RequestLogEntry {
+                status: Some(200),
+                response_body: Some(&output.logged),
+                ..entry
+            })
+            .await;
+        }
+        Err(error) => {
+            let error = error.to_ir_error();
+            proxy::log_request(&state.db, RequestLogEntry {
+                status: Some(i32::from(error.status)),
+                error_message: Some(&error.message),
+                ..entry
+            })
+            .await;
+        }
+    }
+
+    // Replace the quota reservation with the actual usage (the estimate
+    // stays charged when the upstream reports none)
+    if let Some((prompt, completion)) = usage {
+        reservation.settle(&state.db, prompt + completion).await;
+        if let Some(permit) = selected.permit.as_mut() {
+            permit.record_usage(prompt + completion);
+        }
+    }
+    let output = output?;
+
+    // Hold the rate-limit permit until the body has been fully relayed
+    let permit = selected.permit.take();
+    let body = async_stream::stream! {
+        let _permit = permit;
+        let mut body = output.body.into_data_stream();
+        while let Some(chunk) = body.next().await {
+            yield chunk;
+        }
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, output.content_type)
+        .body(Body::from_stream(body))
+        .unwrap())
+}
@@ -1,14 +1,25 @@
+use crate::db::models::Token;
 use crate::error::AppError;
+use crate::modality::chat::ir::{IrChatRequest, IrError};
+use crate::modality::chat::structured_output::{self, StreamUnwrapper};
 use crate::modality::chat::{self, ChatFormat};
-use crate::routing::balancer;
+use crate::routing::balancer::{self, SelectedChannel};
 use crate::routing::circuit::CircuitBreaker;
-use crate::rules::registry::{CodecProvider, RuleRegistry, JsonataDecoder, JsonataEncoder};
+use crate::routing::keys::{self, KeyScheduler};
+use crate::routing::limiter::{RateLimitPermit, RateLimiter};
+use crate::routing::retry::RetryPolicy;
+use crate::rules::registry::{CodecProvider, RuleRegistry};
 use crate::rules::HttpConfig;
-use crate::server::middleware;
+use crate::server::access::{self, CachePricing, QuotaReservation};
+use crate::server::media::ImageInliner;
+use crate::server::middleware::{self, ApiKey};
+use crate::server::usage::StreamUsage;
+use crate::sse::SseDecoder;
+use crate::tokenizer;
 use axum::body::Body;
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
-use axum::response::Response;
+use axum::response::{IntoResponse, Json, Response};
 use bytes::Bytes;
 use sqlx::SqlitePool;
 use std::sync::Arc;
@@ -19,14 +30,27 @@ pub struct ProxyState {
     pub db: SqlitePool,
     pub http_client: reqwest::Client,
     pub circuit: Arc<CircuitBreaker>,
+    pub keys: Arc<KeyScheduler>,
+    pub limiter: Arc<RateLimiter>,
     pub registry: Arc<RuleRegistry>,
+    /// Shared with `AppState` so config updates reach running requests.
+    pub retry: Arc<std::sync::RwLock<RetryPolicy>>,
+    /// Shared with `AppState` like `retry`.
+    pub pricing: Arc<std::sync::RwLock<CachePricing>>,
+    pub images: Arc<ImageInliner>,
+}
+
+impl ProxyState {
+    /// The cache pricing currently configured.
+    pub(super) fn cache_pricing(&self) -> CachePricing {
+        *self.pricing.read().unwrap_or_else(|e| e.into_inner())
+    }
 }
 
 /// Resolve a codec slug to a Decoder via the registry.
 async fn resolve_decoder(registry: &RuleRegistry, slug: &str) -> Result<Box<dyn chat::Decoder>, AppError> {
     match registry.get(slug).await {
-        Some(CodecProvider::Builtin(format)) => Ok(chat::get_decoder(format)),
-        Some(CodecProvider::Jsonata(rule)) => Ok(Box::new(JsonataDecoder { rule })),
+        Some(provider) => Ok(provider.decoder()),
         None => {
             // Fallback: try ChatFormat::from_str_loose for backward compat
             ChatFormat::from_str_loose(slug)
@@ -38,8 +62,7 @@ async fn resolve_decoder(registry: &RuleRegistry, slug: &str) -> Result<Box<dyn
 
 async fn resolve_encoder(registry: &RuleRegistry, slug: &str) -> Result<Box<dyn chat::Encoder>, AppError> {
     match registry.get(slug).await {
-        Some(CodecProvider::Builtin(format)) => Ok(chat::get_encoder(format)),
-        Some(CodecProvider::Jsonata(rule)) => Ok(Box::new(JsonataEncoder { rule })),
+        Some(provider) => Ok(provider.encoder()),
         None => {
             ChatFormat::from_str_loose(slug)
                 .map(chat::get_encoder)
@@ -94,22 +117,35 @@ async fn build_upstream_request(
     }
 }
 
-/// Main proxy handler for chat completion requests.
-/// The `input_format_slug` is determined from the route path.
-pub async fn proxy_chat(
-    State(state): State<ProxyState>,
-    headers: HeaderMap,
-    input_format_slug: &str,
-    body: Bytes,
-) -> Result<Response, AppError> {
-    let start = std::time::Instant::now();
+/// Parts of an inbound chat request that some formats carry outside the
+/// body. Unset fields come from the usual places.
+#[derive(Default)]
+pub struct InboundChat {
+    /// Model named in the path (Gemini).
+    pub model: Option<String>,
+    /// Streaming requested by the path rather than the body (Gemini).
+    pub stream: Option<bool>,
+}
 
-    // 1. Authenticate
-    let token_value = middleware::extract_bearer_token(&headers)?;
-    let token = sqlx::query_as::<_, crate::db::models::Token>(
+impl InboundChat {
+    /// Apply the path parts to a decoded request.
+    fn apply(&self, ir: &mut IrChatRequest) {
+        if let Some(model) = &self.model {
+            ir.model = model.clone();
+        }
+        if let Some(stream) = self.stream {
+            ir.stream = stream;
+        }
+    }
+}
+
+/// Look up the caller's API key and check that it is enabled and not expired.
+pub(super) async fn authenticate(state: &ProxyState, api_key: &ApiKey) -> Result<Token, AppError> {
+    let token_value = &api_key.0;
+    let token = sqlx::query_as::<_, Token>(
         "SELECT * FROM tokens WHERE key_value = ? AND enabled = 1",
     )
-    .bind(&token_value)
+    .bind(token_value)
     .fetch_optional(&state.db)
     .await?
     .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
@@ -122,9 +158,77 @@ pub async fn proxy_chat(
         }
     }
 
+    Ok(token)
+}
+
+/// Count the input tokens of a chat request locally, without calling an upstream.
+/// Serves Anthropic's `/v1/messages/count_tokens`, Gemini's `:countTokens`
+/// and the OpenAI-style `input_tokens` endpoints; the response shape follows
+/// `input_format_slug`.
+pub async fn count_tokens(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    input_format_slug: &str,
+    inbound: InboundChat,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let result = async {
+        let token = authenticate(&state, &api_key).await?;
+        let decoder = resolve_decoder(&state.registry, input_format_slug).await?;
+        let mut ir = decoder.decode_request(&body)?;
+        inbound.apply(&mut ir);
+        access::check_model_allowed(&token, &ir.model)?;
+
+        let input_tokens = tokenizer::count_request_tokens(&ir);
+        let body = match input_format_slug {
+            "openai-responses" => serde_json::json!({
+                "object": "response.input_tokens",
+                "input_tokens": input_tokens,
+            }),
+            "gemini" => serde_json::json!({ "totalTokens": input_tokens }),
+            _ => serde_json::json!({ "input_tokens": input_tokens }),
+        };
+        Ok::<_, AppError>(Json(body).into_response())
+    }
+    .await;
+
+    Ok(result.unwrap_or_else(|e| e.into_response_for(input_format_slug)))
+}
+
+/// Main proxy handler for chat completion requests.
+/// The `input_format_slug` is determined from the route path; errors are
+/// rendered in that format's wire shape.
+pub async fn proxy_chat(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    headers: HeaderMap,
+    input_format_slug: &str,
+    inbound: InboundChat,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    match handle_chat(state, api_key, headers, input_format_slug, inbound, body).await {
+        Ok(resp) => Ok(resp),
+        Err(e) => Ok(e.into_response_for(input_format_slug)),
+    }
+}
+
+async fn handle_chat(
+    state: ProxyState,
+    api_key: ApiKey,
+    headers: HeaderMap,
+    input_format_slug: &str,
+    inbound: InboundChat,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let start = std::time::Instant::now();
+
+    // 1. Authenticate
+    let token = authenticate(&state, &api_key).await?;
+
     // 2. Decode request
     let decoder = resolve_decoder(&state.registry, input_format_slug).await?;
-    let ir = decoder.decode_request(&body)?;
+    let mut ir = decoder.decode_request(&body)?;
+    inbound.apply(&mut ir);
 
     // 3. Determine output format
     let output_format_str = middleware::extract_output_format(&headers, None);
@@ -132,114 +236,161 @@ pub async fn proxy_chat(
         .as_deref()
         .unwrap_or(input_format_slug)
         .to_string();
+    let output_encoder = resolve_encoder(&state.registry, &output_slug).await?;
 
-    // 4. Select channel via routing (priority + weighted random + circuit breaker)
-    let selected =
-        balancer::select_channel(&ir.model, &state.db, &state.circuit).await?;
-
-    let channel = &selected.channel;
-    let mapping = &selected.mapping;
-    let api_key = &selected.api_key;
+    // 4. Enforce token model restrictions and reserve quota
+    access::check_model_allowed(&token, &ir.model)?;
+    let estimated_cost = access::estimate_request_cost(&ir);
+    let reservation = access::reserve_quota(&state.db, &token, estimated_cost).await?;
 
     // Save context for logging
+    let request_id = uuid::Uuid::new_v4().to_string();
     let token_id = token.id.clone();
-    let channel_id = channel.id.clone();
     let model = ir.model.clone();
     let input_fmt_str = input_format_slug.to_string();
     let request_body_str = String::from_utf8_lossy(&body).to_string();
+    let base_entry = RequestLogEntry {
+        token_id: &token_id,
+        model: &model,
+        modality: "chat",
+        input_format: &input_fmt_str,
+        stream: ir.stream,
+        request_body: Some(&request_body_str),
+        ..Default::default()
+    };
 
-    // 5. Determine upstream format from channel provider
-    let upstream_slug = channel.provider.clone();
-    let output_fmt_str = upstream_slug.clone();
-
-    // 6. Encode IR → upstream format
-    let upstream_encoder = resolve_encoder(&state.registry, &upstream_slug).await?;
-    let upstream_body = upstream_encoder.encode_request(&ir, &mapping.actual_name)?;
-
-    // 7. Build upstream URL and request with provider-specific auth
-    let (upstream_url, req_builder) = build_upstream_request(
-        &state, &upstream_slug, &channel.base_url, &mapping.actual_name,
-        ir.stream, api_key, upstream_body,
-    ).await?;
-
-    // 9. Send request
-    let upstream_resp = req_builder.send().await;
-
-    let upstream_resp = match upstream_resp {
-        Ok(r) => r,
+    // 5–9. Select a channel, encode for its provider and send, failing over
+    // to other channels on retryable errors. Nothing was served if this
+    // fails, so the whole reservation is returned.
+    let attempt_log = AttemptLog { base: base_entry, request_id: &request_id, start };
+    // Remote images are downloaded at most once per request, by the first
+    // attempt on a provider that needs them inline
+    let inlined = tokio::sync::OnceCell::new();
+    let (state_ref, ir_ref, inlined_ref) = (&state, &ir, &inlined);
+    let build_request = move |target: UpstreamTarget| async move {
+        let upstream_encoder = resolve_encoder(&state_ref.registry, &target.provider).await?;
+        let inlined = if !upstream_encoder.accepts_image_urls() {
+            inlined_ref
+                .get_or_try_init(|| state_ref.images.inline_request(ir_ref))
+                .await?
+                .as_ref()
+        } else {
+            None
+        };
+        let upstream_body = upstream_encoder.encode_request(inlined.unwrap_or(ir_ref), &target.model)?;
+        build_upstream_request(
+            state_ref, &target.provider, &target.base_url, &target.model,
+            ir_ref.stream, &target.api_key, upstream_body,
+        ).await
+    };
+    let sent = send_with_failover(&state, &ir.model, estimated_cost, attempt_log, build_request).await;
+    let UpstreamSuccess { mut selected, response: upstream_resp, attempt } = match sent {
+        Ok(success) => success,
         Err(e) => {
-            state.circuit.record_failure(&channel.id);
-            let latency = start.elapsed().as_millis() as i64;
-            log_request(
-                &state.db, &token_id, &channel_id, &model, "chat",
-                &input_fmt_str, &output_fmt_str, None,
-                latency, None, None, Some(&request_body_str), Some(&e.to_string()),
-            ).await;
-            return Err(AppError::HttpClient(e));
+            reservation.release(&state.db).await;
+            return Err(e);
         }
     };
 
-    let status = upstream_resp.status();
-
-    if !status.is_success() {
-        state.circuit.record_failure(&channel.id);
-        let error_body = upstream_resp.text().await.unwrap_or_default();
-        let latency = start.elapsed().as_millis() as i64;
-        log_request(
-            &state.db, &token_id, &channel_id, &model, "chat",
-            &input_fmt_str, &output_fmt_str, Some(status.as_u16() as i32),
-            latency, None, None, Some(&request_body_str), Some(&error_body),
-        ).await;
-        return Err(AppError::Upstream {
-            status: status.as_u16(),
-            body: error_body,
-        });
-    }
-
-    // Success — record it
-    state.circuit.record_success(&channel.id);
+    // From here on the upstream has done (and billed) the work, so the
+    // request is logged and charged even if its response cannot be relayed
+    let channel_id = selected.channel.id.clone();
+    let upstream_slug = selected.channel.provider.clone();
+    // Structured output requested from a provider that emulates it with a
+    // forced tool call; the call is turned back into content below
+    let emulated_format = ir.response_format.as_ref().is_some_and(|f| f.json_schema().is_some())
+        && ChatFormat::from_str_loose(&upstream_slug).is_some_and(|f| f.emulates_response_format());
+    let output_fmt_str = upstream_slug.clone();
+    let success_entry = RequestLogEntry {
+        id: Some(&request_id),
+        attempt,
+        channel_id: &channel_id,
+        output_format: &output_fmt_str,
+        status: Some(200),
+        ..base_entry
+    };
+    let upstream_decoder = match resolve_decoder(&state.registry, &upstream_slug).await {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            log_unrelayed(&state.db, success_entry, start, &e, None).await;
+            return Err(e);
+        }
+    };
 
     // 10. Handle streaming vs non-streaming
     if ir.stream {
         // Log streaming request (response body will be updated after stream ends)
         let latency = start.elapsed().as_millis() as i64;
-        let log_id = log_request(
-            &state.db, &token_id, &channel_id, &model, "chat",
-            &input_fmt_str, &output_fmt_str, Some(200),
-            latency, None, None, Some(&request_body_str), None,
-        ).await;
-        return proxy_stream(upstream_resp, upstream_slug.clone(), output_slug.clone(), state.registry.clone(), state.db.clone(), log_id).await;
+        let log_id = log_request(&state.db, RequestLogEntry {
+            latency_ms: latency,
+            ..success_entry
+        }).await;
+        let accounting = StreamAccounting {
+            db: state.db.clone(),
+            log_id,
+            reservation,
+            permit: selected.permit.take(),
+            estimated_prompt_tokens: tokenizer::count_request_tokens(&ir) as i64,
+            model: ir.model.clone(),
+            pricing: state.cache_pricing(),
+            circuit: state.circuit.clone(),
+            channel_id: channel_id.clone(),
+        };
+        let unwrapper = emulated_format.then(StreamUnwrapper::default);
+        return Ok(proxy_stream(upstream_resp, upstream_decoder, output_encoder, accounting, unwrapper));
     }
 
     // Non-streaming: decode upstream response → IR → encode to output format
-    let resp_bytes = upstream_resp.bytes().await?;
-    let upstream_decoder = resolve_decoder(&state.registry, &upstream_slug).await?;
-    let ir_response = upstream_decoder.decode_response(&resp_bytes)?;
-
-    let output_encoder = resolve_encoder(&state.registry, &output_slug).await?;
-    let output_bytes = output_encoder.encode_response(&ir_response)?;
+    let resp_bytes = match upstream_resp.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let e = AppError::from(e);
+            log_unrelayed(&state.db, success_entry, start, &e, None).await;
+            return Err(e);
+        }
+    };
+    let converted = upstream_decoder.decode_response(&resp_bytes).and_then(|mut ir_response| {
+        if emulated_format {
+            structured_output::unwrap_response(&mut ir_response);
+        }
+        let output_bytes = output_encoder.encode_response(&ir_response)?;
+        Ok((ir_response, output_bytes))
+    });
+    let (ir_response, output_bytes) = match converted {
+        Ok(converted) => converted,
+        Err(e) => {
+            let upstream_body = String::from_utf8_lossy(&resp_bytes);
+            log_unrelayed(&state.db, success_entry, start, &e, Some(&upstream_body)).await;
+            return Err(e);
+        }
+    };
 
     // Log the request with token usage
     let latency = start.elapsed().as_millis() as i64;
     let prompt_tokens = ir_response.usage.as_ref().map(|u| u.prompt_tokens as i64);
     let completion_tokens = ir_response.usage.as_ref().map(|u| u.completion_tokens as i64);
+    let cache_read_tokens = ir_response.usage.as_ref().and_then(|u| u.cache_read_tokens).map(i64::from);
+    let cache_write_tokens = ir_response.usage.as_ref().and_then(|u| u.cache_write_tokens).map(i64::from);
     let resp_body_str = String::from_utf8_lossy(&output_bytes).to_string();
-    log_request(
-        &state.db, &token_id, &channel_id, &model, "chat",
-        &input_fmt_str, &output_fmt_str, Some(200),
-        latency, prompt_tokens, completion_tokens,
-        Some(&request_body_str), Some(&resp_body_str),
-    ).await;
-
-    // Update token quota usage
+    log_request(&state.db, RequestLogEntry {
+        latency_ms: latency,
+        prompt_tokens,
+        completion_tokens,
+        cache_read_tokens,
+        cache_write_tokens,
+        response_body: Some(&resp_body_str),
+        ..success_entry
+    }).await;
+
+    // Replace the quota reservation with the actual usage (the estimate
+    // stays charged when the upstream reports none)
     if let Some(pt) = prompt_tokens {
         if let Some(ct) = completion_tokens {
-            let total = pt + ct;
-            let _ = sqlx::query("UPDATE tokens SET quota_used = quota_used + ? WHERE id = ?")
-                .bind(total)
-                .bind(&token_id)
-                .execute(&state.db)
-                .await;
+            let charged = state.cache_pricing().charge(pt, ct, cache_read_tokens, cache_write_tokens);
+            reservation.settle(&state.db, charged).await;
+            if let Some(permit) = selected.permit.as_mut() {
+                permit.record_usage(pt + ct);
+            }
         }
     }
 
@@ -250,142 +401,549 @@ pub async fn proxy_chat(
         .unwrap())
 }
 
-/// Handle streaming proxy: pipe upstream SSE → decode → re-encode → downstream SSE.
-/// Accumulates the output chunks and updates the log entry's response_body when the stream ends.
-async fn proxy_stream(
-    upstream_resp: reqwest::Response,
-    upstream_slug: String,
-    output_slug: String,
-    registry: Arc<RuleRegistry>,
+/// Log a request whose upstream answered but whose response could not be
+/// relayed, with the error and the raw upstream body if it was read. The
+/// quota reservation is left in place, so the estimate stays charged.
+async fn log_unrelayed(
+    db: &SqlitePool,
+    entry: RequestLogEntry<'_>,
+    start: std::time::Instant,
+    error: &AppError,
+    upstream_body: Option<&str>,
+) {
+    let error = error.to_ir_error();
+    log_request(db, RequestLogEntry {
+        status: Some(i32::from(error.status)),
+        latency_ms: elapsed_ms(start),
+        response_body: upstream_body,
+        error_message: Some(&error.message),
+        ..entry
+    }).await;
+}
+
+/// State needed to finish bookkeeping once a streamed response ends.
+struct StreamAccounting {
     db: SqlitePool,
     log_id: String,
-) -> Result<Response, AppError> {
-    let upstream_decoder = resolve_decoder(&registry, &upstream_slug).await?;
-    let output_encoder = resolve_encoder(&registry, &output_slug).await?;
+    reservation: QuotaReservation,
+    /// The channel's rate-limit permit, held until the stream is dropped.
+    permit: Option<RateLimitPermit>,
+    /// Fallback prompt size if the upstream reports no usage.
+    estimated_prompt_tokens: i64,
+    /// Public model name, used to tokenize the output if the upstream reports no usage.
+    model: String,
+    pricing: CachePricing,
+    circuit: Arc<CircuitBreaker>,
+    channel_id: String,
+}
+
+/// Why a stream stopped before the upstream finished it.
+enum StreamFailure {
+    /// The upstream broke off, sent an error event or sent something that
+    /// could not be decoded. Counts against the channel's circuit.
+    Upstream(IrError),
+    /// The response could not be encoded for the client.
+    Encode(IrError),
+    /// The client went away; the upstream request is dropped with the stream.
+    Disconnected,
+}
+
+/// Status recorded for streams the client abandoned (nginx's "client closed request").
+const CLIENT_CLOSED_STATUS: i32 = 499;
+
+/// Collects the output of a stream and settles it when dropped. The stream
+/// is dropped both when it ends and when the client disconnects, so partial
+/// usage is recorded either way.
+struct StreamRecorder {
+    accounting: Option<StreamAccounting>,
+    usage: StreamUsage,
+    chunks: Vec<String>,
+    failure: Option<StreamFailure>,
+    /// Set once the upstream stream is fully consumed or has failed.
+    finished: bool,
+}
+
+impl StreamRecorder {
+    fn fail(&mut self, failure: StreamFailure) {
+        self.failure = Some(failure);
+        self.finished = true;
+    }
+}
+
+impl Drop for StreamRecorder {
+    fn drop(&mut self) {
+        let Some(accounting) = self.accounting.take() else {
+            return;
+        };
+        let failure = match self.failure.take() {
+            Some(failure) => Some(failure),
+            None if !self.finished => Some(StreamFailure::Disconnected),
+            None => None,
+        };
+        let usage = std::mem::take(&mut self.usage);
+        let chunks = std::mem::take(&mut self.chunks);
+        tokio::spawn(accounting.settle(usage, chunks, failure));
+    }
+}
+
+impl StreamAccounting {
+    /// Update the log entry with the response body, token counts and any
+    /// failure, and charge the usage to the token.
+    async fn settle(mut self, usage: StreamUsage, chunks: Vec<String>, failure: Option<StreamFailure>) {
+        let totals = usage.finish(self.estimated_prompt_tokens, &self.model);
+        if totals.estimated {
+            log::debug!("No upstream usage reported for {}, using local estimate", self.log_id);
+        }
+        let (status, error_message) = match &failure {
+            Some(StreamFailure::Upstream(error) | StreamFailure::Encode(error)) => {
+                (Some(i32::from(error.status)), Some(error.message.clone()))
+            }
+            Some(StreamFailure::Disconnected) => {
+                (Some(CLIENT_CLOSED_STATUS), Some("Client disconnected".to_string()))
+            }
+            None => (None, None),
+        };
+        if let Some(StreamFailure::Upstream(_)) = failure {
+            self.circuit.record_failure(&self.channel_id);
+        }
 
+        let response_body = (!chunks.is_empty()).then(|| format!("[{}]", chunks.join(",")));
+        let _ = sqlx::query(
+            "UPDATE request_logs SET response_body = ?, prompt_tokens = ?, completion_tokens = ?, cache_read_tokens = ?, cache_write_tokens = ?, status = COALESCE(?, status), error_message = ? WHERE id = ?"
+        )
+            .bind(&response_body)
+            .bind(totals.prompt_tokens)
+            .bind(totals.completion_tokens)
+            .bind(totals.cache_read_tokens)
+            .bind(totals.cache_write_tokens)
+            .bind(status)
+            .bind(&error_message)
+            .bind(&self.log_id)
+            .execute(&self.db)
+            .await;
+
+        // Charge the token and correct the channel's TPM budget
+        let total_tokens = totals.prompt_tokens + totals.completion_tokens;
+        let charged = self.pricing.charge(
+            totals.prompt_tokens,
+            totals.completion_tokens,
+            totals.cache_read_tokens,
+            totals.cache_write_tokens,
+        );
+        self.reservation.settle(&self.db, charged).await;
+        if let Some(permit) = self.permit.as_mut() {
+            permit.record_usage(total_tokens);
+        }
+    }
+}
+
+/// Handle streaming proxy: pipe upstream SSE → decode → re-encode → downstream SSE.
+/// Accumulates the output chunks and usage, which are settled when the stream
+/// ends or the client disconnects. Failures after the first byte end the
+/// stream with an error event in the output format.
+fn proxy_stream(
+    upstream_resp: reqwest::Response,
+    upstream_decoder: Box<dyn chat::Decoder>,
+    output_encoder: Box<dyn chat::Encoder>,
+    accounting: StreamAccounting,
+    mut unwrapper: Option<StreamUnwrapper>,
+) -> Response {
     let byte_stream = upstream_resp.bytes_stream();
 
+    // Created outside the generator so it is settled even if the body is
+    // dropped before it is first polled
+    let recorder = StreamRecorder {
+        accounting: Some(accounting),
+        usage: StreamUsage::default(),
+        chunks: Vec::new(),
+        failure: None,
+        finished: false,
+    };
+
     let sse_stream = async_stream::stream! {
-        let mut buffer = String::new();
+        let mut recorder = recorder;
+        let mut sse = SseDecoder::new();
         let mut byte_stream = Box::pin(byte_stream);
-        let mut response_chunks: Vec<String> = Vec::new();
 
-        while let Some(chunk_result) = byte_stream.next().await {
-            let chunk = match chunk_result {
-                Ok(c) => c,
-                Err(e) => {
+        'read: loop {
+            let events = match byte_stream.next().await {
+                Some(Ok(chunk)) => sse.feed(&chunk),
+                Some(Err(e)) => {
                     log::error!("Upstream stream error: {}", e);
+                    let error = IrError::new(502, format!("Upstream stream interrupted: {}", e));
+                    yield Ok::<_, std::convert::Infallible>(output_encoder.encode_stream_error(&error).encode());
+                    recorder.fail(StreamFailure::Upstream(error));
                     break;
                 }
+                // Dispatch an event left unterminated at the end of the stream
+                None => match sse.finish() {
+                    Some(event) => vec![event],
+                    None => break,
+                },
             };
 
-            buffer.push_str(&String::from_utf8_lossy(&chunk));
-
-            // Process complete SSE lines
-            while let Some(pos) = buffer.find("\n\n") {
-                let event_block = buffer[..pos].to_string();
-                buffer = buffer[pos + 2..].to_string();
-
-                for line in event_block.lines() {
-                    let data = if let Some(d) = line.strip_prefix("data: ") {
-                        d.trim()
-                    } else if let Some(d) = line.strip_prefix("data:") {
-                        d.trim()
-                    } else {
-                        continue;
-                    };
-
-                    if upstream_decoder.is_stream_done(data) {
-                        // Send output format's done signal
-                        if let Some(done) = output_encoder.stream_done_signal() {
-                            yield Ok::<_, std::convert::Infallible>(
-                                format!("data: {}\n\n", done)
-                            );
-                        }
-                        break;
+            for event in events {
+                let data = event.data.trim();
+
+                if upstream_decoder.is_stream_done(data) {
+                    // Send output format's done signal
+                    if let Some(done) = output_encoder.stream_done_signal() {
+                        yield Ok(done.encode());
                     }
+                    break 'read;
+                }
 
-                    match upstream_decoder.decode_stream_chunk(data) {
-                        Ok(Some(ir_chunk)) => {
-                            match output_encoder.encode_stream_chunk(&ir_chunk) {
-                                Ok(Some(encoded)) => {
-                                    response_chunks.push(encoded.clone());
-                                    yield Ok(format!("data: {}\n\n", encoded));
-                                }
-                                Ok(None) => {}
-                                Err(e) => {
-                                    log::error!("Encode stream chunk error: {}", e);
-                                }
-                            }
-                        }
-                        Ok(None) => {}
-                        Err(e) => {
-                            log::error!("Decode stream chunk error: {}", e);
+                // Relay upstream error events in the output format
+                if let Some(error) = chat::decode_stream_error(data) {
+                    log::error!("Upstream stream error: {}", error.message);
+                    yield Ok(output_encoder.encode_stream_error(&error).encode());
+                    recorder.fail(StreamFailure::Upstream(error));
+                    break 'read;
+                }
+
+                let mut ir_chunk = match upstream_decoder.decode_stream_chunk(event.event_name(), data) {
+                    Ok(Some(ir_chunk)) => ir_chunk,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::error!("Decode stream chunk error: {}", e);
+                        let error = IrError::new(502, format!("Invalid upstream stream chunk: {}", e));
+                        yield Ok(output_encoder.encode_stream_error(&error).encode());
+                        recorder.fail(StreamFailure::Upstream(error));
+                        break 'read;
+                    }
+                };
+                if let Some(unwrapper) = unwrapper.as_mut() {
+                    unwrapper.apply(&mut ir_chunk);
+                }
+                recorder.usage.observe(&ir_chunk);
+                match output_encoder.encode_stream_chunk(&ir_chunk) {
+                    Ok(encoded) => {
+                        for event in encoded {
+                            yield Ok(event.encode());
+                            recorder.chunks.push(event.data);
                         }
                     }
+                    Err(e) => {
+                        log::error!("Encode stream chunk error: {}", e);
+                        let error = IrError::new(500, e.to_string());
+                        yield Ok(output_encoder.encode_stream_error(&error).encode());
+                        recorder.fail(StreamFailure::Encode(error));
+                        break 'read;
+                    }
                 }
             }
         }
 
-        // Stream finished — update the log with accumulated response body
-        if !response_chunks.is_empty() {
-            let response_body = format!("[{}]", response_chunks.join(","));
-            let _ = sqlx::query("UPDATE request_logs SET response_body = ? WHERE id = ?")
-                .bind(&response_body)
-                .bind(&log_id)
-                .execute(&db)
-                .await;
-        }
+        // Settled when the recorder is dropped right after this
+        recorder.finished = true;
     };
 
     let body = Body::from_stream(sse_stream);
 
-    Ok(Response::builder()
+    Response::builder()
         .status(StatusCode::OK)
         .header("Content-Type", "text/event-stream")
         .header("Cache-Control", "no-cache")
         .header("Connection", "keep-alive")
         .body(body)
-        .unwrap())
+        .unwrap()
 }
 
-/// Log a request to the request_logs table (fire-and-forget, errors are only logged).
-/// Returns the generated log ID.
-#[allow(clippy::too_many_arguments)]
-async fn log_request(
-    db: &SqlitePool,
-    token_id: &str,
-    channel_id: &str,
+/// Where an upstream attempt goes: the selected channel's provider, endpoint,
+/// mapped model name and API key.
+pub(super) struct UpstreamTarget {
+    pub provider: String,
+    pub base_url: String,
+    pub model: String,
+    pub api_key: String,
+}
+
+/// Context for logging the attempts of one client request.
+#[derive(Clone, Copy)]
+pub(super) struct AttemptLog<'a> {
+    /// Fields shared by every row of this request.
+    pub base: RequestLogEntry<'a>,
+    /// ID of the request row; retried attempts become its children.
+    pub request_id: &'a str,
+    pub start: std::time::Instant,
+}
+
+/// A successful upstream response and the channel that produced it.
+pub(super) struct UpstreamSuccess {
+    pub selected: SelectedChannel,
+    pub response: reqwest::Response,
+    /// Number of attempts made, including the successful one.
+    pub attempt: u32,
+}
+
+/// Select a channel, build the upstream request for it via `build_request` and
+/// send it. Retryable failures (see [`RetryPolicy`]) fail over to the next
+/// candidate channel. Failed attempts that were retried are logged as child rows
+/// of the request; a final failure, including a request that `build_request`
+/// cannot encode, is logged as the request row itself.
+pub(super) async fn send_with_failover<F, Fut>(
+    state: &ProxyState,
     model: &str,
-    modality: &str,
-    input_format: &str,
-    output_format: &str,
-    status: Option<i32>,
+    cost: i64,
+    log: AttemptLog<'_>,
+    mut build_request: F,
+) -> Result<UpstreamSuccess, AppError>
+where
+    F: FnMut(UpstreamTarget) -> Fut,
+    Fut: std::future::Future<Output = Result<(String, reqwest::RequestBuilder), AppError>>,
+{
+    // Snapshot the policy so one request fails over under consistent settings
+    let retry = state.retry.read().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut tried: Vec<String> = Vec::new();
+    let mut pending: Option<FailedAttempt> = None;
+    let mut attempt: u32 = 0;
+
+    loop {
+        attempt += 1;
+        let attempt_start = std::time::Instant::now();
+
+        // Select channel and key via routing (priority + weighted random + circuit breaker + rate limit)
+        let selected = match balancer::select_channel(
+            model, log.base.modality, &state.db, &state.circuit, &state.keys, &state.limiter, &tried, cost,
+        ).await {
+            Ok(s) => s,
+            Err(e) => match pending.take() {
+                // No other candidate left — the previous failure is the final outcome
+                Some(failed) => {
+                    failed.log(&state.db, log.base, Some(log.request_id), None, attempt - 1, elapsed_ms(log.start)).await;
+                    return Err(failed.error);
+                }
+                None => return Err(e),
+            },
+        };
+
+        if let Some(failed) = pending.take() {
+            failed.log(&state.db, log.base, None, Some(log.request_id), attempt - 1, failed.latency_ms).await;
+        }
+
+        // Encode for the channel's provider and build the request
+        let built = build_request(UpstreamTarget {
+            provider: selected.channel.provider.clone(),
+            base_url: selected.channel.base_url.clone(),
+            model: selected.mapping.actual_name.clone(),
+            api_key: selected.api_key.clone(),
+        }).await;
+        let (upstream_url, req_builder) = match built {
+            Ok(built) => built,
+            // Nothing was sent, so the channel is not at fault; the request
+            // itself cannot be encoded and this is the final outcome
+            Err(error) => {
+                let failed = FailedAttempt {
+                    channel_id: selected.channel.id.clone(),
+                    output_format: selected.channel.provider.clone(),
+                    status: None,
+                    error_body: error.to_string(),
+                    error,
+                    latency_ms: elapsed_ms(attempt_start),
+                };
+                failed.log(&state.db, log.base, Some(log.request_id), None, attempt, elapsed_ms(log.start)).await;
+                return Err(failed.error);
+            }
+        };
+
+        // Send request
+        let (status, error_body, retryable, error) = match req_builder.send().await {
+            Ok(r) if r.status().is_success() => {
+                state.circuit.record_success(&selected.channel.id);
+                return Ok(UpstreamSuccess { selected, response: r, attempt });
+            }
+            Ok(r) => {
+                let status = r.status().as_u16();
+                match status {
+                    401 | 403 => state.keys.bench(&selected.api_key_id),
+                    429 => {
+                        let retry_after = r.headers()
+                            .get("retry-after")
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(keys::parse_retry_after);
+                        state.keys.park(&selected.api_key_id, retry_after);
+                    }
+                    _ => {}
+                }
+                let error_body = r.text().await.unwrap_or_default();
+                let retryable = retry.is_retryable_status(status);
+                let error = AppError::Upstream { status, body: error_body.clone() };
+                (Some(status), error_body, retryable, error)
+            }
+            Err(e) => (None, e.to_string(), true, AppError::HttpClient(e)),
+        };
+
+        // A request the upstream rejects as malformed says nothing about the
+        // channel's health, so only retryable failures count toward the breaker
+        if retryable {
+            state.circuit.record_failure(&selected.channel.id);
+        }
+        let failed = FailedAttempt {
+            channel_id: selected.channel.id.clone(),
+            output_format: selected.channel.provider.clone(),
+            status,
+            error_body,
+            error,
+            latency_ms: elapsed_ms(attempt_start),
+        };
+
+        // Give up on non-retryable errors or once attempts are exhausted
+        if !retryable || !retry.has_attempts_left(attempt) {
+            failed.log(&state.db, log.base, Some(log.request_id), None, attempt, elapsed_ms(log.start)).await;
+            return Err(failed.error);
+        }
+
+        log::warn!(
+            "Upstream attempt {} to {} failed ({}), failing over",
+            attempt,
+            upstream_url,
+            status.map(|s| s.to_string()).unwrap_or_else(|| "connection error".into()),
+        );
+        tried.push(selected.channel.id);
+        pending = Some(failed);
+        tokio::time::sleep(retry.backoff(attempt)).await;
+    }
+}
+
+/// A failed upstream attempt, kept until it is known whether it was retried
+/// (logged as a child row) or is the final outcome (logged as the request row).
+struct FailedAttempt {
+    channel_id: String,
+    output_format: String,
+    status: Option<u16>,
+    error_body: String,
+    error: AppError,
+    /// Duration of this attempt alone.
     latency_ms: i64,
-    prompt_tokens: Option<i64>,
-    completion_tokens: Option<i64>,
-    request_body: Option<&str>,
-    response_body: Option<&str>,
-) -> String {
-    let id = uuid::Uuid::new_v4().to_string();
+}
+
+impl FailedAttempt {
+    async fn log(
+        &self,
+        db: &SqlitePool,
+        base: RequestLogEntry<'_>,
+        id: Option<&str>,
+        parent_id: Option<&str>,
+        attempt: u32,
+        latency_ms: i64,
+    ) {
+        let error_message = self.error.to_ir_error().message;
+        log_request(db, RequestLogEntry {
+            id,
+            parent_id,
+            attempt,
+            channel_id: &self.channel_id,
+            output_format: &self.output_format,
+            status: self.status.map(i32::from),
+            latency_ms,
+            response_body: Some(&self.error_body),
+            error_message: Some(&error_message),
+            ..base
+        }).await;
+    }
+}
+
+/// Inline payloads (base64 images or audio, data URLs) longer than this are
+/// cut before a body is logged.
+const MAX_LOGGED_PAYLOAD_LEN: usize = 256;
+
+/// Prepare a JSON body for `request_logs`, shortening inline base64 payloads
+/// so generated images don't balloon the database. Non-JSON bodies are kept
+/// as text.
+pub(super) fn truncate_log_payloads(body: &[u8]) -> String {
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(mut value) => {
+            truncate_payload_strings(&mut value);
+            value.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).to_string(),
+    }
+}
+
+fn truncate_payload_strings(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) if s.len() > MAX_LOGGED_PAYLOAD_LEN && is_payload(s) => {
+            let omitted = s.len() - MAX_LOGGED_PAYLOAD_LEN;
+            // Payloads are ASCII, so any offset is a char boundary
+            s.truncate(MAX_LOGGED_PAYLOAD_LEN);
+            s.push_str(&format!("...[{} bytes truncated]", omitted));
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(truncate_payload_strings),
+        serde_json::Value::Object(map) => map.values_mut().for_each(truncate_payload_strings),
+        _ => {}
+    }
+}
+
+/// True for data URLs and base64 text, as opposed to prose.
+fn is_payload(s: &str) -> bool {
+    s.is_ascii() && s.starts_with("data:")
+        || s.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=' | b'-' | b'_'))
+}
+
+pub(super) fn elapsed_ms(since: std::time::Instant) -> i64 {
+    since.elapsed().as_millis() as i64
+}
+
+/// A row to be written to the request_logs table.
+#[derive(Clone, Copy, Default)]
+pub(super) struct RequestLogEntry<'a> {
+    /// Explicit row ID; a new one is generated when `None`.
+    pub id: Option<&'a str>,
+    /// ID of the request row this failover attempt belongs to.
+    pub parent_id: Option<&'a str>,
+    pub attempt: u32,
+    pub token_id: &'a str,
+    pub channel_id: &'a str,
+    pub model: &'a str,
+    pub modality: &'a str,
+    pub input_format: &'a str,
+    pub output_format: &'a str,
+    /// Whether the client asked for a stream.
+    pub stream: bool,
+    pub status: Option<i32>,
+    pub latency_ms: i64,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    /// Parts of `prompt_tokens` read from / written to the prompt cache.
+    pub cache_read_tokens: Option<i64>,
+    pub cache_write_tokens: Option<i64>,
+    pub request_body: Option<&'a str>,
+    pub response_body: Option<&'a str>,
+    /// Why the request failed, if it did.
+    pub error_message: Option<&'a str>,
+}
+
+/// Log a request to the request_logs table (fire-and-forget, errors are only logged).
+/// Returns the log ID.
+pub(super) async fn log_request(db: &SqlitePool, entry: RequestLogEntry<'_>) -> String {
+    let id = entry
+        .id
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     let now = chrono::Utc::now().to_rfc3339();
     let result = sqlx::query(
-        "INSERT INTO request_logs (id, token_id, channel_id, model, modality, input_format, output_format, status, latency_ms, prompt_tokens, completion_tokens, request_body, response_body, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT INTO request_logs (id, token_id, channel_id, model, modality, input_format, output_format, status, latency_ms, prompt_tokens, completion_tokens, cache_read_tokens, cache_write_tokens, request_body, response_body, error_message, created_at, parent_id, attempt, stream) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&id)
-    .bind(token_id)
-    .bind(channel_id)
-    .bind(model)
-    .bind(modality)
-    .bind(input_format)
-    .bind(output_format)
-    .bind(status)
-    .bind(latency_ms)
-    .bind(prompt_tokens)
-    .bind(completion_tokens)
-    .bind(request_body)
-    .bind(response_body)
+    .bind(entry.token_id)
+    .bind(entry.channel_id)
+    .bind(entry.model)
+    .bind(entry.modality)
+    .bind(entry.input_format)
+    .bind(entry.output_format)
+    .bind(entry.status)
+    .bind(entry.latency_ms)
+    .bind(entry.prompt_tokens)
+    .bind(entry.completion_tokens)
+    .bind(entry.cache_read_tokens)
+    .bind(entry.cache_write_tokens)
+    .bind(entry.request_body)
+    .bind(entry.response_body)
+    .bind(entry.error_message)
     .bind(&now)
+    .bind(entry.parent_id)
+    .bind(entry.attempt.max(1) as i64)
+    .bind(entry.stream)
     .execute(db)
     .await;
 
@@ -435,3 +993,172 @@ fn apply_auth(
         ChatFormat::Gemini => builder.header("x-goog-api-key", api_key),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use axum::routing::post;
+    use axum::Router;
+    use serde_json::{json, Value};
+    use sqlx::sqlite::SqlitePoolOptions;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Request bodies the healthy channel received.
+    type Received = Arc<Mutex<Vec<Value>>>;
+
+    /// A local upstream serving two channels: `/a` speaks OpenAI Chat and
+    /// always fails with `status`, `/b` speaks Anthropic and succeeds.
+    async fn mock_upstream(status: StatusCode) -> (String, Received) {
+        let received = Received::default();
+        let recorded = received.clone();
+        let app = Router::new()
+            .route(
+                "/a/v1/chat/completions",
+                post(move || async move { (status, Json(json!({"error": {"message": "channel a says no"}}))) }),
+            )
+            .route(
+                "/b/v1/messages",
+                post(move |Json(body): Json<Value>| async move {
+                    recorded.lock().unwrap().push(body);
+                    Json(json!({
+                        "id": "msg_1",
+                        "type": "message",
+                        "role": "assistant",
+                        "model": "b-model",
+                        "content": [{"type": "text", "text": "from b"}],
+                        "stop_reason": "end_turn",
+                        "usage": {"input_tokens": 5, "output_tokens": 2}
+                    }))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), received)
+    }
+
+    /// Proxy state over an in-memory database where model `m` maps to
+    /// channel `a` (OpenAI Chat, priority 0) and channel `b` (Anthropic,
+    /// priority 1). A single failure opens a channel's circuit.
+    async fn proxy_state(upstream: &str) -> ProxyState {
+        let db = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        sqlx::query("INSERT INTO tokens (id, key_value) VALUES ('t', 'sk-test')")
+            .execute(&db)
+            .await
+            .unwrap();
+        for (id, provider, priority) in [("a", "openai-chat", 0), ("b", "anthropic", 1)] {
+            sqlx::query("INSERT INTO channels (id, name, provider, base_url, priority) VALUES (?1, ?1, ?2, ?3, ?4)")
+                .bind(id)
+                .bind(provider)
+                .bind(format!("{upstream}/{id}"))
+                .bind(priority)
+                .execute(&db)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO channel_api_keys (id, channel_id, key_value) VALUES (?1, ?1, ?1)")
+                .bind(id)
+                .execute(&db)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO model_mappings (id, public_name, channel_id, actual_name) VALUES (?1, 'm', ?1, ?2)")
+                .bind(id)
+                .bind(format!("{id}-model"))
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            retryable_statuses: vec![503],
+            backoff_base: Duration::ZERO,
+            backoff_max: Duration::ZERO,
+        };
+        ProxyState {
+            db,
+            http_client: reqwest::Client::new(),
+            circuit: Arc::new(CircuitBreaker::new(1, 60)),
+            keys: Arc::new(KeyScheduler::new()),
+            limiter: Arc::new(RateLimiter::new()),
+            registry: Arc::new(RuleRegistry::new()),
+            retry: Arc::new(std::sync::RwLock::new(retry)),
+            pricing: Arc::new(std::sync::RwLock::new(CachePricing::from_config(&AppConfig::default()))),
+            images: Arc::new(ImageInliner::new()),
+        }
+    }
+
+    async fn send_chat(state: &ProxyState) -> Response {
+        let body = json!({"model": "m", "messages": [{"role": "user", "content": "hi"}]});
+        proxy_chat(
+            State(state.clone()),
+            ApiKey("sk-test".into()),
+            HeaderMap::new(),
+            "openai-chat",
+            InboundChat::default(),
+            Bytes::from(body.to_string()),
+        )
+        .await
+        .unwrap()
+    }
+
+    /// `(id, parent_id, attempt, channel_id, status)` of every logged row, oldest attempt first.
+    async fn logged_rows(db: &SqlitePool) -> Vec<(String, Option<String>, i64, String, i32)> {
+        sqlx::query_as("SELECT id, parent_id, attempt, channel_id, status FROM request_logs ORDER BY attempt")
+            .fetch_all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retryable_failure_fails_over_to_the_next_priority() {
+        let (upstream, received) = mock_upstream(StatusCode::SERVICE_UNAVAILABLE).await;
+        let state = proxy_state(&upstream).await;
+
+        let response = send_chat(&state).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["choices"][0]["message"]["content"], "from b");
+
+        // The request was encoded again for channel b's provider
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["model"], "b-model");
+        assert_eq!(received[0]["messages"][0]["role"], "user");
+        assert!(received[0].get("max_tokens").is_some());
+
+        let rows = logged_rows(&state.db).await;
+        assert_eq!(rows.len(), 2);
+        let (parent_id, child) = (&rows[1].0, &rows[0]);
+        assert_eq!(child.1.as_ref(), Some(parent_id));
+        assert_eq!((child.2, child.3.as_str(), child.4), (1, "a", 503));
+        assert_eq!((&rows[1].1, rows[1].2, rows[1].3.as_str(), rows[1].4), (&None, 2, "b", 200));
+
+        assert!(!state.circuit.is_available("a"));
+        assert!(state.circuit.is_available("b"));
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_failure_is_final() {
+        let (upstream, received) = mock_upstream(StatusCode::BAD_REQUEST).await;
+        let state = proxy_state(&upstream).await;
+
+        let response = send_chat(&state).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(received.lock().unwrap().is_empty());
+
+        let rows = logged_rows(&state.db).await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!((&rows[0].1, rows[0].2, rows[0].3.as_str(), rows[0].4), (&None, 1, "a", 400));
+
+        // A rejected request says nothing about the channel's health
+        assert!(state.circuit.is_available("a"));
+    }
+}
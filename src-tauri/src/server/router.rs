@@ -1,25 +1,43 @@
+use super::access::CachePricing;
+use super::audio;
+use super::embeddings;
 use super::generic_proxy::{self, GenericProxyState};
-use super::proxy::{self, ProxyState};
+use super::images;
+use super::media::ImageInliner;
+use super::middleware::ApiKey;
+use super::proxy::{self, InboundChat, ProxyState};
 use crate::error::AppError;
 use crate::rules::registry::RuleRegistry;
 use crate::routing::circuit::CircuitBreaker;
+use crate::routing::keys::KeyScheduler;
+use crate::routing::limiter::RateLimiter;
+use crate::routing::retry::RetryPolicy;
 use axum::body::{Body, Bytes};
-use axum::extract::{Query, State};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
 use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
-use axum::response::{Json, Response};
+use axum::response::{IntoResponse, Json, Response};
 use axum::routing::{get, post};
 use axum::Router;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::SqlitePool;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tower_http::cors::CorsLayer;
 
-pub async fn create_router(pool: SqlitePool) -> Router {
+/// Body size limit for endpoints that accept file uploads (the default is 2 MB).
+const UPLOAD_BODY_LIMIT: usize = 50 * 1024 * 1024;
+
+pub async fn create_router(
+    pool: SqlitePool,
+    registry: Arc<RuleRegistry>,
+    retry: Arc<RwLock<RetryPolicy>>,
+    pricing: Arc<RwLock<CachePricing>>,
+) -> Router {
     let http_client = reqwest::Client::new();
     let circuit = Arc::new(CircuitBreaker::new(5, 60));
-    let registry = Arc::new(RuleRegistry::new());
-    registry.load_from_db(&pool).await;
+    let keys = Arc::new(KeyScheduler::new());
+    let limiter = Arc::new(RateLimiter::new());
+    let images = Arc::new(ImageInliner::new());
 
     let generic_state = GenericProxyState {
         db: pool.clone(),
@@ -30,7 +48,12 @@ pub async fn create_router(pool: SqlitePool) -> Router {
         db: pool,
         http_client,
         circuit,
+        keys,
+        limiter,
         registry,
+        retry,
+        pricing,
+        images,
     };
 
     Router::new()
@@ -45,6 +68,31 @@ pub async fn create_router(pool: SqlitePool) -> Router {
         .route("/v1/responses", post(handle_openai_responses))
         // Anthropic Messages compatible endpoint
         .route("/v1/messages", post(handle_anthropic))
+        // Gemini compatible endpoints; the model and method are in the path
+        .route("/v1beta/models", get(list_gemini_models))
+        .route("/v1beta/models/{model_method}", get(get_gemini_model).post(handle_gemini))
+        // Local token counting
+        .route("/v1/messages/count_tokens", post(handle_anthropic_count_tokens))
+        .route("/v1/responses/input_tokens", post(handle_openai_responses_count_tokens))
+        .route("/v1/chat/completions/input_tokens", post(handle_openai_chat_count_tokens))
+        // OpenAI Embeddings compatible endpoint
+        .route("/v1/embeddings", post(embeddings::proxy_embeddings))
+        // OpenAI Images compatible endpoints
+        .route("/v1/images/generations", post(images::proxy_image_generation))
+        .route(
+            "/v1/images/edits",
+            post(images::proxy_image_edit).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)),
+        )
+        // OpenAI Audio compatible endpoints
+        .route(
+            "/v1/audio/transcriptions",
+            post(audio::proxy_transcription).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)),
+        )
+        .route(
+            "/v1/audio/translations",
+            post(audio::proxy_translation).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)),
+        )
+        .route("/v1/audio/speech", post(audio::proxy_speech))
         .layer(CorsLayer::permissive())
         .with_state(proxy_state)
         .fallback(
@@ -60,14 +108,16 @@ async fn health_check() -> Json<Value> {
     }))
 }
 
+async fn public_model_names(db: &SqlitePool) -> Result<Vec<String>, AppError> {
+    Ok(sqlx::query_scalar("SELECT DISTINCT public_name FROM model_mappings")
+        .fetch_all(db)
+        .await?)
+}
+
 async fn list_models(
     State(state): State<ProxyState>,
 ) -> Result<Json<Value>, AppError> {
-    let models: Vec<String> = sqlx::query_scalar(
-        "SELECT DISTINCT public_name FROM model_mappings",
-    )
-    .fetch_all(&state.db)
-    .await?;
+    let models = public_model_names(&state.db).await?;
 
     let model_list: Vec<Value> = models
         .iter()
@@ -86,28 +136,118 @@ async fn list_models(
     })))
 }
 
+fn gemini_model(name: &str) -> Value {
+    json!({
+        "name": format!("models/{}", name),
+        "baseModelId": name,
+        "displayName": name,
+        "supportedGenerationMethods": ["generateContent", "streamGenerateContent", "countTokens"],
+    })
+}
+
+async fn list_gemini_models(State(state): State<ProxyState>, api_key: ApiKey) -> Response {
+    if let Err(e) = proxy::authenticate(&state, &api_key).await {
+        return e.into_response_for("gemini");
+    }
+    match public_model_names(&state.db).await {
+        Ok(models) => {
+            let models: Vec<Value> = models.iter().map(|m| gemini_model(m)).collect();
+            Json(json!({ "models": models })).into_response()
+        }
+        Err(e) => e.into_response_for("gemini"),
+    }
+}
+
+async fn get_gemini_model(
+    State(state): State<ProxyState>,
+    api_key: ApiKey,
+    Path(model): Path<String>,
+) -> Response {
+    if let Err(e) = proxy::authenticate(&state, &api_key).await {
+        return e.into_response_for("gemini");
+    }
+    match public_model_names(&state.db).await {
+        Ok(models) if models.contains(&model) => Json(gemini_model(&model)).into_response(),
+        Ok(_) => AppError::NoChannel(model).into_response_for("gemini"),
+        Err(e) => e.into_response_for("gemini"),
+    }
+}
+
+/// `POST /v1beta/models/{model}:{method}`. Streams are always sent as SSE,
+/// the `alt=sse` form Gemini SDKs request.
+async fn handle_gemini(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    headers: HeaderMap,
+    Path(model_method): Path<String>,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let Some((model, method)) = model_method.rsplit_once(':') else {
+        return Ok(AppError::BadRequest(format!("Expected models/{{model}}:{{method}}, got models/{}", model_method))
+            .into_response_for("gemini"));
+    };
+    let inbound = InboundChat {
+        model: Some(model.to_string()),
+        stream: Some(method == "streamGenerateContent"),
+    };
+    match method {
+        "generateContent" | "streamGenerateContent" => {
+            proxy::proxy_chat(state, api_key, headers, "gemini", inbound, body).await
+        }
+        "countTokens" => proxy::count_tokens(state, api_key, "gemini", inbound, body).await,
+        _ => Ok(AppError::BadRequest(format!("Unsupported method: {}", method)).into_response_for("gemini")),
+    }
+}
+
 async fn handle_openai_chat(
     state: State<ProxyState>,
+    api_key: ApiKey,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Response, AppError> {
-    proxy::proxy_chat(state, headers, "openai-chat", body).await
+    proxy::proxy_chat(state, api_key, headers, "openai-chat", InboundChat::default(), body).await
 }
 
 async fn handle_openai_responses(
     state: State<ProxyState>,
+    api_key: ApiKey,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Response, AppError> {
-    proxy::proxy_chat(state, headers, "openai-responses", body).await
+    proxy::proxy_chat(state, api_key, headers, "openai-responses", InboundChat::default(), body).await
 }
 
 async fn handle_anthropic(
     state: State<ProxyState>,
+    api_key: ApiKey,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Response, AppError> {
-    proxy::proxy_chat(state, headers, "anthropic", body).await
+    proxy::proxy_chat(state, api_key, headers, "anthropic", InboundChat::default(), body).await
+}
+
+async fn handle_anthropic_count_tokens(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    proxy::count_tokens(state, api_key, "anthropic", InboundChat::default(), body).await
+}
+
+async fn handle_openai_responses_count_tokens(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    proxy::count_tokens(state, api_key, "openai-responses", InboundChat::default(), body).await
+}
+
+async fn handle_openai_chat_count_tokens(
+    state: State<ProxyState>,
+    api_key: ApiKey,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    proxy::count_tokens(state, api_key, "openai-chat", InboundChat::default(), body).await
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,126 @@
+use crate::modality::chat::ir::IrStreamChunk;
+use crate::tokenizer;
+
+/// Token usage of a finished streamed response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamUsageTotals {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// Parts of `prompt_tokens` read from / written to the prompt cache.
+    pub cache_read_tokens: Option<i64>,
+    pub cache_write_tokens: Option<i64>,
+    /// True if the upstream reported no usage and the totals are local estimates.
+    pub estimated: bool,
+}
+
+/// Collects usage while a response streams.
+///
+/// Providers report usage differently (OpenAI in a final chunk, Anthropic split
+/// across `message_start` and `message_delta`, Gemini cumulatively on every
+/// chunk), so each field keeps the largest value seen. The generated text is
+/// tracked as well for a local estimate when no usage is reported at all.
+#[derive(Debug, Default)]
+pub struct StreamUsage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+    cache_read_tokens: Option<u32>,
+    cache_write_tokens: Option<u32>,
+    completion_text: String,
+}
+
+impl StreamUsage {
+    pub fn observe(&mut self, chunk: &IrStreamChunk) {
+        if let Some(usage) = &chunk.usage {
+            self.prompt_tokens = self.prompt_tokens.max(Some(usage.prompt_tokens));
+            self.completion_tokens = self.completion_tokens.max(Some(usage.completion_tokens));
+            self.cache_read_tokens = self.cache_read_tokens.max(usage.cache_read_tokens);
+            self.cache_write_tokens = self.cache_write_tokens.max(usage.cache_write_tokens);
+        }
+        if let Some(text) = &chunk.delta_content {
+            self.completion_text.push_str(text);
+        }
+        if let Some(text) = chunk.delta_reasoning.as_ref().and_then(|r| r.text.as_ref()) {
+            self.completion_text.push_str(text);
+        }
+        for call in chunk.delta_tool_calls.iter().flatten() {
+            if let Some(name) = &call.name {
+                self.completion_text.push_str(name);
+            }
+            if let Some(args) = &call.arguments {
+                self.completion_text.push_str(args);
+            }
+        }
+    }
+
+    /// Final totals; missing values fall back to local counts, using
+    /// `estimated_prompt_tokens` for the prompt side and the generated text
+    /// tokenized for `model` for the completion side.
+    pub fn finish(&self, estimated_prompt_tokens: i64, model: &str) -> StreamUsageTotals {
+        let reported = self.prompt_tokens.is_some() || self.completion_tokens.is_some();
+        StreamUsageTotals {
+            prompt_tokens: match self.prompt_tokens {
+                Some(n) if n > 0 => n as i64,
+                _ => estimated_prompt_tokens,
+            },
+            completion_tokens: match self.completion_tokens {
+                Some(n) => n as i64,
+                None => tokenizer::count_text_tokens(model, &self.completion_text) as i64,
+            },
+            cache_read_tokens: self.cache_read_tokens.map(i64::from),
+            cache_write_tokens: self.cache_write_tokens.map(i64::from),
+            estimated: !reported,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modality::chat::ir::IrUsage;
+
+    fn chunk(content: Option<&str>, usage: Option<(u32, u32)>) -> IrStreamChunk {
+        IrStreamChunk {
+            id: String::new(),
+            model: None,
+            delta_role: None,
+            delta_content: content.map(str::to_string),
+            delta_tool_calls: None,
+            delta_reasoning: None,
+            finish_reason: None,
+            usage: usage.map(|(p, c)| IrUsage {
+                prompt_tokens: p,
+                completion_tokens: c,
+                total_tokens: None,
+                reasoning_tokens: None,
+                cache_read_tokens: None,
+                cache_write_tokens: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_stream_usage_merges_split_reports() {
+        // Anthropic: input tokens on message_start, output tokens on message_delta
+        let mut usage = StreamUsage::default();
+        usage.observe(&chunk(None, Some((25, 1))));
+        usage.observe(&chunk(Some("Hello"), None));
+        usage.observe(&chunk(None, Some((0, 12))));
+
+        let totals = usage.finish(99, "gpt-4o");
+        assert_eq!(totals.prompt_tokens, 25);
+        assert_eq!(totals.completion_tokens, 12);
+        assert!(!totals.estimated);
+    }
+
+    #[test]
+    fn test_stream_usage_falls_back_to_estimate() {
+        let mut usage = StreamUsage::default();
+        usage.observe(&chunk(Some("Hello, "), None));
+        usage.observe(&chunk(Some("world!"), None));
+
+        let totals = usage.finish(40, "gpt-4o");
+        assert_eq!(totals.prompt_tokens, 40);
+        assert_eq!(totals.completion_tokens, 4);
+        assert!(totals.estimated);
+    }
+}
@@ -0,0 +1,270 @@
+//! Incremental Server-Sent Events parsing and serialization, following the
+//! WHATWG event stream format.
+
+/// One dispatched event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type; `None` is the default `message` type.
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// An unnamed event carrying only data.
+    pub fn data(data: impl Into<String>) -> Self {
+        Self {
+            event: None,
+            data: data.into(),
+            id: None,
+        }
+    }
+
+    /// An event with an explicit type, e.g. Anthropic's `content_block_delta`.
+    pub fn named(event: impl Into<String>, data: impl Into<String>) -> Self {
+        Self {
+            event: Some(event.into()),
+            data: data.into(),
+            id: None,
+        }
+    }
+
+    /// The event type as a client sees it: `message` unless named.
+    pub fn event_name(&self) -> &str {
+        self.event.as_deref().unwrap_or("message")
+    }
+
+    /// Serialize the event, including the blank line that dispatches it.
+    /// Multi-line data is split over several `data:` fields.
+    pub fn encode(&self) -> String {
+        let mut out = String::with_capacity(self.data.len() + 16);
+        if let Some(event) = &self.event {
+            out.push_str("event: ");
+            out.push_str(event);
+            out.push('\n');
+        }
+        if let Some(id) = &self.id {
+            out.push_str("id: ");
+            out.push_str(id);
+            out.push('\n');
+        }
+        for line in split_lines(&self.data) {
+            out.push_str("data: ");
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Split on `\r\n`, `\r` or `\n`, the line endings the format allows.
+fn split_lines(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(s);
+    std::iter::from_fn(move || {
+        let s = rest?;
+        match s.find(['\r', '\n']) {
+            Some(pos) => {
+                let skip = if s[pos..].starts_with("\r\n") { 2 } else { 1 };
+                rest = Some(&s[pos + skip..]);
+                Some(&s[..pos])
+            }
+            None => {
+                rest = None;
+                Some(s)
+            }
+        }
+    })
+}
+
+/// Incremental event stream parser. Bytes may be fed in chunks split at any
+/// point, including inside a line ending or a multi-byte UTF-8 character;
+/// lines are only decoded once complete.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    /// Bytes of the current, not yet terminated line.
+    line: Vec<u8>,
+    /// The last byte fed was a CR, so a leading LF of the next chunk
+    /// belongs to the same line ending.
+    after_cr: bool,
+    /// Whether the first line (which may start with a BOM) was seen.
+    started: bool,
+    event: String,
+    data: String,
+    /// Set by the first `data` field of an event, which may be empty.
+    has_data: bool,
+    /// The last event ID, which carries over to later events.
+    last_id: Option<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a chunk of the stream and return the events it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        let mut rest = bytes;
+        if self.after_cr && !rest.is_empty() {
+            self.after_cr = false;
+            if let Some(stripped) = rest.strip_prefix(b"\n") {
+                rest = stripped;
+            }
+        }
+        while let Some(pos) = rest.iter().position(|&b| b == b'\r' || b == b'\n') {
+            self.line.extend_from_slice(&rest[..pos]);
+            let line = std::mem::take(&mut self.line);
+            events.extend(self.process_line(&line));
+
+            let ending = &rest[pos..];
+            rest = if ending.starts_with(b"\r\n") {
+                &ending[2..]
+            } else if ending == b"\r" {
+                // The LF may arrive with the next chunk
+                self.after_cr = true;
+                &ending[1..]
+            } else {
+                &ending[1..]
+            };
+        }
+        self.line.extend_from_slice(rest);
+        events
+    }
+
+    /// End of stream. The format discards an unterminated event, but many
+    /// servers omit the final blank line, so a pending event is still
+    /// dispatched.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() {
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        let mut line = String::from_utf8_lossy(line);
+        if !self.started {
+            self.started = true;
+            if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                line = stripped.to_string().into();
+            }
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment, e.g. a keep-alive
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            // `retry` only matters to reconnecting clients; unknown fields are ignored
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = std::mem::take(&mut self.event);
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(SseEvent {
+            event: (!event.is_empty()).then_some(event),
+            data: std::mem::take(&mut self.data),
+            id: self.last_id.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+        let mut decoder = SseDecoder::new();
+        let mut events: Vec<SseEvent> = chunks.iter().flat_map(|c| decoder.feed(c)).collect();
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn test_decodes_fields_comments_and_line_endings() {
+        let stream = "\u{feff}: keep-alive\r\nevent: content_block_delta\r\ndata: {\"a\":\r\ndata:1}\r\nid: 7\r\n\r\ndata\n\nevent: ping\n\ndata: last";
+        let events = decode_all(&[stream.as_bytes()]);
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("content_block_delta".into()),
+                    data: "{\"a\":\n1}".into(),
+                    id: Some("7".into()),
+                },
+                SseEvent { event: None, data: String::new(), id: Some("7".into()) },
+                SseEvent { event: None, data: "last".into(), id: Some("7".into()) },
+            ]
+        );
+    }
+
+    fn arb_event() -> impl Strategy<Value = SseEvent> {
+        (
+            proptest::option::of("[a-z_.]{1,20}"),
+            // Multi-line data with multi-byte characters
+            proptest::collection::vec("[a-z 你好世界😀{}\":,]{0,12}", 1..4),
+            proptest::option::of("[a-z0-9]{1,8}"),
+        )
+            .prop_map(|(event, lines, id)| SseEvent { event, data: lines.join("\n"), id })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trips_across_arbitrary_chunk_splits(
+            events in proptest::collection::vec(arb_event(), 0..8),
+            splits in proptest::collection::vec(any::<prop::sample::Index>(), 0..12),
+            line_ending in prop_oneof![Just("\n"), Just("\r\n"), Just("\r")],
+        ) {
+            let stream: String = events.iter().map(SseEvent::encode).collect();
+            let stream = stream.replace('\n', line_ending);
+            let bytes = stream.as_bytes();
+
+            let mut cuts: Vec<usize> = splits.iter().map(|i| i.index(bytes.len() + 1)).collect();
+            cuts.sort_unstable();
+            let mut chunks = Vec::new();
+            let mut start = 0;
+            for cut in cuts {
+                chunks.push(&bytes[start..cut]);
+                start = cut;
+            }
+            chunks.push(&bytes[start..]);
+
+            // IDs carry over to later events that don't set one
+            let mut last_id = None;
+            let expected: Vec<SseEvent> = events
+                .iter()
+                .map(|e| {
+                    last_id = e.id.clone().or(last_id.take());
+                    SseEvent { id: last_id.clone(), ..e.clone() }
+                })
+                .collect();
+            prop_assert_eq!(decode_all(&chunks), expected);
+        }
+    }
+}
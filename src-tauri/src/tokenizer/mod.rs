@@ -0,0 +1,127 @@
+use crate::modality::chat::ir::{IrChatRequest, IrContent, IrContentPart};
+use tiktoken_rs::CoreBPE;
+
+/// Fixed per-message overhead (role and delimiters) in the OpenAI chat format.
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens priming the assistant reply.
+const TOKENS_PER_REPLY: usize = 3;
+/// Flat estimate for an image part; actual cost depends on resolution.
+pub const TOKENS_PER_IMAGE: usize = 1_000;
+/// Flat estimate for an audio part; actual cost depends on duration.
+pub const TOKENS_PER_AUDIO: usize = 1_000;
+/// Flat estimate for a document or other file part; actual cost depends on its length.
+const TOKENS_PER_FILE: usize = 1_000;
+/// Fixed overhead per tool definition.
+const TOKENS_PER_TOOL: usize = 8;
+
+/// Embedded BPE vocabulary used for local token counting. Counts are exact for
+/// OpenAI models and a close approximation for other providers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Cl100k,
+    O200k,
+}
+
+impl Encoding {
+    /// Pick the vocabulary for a model name. The o200k family covers the
+    /// GPT-4o / GPT-4.1 / GPT-5 and o-series models; everything else uses cl100k.
+    pub fn for_model(model: &str) -> Self {
+        let model = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+        let o200k_prefixes = ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "chatgpt-4o", "o1", "o3", "o4"];
+        if o200k_prefixes.iter().any(|p| model.starts_with(p)) {
+            Self::O200k
+        } else {
+            Self::Cl100k
+        }
+    }
+
+    fn bpe(self) -> &'static CoreBPE {
+        match self {
+            Self::Cl100k => tiktoken_rs::cl100k_base_singleton(),
+            Self::O200k => tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+
+    /// Number of tokens in a plain text.
+    pub fn count(self, text: &str) -> usize {
+        if text.is_empty() {
+            return 0;
+        }
+        self.bpe().encode_ordinary(text).len()
+    }
+}
+
+/// Count the tokens of a plain text for the given model.
+pub fn count_text_tokens(model: &str, text: &str) -> usize {
+    Encoding::for_model(model).count(text)
+}
+
+/// Count the input tokens of a chat request: system prompt, messages (text,
+/// images, tool calls and results), tool definitions and output schema.
+pub fn count_request_tokens(ir: &IrChatRequest) -> usize {
+    let enc = Encoding::for_model(&ir.model);
+    let mut total = TOKENS_PER_REPLY;
+
+    if let Some(system) = &ir.system {
+        total += TOKENS_PER_MESSAGE + enc.count(system);
+    }
+
+    for msg in &ir.messages {
+        total += TOKENS_PER_MESSAGE + count_content(enc, &msg.content);
+        if let Some(name) = &msg.name {
+            total += enc.count(name);
+        }
+        for call in msg.tool_calls.iter().flatten() {
+            total += enc.count(&call.name) + enc.count(&call.arguments);
+        }
+    }
+
+    for tool in ir.tools.iter().flatten() {
+        total += TOKENS_PER_TOOL + enc.count(&tool.name);
+        if let Some(desc) = &tool.description {
+            total += enc.count(desc);
+        }
+        total += enc.count(&tool.parameters.to_string());
+    }
+
+    if let Some(schema) = ir.response_format.as_ref().and_then(|f| f.json_schema()) {
+        total += enc.count(&schema.to_string());
+    }
+
+    total
+}
+
+fn count_content(enc: Encoding, content: &IrContent) -> usize {
+    match content {
+        IrContent::Text(text) => enc.count(text),
+        IrContent::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                IrContentPart::Text { text } => enc.count(text),
+                IrContentPart::Image { .. } => TOKENS_PER_IMAGE,
+                IrContentPart::Audio { .. } => TOKENS_PER_AUDIO,
+                IrContentPart::File { .. } => TOKENS_PER_FILE,
+            })
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(Encoding::for_model("gpt-4o-mini"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("openai/o3-mini"), Encoding::O200k);
+        assert_eq!(Encoding::for_model("gpt-4-turbo"), Encoding::Cl100k);
+        assert_eq!(Encoding::for_model("claude-sonnet-4"), Encoding::Cl100k);
+    }
+
+    #[test]
+    fn test_count_text() {
+        assert_eq!(Encoding::Cl100k.count("Hello, world!"), 4);
+        assert_eq!(Encoding::O200k.count("Hello, world!"), 4);
+        assert_eq!(Encoding::Cl100k.count(""), 0);
+    }
+}
@@ -87,6 +87,18 @@ export interface Translations {
     optional: string;
     required: string;
     systemRuleReadonly: string;
+    overlayHint: string;
+    ruleLimitExceeded: string;
+    fixtures: string;
+    fixturesHint: string;
+    runFixtures: string;
+    checkAgainstBuiltin: string;
+    noBuiltin: string;
+    saveBeforeRunning: string;
+    fixturesPassed: (passed: number, total: number) => string;
+    fixturesExpected: string;
+    fixturesActual: string;
+    testsFailed: (names: string) => string;
     confirmDelete: string;
     importSuccess: string;
     exportSuccess: string;
@@ -137,6 +149,12 @@ export interface Translations {
     lowerPriority: string;
     higherWeight: string;
     keyRotation: string;
+    keyStrategy: string;
+    keyStrategyRoundRobin: string;
+    keyStrategyLru: string;
+    keyStrategyRandom: string;
+    rateLimit: string;
+    rateLimitHint: string;
     saveChanges: string;
     createChannel: string;
     // API Keys dialog
@@ -243,6 +261,7 @@ export interface Translations {
     inputOutput: string;
     latency: string;
     tokensCol: string;
+    cacheTokens: string;
     viewDetails: string;
     loadingLogs: string;
     noLogs: string;
@@ -257,6 +276,7 @@ export interface Translations {
     requestBody: string;
     responseBody: string;
     logNotFound: string;
+    errorMessage: string;
     retry: string;
     retryFailed: string;
     streamingNoBody: string;
@@ -487,6 +507,20 @@ const en: Translations = {
     optional: "Optional",
     required: "Required",
     systemRuleReadonly: "System rules are read-only",
+    overlayHint:
+      "This rule uses the built-in codec. A template entered here post-processes the built-in output for that step; leave it empty to keep the built-in behavior.",
+    ruleLimitExceeded: "Execution limit exceeded",
+    fixtures: "Test Fixtures",
+    fixturesHint:
+      'JSON array of {"name", "step", "input", "expected"}. Decode steps map provider JSON to IR, encode steps map IR to provider JSON. An enabled rule that fails its fixtures cannot be saved.',
+    runFixtures: "Run Fixtures",
+    checkAgainstBuiltin: "Also check against built-in",
+    noBuiltin: "Rule fixtures only",
+    saveBeforeRunning: "Save the rule to run its fixtures",
+    fixturesPassed: (passed: number, total: number) => `${passed} of ${total} fixtures passed`,
+    fixturesExpected: "expected",
+    fixturesActual: "actual",
+    testsFailed: (names: string) => `The rule was saved but left disabled because these fixtures fail: ${names}`,
     confirmDelete: "Are you sure you want to delete this rule?",
     importSuccess: "Rule imported successfully",
     exportSuccess: "Rule exported successfully",
@@ -538,6 +572,12 @@ const en: Translations = {
     lowerPriority: "Lower value = higher priority",
     higherWeight: "Higher = more traffic share",
     keyRotation: "Key Rotation",
+    keyStrategy: "Rotation Strategy",
+    keyStrategyRoundRobin: "Round robin",
+    keyStrategyLru: "Least recently used",
+    keyStrategyRandom: "Random",
+    rateLimit: "Rate Limit",
+    rateLimitHint: "JSON with optional rpm, tpm and concurrency. Leave blank for no limit.",
     saveChanges: "Save Changes",
     createChannel: "Create Channel",
     apiKeys: "API Keys",
@@ -612,7 +652,7 @@ const en: Translations = {
     quotaPlaceholder: "Leave blank for unlimited",
     expiresAtLabel: "Expires At",
     allowedModelsLabel: "Allowed Models",
-    allowedModelsPlaceholder: "Comma-separated model names (leave blank for all)",
+    allowedModelsPlaceholder: "Comma-separated model names or patterns like gpt-4* (leave blank for all)",
     generate: "Generate",
     tokenCreated: "Token Created",
     tokenCreatedDesc: "Copy your API key now. This is the only time the full key will be shown.",
@@ -640,6 +680,7 @@ const en: Translations = {
     inputOutput: "Input / Output",
     latency: "Latency",
     tokensCol: "Tokens",
+    cacheTokens: "Cache Read / Write",
     viewDetails: "View Details",
     loadingLogs: "Loading logs...",
     noLogs: "No request logs found",
@@ -654,6 +695,7 @@ const en: Translations = {
     requestBody: "Request Body",
     responseBody: "Response Body",
     logNotFound: "Log not found.",
+    errorMessage: "Error",
     retry: "Retry",
     retryFailed: "Retry failed",
     streamingNoBody: "Response body is not captured for streaming requests.",
@@ -884,6 +926,19 @@ const zh: Translations = {
     optional: "可选",
     required: "必填",
     systemRuleReadonly: "系统规则不可编辑",
+    overlayHint: "此规则使用内置编解码器。在此填写的模板会对该步骤的内置输出进行后处理；留空则保持内置行为。",
+    ruleLimitExceeded: "超出执行限制",
+    fixtures: "测试用例",
+    fixturesHint:
+      'JSON 数组，元素为 {"name", "step", "input", "expected"}。解码步骤将提供商 JSON 转为 IR，编码步骤将 IR 转为提供商 JSON。未通过测试用例的规则无法以启用状态保存。',
+    runFixtures: "运行测试用例",
+    checkAgainstBuiltin: "同时对照内置编解码器",
+    noBuiltin: "仅规则自身用例",
+    saveBeforeRunning: "保存规则后才能运行测试用例",
+    fixturesPassed: (passed: number, total: number) => `${passed} / ${total} 个用例通过`,
+    fixturesExpected: "期望",
+    fixturesActual: "实际",
+    testsFailed: (names: string) => `以下测试用例未通过，规则已保存但未启用：${names}`,
     confirmDelete: "确定要删除此规则吗？",
     importSuccess: "规则导入成功",
     exportSuccess: "规则导出成功",
@@ -935,6 +990,12 @@ const zh: Translations = {
     lowerPriority: "数值越小优先级越高",
     higherWeight: "数值越大流量占比越高",
     keyRotation: "密钥轮询",
+    keyStrategy: "轮询策略",
+    keyStrategyRoundRobin: "顺序轮询",
+    keyStrategyLru: "最久未使用",
+    keyStrategyRandom: "随机",
+    rateLimit: "速率限制",
+    rateLimitHint: "JSON 格式，可选 rpm、tpm 和 concurrency 字段。留空表示不限制。",
     saveChanges: "保存更改",
     createChannel: "创建渠道",
     apiKeys: "API 密钥",
@@ -1009,7 +1070,7 @@ const zh: Translations = {
     quotaPlaceholder: "留空表示无限制",
     expiresAtLabel: "过期时间",
     allowedModelsLabel: "允许的模型",
-    allowedModelsPlaceholder: "逗号分隔的模型名称（留空表示全部）",
+    allowedModelsPlaceholder: "逗号分隔的模型名称或通配符，如 gpt-4*（留空表示全部）",
     generate: "生成",
     tokenCreated: "令牌已创建",
     tokenCreatedDesc: "请立即复制您的 API 密钥。这是唯一一次显示完整密钥的机会。",
@@ -1037,6 +1098,7 @@ const zh: Translations = {
     inputOutput: "输入 / 输出",
     latency: "延迟",
     tokensCol: "Token 数",
+    cacheTokens: "缓存读取 / 写入",
     viewDetails: "查看详情",
     loadingLogs: "正在加载日志...",
     noLogs: "未找到请求日志",
@@ -1051,6 +1113,7 @@ const zh: Translations = {
     requestBody: "请求体",
     responseBody: "响应体",
     logNotFound: "未找到日志。",
+    errorMessage: "错误",
     retry: "重试",
     retryFailed: "重试失败",
     streamingNoBody: "流式请求不会捕获响应体。",
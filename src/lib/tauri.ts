@@ -26,6 +26,14 @@ export interface PaginatedResult<T> {
 export interface AppConfig {
   server_port: number;
   log_retention_days: number;
+  retry_max_attempts: number;
+  retry_status_codes: number[];
+  retry_backoff_ms: number;
+  cache_read_price_ratio: number;
+  cache_write_price_ratio: number;
+  rule_timeout_ms: number;
+  rule_max_depth: number;
+  rule_max_output_bytes: number;
 }
 
 export interface ServerStatus {
@@ -36,6 +44,8 @@ export interface ServerStatus {
 
 // === Channel types ===
 
+export type KeyStrategy = "round_robin" | "least_recently_used" | "random";
+
 export interface Channel {
   id: string;
   name: string;
@@ -45,6 +55,7 @@ export interface Channel {
   weight: number;
   enabled: boolean;
   key_rotation: boolean;
+  key_strategy: KeyStrategy;
   rate_limit: string | null;
   test_url: string | null;
   test_headers: string | null;
@@ -98,9 +109,15 @@ export interface RequestLog {
   latency_ms: number | null;
   prompt_tokens: number | null;
   completion_tokens: number | null;
+  cache_read_tokens: number | null;
+  cache_write_tokens: number | null;
   request_body: string | null;
   response_body: string | null;
   created_at: string;
+  parent_id: string | null;
+  attempt: number;
+  error_message: string | null;
+  stream: boolean;
 }
 
 // === Usage Stats types ===
@@ -153,10 +170,26 @@ export async function getServerStatus(): Promise<ServerStatus> {
 export async function updateConfig(data: {
   server_port: number;
   log_retention_days: number;
+  retry_max_attempts?: number;
+  retry_status_codes?: number[];
+  retry_backoff_ms?: number;
+  cache_read_price_ratio?: number;
+  cache_write_price_ratio?: number;
+  rule_timeout_ms?: number;
+  rule_max_depth?: number;
+  rule_max_output_bytes?: number;
 }): Promise<AppConfig> {
   return invoke<AppConfig>("update_config", {
     serverPort: data.server_port,
     logRetentionDays: data.log_retention_days,
+    retryMaxAttempts: data.retry_max_attempts,
+    retryStatusCodes: data.retry_status_codes,
+    retryBackoffMs: data.retry_backoff_ms,
+    cacheReadPriceRatio: data.cache_read_price_ratio,
+    cacheWritePriceRatio: data.cache_write_price_ratio,
+    ruleTimeoutMs: data.rule_timeout_ms,
+    ruleMaxDepth: data.rule_max_depth,
+    ruleMaxOutputBytes: data.rule_max_output_bytes,
   });
 }
 
@@ -172,6 +205,8 @@ export async function createChannel(data: {
   base_url: string;
   priority: number;
   weight: number;
+  key_strategy?: KeyStrategy;
+  rate_limit?: string;
 }): Promise<Channel> {
   return invoke<Channel>("create_channel", {
     name: data.name,
@@ -179,6 +214,8 @@ export async function createChannel(data: {
     baseUrl: data.base_url,
     priority: data.priority,
     weight: data.weight,
+    keyStrategy: data.key_strategy,
+    rateLimit: data.rate_limit,
   });
 }
 
@@ -191,6 +228,9 @@ export async function updateChannel(data: {
   weight: number;
   enabled: boolean;
   key_rotation: boolean;
+  key_strategy?: KeyStrategy;
+  /** Left unchanged when absent; an empty string removes the limit. */
+  rate_limit?: string;
 }): Promise<void> {
   return invoke<void>("update_channel", {
     id: data.id,
@@ -201,6 +241,8 @@ export async function updateChannel(data: {
     weight: data.weight,
     enabled: data.enabled,
     keyRotation: data.key_rotation,
+    keyStrategy: data.key_strategy,
+    rateLimit: data.rate_limit,
   });
 }
 
@@ -359,6 +401,10 @@ export async function getRequestLog(id: string): Promise<RequestLog | null> {
   return invoke<RequestLog | null>("get_request_log", { id });
 }
 
+export async function listRequestLogAttempts(id: string): Promise<RequestLog[]> {
+  return invoke<RequestLog[]>("list_request_log_attempts", { id });
+}
+
 export async function clearRequestLogs(): Promise<void> {
   return invoke<void>("clear_request_logs");
 }
@@ -476,11 +522,37 @@ export interface ConversionRule {
   decode_stream_chunk: string | null;
   encode_stream_chunk: string | null;
   http_config: string | null;
+  test_fixtures: string | null;
   enabled: boolean;
   created_at: string;
   updated_at: string;
 }
 
+export interface FixtureDiff {
+  path: string;
+  expected?: unknown;
+  actual?: unknown;
+}
+
+export interface FixtureResult {
+  name: string;
+  step: string;
+  passed: boolean;
+  error: string | null;
+  diffs: FixtureDiff[];
+}
+
+export interface RuleTestReport {
+  passed: number;
+  failed: number;
+  results: FixtureResult[];
+}
+
+/** A stored rule; `test_report` is set when it was kept disabled because its fixtures fail. */
+export interface SavedRule extends ConversionRule {
+  test_report?: RuleTestReport;
+}
+
 // === Conversion Rule commands ===
 
 export async function listConversionRules(): Promise<ConversionRule[]> {
@@ -506,8 +578,9 @@ export async function createConversionRule(data: {
   decode_stream_chunk?: string;
   encode_stream_chunk?: string;
   http_config?: string;
-}): Promise<ConversionRule> {
-  return invoke<ConversionRule>("create_conversion_rule", {
+  test_fixtures?: string;
+}): Promise<SavedRule> {
+  return invoke<SavedRule>("create_conversion_rule", {
     slug: data.slug,
     name: data.name,
     description: data.description,
@@ -522,6 +595,7 @@ export async function createConversionRule(data: {
     decodeStreamChunk: data.decode_stream_chunk,
     encodeStreamChunk: data.encode_stream_chunk,
     httpConfig: data.http_config,
+    testFixtures: data.test_fixtures,
   });
 }
 
@@ -541,9 +615,10 @@ export async function updateConversionRule(data: {
   decode_stream_chunk?: string;
   encode_stream_chunk?: string;
   http_config?: string;
+  test_fixtures?: string;
   enabled: boolean;
-}): Promise<void> {
-  return invoke<void>("update_conversion_rule", {
+}): Promise<SavedRule> {
+  return invoke<SavedRule>("update_conversion_rule", {
     id: data.id,
     slug: data.slug,
     name: data.name,
@@ -559,6 +634,7 @@ export async function updateConversionRule(data: {
     decodeStreamChunk: data.decode_stream_chunk,
     encodeStreamChunk: data.encode_stream_chunk,
     httpConfig: data.http_config,
+    testFixtures: data.test_fixtures,
     enabled: data.enabled,
   });
 }
@@ -567,8 +643,8 @@ export async function deleteConversionRule(id: string): Promise<void> {
   return invoke<void>("delete_conversion_rule", { id });
 }
 
-export async function duplicateConversionRule(id: string): Promise<ConversionRule> {
-  return invoke<ConversionRule>("duplicate_conversion_rule", { id });
+export async function duplicateConversionRule(id: string): Promise<SavedRule> {
+  return invoke<SavedRule>("duplicate_conversion_rule", { id });
 }
 
 export async function validateRuleTemplates(data: {
@@ -599,6 +675,13 @@ export async function testRuleTemplate(
   });
 }
 
+export async function runRuleTests(
+  id: string,
+  builtin?: string,
+): Promise<RuleTestReport> {
+  return invoke<RuleTestReport>("run_rule_tests", { id, builtin });
+}
+
 // === Rule Store commands ===
 
 export interface RuleIndexEntry {
@@ -619,8 +702,8 @@ export async function fetchRuleStoreIndex(): Promise<RuleIndex> {
   return invoke<RuleIndex>("fetch_rule_store_index");
 }
 
-export async function installRuleFromStore(slug: string): Promise<ConversionRule> {
-  return invoke<ConversionRule>("install_rule_from_store", { slug });
+export async function installRuleFromStore(slug: string): Promise<SavedRule> {
+  return invoke<SavedRule>("install_rule_from_store", { slug });
 }
 
 // === AI Rule Generation ===
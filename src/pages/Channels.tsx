@@ -69,6 +69,7 @@ import {
 } from "@/components/ui/tooltip";
 import {
   type Channel,
+  type KeyStrategy,
   type ChannelApiKey,
   type TestResult,
   listChannels,
@@ -103,6 +104,7 @@ const PROVIDER_DEFAULT_URLS: Record<string, string> = {
   gemini: "https://generativelanguage.googleapis.com",
   moonshot: "https://api.moonshot.cn",
   "openai-responses": "https://api.openai.com",
+  ollama: "http://localhost:11434",
 };
 
 // ---------------------------------------------------------------------------
@@ -126,6 +128,8 @@ interface ChannelFormData {
   weight: number;
   enabled: boolean;
   key_rotation: boolean;
+  key_strategy: KeyStrategy;
+  rate_limit: string;
 }
 
 const defaultFormData: ChannelFormData = {
@@ -136,6 +140,8 @@ const defaultFormData: ChannelFormData = {
   weight: 1,
   enabled: true,
   key_rotation: false,
+  key_strategy: "round_robin",
+  rate_limit: "",
 };
 
 // ---------------------------------------------------------------------------
@@ -243,6 +249,8 @@ export default function Channels() {
       weight: channel.weight,
       enabled: channel.enabled,
       key_rotation: channel.key_rotation,
+      key_strategy: channel.key_strategy,
+      rate_limit: channel.rate_limit ?? "",
     });
     setFormOpen(true);
   }
@@ -261,6 +269,8 @@ export default function Channels() {
           weight: formData.weight,
           enabled: formData.enabled,
           key_rotation: formData.key_rotation,
+          key_strategy: formData.key_strategy,
+          rate_limit: formData.rate_limit.trim(),
         });
       } else {
         await createChannel({
@@ -269,6 +279,8 @@ export default function Channels() {
           base_url: formData.base_url || PROVIDER_DEFAULT_URLS[formData.provider],
           priority: formData.priority,
           weight: formData.weight,
+          key_strategy: formData.key_strategy,
+          rate_limit: formData.rate_limit.trim(),
         });
       }
       setFormOpen(false);
@@ -773,6 +785,54 @@ export default function Channels() {
                     }
                   />
                 </div>
+                <div className="grid gap-2">
+                  <Label htmlFor="channel-rate-limit">
+                    {t.channels.rateLimit}
+                  </Label>
+                  <Input
+                    id="channel-rate-limit"
+                    className="font-mono"
+                    placeholder='{"rpm":60,"tpm":100000,"concurrency":4}'
+                    value={formData.rate_limit}
+                    onChange={(e) =>
+                      setFormData((prev) => ({ ...prev, rate_limit: e.target.value }))
+                    }
+                  />
+                  <p className="text-xs text-muted-foreground">
+                    {t.channels.rateLimitHint}
+                  </p>
+                </div>
+                {formData.key_rotation && (
+                  <div className="grid gap-2">
+                    <Label htmlFor="channel-key-strategy">
+                      {t.channels.keyStrategy}
+                    </Label>
+                    <Select
+                      value={formData.key_strategy}
+                      onValueChange={(value) =>
+                        setFormData((prev) => ({
+                          ...prev,
+                          key_strategy: value as KeyStrategy,
+                        }))
+                      }
+                    >
+                      <SelectTrigger id="channel-key-strategy" className="w-full">
+                        <SelectValue />
+                      </SelectTrigger>
+                      <SelectContent>
+                        <SelectItem value="round_robin">
+                          {t.channels.keyStrategyRoundRobin}
+                        </SelectItem>
+                        <SelectItem value="least_recently_used">
+                          {t.channels.keyStrategyLru}
+                        </SelectItem>
+                        <SelectItem value="random">
+                          {t.channels.keyStrategyRandom}
+                        </SelectItem>
+                      </SelectContent>
+                    </Select>
+                  </div>
+                )}
               </div>
             )}
           </div>
@@ -54,6 +54,7 @@ import { parseIpcError } from "@/lib/tauri";
 
 const MODALITIES = [
   { value: "chat", label: "Chat" },
+  { value: "embedding", label: "Embedding" },
   { value: "image", label: "Image" },
   { value: "tts", label: "TTS" },
   { value: "asr", label: "ASR" },
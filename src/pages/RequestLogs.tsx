@@ -419,6 +419,15 @@ export default function RequestLogs() {
                     )}
                   </p>
                 </div>
+                <div>
+                  <span className="text-muted-foreground">{t.requestLogs.cacheTokens}</span>
+                  <p className="font-medium tabular-nums">
+                    {formatTokens(
+                      selectedLog.cache_read_tokens,
+                      selectedLog.cache_write_tokens
+                    )}
+                  </p>
+                </div>
                 <div>
                   <span className="text-muted-foreground">{t.requestLogs.conversion}</span>
                   <p className="font-medium">
@@ -442,6 +451,15 @@ export default function RequestLogs() {
                 </div>
               </div>
 
+              {selectedLog.error_message && (
+                <div className="text-sm">
+                  <span className="text-muted-foreground">{t.requestLogs.errorMessage}</span>
+                  <p className="font-medium break-all text-destructive">
+                    {selectedLog.error_message}
+                  </p>
+                </div>
+              )}
+
               {/* Retry button */}
               <Button
                 variant="outline"
@@ -1,4 +1,5 @@
 import { useState, useEffect, useCallback, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
 import {
   Plus,
   MoreHorizontal,
@@ -66,6 +67,8 @@ import { Tabs, TabsList, TabsTrigger, TabsContent } from "@/components/ui/tabs";
 import {
   type ConversionRule,
   type RuleIndexEntry,
+  type RuleTestReport,
+  type SavedRule,
   type Channel,
   listConversionRules,
   createConversionRule,
@@ -74,6 +77,7 @@ import {
   duplicateConversionRule,
   validateRuleTemplates,
   testRuleTemplate,
+  runRuleTests,
   fetchRuleStoreIndex,
   installRuleFromStore,
   listChannels,
@@ -116,6 +120,7 @@ interface RuleFormData {
   encode_response: string;
   decode_stream_chunk: string;
   encode_stream_chunk: string;
+  test_fixtures: string;
   enabled: boolean;
 }
 
@@ -134,6 +139,7 @@ const defaultFormData: RuleFormData = {
   encode_response: "",
   decode_stream_chunk: "",
   encode_stream_chunk: "",
+  test_fixtures: "",
   enabled: true,
 };
 
@@ -152,6 +158,19 @@ const TEMPLATE_FIELDS = [
 
 type TemplateKey = (typeof TEMPLATE_FIELDS)[number]["key"];
 
+/** Built-in codecs whose shipped fixtures a rule can be checked against. */
+const BUILTIN_CODECS = [
+  "openai-chat",
+  "openai-responses",
+  "anthropic",
+  "gemini",
+  "moonshot",
+] as const;
+
+function formatFixtureValue(value: unknown): string {
+  return value === undefined ? "(missing)" : JSON.stringify(value);
+}
+
 // ---------------------------------------------------------------------------
 // Component
 // ---------------------------------------------------------------------------
@@ -180,6 +199,9 @@ export default function Rules() {
   const [testOutput, setTestOutput] = useState("");
   const [testRunning, setTestRunning] = useState(false);
   const [validating, setValidating] = useState(false);
+  const [fixtureBuiltin, setFixtureBuiltin] = useState("none");
+  const [fixtureReport, setFixtureReport] = useState<RuleTestReport | null>(null);
+  const [fixturesRunning, setFixturesRunning] = useState(false);
 
   // --- Import file input ref ---
   const fileInputRef = useRef<HTMLInputElement>(null);
@@ -199,6 +221,9 @@ export default function Rules() {
   const [aiPrompt, setAiPrompt] = useState("");
   const [aiGenerating, setAiGenerating] = useState(false);
 
+  // System rules keep their identity; only their overlay templates are editable
+  const isSystemRule = editingRule?.rule_type === "system";
+
   // --- Fetch rules ---
   const fetchRules = useCallback(async () => {
     try {
@@ -216,14 +241,26 @@ export default function Rules() {
     fetchRules();
   }, [fetchRules]);
 
+  // Rules changed elsewhere, e.g. in another window or by a store install
+  useEffect(() => {
+    const unlisten = listen("rules-changed", () => {
+      fetchRules();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [fetchRules]);
+
   // --- Open add dialog ---
   function openAddDialog() {
     setEditingRule(null);
     setFormData(defaultFormData);
     setSlugManuallyEdited(false);
+    setFixtureBuiltin("none");
     setTestInput("");
     setTestOutput("");
     setTestTemplate("decode_request");
+    setFixtureReport(null);
     setFormOpen(true);
   }
 
@@ -245,12 +282,15 @@ export default function Rules() {
       encode_response: rule.encode_response,
       decode_stream_chunk: rule.decode_stream_chunk ?? "",
       encode_stream_chunk: rule.encode_stream_chunk ?? "",
+      test_fixtures: rule.test_fixtures ?? "",
       enabled: rule.enabled,
     });
     setSlugManuallyEdited(true);
+    setFixtureBuiltin(rule.rule_type === "system" ? rule.slug : "none");
     setTestInput("");
     setTestOutput("");
     setTestTemplate("decode_request");
+    setFixtureReport(null);
     setFormOpen(true);
   }
 
@@ -258,8 +298,9 @@ export default function Rules() {
   async function handleFormSubmit() {
     try {
       setFormSubmitting(true);
+      let saved: SavedRule;
       if (editingRule) {
-        await updateConversionRule({
+        saved = await updateConversionRule({
           id: editingRule.id,
           slug: formData.slug,
           name: formData.name,
@@ -275,10 +316,11 @@ export default function Rules() {
           decode_stream_chunk: formData.decode_stream_chunk || undefined,
           encode_stream_chunk: formData.encode_stream_chunk || undefined,
           http_config: formData.http_config || undefined,
+          test_fixtures: formData.test_fixtures || undefined,
           enabled: formData.enabled,
         });
       } else {
-        await createConversionRule({
+        saved = await createConversionRule({
           slug: formData.slug,
           name: formData.name,
           description: formData.description || undefined,
@@ -293,10 +335,18 @@ export default function Rules() {
           decode_stream_chunk: formData.decode_stream_chunk || undefined,
           encode_stream_chunk: formData.encode_stream_chunk || undefined,
           http_config: formData.http_config || undefined,
+          test_fixtures: formData.test_fixtures || undefined,
         });
       }
-      setFormOpen(false);
       await fetchRules();
+      if (saved.test_report) {
+        // Stored but kept disabled: stay on the saved rule to show the report
+        setEditingRule(saved);
+        setFormData((prev) => ({ ...prev, enabled: false }));
+        showTestReport(saved.test_report);
+        return;
+      }
+      setFormOpen(false);
     } catch (err) {
       toast.error(parseIpcError(err).message);
     } finally {
@@ -304,6 +354,13 @@ export default function Rules() {
     }
   }
 
+  // --- Rules that fail their fixtures are stored disabled with a report ---
+  function showTestReport(report: RuleTestReport) {
+    setFixtureReport(report);
+    const failing = report.results.filter((result) => !result.passed);
+    toast.error(t.rules.testsFailed(failing.map((result) => result.name).join(", ")));
+  }
+
   // --- Delete rule ---
   async function handleDelete() {
     if (!deleteTarget) return;
@@ -322,9 +379,13 @@ export default function Rules() {
   // --- Duplicate rule ---
   async function handleDuplicate(rule: ConversionRule) {
     try {
-      await duplicateConversionRule(rule.id);
+      const saved = await duplicateConversionRule(rule.id);
       await fetchRules();
-      toast.success(t.rules.duplicateRule + " - OK");
+      if (saved.test_report) {
+        showTestReport(saved.test_report);
+      } else {
+        toast.success(t.rules.duplicateRule + " - OK");
+      }
     } catch (err) {
       toast.error(parseIpcError(err).message);
     }
@@ -333,7 +394,7 @@ export default function Rules() {
   // --- Toggle enabled ---
   async function handleToggleEnabled(rule: ConversionRule, enabled: boolean) {
     try {
-      await updateConversionRule({
+      const saved = await updateConversionRule({
         id: rule.id,
         slug: rule.slug,
         name: rule.name,
@@ -349,9 +410,13 @@ export default function Rules() {
         decode_stream_chunk: rule.decode_stream_chunk ?? undefined,
         encode_stream_chunk: rule.encode_stream_chunk ?? undefined,
         http_config: rule.http_config ?? undefined,
+        test_fixtures: rule.test_fixtures ?? undefined,
         enabled,
       });
       await fetchRules();
+      if (saved.test_report) {
+        showTestReport(saved.test_report);
+      }
     } catch (err) {
       toast.error(parseIpcError(err).message);
     }
@@ -389,12 +454,33 @@ export default function Rules() {
       const result = await testRuleTemplate(expression, testInput);
       setTestOutput(result);
     } catch (err) {
-      setTestOutput(parseIpcError(err).message);
+      const error = parseIpcError(err);
+      if (error.code === "RULE_LIMIT") {
+        toast.error(t.rules.ruleLimitExceeded);
+      }
+      setTestOutput(error.message);
     } finally {
       setTestRunning(false);
     }
   }
 
+  // --- Run the stored rule's fixtures ---
+  async function handleRunFixtures() {
+    if (!editingRule) return;
+    try {
+      setFixturesRunning(true);
+      const report = await runRuleTests(
+        editingRule.id,
+        fixtureBuiltin === "none" ? undefined : fixtureBuiltin,
+      );
+      setFixtureReport(report);
+    } catch (err) {
+      toast.error(parseIpcError(err).message);
+    } finally {
+      setFixturesRunning(false);
+    }
+  }
+
   // --- Export single rule ---
   function handleExportRule(rule: ConversionRule) {
     const exportData = {
@@ -412,6 +498,7 @@ export default function Rules() {
       decode_stream_chunk: rule.decode_stream_chunk,
       encode_stream_chunk: rule.encode_stream_chunk,
       http_config: rule.http_config,
+      test_fixtures: rule.test_fixtures ? JSON.parse(rule.test_fixtures) : null,
     };
     const blob = new Blob([JSON.stringify(exportData, null, 2)], {
       type: "application/json",
@@ -441,7 +528,7 @@ export default function Rules() {
     try {
       const text = await file.text();
       const data = JSON.parse(text);
-      await createConversionRule({
+      const saved = await createConversionRule({
         slug: data.slug,
         name: data.name,
         description: data.description || undefined,
@@ -456,9 +543,14 @@ export default function Rules() {
         decode_stream_chunk: data.decode_stream_chunk || undefined,
         encode_stream_chunk: data.encode_stream_chunk || undefined,
         http_config: data.http_config || undefined,
+        test_fixtures: data.test_fixtures ? JSON.stringify(data.test_fixtures) : undefined,
       });
-      toast.success(t.rules.importSuccess);
       await fetchRules();
+      if (saved.test_report) {
+        showTestReport(saved.test_report);
+      } else {
+        toast.success(t.rules.importSuccess);
+      }
     } catch (err) {
       toast.error(parseIpcError(err).message);
     } finally {
@@ -519,9 +611,13 @@ export default function Rules() {
   async function handleInstallFromStore(slug: string) {
     try {
       setInstallingSlug(slug);
-      await installRuleFromStore(slug);
+      const saved = await installRuleFromStore(slug);
       await fetchRules();
-      toast.success(t.rules.install + " - OK");
+      if (saved.test_report) {
+        showTestReport(saved.test_report);
+      } else {
+        toast.success(t.rules.install + " - OK");
+      }
     } catch (err) {
       toast.error(parseIpcError(err).message);
     } finally {
@@ -698,14 +794,12 @@ export default function Rules() {
                             </Button>
                           </DropdownMenuTrigger>
                           <DropdownMenuContent align="end">
-                            {rule.rule_type !== "system" && (
-                              <DropdownMenuItem
-                                onClick={() => openEditDialog(rule)}
-                              >
-                                <Pencil className="size-4" />
-                                {t.common.edit}
-                              </DropdownMenuItem>
-                            )}
+                            <DropdownMenuItem
+                              onClick={() => openEditDialog(rule)}
+                            >
+                              <Pencil className="size-4" />
+                              {t.common.edit}
+                            </DropdownMenuItem>
                             <DropdownMenuItem
                               onClick={() => handleDuplicate(rule)}
                             >
@@ -937,7 +1031,7 @@ export default function Rules() {
 
             {/* ---- Tab: Basic Info ---- */}
             <TabsContent value="basic" className="flex-1 overflow-y-auto mt-4">
-              <div className="space-y-4">
+              <fieldset disabled={isSystemRule} className="space-y-4">
                 {/* Name + Slug */}
                 <div className="grid grid-cols-2 gap-4">
                   <div className="grid gap-2">
@@ -1095,19 +1189,30 @@ export default function Rules() {
                     }
                   />
                 </div>
-              </div>
+              </fieldset>
             </TabsContent>
 
             {/* ---- Tab: Templates ---- */}
             <TabsContent value="templates" className="flex-1 overflow-y-auto mt-4">
               <div className="space-y-4">
+                {isSystemRule && (
+                  <p className="text-sm text-muted-foreground">
+                    {t.rules.overlayHint}
+                  </p>
+                )}
                 {/* Required templates: 2 columns */}
                 <div className="grid grid-cols-2 gap-4">
                   {TEMPLATE_FIELDS.filter((f) => f.required).map((field) => (
                     <div key={field.key} className="grid gap-1.5">
                       <Label htmlFor={`rule-${field.key}`} className="text-xs">
                         {templateLabel(field.key)}{" "}
-                        <span className="text-destructive">*</span>
+                        {isSystemRule ? (
+                          <span className="text-muted-foreground">
+                            ({t.rules.optional})
+                          </span>
+                        ) : (
+                          <span className="text-destructive">*</span>
+                        )}
                       </Label>
                       <textarea
                         id={`rule-${field.key}`}
@@ -1225,6 +1330,98 @@ export default function Rules() {
                     </pre>
                   </div>
                 </div>
+
+                <Separator />
+
+                {/* Fixtures */}
+                <div className="space-y-3">
+                  <div className="grid gap-1.5">
+                    <Label className="text-xs">{t.rules.fixtures}</Label>
+                    <p className="text-xs text-muted-foreground">{t.rules.fixturesHint}</p>
+                    <textarea
+                      className="flex w-full rounded-md border border-input bg-background px-3 py-2 text-xs font-mono ring-offset-background placeholder:text-muted-foreground focus-visible:outline-none focus-visible:ring-2 focus-visible:ring-ring focus-visible:ring-offset-2 resize-y"
+                      rows={8}
+                      placeholder='[{"name": "basic request", "step": "decode_request", "input": {...}, "expected": {...}}]'
+                      value={formData.test_fixtures}
+                      onChange={(e) =>
+                        setFormData((prev) => ({
+                          ...prev,
+                          test_fixtures: e.target.value,
+                        }))
+                      }
+                    />
+                  </div>
+                  <div className="flex items-center gap-2">
+                    <Select value={fixtureBuiltin} onValueChange={setFixtureBuiltin}>
+                      <SelectTrigger className="flex-1 h-8 text-xs">
+                        <SelectValue placeholder={t.rules.checkAgainstBuiltin} />
+                      </SelectTrigger>
+                      <SelectContent>
+                        <SelectItem value="none">{t.rules.noBuiltin}</SelectItem>
+                        {BUILTIN_CODECS.map((slug) => (
+                          <SelectItem key={slug} value={slug}>
+                            {t.rules.checkAgainstBuiltin}: {slug}
+                          </SelectItem>
+                        ))}
+                      </SelectContent>
+                    </Select>
+                    <Button
+                      size="sm"
+                      onClick={handleRunFixtures}
+                      disabled={fixturesRunning || !editingRule}
+                      title={editingRule ? undefined : t.rules.saveBeforeRunning}
+                    >
+                      {fixturesRunning ? (
+                        <Loader2 className="size-3 animate-spin" />
+                      ) : (
+                        <Play className="size-3" />
+                      )}
+                      {t.rules.runFixtures}
+                    </Button>
+                  </div>
+                  {!editingRule && (
+                    <p className="text-xs text-muted-foreground">{t.rules.saveBeforeRunning}</p>
+                  )}
+
+                  {fixtureReport && (
+                    <div className="space-y-2">
+                      <p
+                        className={
+                          fixtureReport.failed === 0
+                            ? "text-xs font-medium text-green-600 dark:text-green-400"
+                            : "text-xs font-medium text-destructive"
+                        }
+                      >
+                        {t.rules.fixturesPassed(
+                          fixtureReport.passed,
+                          fixtureReport.passed + fixtureReport.failed,
+                        )}
+                      </p>
+                      {fixtureReport.results
+                        .filter((result) => !result.passed)
+                        .map((result, i) => (
+                          <div key={i} className="rounded-md border p-2 text-xs space-y-1">
+                            <div className="font-medium">
+                              {result.name}{" "}
+                              <Badge variant="outline" className="text-[10px] font-mono">
+                                {result.step}
+                              </Badge>
+                            </div>
+                            {result.error && (
+                              <p className="font-mono text-destructive break-all">{result.error}</p>
+                            )}
+                            {result.diffs.map((diff, j) => (
+                              <div key={j} className="font-mono break-all">
+                                <span className="text-muted-foreground">{diff.path || "/"}</span>{" "}
+                                {t.rules.fixturesExpected} {formatFixtureValue(diff.expected)},{" "}
+                                {t.rules.fixturesActual} {formatFixtureValue(diff.actual)}
+                              </div>
+                            ))}
+                          </div>
+                        ))}
+                    </div>
+                  )}
+                </div>
               </div>
             </TabsContent>
           </Tabs>
@@ -1241,12 +1438,13 @@ export default function Rules() {
               onClick={handleFormSubmit}
               disabled={
                 formSubmitting ||
-                !formData.name.trim() ||
-                !formData.slug.trim() ||
-                !formData.decode_request.trim() ||
-                !formData.encode_request.trim() ||
-                !formData.decode_response.trim() ||
-                !formData.encode_response.trim()
+                (!isSystemRule &&
+                  (!formData.name.trim() ||
+                    !formData.slug.trim() ||
+                    !formData.decode_request.trim() ||
+                    !formData.encode_request.trim() ||
+                    !formData.decode_response.trim() ||
+                    !formData.encode_response.trim()))
               }
             >
               {formSubmitting && <Loader2 className="size-4 animate-spin" />}